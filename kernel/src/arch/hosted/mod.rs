@@ -6,6 +6,7 @@ pub mod mem;
 pub mod process;
 pub mod rand;
 pub mod syscall;
+pub mod time;
 
 use std::cell::RefCell;
 use std::convert::TryInto;
@@ -477,6 +478,7 @@ pub fn idle() -> bool {
     let pid1_key = PID1_KEY.with(|p1k| *p1k.borrow());
     let pid1_init = ProcessInit {
         key: ProcessKey::new(pid1_key),
+        page_quota: 0,
     };
     let pid1 = SystemServices::with_mut(|ss| ss.create_process(pid1_init)).unwrap();
     assert_eq!(pid1.get(), 1);
@@ -527,6 +529,7 @@ pub fn idle() -> bool {
             let process_key = generate_pid_key();
             let init = xous_kernel::ProcessInit {
                 key: ProcessKey::new(process_key),
+                page_quota: 0,
             };
             let new_pid = SystemServices::with_mut(|ss| ss.create_process(init)).unwrap();
             println!(" {:^5} |  {}", new_pid, arg);
@@ -536,7 +539,27 @@ pub fn idle() -> bool {
         }
     }
 
-    while let Ok(msg) = message_receiver.recv() {
+    loop {
+        // Wake any threads whose timeout has passed, and make sure not to
+        // sleep beyond the next deadline.
+        let next_deadline = SystemServices::with_mut(|ss| {
+            ss.expire_deadlines(time::elapsed_ms());
+            ss.next_deadline()
+        });
+        let msg = match next_deadline {
+            None => match message_receiver.recv() {
+                Ok(msg) => msg,
+                Err(RecvError) => break,
+            },
+            Some(deadline) => {
+                let timeout = deadline.saturating_sub(time::elapsed_ms());
+                match message_receiver.recv_timeout(std::time::Duration::from_millis(timeout)) {
+                    Ok(msg) => msg,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        };
         match msg {
             ThreadMessage::NewConnection(conn, access_key) => {
                 // The new process should already have a PID registered. Convert its access key
@@ -573,9 +596,13 @@ pub fn idle() -> bool {
                 crate::arch::process::set_current_pid(pid);
                 // println!("KERNEL({}): Now running as the new process", pid);
 
+                // Charge the time since this thread was last let go to the thread itself.
+                let (run_ms, woken) = Process::current().stop_cpu_clock(thread_id);
+                SystemServices::with_mut(|ss| ss.charge_cpu_time(pid, thread_id, run_ms, woken));
+
                 // If the call being made is to terminate the current process, we need to know
                 // because we won't be able to send a response.
-                let is_terminate = call == SysCall::TerminateProcess(0);
+                let is_terminate = matches!(call, SysCall::TerminateProcess(_));
                 let is_shutdown = call == SysCall::Shutdown;
 
                 // For a "Shutdown" command, send the response before we issue the shutdown.
@@ -640,6 +667,7 @@ pub fn idle() -> bool {
                         crate::syscall::handle(pid, thread_id, false, SysCall::TerminateProcess(0))
                             .ok();
                     });
+                    process.start_cpu_clock(thread_id, false);
                     crate::arch::process::set_current_pid(existing_pid);
                     // println!(
                     //     "KERNEL [{:2}:{:2}] Syscall took {:7} usec",
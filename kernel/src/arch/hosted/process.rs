@@ -116,11 +116,22 @@ pub fn register_connection_for_key(
 /// In a `std` environment, we can't manage threads so this is a no-op.
 pub struct Thread {
     allocated: bool,
+
+    /// When the kernel last let this thread continue, if it is running
+    running_since: Option<u64>,
+
+    /// Whether this thread was woken up after blocking, rather than being
+    /// answered straight away
+    woken: bool,
 }
 
 impl Default for Thread {
     fn default() -> Self {
-        Thread { allocated: false }
+        Thread {
+            allocated: false,
+            running_since: None,
+            woken: false,
+        }
     }
 }
 
@@ -195,7 +206,47 @@ impl Process {
         let process = &mut process_table.table[current_pid_idx].as_mut().unwrap();
 
         assert!(!process.threads[thread - 1].allocated);
-        process.threads[thread - 1].allocated = true;
+        process.threads[thread - 1] = Thread {
+            allocated: true,
+            running_since: Some(crate::arch::time::elapsed_ms()),
+            woken: true,
+        };
+    }
+
+    /// Note that thread `tid` is running again as of now. Processes all run
+    /// at once in hosted mode, so each thread keeps its own clock.
+    pub fn start_cpu_clock(&mut self, tid: TID, woken: bool) {
+        PROCESS_TABLE.with(|pt| {
+            let mut process_table = pt.borrow_mut();
+            let current_pid_idx = process_table.current.get() as usize - 1;
+            if let Some(thread) = process_table.table[current_pid_idx]
+                .as_mut()
+                .and_then(|process| process.threads.get_mut(tid - 1))
+            {
+                thread.running_since = Some(crate::arch::time::elapsed_ms());
+                thread.woken = woken;
+            }
+        })
+    }
+
+    /// Thread `tid` has entered the kernel. Return how many milliseconds it
+    /// has been running for, and whether it had been woken up from blocking.
+    pub fn stop_cpu_clock(&mut self, tid: TID) -> (u64, bool) {
+        PROCESS_TABLE.with(|pt| {
+            let mut process_table = pt.borrow_mut();
+            let current_pid_idx = process_table.current.get() as usize - 1;
+            process_table.table[current_pid_idx]
+                .as_mut()
+                .and_then(|process| process.threads.get_mut(tid - 1))
+                .and_then(|thread| {
+                    let since = thread.running_since.take()?;
+                    Some((
+                        crate::arch::time::elapsed_ms().saturating_sub(since),
+                        core::mem::replace(&mut thread.woken, false),
+                    ))
+                })
+                .unwrap_or((0, false))
+        })
     }
 
     pub fn retry_instruction(&mut self, _tid: TID) -> Result<(), xous_kernel::Error> {
@@ -277,6 +328,18 @@ impl Process {
         false
     }
 
+    /// Return the number of threads that are allocated in this process.
+    pub fn thread_count(&self) -> usize {
+        PROCESS_TABLE.with(|pt| {
+            let process_table = pt.borrow();
+            let current_pid_idx = process_table.current.get() as usize - 1;
+            process_table.table[current_pid_idx]
+                .as_ref()
+                .map(|process| process.threads.iter().filter(|t| t.allocated).count())
+                .unwrap_or(0)
+        })
+    }
+
     pub fn set_thread_result(&mut self, tid: TID, result: xous_kernel::Result) {
         assert!(tid > 0);
         PROCESS_TABLE.with(|pt| {
@@ -288,6 +351,8 @@ impl Process {
                 "thread {} is not allocated",
                 tid,
             );
+            process.threads[tid - 1].running_since = Some(crate::arch::time::elapsed_ms());
+            process.threads[tid - 1].woken = true;
 
             let mut response = vec![];
             // Add the destination thread ID to the start of the packet.
@@ -348,7 +413,7 @@ impl Process {
                 key: init_data.key,
                 memory_to_return: filled_array![None; 32 /* MAX_THREAD */],
                 current_thread: INITIAL_TID,
-                threads: [Thread { allocated: false }; MAX_THREAD + 1],
+                threads: [Thread::default(); MAX_THREAD + 1],
             };
 
             process_table.total += 1;
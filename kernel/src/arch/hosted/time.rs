@@ -0,0 +1,12 @@
+// SPDX-FileCopyrightText: 2020 Sean Cross <sean@xobs.io>
+// SPDX-License-Identifier: Apache-2.0
+
+use std::thread_local;
+use std::time::Instant;
+
+thread_local!(static KERNEL_START: Instant = Instant::now());
+
+/// Return the number of milliseconds since the kernel started.
+pub fn elapsed_ms() -> u64 {
+    KERNEL_START.with(|start| start.elapsed().as_millis() as u64)
+}
@@ -14,6 +14,9 @@ extern "Rust" {
     fn _xous_syscall_return_result(result: &xous_kernel::Result, context: &Thread) -> !;
 }
 
+/// The thread that most recently trapped into the kernel, and when it did so
+static mut LAST_TRAP: Option<(PID, TID, u64)> = None;
+
 // use RAM-based backing so this variable is automatically saved on suspend
 static mut SIM_BACKING: usize = 0;
 /// Disable external interrupts
@@ -49,9 +52,15 @@ pub unsafe fn take_isr_return_pair() -> Option<(PID, TID)> {
     PREVIOUS_PAIR.take()
 }
 
-/// Convert a RISC-V `Exception` into a Xous exception argument list.
-fn generate_exception_args(ex: &RiscvException) -> Option<[usize; 3]> {
+/// Convert a RISC-V `Exception` raised by thread `tid` into a Xous exception
+/// argument list.
+fn generate_exception_args(ex: &RiscvException, tid: TID) -> Option<[usize; 3]> {
     match *ex {
+        RiscvException::LoadPageFault(epc, addr) | RiscvException::StorePageFault(epc, addr)
+            if crate::arch::mem::is_guard_page(addr) =>
+        {
+            Some([xous_kernel::ExceptionType::StackOverflow as usize, epc, tid])
+        }
         RiscvException::InstructionAddressMisaligned(epc, addr) => Some([
             xous_kernel::ExceptionType::InstructionAddressMisaligned as usize,
             epc,
@@ -136,6 +145,18 @@ pub extern "C" fn trap_handler(
 
     let pid = current_pid();
 
+    // Whatever ran since the last trap did so on behalf of this thread. If a
+    // different thread trapped last time, the kernel switched to this one.
+    let tid = ArchProcess::with_current(|p| p.current_tid());
+    let now = crate::arch::time::elapsed_ms();
+    let (run_ms, switched) = match unsafe { LAST_TRAP.replace((pid, tid, now)) } {
+        Some((last_pid, last_tid, since)) => {
+            (now.saturating_sub(since), last_pid != pid || last_tid != tid)
+        }
+        None => (0, true),
+    };
+    SystemServices::with_mut(|ss| ss.charge_cpu_time(pid, tid, run_ms, switched));
+
     if (sc.bits() == 9) || (sc.bits() == 8) {
         // We got here because of an `ecall` instruction.  When we return, skip
         // past this instruction.  If this is a call such as `SwitchTo`, then we
@@ -270,8 +291,15 @@ pub extern "C" fn trap_handler(
         // This exception is not due to something we're aware of. In this case,
         // determine if there is an exception handler in this particular program
         // and call that handler if so.
-        if let Some(args) = generate_exception_args(&ex) {
+        if let Some(args) = generate_exception_args(&ex, tid) {
             klog!("Generated exception args -- invoking handler");
+            // Pass along the faulting thread's ra, sp and fp as well, so that the
+            // handler can walk its stack. Grab them now, since the exception
+            // thread is about to become the current one.
+            let (ra, sp, fp) = ArchProcess::with_current(|process| {
+                let thread = process.current_thread();
+                (thread.registers[0], thread.registers[1], thread.registers[7])
+            });
             if let Some(handler) = SystemServices::with_mut(|ss| ss.begin_exception_handler(pid)) {
                 klog!("Exception handler for process exists ({:x?})", handler);
                 // If this is the sort of exception that may be able to be handled by
@@ -289,7 +317,7 @@ pub extern "C" fn trap_handler(
                         handler.pc,
                         handler.sp,
                         RETURN_FROM_EXCEPTION_HANDLER,
-                        &args,
+                        &[args[0], args[1], args[2], ra, sp, fp],
                     );
                     crate::arch::syscall::resume(
                         current_pid().get() == 1,
@@ -315,6 +343,13 @@ pub extern "C" fn trap_handler(
             ex
         );
         ArchProcess::with_current(|process| {
+            if let RiscvException::LoadPageFault(_, addr)
+            | RiscvException::StorePageFault(_, addr) = &ex
+            {
+                if crate::arch::mem::is_guard_page(*addr) {
+                    println!("Thread {} overflowed its stack", process.current_tid());
+                }
+            }
             println!("Current thread {}:", process.current_tid());
             process.print_current_thread();
         });
@@ -333,8 +368,15 @@ pub extern "C" fn trap_handler(
                     .expect("couldn't debug current process");
                 println!("Program suspended. You may inspect it using gdb.");
             } else {
-                ss.terminate_process(pid)
-                    .expect("couldn't terminate current process");
+                ss.terminate_process(
+                    pid,
+                    xous_kernel::ExitReason::Exception {
+                        cause: sc.bits(),
+                        pc: sepc::read(),
+                        addr: stval::read(),
+                    },
+                )
+                .expect("couldn't terminate current process");
             };
             crate::syscall::reset_switchto_caller();
         });
@@ -344,6 +386,10 @@ pub extern "C" fn trap_handler(
             crate::arch::syscall::resume(current_pid().get() == 1, process.current_thread())
         })
     } else {
+        // A thread's timeout may have passed while the core was idle, and the
+        // thread that was interrupted may not give up the CPU any time soon.
+        SystemServices::with_mut(|ss| ss.expire_deadlines(now));
+
         let irqs_pending = sip::read();
         // println!("irqs: {:x}", irqs_pending);
 
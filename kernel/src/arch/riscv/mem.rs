@@ -46,6 +46,12 @@ bitflags! {
     }
 }
 
+/// The page table entry of a stack guard page. It is not `VALID`, so any
+/// access faults, and `SHARED` keeps the page from being handed out again
+/// or backed by `ensure_page_exists_inner()`. No other page has `SHARED` and
+/// `PREVIOUSLY_WRITABLE` set without any permissions.
+const GUARD_PAGE: usize = MMUFlags::S.bits() | MMUFlags::P.bits();
+
 #[derive(Copy, Clone, Default, PartialEq)]
 pub struct MemoryMapping {
     satp: usize,
@@ -697,6 +703,129 @@ pub fn return_page_inner(
     Ok(phys)
 }
 
+/// Map a page from `src_space` into `dest_space` while leaving it mapped in
+/// `src_space`. Both entries get the `SHARED` bit, which prevents either
+/// process from lending, moving, or unmapping the page until it is unshared.
+///
+/// # Errors
+///
+/// * **BadAddress**: The page isn't allocated
+/// * **ShareViolation**: The page is already lent or shared
+pub fn share_page_inner(
+    mm: &mut MemoryManager,
+    src_space: &MemoryMapping,
+    src_addr: *mut u8,
+    dest_pid: PID,
+    dest_space: &MemoryMapping,
+    dest_addr: *mut u8,
+    writable: bool,
+) -> Result<usize, xous_kernel::Error> {
+    let entry = pagetable_entry(src_addr as usize)?;
+    if *entry & MMUFlags::VALID.bits() == 0 {
+        return Err(xous_kernel::Error::BadAddress);
+    }
+    if *entry & MMUFlags::S.bits() != 0 {
+        return Err(xous_kernel::Error::ShareViolation);
+    }
+    let phys = (*entry >> 10) << 12;
+
+    let new_flags = if writable && (*entry & MMUFlags::W.bits()) != 0 {
+        MemoryFlags::R | MemoryFlags::W
+    } else {
+        MemoryFlags::R
+    };
+
+    // Switch to the new address space and map the page
+    dest_space.activate()?;
+    let result = map_page_inner(
+        mm,
+        dest_pid,
+        phys,
+        dest_addr as usize,
+        new_flags,
+        dest_pid.get() != 1,
+    )
+    .and_then(|_| {
+        let dest_entry = pagetable_entry(dest_addr as usize)?;
+        *dest_entry |= MMUFlags::S.bits();
+        Ok(())
+    });
+    unsafe { flush_mmu() };
+
+    // Switch back to our process space. `entry` points into the current
+    // pagetable, so it may only be modified once we're back.
+    src_space.activate().unwrap();
+    if result.is_ok() {
+        *entry |= MMUFlags::S.bits();
+        unsafe { flush_mmu() };
+    }
+
+    result.map(|_| phys)
+}
+
+/// Undo `share_page_inner()`: remove the page from `dest_space` and clear
+/// the `SHARED` bit in `src_space`. The page remains owned by `src_space`.
+pub fn unshare_page_inner(
+    src_space: &MemoryMapping,
+    src_addr: *mut u8,
+    dest_space: &MemoryMapping,
+    dest_addr: *mut u8,
+) -> Result<usize, xous_kernel::Error> {
+    dest_space.activate()?;
+    if let Ok(dest_entry) = pagetable_entry(dest_addr as usize) {
+        *dest_entry = 0;
+        unsafe { flush_mmu() };
+    }
+
+    src_space.activate()?;
+    let entry = pagetable_entry(src_addr as usize)?;
+    *entry &= !MMUFlags::S.bits();
+    unsafe { flush_mmu() };
+    Ok((*entry >> 10) << 12)
+}
+
+/// Turn the page at `virt` in the current process into a stack guard page.
+/// Any memory behind it must already have been released.
+///
+/// # Errors
+///
+/// * **BadAddress**: The page was never mapped or reserved
+/// * **ShareViolation**: The page is lent or shared
+pub fn set_guard_page(virt: usize) -> Result<(), xous_kernel::Error> {
+    let entry = pagetable_entry(virt)?;
+    if *entry == GUARD_PAGE {
+        return Ok(());
+    }
+    if *entry == 0 {
+        return Err(xous_kernel::Error::BadAddress);
+    }
+    if *entry & MMUFlags::S.bits() != 0 {
+        return Err(xous_kernel::Error::ShareViolation);
+    }
+    *entry = GUARD_PAGE;
+    unsafe { flush_mmu() };
+    Ok(())
+}
+
+/// Determine whether `virt` lies in a stack guard page of the current process.
+pub fn is_guard_page(virt: usize) -> bool {
+    pagetable_entry(virt & !0xfff)
+        .map(|entry| *entry == GUARD_PAGE)
+        .unwrap_or(false)
+}
+
+/// If `virt` is a stack guard page, forget it and return `true`.
+pub fn clear_guard_page(virt: usize) -> bool {
+    match pagetable_entry(virt & !0xfff) {
+        Ok(entry) if *entry == GUARD_PAGE => {
+            *entry = 0;
+            unsafe { flush_mmu() };
+            true
+        }
+        _ => false,
+    }
+}
+
 pub fn virt_to_phys(virt: usize) -> Result<usize, xous_kernel::Error> {
     let vpn1 = (virt >> 22) & ((1 << 10) - 1);
     let vpn0 = (virt >> 12) & ((1 << 10) - 1);
@@ -756,10 +885,10 @@ pub fn ensure_page_exists_inner(address: usize) -> Result<usize, xous_kernel::Er
         return Err(xous_kernel::Error::BadAddress);
     }
 
-    let new_page = MemoryManager::with_mut(|mm| {
-        mm.alloc_page(crate::arch::process::current_pid())
-            .expect("Couldn't allocate new page")
-    });
+    // If the process is out of memory or over its quota, let the caller turn
+    // this into a fault for that process rather than bringing down the kernel.
+    let new_page =
+        MemoryManager::with_mut(|mm| mm.alloc_page(crate::arch::process::current_pid()))?;
     let ppn1 = (new_page >> 22) & ((1 << 12) - 1);
     let ppn0 = (new_page >> 12) & ((1 << 10) - 1);
     unsafe {
@@ -9,6 +9,7 @@ pub mod mem;
 pub mod process;
 pub mod rand;
 pub mod syscall;
+pub mod time;
 
 pub use process::Thread;
 
@@ -50,6 +51,7 @@ pub fn init() {
         sie::set_sext();
     }
     rand::init();
+    time::init();
 }
 
 /// Put the core to sleep until an interrupt hits. Returns `true`
@@ -57,6 +59,11 @@ pub fn init() {
 pub fn idle() -> bool {
     let mut wfi_kernel_csr = CSR::new(WFI_KERNEL.base as *mut u32);
 
+    // Threads waiting with a timeout don't need an alarm of their own: the
+    // pre-emption tick (`timer0`, every 20 ms) keeps waking the core, and
+    // deadlines are expired on every interrupt. This leaves the ticktimer's
+    // alarm to the ticktimer server.
+
     // Issue `wfi`. This will return as soon as an external interrupt
     // is available.
     if false {
@@ -67,7 +67,7 @@ struct ProcessImpl {
 
     /// Pad everything to 128 bytes, so the Thread slice starts at
     /// offset 128.
-    _padding: [u32; 13],
+    _padding: [u32; 5],
 
     /// This enables the kernel to keep track of threads in the
     /// target process, and know which threads are ready to
@@ -196,6 +196,12 @@ impl Process {
         self.thread(tid).sepc != 0
     }
 
+    /// Return the number of threads that are allocated in this process.
+    pub fn thread_count(&self) -> usize {
+        let process = unsafe { &*PROCESS };
+        process.threads.iter().filter(|t| t.sepc != 0).count()
+    }
+
     /// Set the current thread number.
     pub fn set_tid(&mut self, thread: TID) -> Result<(), xous_kernel::Error> {
         let mut process = unsafe { &mut *PROCESS };
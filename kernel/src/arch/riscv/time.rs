@@ -0,0 +1,42 @@
+// SPDX-FileCopyrightText: 2020 Sean Cross <sean@xobs.io>
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::mem::MemoryManager;
+use utralib::generated::*;
+use xous_kernel::{MemoryFlags, PID};
+
+const TICKS_PER_MS: u64 = 1;
+
+pub const TICKTIMER_KERNEL: Ticktimer = Ticktimer {
+    // the manually chosen virtual address has to be in the top 4MiB as it is the only page shared among all processes
+    base: 0xffcc_0000 as *mut usize, // see https://github.com/betrusted-io/xous-core/blob/master/docs/memory.md
+};
+
+pub struct Ticktimer {
+    pub base: *mut usize,
+}
+
+pub fn init() {
+    // The ticktimer block belongs to the ticktimer server. The kernel only
+    // ever reads the free-running counter, so map the page read-only
+    // without claiming it, which leaves the server free to claim it later.
+    MemoryManager::with_mut(|memory_manager| {
+        crate::arch::mem::map_page_inner(
+            memory_manager,
+            PID::new(1).unwrap(),
+            utra::ticktimer::HW_TICKTIMER_BASE,
+            TICKTIMER_KERNEL.base as usize,
+            MemoryFlags::R,
+            false,
+        )
+        .expect("unable to map ticktimer")
+    });
+}
+
+/// Return the number of milliseconds since the system started.
+pub fn elapsed_ms() -> u64 {
+    let csr = CSR::new(TICKTIMER_KERNEL.base as *mut u32);
+    let mut time: u64 = csr.r(utra::ticktimer::TIME0) as u64;
+    time |= (csr.r(utra::ticktimer::TIME1) as u64) << 32;
+    time / TICKS_PER_MS
+}
@@ -105,6 +105,8 @@ pub unsafe extern "C" fn init(arg_offset: *const u32, init_offset: *const u32, r
 }
 
 /// Loop through the SystemServices list to determine the next PID to be run.
+/// The process with the highest-priority runnable thread wins, and processes
+/// with equal priority are picked round-robin starting after `last_pid`.
 /// If no process is ready, return `None`.
 fn next_pid_to_run(last_pid: Option<PID>) -> Option<PID> {
     // PIDs are 1-indexed but arrays are 0-indexed.  By not subtracting
@@ -113,33 +115,22 @@ fn next_pid_to_run(last_pid: Option<PID>) -> Option<PID> {
     let current_pid = last_pid.unwrap_or(unsafe { PID::new_unchecked(1) }).get() as usize;
 
     SystemServices::with(|system_services| {
-        for test_idx in current_pid..system_services.processes.len() {
-            if system_services.processes[test_idx].ppid.get() == 1 {
-                // print!("PID {} is owned by PID1... ", test_idx + 1);
-                if system_services.processes[test_idx].runnable() {
-                    // println!(" and is runnable");
-                    return match pid_from_usize(test_idx + 1) {
-                        Ok(x) => Some(x),
-                        Err(_) => None,
-                    };
-                }
-                // println!(" and is NOT RUNNABLE");
+        let process_count = system_services.processes.len();
+        let mut best: Option<(usize, ThreadPriority)> = None;
+        for test_idx in (current_pid..process_count).chain(0..current_pid) {
+            let process = &system_services.processes[test_idx];
+            if process.ppid.get() != 1 {
+                continue;
             }
-        }
-        for test_idx in 0..current_pid {
-            if system_services.processes[test_idx].ppid.get() == 1 {
-                // print!("PID {} is owned by PID1... ", test_idx + 1);
-                if system_services.processes[test_idx].runnable() {
-                    // println!(" and is runnable");
-                    return match pid_from_usize(test_idx + 1) {
-                        Ok(x) => Some(x),
-                        Err(_) => None,
-                    };
+            if let Some(priority) = process.runnable_priority() {
+                // Only replace the candidate if this one is strictly better, so
+                // the first process found at a given level keeps its turn.
+                if best.map(|(_, p)| priority > p).unwrap_or(true) {
+                    best = Some((test_idx, priority));
                 }
-                // println!(" and is NOT RUNNABLE");
             }
         }
-        None
+        best.and_then(|(test_idx, _)| pid_from_usize(test_idx + 1).ok())
     })
 }
 
@@ -160,6 +151,9 @@ pub extern "C" fn kmain() {
     }
 
     loop {
+        // Wake any threads whose timeout has passed.
+        SystemServices::with_mut(|ss| ss.expire_deadlines(arch::time::elapsed_ms()));
+
         pid = next_pid_to_run(pid);
 
         match pid {
@@ -4,7 +4,7 @@
 use core::fmt;
 
 pub use crate::arch::mem::{MemoryMapping, PAGE_SIZE};
-use crate::arch::process::Process;
+use crate::arch::process::{Process, MAX_PROCESS_COUNT};
 
 use xous_kernel::{MemoryFlags, MemoryRange, PID};
 
@@ -47,6 +47,14 @@ pub struct MemoryManager {
     ram_name: u32,
     #[allow(dead_code)]
     last_ram_page: usize,
+
+    /// The most RAM pages each process may own, indexed by PID - 1. A quota
+    /// of 0 means the process may use as much as it likes.
+    page_quotas: [usize; MAX_PROCESS_COUNT],
+
+    /// The number of RAM pages each process owns, indexed by PID - 1, so that
+    /// quotas can be checked without walking the allocation table
+    page_counts: [usize; MAX_PROCESS_COUNT],
 }
 
 impl Default for MemoryManager {
@@ -77,6 +85,8 @@ impl MemoryManager {
             ram_size: 0,
             ram_name: 0,
             last_ram_page: 0,
+            page_quotas: [0; MAX_PROCESS_COUNT],
+            page_counts: [0; MAX_PROCESS_COUNT],
         }
     }
 
@@ -107,7 +117,6 @@ impl MemoryManager {
         MEMORY_MANAGER.with(|ss| f(&mut ss.borrow_mut()))
     }
 
-    #[cfg(baremetal)]
     pub fn with<F, R>(f: F) -> R
     where
         F: FnOnce(&MemoryManager) -> R,
@@ -161,6 +170,13 @@ impl MemoryManager {
                         tag.data.len() * 4 / core::mem::size_of::<MemoryRangeExtra>(),
                     )
                 };
+            } else if tag.name == u32::from_le_bytes(*b"PQuo") {
+                // Pairs of (PID, pages) for initial processes
+                for pair in tag.data.chunks_exact(2) {
+                    if let Some(pid) = PID::new(pair[0] as u8) {
+                        self.set_page_quota(pid, pair[1] as usize);
+                    }
+                }
             }
         }
 
@@ -173,23 +189,71 @@ impl MemoryManager {
         unsafe {
             MEMORY_ALLOCATIONS = slice::from_raw_parts_mut(base as *mut Option<PID>, mem_size)
         };
+
+        // The loader has already handed out pages to the initial processes
+        for owner in unsafe { MEMORY_ALLOCATIONS[..self.ram_size / PAGE_SIZE].iter() } {
+            self.note_owner_change(None, *owner);
+        }
         Ok(())
     }
 
     /// Print the number of RAM bytes used by the specified process.
     /// This does not include memory such as peripherals and CSRs.
-    #[cfg(baremetal)]
+    /// Hosted processes own their memory directly, so this is always 0 there.
     pub fn ram_used_by(&self, pid: PID) -> usize {
-        let mut owned_bytes = 0;
-        #[cfg(baremetal)]
-        unsafe {
-            for owner in &MEMORY_ALLOCATIONS[0..self.ram_size / PAGE_SIZE] {
-                if owner == &Some(pid) {
-                    owned_bytes += PAGE_SIZE;
-                }
-            }
+        self.page_counts
+            .get(pid.get() as usize - 1)
+            .copied()
+            .unwrap_or(0)
+            * PAGE_SIZE
+    }
+
+    /// Move one RAM page from `previous` to `next` in the per-process counts.
+    /// Either may be `None`, for a page that is free.
+    #[cfg(any(baremetal, test))]
+    pub fn note_owner_change(&mut self, previous: Option<PID>, next: Option<PID>) {
+        if previous == next {
+            return;
+        }
+        if let Some(count) =
+            previous.and_then(|pid| self.page_counts.get_mut(pid.get() as usize - 1))
+        {
+            *count = count.saturating_sub(1);
+        }
+        if let Some(count) = next.and_then(|pid| self.page_counts.get_mut(pid.get() as usize - 1)) {
+            *count += 1;
+        }
+    }
+
+    /// Limit the number of RAM pages that the given process may own. A quota
+    /// of 0 removes the limit.
+    pub fn set_page_quota(&mut self, pid: PID, pages: usize) {
+        if let Some(quota) = self.page_quotas.get_mut(pid.get() as usize - 1) {
+            *quota = pages;
+        }
+    }
+
+    /// Return the number of RAM pages that the given process may own, or 0
+    /// if there is no limit.
+    pub fn page_quota(&self, pid: PID) -> usize {
+        self.page_quotas
+            .get(pid.get() as usize - 1)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Ensure that giving `pages` more RAM pages to `pid` keeps it within its
+    /// quota.
+    ///
+    /// # Errors
+    ///
+    /// * **QuotaExceeded**: The process would end up owning too much RAM
+    pub fn check_page_quota(&self, pid: PID, pages: usize) -> Result<(), xous_kernel::Error> {
+        let quota = self.page_quota(pid);
+        if quota != 0 && self.ram_used_by(pid) / PAGE_SIZE + pages > quota {
+            return Err(xous_kernel::Error::QuotaExceeded);
         }
-        owned_bytes
+        Ok(())
     }
 
     #[cfg(all(baremetal, feature = "print-debug"))]
@@ -248,8 +312,15 @@ impl MemoryManager {
 
     /// Allocate a single page to the given process. DOES NOT ZERO THE PAGE!!!
     /// This function CANNOT zero the page, as it hasn't been mapped yet.
+    ///
+    /// # Errors
+    ///
+    /// * **QuotaExceeded**: The process already owns as many pages as it may
+    /// * **OutOfMemory**: There are no free pages left
     #[cfg(baremetal)]
     pub fn alloc_page(&mut self, pid: PID) -> Result<usize, xous_kernel::Error> {
+        self.check_page_quota(pid, 1)?;
+
         // Go through all RAM pages looking for a free page.
         // println!("Allocating page for PID {}", pid);
         unsafe {
@@ -266,6 +337,7 @@ impl MemoryManager {
                 // );
                 if allocation.is_none() {
                     *allocation = Some(pid);
+                    self.note_owner_change(None, Some(pid));
                     self.last_ram_page = index + 1;
                     // if self.last_ram_page >= end_point {
                     //     self.last_ram_page = 0;
@@ -489,6 +561,11 @@ impl MemoryManager {
             return self.reserve_range(virt, size, flags);
         }
 
+        // Main memory counts against the process' quota, while peripherals do not.
+        if self.is_main_memory(phys_ptr) {
+            self.check_page_quota(pid, size / PAGE_SIZE)?;
+        }
+
         // 1. Attempt to claim all physical pages in the range
         for claim_phys in (phys..(phys + size)).step_by(PAGE_SIZE) {
             if let Err(err) = self.claim_page(claim_phys as *mut usize, pid) {
@@ -530,11 +607,35 @@ impl MemoryManager {
     /// * MemoryInUse - The specified page is already mapped
     pub fn unmap_page(&mut self, virt: *mut usize) -> Result<usize, xous_kernel::Error> {
         let pid = crate::arch::process::current_pid();
+        // A stack guard page has nothing behind it, so there is nothing to free.
+        #[cfg(baremetal)]
+        if crate::arch::mem::clear_guard_page(virt as usize) {
+            return Ok(0);
+        }
         let phys = crate::arch::mem::virt_to_phys(virt as usize)?;
         self.release_page(phys as *mut usize, pid)?;
         crate::arch::mem::unmap_page_inner(self, virt as usize)
     }
 
+    /// Release whatever memory backs `virt` in the current process and leave
+    /// an unmapped guard page in its place, so a stack growing down into it
+    /// faults instead of running into the next allocation.
+    #[cfg(baremetal)]
+    pub fn guard_page(&mut self, virt: *mut usize) -> Result<(), xous_kernel::Error> {
+        let pid = crate::arch::process::current_pid();
+        match crate::arch::mem::virt_to_phys(virt as usize) {
+            Ok(phys) => self.release_page(phys as *mut usize, pid)?,
+            // Reserved pages have no memory behind them yet
+            Err(xous_kernel::Error::MemoryInUse) => (),
+            Err(e) => {
+                if !crate::arch::mem::is_guard_page(virt as usize) {
+                    return Err(e);
+                }
+            }
+        }
+        crate::arch::mem::set_guard_page(virt as usize)
+    }
+
     /// Move a page from one process into another, keeping its permissions.
     #[allow(dead_code)]
     pub fn move_page(
@@ -684,7 +785,12 @@ impl MemoryManager {
         // Happy path: The address is in main RAM
         if addr >= self.ram_start && addr < self.ram_start + self.ram_size {
             offset += (addr - self.ram_start) / PAGE_SIZE;
-            return unsafe { action_inner(&mut MEMORY_ALLOCATIONS[offset], pid, action) };
+            let owner = unsafe { &mut MEMORY_ALLOCATIONS[offset] };
+            let previous = *owner;
+            action_inner(owner, pid, action)?;
+            let next = *owner;
+            self.note_owner_change(previous, next);
+            return Ok(());
         }
 
         offset += self.ram_size / PAGE_SIZE;
@@ -767,6 +873,9 @@ impl MemoryManager {
                     // Mark this page as free, which allows it to be re-allocated.
                     *owner = None;
                 }
+                if idx < self.ram_size / PAGE_SIZE {
+                    self.note_owner_change(Some(_pid), *owner);
+                }
             }
         }
     }
@@ -37,6 +37,22 @@ pub struct Server {
     /// this message. If there are no available contexts, then messages will
     /// need to be queued.
     ready_threads: usize,
+
+    /// Messages that have been placed in the queue
+    queued: usize,
+
+    /// The most messages that have been in the queue at once
+    max_depth: usize,
+
+    /// Messages that were refused because the queue was full
+    full: usize,
+
+    /// The queue depth integrated over time, in message-milliseconds. By
+    /// Little's law this is the total time messages have spent waiting.
+    wait_ms: u64,
+
+    /// When the queue depth last changed, as given by `elapsed_ms()`
+    depth_changed_ms: u64,
 }
 
 pub struct SenderID {
@@ -301,6 +317,11 @@ impl Server {
             tail_generation: 0,
             queue,
             ready_threads: 0,
+            queued: 0,
+            max_depth: 0,
+            full: 0,
+            wait_ms: 0,
+            depth_changed_ms: crate::arch::time::elapsed_ms(),
         });
         Ok(())
     }
@@ -489,6 +510,31 @@ impl Server {
     //     }
     // }
 
+    /// The number of messages that are in the queue and have not yet been
+    /// given to the server.
+    fn depth(&self) -> usize {
+        self.tail_generation.wrapping_sub(self.head_generation) as usize
+    }
+
+    /// Add the time that has passed at the current depth to the wait time.
+    /// This must be called before anything changes the depth.
+    fn account_wait_time(&mut self) {
+        let now = crate::arch::time::elapsed_ms();
+        self.wait_ms += self.depth() as u64 * now.saturating_sub(self.depth_changed_ms);
+        self.depth_changed_ms = now;
+    }
+
+    /// Return the queue counters for this server
+    pub fn stats(&self) -> xous_kernel::ServerStats {
+        xous_kernel::ServerStats {
+            queued: self.queued,
+            depth: self.depth(),
+            max_depth: self.max_depth,
+            full: self.full,
+            wait_ms: self.wait_ms,
+        }
+    }
+
     /// When a process terminates, there may be memory that is lent to us.
     /// Mark all of that memory to be discarded when it is returned, rather than
     /// giving it back to the previous process space.
@@ -554,6 +600,19 @@ impl Server {
         }
     }
 
+    /// Return the client thread that is waiting for a reply to the message at
+    /// `message_index`, if that message has been received and not yet answered.
+    pub fn waiting_sender(&self, message_index: usize) -> Option<(PID, TID)> {
+        match *self.queue.get(message_index)? {
+            QueuedMessage::WaitingReturnMemory(pid, tid, _, _, _, _)
+            | QueuedMessage::WaitingForget(pid, tid, _, _, _, _)
+            | QueuedMessage::WaitingReturnScalar(pid, tid, _, _) => {
+                Some((PID::new(pid as _)?, tid as _))
+            }
+            _ => None,
+        }
+    }
+
     /// Convert a `QueuedMesage::WaitingReturnMemory` into `QueuedMessage::Empty`
     /// and return the pair.  Advance the tail.  Note that the `idx` could be
     /// somewhere other than the tail, but as long as it points to a valid
@@ -677,6 +736,7 @@ impl Server {
             // klog!("self.tail_generation {} == self.head_generation {}", self.tail_generation, self.head_generation);
             return None;
         }
+        self.account_wait_time();
 
         use core::convert::TryInto;
         let mut queue_idx = self.queue_tail;
@@ -984,6 +1044,7 @@ impl Server {
         // If the head and the tail generations will end up the same, then
         // the queue is full.
         if self.tail_generation == self.head_generation.wrapping_sub(1) {
+            self.full = self.full.wrapping_add(1);
             return Err(xous_kernel::Error::ServerQueueFull);
         }
 
@@ -1004,9 +1065,11 @@ impl Server {
             }
         }
         if discovered_index.is_none() {
+            self.full = self.full.wrapping_add(1);
             return Err(xous_kernel::Error::ServerQueueFull);
         }
         let queue_idx = discovered_index.unwrap();
+        self.account_wait_time();
         let queue_entry = &mut self.queue[queue_idx];
         *queue_entry = match message {
             xous_kernel::Message::Scalar(msg) => QueuedMessage::ScalarMessage(
@@ -1069,6 +1132,8 @@ impl Server {
         // Advance the tail generation, which is used for incoming messages to keep
         // them in sequence.
         self.tail_generation = self.tail_generation.wrapping_add(1);
+        self.queued = self.queued.wrapping_add(1);
+        self.max_depth = self.max_depth.max(self.depth());
         if queue_idx == self.queue_head {
             self.queue_head += 1;
             if self.queue_head >= self.queue.len() {
@@ -1195,6 +1260,14 @@ impl Server {
         self.ready_threads |= 1 << tid;
     }
 
+    /// Remove the given context from the list of ready and waiting contexts,
+    /// returning `false` if it was not waiting.
+    pub fn unpark_thread(&mut self, tid: TID) -> bool {
+        let parked = self.ready_threads & (1 << tid) != 0;
+        self.ready_threads &= !(1 << tid);
+        parked
+    }
+
     /// Add the given context to the list of ready and waiting contexts.
     pub fn park_thread(&mut self, tid: TID) {
         klog!("parking thread {}", tid);
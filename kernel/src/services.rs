@@ -10,13 +10,35 @@ use xous_kernel::MemoryRange;
 use core::num::NonZeroU8;
 
 use crate::filled_array;
-use crate::server::Server;
+use crate::server::{SenderID, Server};
 // use core::mem;
 use xous_kernel::{
-    pid_from_usize, Error, MemoryAddress, Message, ProcessInit, ThreadInit, CID, PID, SID, TID,
+    pid_from_usize, ChildTerminated, Error, ExitReason, MemoryAddress, Message, MessageEnvelope,
+    MessageId, MessageSender, ProcessInit, ThreadInit, ThreadPriority, CID, PID, SID,
+    THREAD_PRIORITY_DEFAULT, THREAD_PRIORITY_MAX, TID,
 };
 
 const MAX_SERVER_COUNT: usize = 128;
+const MAX_FUTEX_WAITERS: usize = 128;
+const MAX_RECEIVE_DEADLINES: usize = 64;
+const MAX_SHARED_MEMORY: usize = 32;
+
+/// How long a wakeup that nobody was waiting for is kept around in hosted
+/// mode. A thread that read the old value before the wakeup will have made
+/// its `WaitForAddress` call long before this.
+const PENDING_WAKE_MS: u64 = 1000;
+
+/// The number of distinct thread priority levels
+const PRIORITY_LEVELS: usize = THREAD_PRIORITY_MAX as usize + 1;
+
+/// Every thread begins at the default priority level.
+const DEFAULT_PRIORITY_MASKS: [usize; PRIORITY_LEVELS] = default_priority_masks();
+
+const fn default_priority_masks() -> [usize; PRIORITY_LEVELS] {
+    let mut masks = [0usize; PRIORITY_LEVELS];
+    masks[THREAD_PRIORITY_DEFAULT as usize] = usize::MAX;
+    masks
+}
 
 pub use crate::arch::process::{INITIAL_TID, MAX_PROCESS_COUNT};
 
@@ -29,6 +51,15 @@ pub struct ExceptionHandler {
     pub sp: usize,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Supervisor {
+    /// Server that is told when a child process terminates
+    pub sid: SID,
+
+    /// Message ID of the notification
+    pub id: MessageId,
+}
+
 // fn log_process_update(f: &str, l: u32, process: &Process, old_state: ProcessState) {
 //     if process.pid.get() == 3 {
 //         println!("[{}:{}] Updated PID {:?} state: {:?} -> {:?}", f, l, process.pid, old_state, process.state);
@@ -43,6 +74,88 @@ pub struct SystemServices {
 
     /// A table of all servers in the system
     servers: [Option<Server>; MAX_SERVER_COUNT],
+
+    /// Threads that are blocked in `WaitForAddress`
+    futex_waiters: [Option<FutexWaiter>; MAX_FUTEX_WAITERS],
+
+    /// Server threads that are blocked in `ReceiveMessageTimeout`
+    receive_deadlines: [Option<ReceiveDeadline>; MAX_RECEIVE_DEADLINES],
+
+    /// Ranges of memory that are mapped into two processes at once
+    shared_memory: [Option<SharedMemory>; MAX_SHARED_MEMORY],
+}
+
+/// A range of pages that has been mapped into a second process with
+/// `ShareMemory`. The pages stay owned by `owner`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SharedMemory {
+    /// The process that the pages belong to
+    pub owner: PID,
+
+    /// Where the pages live in the owner's address space
+    pub owner_virt: usize,
+
+    /// The process that the pages were shared with
+    pub peer: PID,
+
+    /// Where the pages were mapped in the peer's address space
+    pub peer_virt: usize,
+
+    /// The length of the region, in bytes
+    pub len: usize,
+}
+
+impl SharedMemory {
+    /// If `pid` is part of this region and sees it at `virt`, return the
+    /// process on the other side.
+    fn other_side(&self, pid: PID, virt: usize) -> Option<PID> {
+        if self.owner == pid && self.owner_virt == virt {
+            Some(self.peer)
+        } else if self.peer == pid && self.peer_virt == virt {
+            Some(self.owner)
+        } else {
+            None
+        }
+    }
+
+    /// Determine whether the range `virt..virt+len` in `pid` overlaps this region.
+    #[cfg(any(baremetal, test))]
+    fn overlaps(&self, pid: PID, virt: usize, len: usize) -> bool {
+        (self.owner == pid && virt < self.owner_virt + self.len && self.owner_virt < virt + len)
+            || (self.peer == pid && virt < self.peer_virt + self.len && self.peer_virt < virt + len)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FutexWaiter {
+    /// Thread `tid` of process `pid` is asleep until somebody wakes `addr`,
+    /// or until the kernel clock passes `deadline`.
+    Waiting {
+        pid: PID,
+        tid: TID,
+        addr: usize,
+        deadline: Option<u64>,
+    },
+
+    /// A wakeup for `addr` arrived when nobody was waiting on it. In hosted
+    /// mode the kernel can't read the address to make sure it still holds
+    /// the expected value, so a thread that was about to go to sleep on
+    /// `addr` consumes this instead of missing the wakeup. It is forgotten
+    /// once the kernel clock passes `expires`, or sooner if a thread needs
+    /// the slot to go to sleep.
+    Pending { pid: PID, addr: usize, expires: u64 },
+}
+
+/// Thread `tid` of process `pid` is parked on server `sidx`, and should be
+/// woken with `Error::Timeout` once the kernel clock passes `deadline`. The
+/// entry is left behind if a message arrives first, so it is only acted on
+/// if the thread is still parked.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ReceiveDeadline {
+    pid: PID,
+    tid: TID,
+    sidx: usize,
+    deadline: u64,
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -85,6 +198,36 @@ pub enum ProcessState {
     BlockedException(usize),
 }
 
+impl ProcessState {
+    /// Convert this into the state that is reported to userspace
+    pub fn status(&self) -> xous_kernel::ProcessStatus {
+        use xous_kernel::ProcessStatus;
+        match *self {
+            ProcessState::Free => ProcessStatus::Unknown,
+            ProcessState::Allocated => ProcessStatus::Allocated,
+            ProcessState::Setup(_) => ProcessStatus::Setup,
+            ProcessState::Ready(_) => ProcessStatus::Ready,
+            ProcessState::Running(_) => ProcessStatus::Running,
+            ProcessState::Sleeping => ProcessStatus::Sleeping,
+            ProcessState::Debug(_) => ProcessStatus::Debug,
+            ProcessState::Exception(_) => ProcessStatus::Exception,
+            ProcessState::BlockedException(_) => ProcessStatus::BlockedException,
+        }
+    }
+
+    /// Return the bitmask of threads that are ready to run
+    pub fn ready_threads(&self) -> usize {
+        match *self {
+            ProcessState::Ready(x)
+            | ProcessState::Running(x)
+            | ProcessState::Debug(x)
+            | ProcessState::Exception(x)
+            | ProcessState::BlockedException(x) => x,
+            _ => 0,
+        }
+    }
+}
+
 impl core::fmt::Debug for ProcessState {
     fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
         use ProcessState::*;
@@ -134,6 +277,21 @@ pub struct Process {
 
     /// When an exception is hit, the kernel will switch to this Thread.
     exception_handler: Option<ExceptionHandler>,
+
+    /// A bitmask of thread IDs for each priority level. This mirrors
+    /// `ProcessInner::thread_priority` so that the scheduler can compare
+    /// processes without switching address spaces.
+    thread_priority_masks: [usize; PRIORITY_LEVELS],
+
+    /// How long each thread has run for, and how often it has been switched to.
+    /// This lives outside of `ProcessInner` so it may be read for any process.
+    cpu_time: [xous_kernel::CpuTime; arch::process::MAX_THREAD + 1],
+
+    /// Where to report the termination of processes this one created.
+    supervisor: Option<Supervisor>,
+
+    /// How many processes have had this PID before, modulo 256
+    generation: u8,
 }
 
 impl Default for Process {
@@ -176,6 +334,9 @@ pub struct ProcessInner {
     /// A mapping of connection IDs to server indexes
     pub connection_map: [Option<NonZeroU8>; 32],
 
+    /// The scheduling priority of each thread
+    pub thread_priority: [ThreadPriority; 32],
+
     /// A copy of this process' ID
     pub pid: PID,
 
@@ -194,6 +355,7 @@ impl Default for ProcessInner {
             mem_heap_size: 0,
             mem_heap_max: 524_288,
             connection_map: [None; 32],
+            thread_priority: [THREAD_PRIORITY_DEFAULT; 32],
             pid: unsafe { PID::new_unchecked(1) },
             _reserved: [0; 1],
         }
@@ -201,19 +363,41 @@ impl Default for ProcessInner {
 }
 
 impl Process {
-    /// This process has at least one context that may be run
-    pub fn runnable(&self) -> bool {
-        matches!(
-            self.state,
-            ProcessState::Setup(_) | ProcessState::Ready(_) | ProcessState::Exception(_)
-        )
-    }
-
     /// This process slot is unallocated and may be turn into a process
     pub fn free(&self) -> bool {
         matches!(self.state, ProcessState::Free)
     }
 
+    /// If this process has at least one context that may be run, return
+    /// the priority of the context that would be run next.
+    pub fn runnable_priority(&self) -> Option<ThreadPriority> {
+        match self.state {
+            ProcessState::Setup(_) => Some(THREAD_PRIORITY_DEFAULT),
+            ProcessState::Ready(x) => highest_priority(&self.thread_priority_masks, x),
+            // Exception handlers run ahead of everything else so that a faulting
+            // process is dealt with promptly.
+            ProcessState::Exception(_) => Some(THREAD_PRIORITY_MAX),
+            _ => None,
+        }
+    }
+
+    /// Pick the next thread to run out of the bitmask `ready_threads`. The
+    /// highest-priority thread is chosen, with threads of equal priority
+    /// being picked round-robin starting after `previous`.
+    pub fn next_ready_thread(&self, ready_threads: usize, previous: TID) -> Option<TID> {
+        next_thread_by_priority(&self.thread_priority_masks, ready_threads, previous)
+    }
+
+    fn set_priority_mask(&mut self, tid: TID, priority: ThreadPriority) {
+        for (level, mask) in self.thread_priority_masks.iter_mut().enumerate() {
+            if level == priority as usize {
+                *mask |= 1 << tid;
+            } else {
+                *mask &= !(1 << tid);
+            }
+        }
+    }
+
     pub fn activate(&self) -> Result<(), xous_kernel::Error> {
         crate::arch::process::set_current_pid(self.pid);
         self.mapping.activate()?;
@@ -237,10 +421,60 @@ impl Process {
         // Remove this PID from the process table
         ArchProcess::destroy(self.pid)?;
         self.state = ProcessState::Free;
+        self.thread_priority_masks = DEFAULT_PRIORITY_MASKS;
+        self.generation = self.generation.wrapping_add(1);
         Ok(())
     }
 }
 
+/// Return the highest priority of any thread in the `threads` bitmask.
+pub fn highest_priority(
+    priority_masks: &[usize; PRIORITY_LEVELS],
+    threads: usize,
+) -> Option<ThreadPriority> {
+    priority_masks
+        .iter()
+        .rposition(|mask| mask & threads != 0)
+        .map(|level| level as ThreadPriority)
+}
+
+/// Ensure a thread stack at `addr` of `len` bytes leaves room for a guard
+/// page below the part the thread uses.
+#[cfg(any(baremetal, test))]
+pub fn check_thread_stack(addr: usize, len: usize) -> Result<(), xous_kernel::Error> {
+    if addr & (crate::mem::PAGE_SIZE - 1) != 0 {
+        return Err(xous_kernel::Error::BadAlignment);
+    }
+    if len <= crate::mem::PAGE_SIZE {
+        return Err(xous_kernel::Error::BadAddress);
+    }
+    Ok(())
+}
+
+/// Select the highest-priority thread out of `threads`. If several threads
+/// share that priority, the first one after `previous` is returned so that
+/// they take turns.
+pub fn next_thread_by_priority(
+    priority_masks: &[usize; PRIORITY_LEVELS],
+    threads: usize,
+    previous: TID,
+) -> Option<TID> {
+    let priority = highest_priority(priority_masks, threads)?;
+    let candidates = threads & priority_masks[priority as usize];
+    let mut tid = previous;
+    for _ in 0..=arch::process::MAX_THREAD {
+        tid = if tid >= arch::process::MAX_THREAD {
+            0
+        } else {
+            tid + 1
+        };
+        if candidates & (1 << tid) != 0 {
+            return Some(tid);
+        }
+    }
+    None
+}
+
 #[cfg(not(baremetal))]
 std::thread_local!(static SYSTEM_SERVICES: core::cell::RefCell<SystemServices> = core::cell::RefCell::new(SystemServices {
     processes: [Process {
@@ -251,10 +485,17 @@ std::thread_local!(static SYSTEM_SERVICES: core::cell::RefCell<SystemServices> =
         current_thread: 0_usize,
         previous_thread: INITIAL_TID as TID,
         exception_handler: None,
+        thread_priority_masks: DEFAULT_PRIORITY_MASKS,
+        cpu_time: [xous_kernel::CpuTime { run_ms: 0, switches: 0 }; arch::process::MAX_THREAD + 1],
+        supervisor: None,
+        generation: 0,
     }; MAX_PROCESS_COUNT],
     // Note we can't use MAX_SERVER_COUNT here because of how Rust's
     // macro tokenization works
     servers: filled_array![None; 128],
+    futex_waiters: [None; MAX_FUTEX_WAITERS],
+    receive_deadlines: [None; MAX_RECEIVE_DEADLINES],
+    shared_memory: [None; MAX_SHARED_MEMORY],
 }));
 
 #[cfg(baremetal)]
@@ -267,10 +508,17 @@ static mut SYSTEM_SERVICES: SystemServices = SystemServices {
         current_thread: 0_usize,
         previous_thread: INITIAL_TID as TID,
         exception_handler: None,
+        thread_priority_masks: DEFAULT_PRIORITY_MASKS,
+        cpu_time: [xous_kernel::CpuTime { run_ms: 0, switches: 0 }; arch::process::MAX_THREAD + 1],
+        supervisor: None,
+        generation: 0,
     }; MAX_PROCESS_COUNT],
     // Note we can't use MAX_SERVER_COUNT here because of how Rust's
     // macro tokenization works
     servers: filled_array![None; 128],
+    futex_waiters: [None; MAX_FUTEX_WAITERS],
+    receive_deadlines: [None; MAX_RECEIVE_DEADLINES],
+    shared_memory: [None; MAX_SHARED_MEMORY],
 };
 
 impl core::fmt::Debug for Process {
@@ -396,6 +644,12 @@ impl SystemServices {
             entry.state = ProcessState::Allocated;
             entry.ppid = ppid;
             entry.pid = new_pid;
+            entry.thread_priority_masks = DEFAULT_PRIORITY_MASKS;
+            entry.cpu_time = [Default::default(); arch::process::MAX_THREAD + 1];
+            entry.supervisor = None;
+            crate::mem::MemoryManager::with_mut(|mm| {
+                mm.set_page_quota(new_pid, init_process.page_quota)
+            });
             return Ok(new_pid);
         }
         Err(xous_kernel::Error::ProcessNotFound)
@@ -719,17 +973,9 @@ impl SystemServices {
             }
             ProcessState::Ready(x) => {
                 let new_thread = match tid {
-                    None => {
-                        let mut new_context = 0;
-
-                        while x & (1 << new_context) == 0 {
-                            new_context += 1;
-                            if new_context > arch::process::MAX_THREAD {
-                                new_context = 0;
-                            }
-                        }
-                        new_context
-                    }
+                    None => process
+                        .next_ready_thread(x, process.current_thread)
+                        .ok_or(xous_kernel::Error::InvalidThread)?,
                     Some(ctx) => {
                         // Ensure the specified context is ready to run
                         if x & (1 << ctx) == 0 {
@@ -760,17 +1006,9 @@ impl SystemServices {
                 let mut p = ArchProcess::current();
                 // let current_thread = p.current_thread();
                 let new_thread = match tid {
-                    None => {
-                        let mut new_thread = 0;
-
-                        while ready_threads & (1 << new_thread) == 0 {
-                            new_thread += 1;
-                            if new_thread > arch::process::MAX_THREAD {
-                                new_thread = 0;
-                            }
-                        }
-                        new_thread
-                    }
+                    None => process
+                        .next_ready_thread(ready_threads, process.current_thread)
+                        .ok_or(xous_kernel::Error::InvalidThread)?,
                     Some(tid) => {
                         // Ensure the specified context is ready to run, or is
                         // currently running.
@@ -933,26 +1171,18 @@ impl SystemServices {
                     // new.current_thread = new_tid;
                 }
                 ProcessState::Running(x) | ProcessState::Ready(x) => {
-                    // If no new context is specified, take the previous
-                    // context.  If that is not runnable, do a round-robin
-                    // search for the next available context.
+                    // If no new context is specified, pick the highest-priority
+                    // context that is ready, going round-robin between contexts
+                    // that have the same priority.
                     assert!(
                         x != 0,
                         "process was {:?} but had no free contexts",
                         new.state
                     );
                     if new_tid == 0 {
-                        new_tid = (new.current_thread + 1) as usize;
-                        while x & (1 << new_tid) == 0 {
-                            new_tid += 1;
-                            if new_tid > arch::process::MAX_THREAD {
-                                new_tid = 0;
-                            } else if new_tid == (new.current_thread + 1) as usize {
-                                // If we've looped around, return an error.
-                                // println!("Looked through all contexts and couldn't find one that was ready");
-                                return Err(xous_kernel::Error::ProcessNotFound);
-                            }
-                        }
+                        new_tid = new
+                            .next_ready_thread(x, new.current_thread)
+                            .ok_or(xous_kernel::Error::ProcessNotFound)?;
                         new.current_thread = new_tid as _;
                         klog!("picked thread ID {}", new_tid);
                     } else if x & (1 << new_tid) == 0 {
@@ -1083,20 +1313,13 @@ impl SystemServices {
             // let old_state = new.state;
             new.state = if let ProcessState::Running(x) = new.state {
                 let previous_tid = new.current_thread;
-                // If no new thread is specified, take the previous
-                // thread.  If that is not runnable, do a round-robin
-                // search for the next available thread.
+                // If no new thread is specified, pick the highest-priority
+                // thread that is ready, going round-robin between threads
+                // that have the same priority.
                 if new_tid == 0 {
-                    new_tid = (new.current_thread + 1) as usize;
-                    while x & (1 << new_tid) == 0 {
-                        new_tid += 1;
-                        if new_tid > arch::process::MAX_THREAD {
-                            new_tid = 0;
-                        } else if new_tid == (new.current_thread + 1) as usize {
-                            // If we've looped around, return an error.
-                            return Err(xous_kernel::Error::ProcessNotFound);
-                        }
-                    }
+                    new_tid = new
+                        .next_ready_thread(x, new.current_thread)
+                        .ok_or(xous_kernel::Error::ProcessNotFound)?;
                     new.current_thread = new_tid as _;
                 } else if x & (1 << new_tid) == 0 {
                     return Err(xous_kernel::Error::ProcessNotFound);
@@ -1375,6 +1598,214 @@ impl SystemServices {
         Ok(src_virt)
     }
 
+    /// Map `len` bytes at `src_virt` in the current process into `dest_pid`
+    /// as well, so both processes see the same pages until the region is
+    /// unshared or either process terminates.
+    ///
+    /// # Returns
+    ///
+    /// Returns the virtual address of the region in the target process.
+    ///
+    /// # Errors
+    ///
+    /// * **ShareViolation**: Part of the range is already lent or shared
+    /// * **BadAddress**: The provided address was not valid
+    /// * **BadAlignment**: The provided address or length was not page-aligned
+    /// * **OutOfMemory**: The table of shared regions is full
+    #[cfg(baremetal)]
+    pub fn share_memory(
+        &mut self,
+        src_virt: *mut u8,
+        dest_pid: PID,
+        len: usize,
+        writable: bool,
+    ) -> Result<*mut u8, xous_kernel::Error> {
+        let current_pid = self.current_pid();
+        let slot = self.check_share_memory(current_pid, src_virt, dest_pid, len)?;
+
+        let src_mapping = self.get_process(current_pid)?.mapping;
+        let dest_mapping = self.get_process(dest_pid)?.mapping;
+        use crate::mem::MemoryManager;
+        let dest_virt = MemoryManager::with_mut(|mm| {
+            // Every page must exist in this process before it can be shared.
+            for offset in (0..len).step_by(crate::mem::PAGE_SIZE) {
+                mm.ensure_page_exists(src_virt as usize + offset)?;
+            }
+
+            dest_mapping.activate()?;
+            let dest_virt = mm
+                .find_virtual_address(core::ptr::null_mut(), len, xous_kernel::MemoryType::Messages);
+            src_mapping.activate().unwrap();
+            let dest_virt = dest_virt?;
+
+            for offset in (0..len).step_by(crate::mem::PAGE_SIZE) {
+                if let Err(e) = crate::arch::mem::share_page_inner(
+                    mm,
+                    &src_mapping,
+                    src_virt.wrapping_add(offset),
+                    dest_pid,
+                    &dest_mapping,
+                    dest_virt.wrapping_add(offset),
+                    writable,
+                ) {
+                    // Undo the pages that were already shared.
+                    for undo in (0..offset).step_by(crate::mem::PAGE_SIZE) {
+                        crate::arch::mem::unshare_page_inner(
+                            &src_mapping,
+                            src_virt.wrapping_add(undo),
+                            &dest_mapping,
+                            dest_virt.wrapping_add(undo),
+                        )
+                        .ok();
+                    }
+                    src_mapping.activate().unwrap();
+                    return Err(e);
+                }
+            }
+            Ok(dest_virt)
+        })?;
+
+        self.shared_memory[slot] = Some(SharedMemory {
+            owner: current_pid,
+            owner_virt: src_virt as usize,
+            peer: dest_pid,
+            peer_virt: dest_virt as usize,
+            len,
+        });
+        Ok(dest_virt)
+    }
+
+    /// Hosted processes each have their own address space, and memory only
+    /// crosses between them as a copy inside a message, so there are no pages
+    /// that both sides could see.
+    #[cfg(all(not(baremetal), not(test)))]
+    pub fn share_memory(
+        &mut self,
+        _src_virt: *mut u8,
+        _dest_pid: PID,
+        _len: usize,
+        _writable: bool,
+    ) -> Result<*mut u8, xous_kernel::Error> {
+        Err(xous_kernel::Error::UnhandledSyscall)
+    }
+
+    /// Test processes are threads that share an address space, so the region
+    /// is merely recorded and the peer sees it at the same address.
+    #[cfg(all(not(baremetal), test))]
+    pub fn share_memory(
+        &mut self,
+        src_virt: *mut u8,
+        dest_pid: PID,
+        len: usize,
+        _writable: bool,
+    ) -> Result<*mut u8, xous_kernel::Error> {
+        let current_pid = self.current_pid();
+        let slot = self.check_share_memory(current_pid, src_virt, dest_pid, len)?;
+        self.shared_memory[slot] = Some(SharedMemory {
+            owner: current_pid,
+            owner_virt: src_virt as usize,
+            peer: dest_pid,
+            peer_virt: src_virt as usize,
+            len,
+        });
+        Ok(src_virt)
+    }
+
+    /// Ensure a region may be shared, and return a free slot to record it in.
+    #[cfg(any(baremetal, test))]
+    fn check_share_memory(
+        &self,
+        src_pid: PID,
+        src_virt: *mut u8,
+        dest_pid: PID,
+        len: usize,
+    ) -> Result<usize, xous_kernel::Error> {
+        if len == 0 {
+            return Err(xous_kernel::Error::BadAddress);
+        }
+        if len & 0xfff != 0 || src_virt as usize & 0xfff != 0 {
+            return Err(xous_kernel::Error::BadAlignment);
+        }
+        if src_pid == dest_pid {
+            return Err(xous_kernel::Error::ShareViolation);
+        }
+        if self
+            .shared_memory
+            .iter()
+            .flatten()
+            .any(|region| region.overlaps(src_pid, src_virt as usize, len))
+        {
+            return Err(xous_kernel::Error::ShareViolation);
+        }
+        self.shared_memory
+            .iter()
+            .position(|region| region.is_none())
+            .ok_or(xous_kernel::Error::OutOfMemory)
+    }
+
+    /// Tear down the shared region that `pid` sees at `virt`. Either side of
+    /// the region may do this.
+    ///
+    /// # Errors
+    ///
+    /// * **BadAddress**: No region is shared at that address
+    pub fn unshare_memory(&mut self, pid: PID, virt: usize) -> Result<(), xous_kernel::Error> {
+        let slot = self
+            .shared_memory
+            .iter()
+            .position(|region| {
+                region
+                    .map(|region| region.other_side(pid, virt).is_some())
+                    .unwrap_or(false)
+            })
+            .ok_or(xous_kernel::Error::BadAddress)?;
+        let region = self.shared_memory[slot].take().unwrap();
+        self.unmap_shared_memory(&region)
+    }
+
+    /// Return the process on the other side of the region that `pid` sees at `virt`.
+    pub fn shared_memory_peer(&self, pid: PID, virt: usize) -> Result<PID, xous_kernel::Error> {
+        self.shared_memory
+            .iter()
+            .flatten()
+            .find_map(|region| region.other_side(pid, virt))
+            .ok_or(xous_kernel::Error::BadAddress)
+    }
+
+    /// Tear down every shared region that `pid` is part of.
+    fn release_shared_memory(&mut self, pid: PID) -> Result<(), xous_kernel::Error> {
+        for slot in 0..self.shared_memory.len() {
+            if let Some(region) = self.shared_memory[slot] {
+                if region.owner == pid || region.peer == pid {
+                    self.shared_memory[slot] = None;
+                    self.unmap_shared_memory(&region)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(baremetal)]
+    fn unmap_shared_memory(&mut self, region: &SharedMemory) -> Result<(), xous_kernel::Error> {
+        let current_pid = self.current_pid();
+        let owner_mapping = self.get_process(region.owner)?.mapping;
+        let peer_mapping = self.get_process(region.peer)?.mapping;
+        for offset in (0..region.len).step_by(crate::mem::PAGE_SIZE) {
+            crate::arch::mem::unshare_page_inner(
+                &owner_mapping,
+                (region.owner_virt + offset) as *mut u8,
+                &peer_mapping,
+                (region.peer_virt + offset) as *mut u8,
+            )?;
+        }
+        self.get_process(current_pid)?.mapping.activate()
+    }
+
+    #[cfg(not(baremetal))]
+    fn unmap_shared_memory(&mut self, _region: &SharedMemory) -> Result<(), xous_kernel::Error> {
+        Ok(())
+    }
+
     /// Return memory from one process back to another
     ///
     /// During this process, memory is unmapped from the source process.
@@ -1498,10 +1929,17 @@ impl SystemServices {
     /// The return address of this thread will be `EXIT_THREAD`, which the
     /// kernel can trap on to indicate a thread exited.
     ///
+    /// On real hardware the lowest page of the stack is unmapped and becomes
+    /// a guard page, so a thread that overflows its stack faults with
+    /// `StackOverflow` rather than corrupting whatever lies below it.
+    ///
     /// # Errors
     ///
     /// * **ThreadNotAvailable**: The process has used all of its context
     ///   slots.
+    /// * **BadAlignment**: The stack does not start on a page boundary
+    /// * **BadAddress**: The stack is not mapped, or is not larger than
+    ///   one page
     pub fn create_thread(
         &mut self,
         pid: PID,
@@ -1515,8 +1953,29 @@ impl SystemServices {
             .find_free_thread()
             .ok_or(xous_kernel::Error::ThreadNotAvailable)?;
 
+        #[cfg(baremetal)]
+        {
+            let stack = thread_init.stack;
+            check_thread_stack(stack.as_ptr() as usize, stack.len())?;
+            crate::mem::MemoryManager::with_mut(|mm| {
+                mm.guard_page(stack.as_mut_ptr() as *mut usize)
+            })?;
+        }
+
         arch_process.setup_thread(new_tid, thread_init)?;
 
+        // New threads always start out at the default priority, regardless
+        // of what the previous occupant of this slot was set to. Hosted mode
+        // may hand out one thread ID past `MAX_THREAD`, which always stays at
+        // the default priority.
+        if new_tid <= arch::process::MAX_THREAD {
+            ArchProcess::with_inner_mut(|process_inner| {
+                process_inner.thread_priority[new_tid] = THREAD_PRIORITY_DEFAULT
+            });
+            process.set_priority_mask(new_tid, THREAD_PRIORITY_DEFAULT);
+            process.cpu_time[new_tid] = Default::default();
+        }
+
         // println!("KERNEL({}): Created new thread {}", pid, new_tid);
 
         // let old_state = process.state;
@@ -2024,7 +2483,11 @@ impl SystemServices {
     // }
 
     /// Terminate the given process. Returns the process' parent PID.
-    pub fn terminate_process(&mut self, target_pid: PID) -> Result<PID, xous_kernel::Error> {
+    pub fn terminate_process(
+        &mut self,
+        target_pid: PID,
+        reason: ExitReason,
+    ) -> Result<PID, xous_kernel::Error> {
         // To terminate a process, we must perform the following:
         //
         // 1. If we have any client connections, remove them.
@@ -2076,6 +2539,37 @@ impl SystemServices {
             }
         }
 
+        // Forget about any threads that were waiting on an address.
+        for waiter in self.futex_waiters.iter_mut() {
+            match waiter {
+                Some(FutexWaiter::Waiting { pid, .. }) | Some(FutexWaiter::Pending { pid, .. })
+                    if *pid == target_pid =>
+                {
+                    *waiter = None
+                }
+                _ => (),
+            }
+        }
+
+        // Forget about any threads that were waiting for a message.
+        for deadline in self.receive_deadlines.iter_mut() {
+            if deadline.map(|d| d.pid) == Some(target_pid) {
+                *deadline = None;
+            }
+        }
+
+        // Unmap any memory this process shares with another, so neither side
+        // is left pointing at pages that are about to be freed.
+        self.release_shared_memory(target_pid)?;
+
+        // Let the parent know, while the process still exists to be the sender.
+        // This is only a courtesy: the teardown has begun and must finish, so
+        // a supervisor that can't take the message (say, because its queue is
+        // full) simply misses out.
+        if let Err(_e) = self.notify_supervisor(target_pid, reason) {
+            klog!("couldn't notify supervisor that {} stopped: {:?}", target_pid, _e);
+        }
+
         let process = self.get_process_mut(target_pid)?;
         process.activate()?;
         let parent_pid = process.ppid;
@@ -2195,6 +2689,61 @@ impl SystemServices {
         Ok(())
     }
 
+    /// Makes `sid` the supervisor of every process that `pid` creates. The
+    /// server must belong to `pid`.
+    pub fn set_supervisor(
+        &mut self,
+        pid: PID,
+        sid: SID,
+        id: MessageId,
+    ) -> Result<(), xous_kernel::Error> {
+        self.sidx_from_sid(sid, pid)
+            .ok_or(xous_kernel::Error::ServerNotFound)?;
+        self.get_process_mut(pid)?.supervisor = Some(Supervisor { sid, id });
+        Ok(())
+    }
+
+    /// If the parent of `pid` has a supervisor, send it a `ChildTerminated`
+    /// message. This borrows the address space of `pid`, so it must be called
+    /// before the process is torn down.
+    fn notify_supervisor(&mut self, pid: PID, reason: ExitReason) -> Result<(), xous_kernel::Error> {
+        let ppid = self.get_process(pid)?.ppid;
+        let supervisor = match self.processes.get(ppid.get() as usize - 1) {
+            Some(parent) if !parent.free() => parent.supervisor,
+            _ => None,
+        };
+        let supervisor = match supervisor {
+            Some(supervisor) => supervisor,
+            None => return Ok(()),
+        };
+
+        // The supervisor server may have been destroyed since it was registered.
+        let sidx = match self.sidx_from_sid(supervisor.sid, ppid) {
+            Some(sidx) => sidx,
+            None => return Ok(()),
+        };
+        let message = ChildTerminated { pid, reason }.to_message(supervisor.id);
+        klog!("notifying supervisor of {} that {} stopped: {:?}", ppid, pid, reason);
+
+        let server = self
+            .server_from_sidx_mut(sidx)
+            .expect("couldn't re-discover server index");
+        if let Some(server_tid) = server.take_available_thread() {
+            let envelope = MessageEnvelope {
+                sender: crate::server::SenderID::new(sidx, 0, Some(pid)).into(),
+                body: message,
+            };
+            self.ready_thread(ppid, server_tid)?;
+            self.clear_receive_deadline(ppid, server_tid);
+            #[cfg(not(baremetal))]
+            self.switch_to_thread(ppid, Some(server_tid))?;
+            self.set_thread_result(ppid, server_tid, xous_kernel::Result::Message(envelope))
+        } else {
+            self.queue_server_message(sidx, pid, 0, message, None)
+                .map(|_| ())
+        }
+    }
+
     /// Causes the provided process to go into an exception state. This will fail
     /// if any of the following are true:
     ///     1. The process does not exist
@@ -2234,6 +2783,448 @@ impl SystemServices {
         Ok(())
     }
 
+    /// Check that `pid` may look into `target`. A process may always look
+    /// into itself, but only PID 1 and the processes of the boot image, which
+    /// it starts, may look into others.
+    ///
+    /// # Errors
+    ///
+    /// * **AccessDenied**: `pid` may not look into other processes
+    pub fn check_inspect(&self, pid: PID, target: PID) -> Result<(), xous_kernel::Error> {
+        if pid == target || pid.get() == 1 || self.get_process(pid)?.ppid.get() == 1 {
+            Ok(())
+        } else {
+            Err(xous_kernel::Error::AccessDenied)
+        }
+    }
+
+    /// Gather information about the first allocated process whose PID is
+    /// `first_pid` or greater.
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: No process exists at or after `first_pid`
+    pub fn process_info(
+        &self,
+        first_pid: PID,
+    ) -> Result<xous_kernel::ProcessInfo, xous_kernel::Error> {
+        let process = self
+            .processes
+            .get(first_pid.get() as usize - 1..)
+            .unwrap_or(&[])
+            .iter()
+            .find(|process| !process.free())
+            .ok_or(xous_kernel::Error::ProcessNotFound)?;
+        let pid = process.pid;
+
+        // Thread and connection information lives in the process itself, so
+        // switch to it for a moment.
+        let current_pid = self.current_pid();
+        process.activate()?;
+        let threads = ArchProcess::current().thread_count();
+        let connections = ArchProcess::with_inner(|process_inner| {
+            // Entries of 1 are tombstones for servers that have gone away.
+            process_inner
+                .connection_map
+                .iter()
+                .flatten()
+                .filter(|mapping| mapping.get() > 1)
+                .count()
+        });
+        self.get_process(current_pid)?.activate()?;
+
+        Ok(xous_kernel::ProcessInfo {
+            pid,
+            ppid: process.ppid,
+            generation: process.generation,
+            status: process.state.status(),
+            ready_threads: process.state.ready_threads(),
+            threads,
+            ram_bytes: crate::mem::MemoryManager::with(|mm| mm.ram_used_by(pid)),
+            servers: self.servers.iter().flatten().filter(|s| s.pid == pid).count(),
+            connections,
+            ram_quota: crate::mem::MemoryManager::with(|mm| mm.page_quota(pid))
+                * crate::mem::PAGE_SIZE,
+        })
+    }
+
+    /// Add `run_ms` to the time thread `tid` of `pid` has spent running. The
+    /// architecture decides how time is attributed, and passes `switched` if
+    /// the kernel switched to this thread at the start of that period.
+    pub fn charge_cpu_time(&mut self, pid: PID, tid: TID, run_ms: u64, switched: bool) {
+        if let Some(cpu_time) = self
+            .processes
+            .get_mut(pid.get() as usize - 1)
+            .and_then(|process| process.cpu_time.get_mut(tid))
+        {
+            cpu_time.run_ms += run_ms;
+            if switched {
+                cpu_time.switches += 1;
+            }
+        }
+    }
+
+    /// Return the CPU time used by thread `tid` of `pid`.
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: The process does not exist
+    /// * **ThreadNotAvailable**: The thread ID is out of range
+    pub fn thread_cpu_time(
+        &self,
+        pid: PID,
+        tid: TID,
+    ) -> Result<xous_kernel::CpuTime, xous_kernel::Error> {
+        let process = self
+            .processes
+            .get(pid.get() as usize - 1)
+            .filter(|process| !process.free())
+            .ok_or(xous_kernel::Error::ProcessNotFound)?;
+        process
+            .cpu_time
+            .get(tid)
+            .copied()
+            .ok_or(xous_kernel::Error::ThreadNotAvailable)
+    }
+
+    /// Return the CPU time used by all threads of `pid` put together.
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: The process does not exist
+    pub fn process_cpu_time(&self, pid: PID) -> Result<xous_kernel::CpuTime, xous_kernel::Error> {
+        let process = self
+            .processes
+            .get(pid.get() as usize - 1)
+            .filter(|process| !process.free())
+            .ok_or(xous_kernel::Error::ProcessNotFound)?;
+        Ok(process
+            .cpu_time
+            .iter()
+            .fold(xous_kernel::CpuTime::default(), |total, thread| {
+                xous_kernel::CpuTime {
+                    run_ms: total.run_ms + thread.run_ms,
+                    switches: total.switches + thread.switches,
+                }
+            }))
+    }
+
+    /// Return the queue counters of the server that `pid` reaches through
+    /// `cid`. Only the server's owner and the owner's parent may look.
+    ///
+    /// # Errors
+    ///
+    /// * **ServerNotFound**: The connection is not valid
+    /// * **AccessDenied**: `pid` is not allowed to inspect this server
+    pub fn server_stats(
+        &self,
+        pid: PID,
+        cid: CID,
+    ) -> Result<xous_kernel::ServerStats, xous_kernel::Error> {
+        let server = self
+            .sidx_from_cid(cid)
+            .and_then(|sidx| self.server_from_sidx(sidx))
+            .ok_or(xous_kernel::Error::ServerNotFound)?;
+        if server.pid != pid && self.get_process(server.pid)?.ppid != pid {
+            return Err(xous_kernel::Error::AccessDenied);
+        }
+        Ok(server.stats())
+    }
+
+    /// Return the thread that sent `sender`, a blocking message that `pid`
+    /// has received and not yet replied to.
+    ///
+    /// # Errors
+    ///
+    /// * **ServerNotFound**: The message wasn't sent to one of `pid`'s servers
+    /// * **BadAddress**: No thread is waiting for a reply to this message
+    pub fn sender_thread(
+        &self,
+        pid: PID,
+        sender: MessageSender,
+    ) -> Result<TID, xous_kernel::Error> {
+        let sender_id = SenderID::from(sender);
+        let server = self
+            .server_from_sidx(sender_id.sidx)
+            .filter(|server| server.pid == pid)
+            .ok_or(xous_kernel::Error::ServerNotFound)?;
+        match server.waiting_sender(sender_id.idx) {
+            Some((client_pid, tid)) if Some(client_pid) == sender.pid() => Ok(tid),
+            _ => Err(xous_kernel::Error::BadAddress),
+        }
+    }
+
+    /// Record that thread `tid` of `pid` is about to go to sleep waiting on
+    /// `addr`. Returns `false` if a wakeup for this address was already
+    /// pending, in which case the thread should not go to sleep at all.
+    ///
+    /// # Errors
+    ///
+    /// * **OutOfMemory**: There is no room left in the waiter table
+    pub fn futex_wait(
+        &mut self,
+        pid: PID,
+        tid: TID,
+        addr: usize,
+        deadline: Option<u64>,
+    ) -> Result<bool, xous_kernel::Error> {
+        if let Some(idx) = self.pending_wake(pid, addr) {
+            self.futex_waiters[idx] = None;
+            return Ok(false);
+        }
+        // A pending wakeup is only there in case somebody turns up for it,
+        // so it gives way to a thread that actually has to sleep.
+        let idx = self
+            .futex_waiters
+            .iter()
+            .position(|waiter| waiter.is_none())
+            .or_else(|| {
+                self.futex_waiters
+                    .iter()
+                    .position(|waiter| matches!(waiter, Some(FutexWaiter::Pending { .. })))
+            })
+            .ok_or(xous_kernel::Error::OutOfMemory)?;
+        self.futex_waiters[idx] = Some(FutexWaiter::Waiting {
+            pid,
+            tid,
+            addr,
+            deadline,
+        });
+        Ok(true)
+    }
+
+    /// Wake up to `count` threads of `pid` that are waiting on `addr`,
+    /// returning the number of threads that were woken.
+    pub fn futex_wake(
+        &mut self,
+        pid: PID,
+        addr: usize,
+        count: usize,
+    ) -> Result<usize, xous_kernel::Error> {
+        let mut woken = 0;
+        for idx in 0..self.futex_waiters.len() {
+            if woken >= count {
+                break;
+            }
+            if let Some(FutexWaiter::Waiting {
+                pid: waiter_pid,
+                tid,
+                addr: waiter_addr,
+                ..
+            }) = self.futex_waiters[idx]
+            {
+                if waiter_pid != pid || waiter_addr != addr {
+                    continue;
+                }
+                self.futex_waiters[idx] = None;
+                self.wake_blocked_thread(pid, tid, xous_kernel::Result::Ok)?;
+                woken += 1;
+            }
+        }
+
+        // If nobody was waiting, a thread may be on its way into
+        // `WaitForAddress` with a value it read before this wakeup. The kernel
+        // can check the value itself on baremetal, but not in hosted mode.
+        if !cfg!(baremetal) && woken == 0 && count > 0 {
+            let idx = self
+                .pending_wake(pid, addr)
+                .or_else(|| self.futex_waiters.iter().position(|w| w.is_none()));
+            if let Some(idx) = idx {
+                self.futex_waiters[idx] = Some(FutexWaiter::Pending {
+                    pid,
+                    addr,
+                    expires: crate::arch::time::elapsed_ms() + PENDING_WAKE_MS,
+                });
+            }
+        }
+        Ok(woken)
+    }
+
+    /// Find the wakeup that arrived for `addr` in `pid` while nobody was
+    /// waiting on it, if there is one.
+    fn pending_wake(&self, pid: PID, addr: usize) -> Option<usize> {
+        self.futex_waiters.iter().position(|waiter| match waiter {
+            Some(FutexWaiter::Pending {
+                pid: p, addr: a, ..
+            }) => *p == pid && *a == addr,
+            _ => false,
+        })
+    }
+
+    /// Wake every thread whose `WaitForAddress` deadline has passed. Those
+    /// threads return `Error::Timeout`. Pending wakeups that have gone stale
+    /// are dropped as well.
+    pub fn futex_expire(&mut self, now: u64) {
+        for idx in 0..self.futex_waiters.len() {
+            match self.futex_waiters[idx] {
+                Some(FutexWaiter::Waiting {
+                    pid,
+                    tid,
+                    deadline: Some(deadline),
+                    ..
+                }) if deadline <= now => {
+                    self.futex_waiters[idx] = None;
+                    self.wake_blocked_thread(
+                        pid,
+                        tid,
+                        xous_kernel::Result::Error(xous_kernel::Error::Timeout),
+                    )
+                    .ok();
+                }
+                Some(FutexWaiter::Pending { expires, .. }) if expires <= now => {
+                    self.futex_waiters[idx] = None;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Remember that thread `tid` of `pid`, which is about to be parked on
+    /// server `sidx`, should stop waiting at `deadline`.
+    ///
+    /// # Errors
+    ///
+    /// * **OutOfMemory**: There is no room to record another deadline
+    pub fn set_receive_deadline(
+        &mut self,
+        pid: PID,
+        tid: TID,
+        sidx: usize,
+        deadline: u64,
+    ) -> Result<(), xous_kernel::Error> {
+        let slot = self
+            .receive_deadlines
+            .iter_mut()
+            .find(|entry| entry.is_none())
+            .ok_or(xous_kernel::Error::OutOfMemory)?;
+        *slot = Some(ReceiveDeadline {
+            pid,
+            tid,
+            sidx,
+            deadline,
+        });
+        Ok(())
+    }
+
+    /// Forget any deadline left over from a previous receive by this thread.
+    pub fn clear_receive_deadline(&mut self, pid: PID, tid: TID) {
+        for entry in self.receive_deadlines.iter_mut() {
+            if entry.map(|d| (d.pid, d.tid)) == Some((pid, tid)) {
+                *entry = None;
+            }
+        }
+    }
+
+    /// Wake every server thread whose `ReceiveMessageTimeout` deadline has
+    /// passed without a message arriving. Those threads return `Error::Timeout`.
+    pub fn receive_expire(&mut self, now: u64) {
+        for idx in 0..self.receive_deadlines.len() {
+            let entry = match self.receive_deadlines[idx] {
+                Some(entry) if entry.deadline <= now => entry,
+                _ => continue,
+            };
+            self.receive_deadlines[idx] = None;
+
+            // If a message already arrived, the thread is no longer parked.
+            let still_parked = match self.servers.get_mut(entry.sidx) {
+                Some(Some(server)) if server.pid == entry.pid => server.unpark_thread(entry.tid),
+                _ => false,
+            };
+            if still_parked {
+                self.wake_blocked_thread(
+                    entry.pid,
+                    entry.tid,
+                    xous_kernel::Result::Error(xous_kernel::Error::Timeout),
+                )
+                .ok();
+            }
+        }
+    }
+
+    /// Wake every thread whose timeout has passed.
+    pub fn expire_deadlines(&mut self, now: u64) {
+        self.futex_expire(now);
+        self.receive_expire(now);
+    }
+
+    /// Return the earliest deadline of any thread waiting with a timeout.
+    pub fn next_deadline(&self) -> Option<u64> {
+        let next_receive = self
+            .receive_deadlines
+            .iter()
+            .filter_map(|entry| entry.map(|d| d.deadline))
+            .min();
+        match (self.next_futex_deadline(), next_receive) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Return the earliest deadline of any thread waiting on an address.
+    pub fn next_futex_deadline(&self) -> Option<u64> {
+        self.futex_waiters
+            .iter()
+            .filter_map(|waiter| match waiter {
+                Some(FutexWaiter::Waiting { deadline, .. }) => *deadline,
+                _ => None,
+            })
+            .min()
+    }
+
+    fn wake_blocked_thread(
+        &mut self,
+        pid: PID,
+        tid: TID,
+        result: xous_kernel::Result,
+    ) -> Result<(), xous_kernel::Error> {
+        self.set_thread_result(pid, tid, result)?;
+        if cfg!(baremetal) {
+            self.ready_thread(pid, tid)?;
+        }
+        Ok(())
+    }
+
+    /// Set the scheduling priority of a thread in the given process. The
+    /// process must be the one that is currently active.
+    ///
+    /// # Errors
+    ///
+    /// * **InvalidThread**: The thread ID is out of range
+    /// * **InvalidSyscall**: The priority is greater than `THREAD_PRIORITY_MAX`
+    pub fn set_thread_priority(
+        &mut self,
+        pid: PID,
+        tid: TID,
+        priority: ThreadPriority,
+    ) -> Result<(), xous_kernel::Error> {
+        if tid == 0 || tid > arch::process::MAX_THREAD {
+            return Err(xous_kernel::Error::InvalidThread);
+        }
+        if priority > THREAD_PRIORITY_MAX {
+            return Err(xous_kernel::Error::InvalidSyscall);
+        }
+        let process = self.get_process_mut(pid)?;
+        ArchProcess::with_inner_mut(|process_inner| process_inner.thread_priority[tid] = priority);
+        process.set_priority_mask(tid, priority);
+        Ok(())
+    }
+
+    /// Return the scheduling priority of a thread in the given process. The
+    /// process must be the one that is currently active.
+    ///
+    /// # Errors
+    ///
+    /// * **InvalidThread**: The thread ID is out of range
+    pub fn thread_priority(&self, pid: PID, tid: TID) -> Result<ThreadPriority, xous_kernel::Error> {
+        if tid == 0 || tid > arch::process::MAX_THREAD {
+            return Err(xous_kernel::Error::InvalidThread);
+        }
+        self.get_process(pid)?;
+        Ok(ArchProcess::with_inner(|process_inner| {
+            process_inner.thread_priority[tid]
+        }))
+    }
+
     /// Returns the process name, if any, of a given PID
     #[cfg(baremetal)]
     pub fn process_name(&self, pid: PID) -> Option<&str> {
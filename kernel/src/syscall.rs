@@ -91,6 +91,11 @@ fn send_message(pid: PID, thread: TID, cid: CID, message: Message) -> SysCallRes
         let message = match message {
             Message::Scalar(_) | Message::BlockingScalar(_) => message,
             Message::Move(msg) => {
+                // Moved memory becomes the server's, so it counts against its quota
+                if server_pid != pid {
+                    let pages = (msg.buf.len() + PAGE_SIZE - 1) / PAGE_SIZE;
+                    MemoryManager::with(|mm| mm.check_page_quota(server_pid, pages))?;
+                }
                 let new_virt = ss.send_memory(
                     msg.buf.as_mut_ptr() as *mut usize,
                     server_pid,
@@ -186,6 +191,7 @@ fn send_message(pid: PID, thread: TID, cid: CID, message: Message) -> SysCallRes
                     .return_available_thread(server_tid);
                 e
             })?;
+            ss.clear_receive_deadline(server_pid, server_tid);
 
             let runnable = ss
                 .runnable(server_pid, Some(server_tid))
@@ -538,7 +544,13 @@ fn return_scalar2(
     })
 }
 
-fn receive_message(pid: PID, tid: TID, sid: SID, blocking: ExecutionType) -> SysCallResult {
+fn receive_message(
+    pid: PID,
+    tid: TID,
+    sid: SID,
+    blocking: ExecutionType,
+    deadline: Option<u64>,
+) -> SysCallResult {
     SystemServices::with_mut(|ss| {
         assert!(
             ss.thread_is_running(pid, tid),
@@ -548,6 +560,7 @@ fn receive_message(pid: PID, tid: TID, sid: SID, blocking: ExecutionType) -> Sys
         let sidx = ss
             .sidx_from_sid(sid, pid)
             .ok_or(xous_kernel::Error::ServerNotFound)?;
+        ss.clear_receive_deadline(pid, tid);
         let server = ss
             .server_from_sidx_mut(sidx)
             .ok_or(xous_kernel::Error::ServerNotFound)?;
@@ -569,6 +582,15 @@ fn receive_message(pid: PID, tid: TID, sid: SID, blocking: ExecutionType) -> Sys
             return Ok(xous_kernel::Result::None);
         }
 
+        // If there is a deadline, note it so the thread can be woken up
+        // with `Timeout` if nothing arrives in time.
+        if let Some(deadline) = deadline {
+            if deadline <= arch::time::elapsed_ms() {
+                return Err(xous_kernel::Error::Timeout);
+            }
+            ss.set_receive_deadline(pid, tid, sidx, deadline)?;
+        }
+
         // There is no pending message, so return control to the parent
         // process and mark ourselves as awaiting an event.  When a message
         // arrives, our return value will already be set to the
@@ -577,7 +599,9 @@ fn receive_message(pid: PID, tid: TID, sid: SID, blocking: ExecutionType) -> Sys
             "did not have any waiting messages -- parking thread {}",
             tid
         );
-        server.park_thread(tid);
+        ss.server_from_sidx_mut(sidx)
+            .ok_or(xous_kernel::Error::ServerNotFound)?
+            .park_thread(tid);
 
         // For baremetal targets, switch away from this process.
         if cfg!(baremetal) {
@@ -597,6 +621,91 @@ fn receive_message(pid: PID, tid: TID, sid: SID, blocking: ExecutionType) -> Sys
     })
 }
 
+fn wait_for_address(
+    pid: PID,
+    tid: TID,
+    addr: MemoryAddress,
+    expected: usize,
+    timeout_ms: usize,
+) -> SysCallResult {
+    let addr = addr.get();
+    if addr & (mem::size_of::<usize>() - 1) != 0 {
+        return Err(xous_kernel::Error::BadAlignment);
+    }
+
+    // On baremetal the caller's address space is active, so compare the
+    // value here. Because nothing else can run while the kernel is handling
+    // this call, a wakeup can't sneak in between this check and going to
+    // sleep. Hosted processes don't share memory with the kernel, so the
+    // comparison is done by the caller instead.
+    #[cfg(baremetal)]
+    {
+        if addr >= arch::mem::USER_AREA_END {
+            return Err(xous_kernel::Error::BadAddress);
+        }
+        if arch::mem::peek_memory(addr as *mut usize)? != expected {
+            return Ok(xous_kernel::Result::Ok);
+        }
+    }
+    #[cfg(not(baremetal))]
+    let _ = expected;
+
+    SystemServices::with_mut(|ss| {
+        let deadline = if timeout_ms == 0 {
+            None
+        } else {
+            Some(arch::time::elapsed_ms() + timeout_ms as u64)
+        };
+        if !ss.futex_wait(pid, tid, addr, deadline)? {
+            return Ok(xous_kernel::Result::Ok);
+        }
+
+        // For baremetal targets, switch away from this process.
+        if cfg!(baremetal) {
+            unsafe { SWITCHTO_CALLER = None };
+            let ppid = ss.get_process(pid).expect("Can't get current process").ppid;
+            ss.activate_process_thread(tid, ppid, 0, false)
+                .map(|_| Ok(xous_kernel::Result::ResumeProcess))
+                .unwrap_or(Err(xous_kernel::Error::ProcessNotFound))
+        }
+        // For hosted targets, simply return `BlockedProcess` indicating we'll make
+        // a callback to their socket at a later time.
+        else {
+            ss.unschedule_thread(pid, tid)
+                .map(|_| xous_kernel::Result::BlockedProcess)
+        }
+    })
+}
+
+fn get_process_name(target_pid: PID, buf: MemoryAddress, len: usize) -> SysCallResult {
+    SystemServices::with(|ss| {
+        if ss.get_process(target_pid)?.free() {
+            return Err(xous_kernel::Error::ProcessNotFound);
+        }
+
+        // The kernel only knows the names of processes that were started by
+        // the loader, and those only exist on baremetal.
+        #[cfg(baremetal)]
+        {
+            let buf = buf.get();
+            if buf.checked_add(len).map(|end| end > arch::mem::USER_AREA_END) != Some(false) {
+                return Err(xous_kernel::Error::BadAddress);
+            }
+            let name = ss.process_name(target_pid).unwrap_or("");
+            let copy_len = name.len().min(len);
+            for (offset, byte) in name.as_bytes()[..copy_len].iter().enumerate() {
+                arch::mem::poke_memory((buf + offset) as *mut u8, *byte)?;
+            }
+            Ok(xous_kernel::Result::Scalar1(copy_len))
+        }
+        #[cfg(not(baremetal))]
+        {
+            let _ = (buf, len);
+            Ok(xous_kernel::Result::Scalar1(0))
+        }
+    })
+}
+
 pub fn handle(pid: PID, tid: TID, in_irq: bool, call: SysCall) -> SysCallResult {
     #[cfg(feature = "debug-print")]
     print!("KERNEL({}:{}): Syscall {:x?}", pid, tid, call);
@@ -775,9 +884,15 @@ pub fn handle_inner(pid: PID, tid: TID, in_irq: bool, call: SysCall) -> SysCallR
             };
             Ok(xous_kernel::Result::ResumeProcess)
         }
-        SysCall::ReceiveMessage(sid) => receive_message(pid, tid, sid, ExecutionType::Blocking),
+        SysCall::ReceiveMessage(sid) => {
+            receive_message(pid, tid, sid, ExecutionType::Blocking, None)
+        }
         SysCall::TryReceiveMessage(sid) => {
-            receive_message(pid, tid, sid, ExecutionType::NonBlocking)
+            receive_message(pid, tid, sid, ExecutionType::NonBlocking, None)
+        }
+        SysCall::ReceiveMessageTimeout(sid, timeout_ms) => {
+            let deadline = arch::time::elapsed_ms() + timeout_ms as u64;
+            receive_message(pid, tid, sid, ExecutionType::Blocking, Some(deadline))
         }
         SysCall::WaitEvent => SystemServices::with_mut(|ss| {
             let process = ss.get_process(pid).expect("Can't get current process");
@@ -828,9 +943,9 @@ pub fn handle_inner(pid: PID, tid: TID, in_irq: bool, call: SysCall) -> SysCallR
             return_scalar2(pid, tid, in_irq, sender, arg1, arg2)
         }
         SysCall::TrySendMessage(cid, message) => send_message(pid, tid, cid, message),
-        SysCall::TerminateProcess(_ret) => SystemServices::with_mut(|ss| {
+        SysCall::TerminateProcess(exit_code) => SystemServices::with_mut(|ss| {
             ss.unschedule_thread(pid, tid)?;
-            ss.terminate_process(pid)?;
+            ss.terminate_process(pid, xous_kernel::ExitReason::Exited(exit_code))?;
             // Clear out `SWITCHTO_CALLER` since we're resuming the parent process.
             unsafe { SWITCHTO_CALLER = None };
             Ok(xous_kernel::Result::ResumeProcess)
@@ -888,6 +1003,86 @@ pub fn handle_inner(pid: PID, tid: TID, in_irq: bool, call: SysCall) -> SysCallR
             ss.set_exception_handler(pid, pc, sp)
                 .and(Ok(xous_kernel::Result::Ok))
         }),
+        SysCall::SetThreadPriority(target_tid, priority) => SystemServices::with_mut(|ss| {
+            ss.set_thread_priority(pid, target_tid, priority)
+                .and(Ok(xous_kernel::Result::Ok))
+        }),
+        SysCall::GetThreadPriority(target_tid) => SystemServices::with(|ss| {
+            ss.thread_priority(pid, target_tid)
+                .map(|priority| xous_kernel::Result::Scalar1(priority as usize))
+        }),
+        SysCall::WaitForAddress(addr, expected, timeout_ms) => {
+            wait_for_address(pid, tid, addr, expected, timeout_ms)
+        }
+        SysCall::WakeAddress(addr, count) => SystemServices::with_mut(|ss| {
+            ss.futex_wake(pid, addr.get(), count)
+                .map(xous_kernel::Result::Scalar1)
+        }),
+        SysCall::GetProcessInfo(first_pid) => SystemServices::with(|ss| {
+            ss.check_inspect(pid, first_pid)?;
+            ss.process_info(first_pid).map(|info| {
+                let [a1, a2, a3, a4, a5, a6, a7] = info.to_args();
+                xous_kernel::Result::ProcessInfo(a1, a2, a3, a4, a5, a6, a7)
+            })
+        }),
+        SysCall::GetProcessName(target_pid, buf, len) => {
+            SystemServices::with(|ss| ss.check_inspect(pid, target_pid))?;
+            get_process_name(target_pid, buf, len)
+        }
+        SysCall::GetThreadCpuTime(target_pid, target_tid) => SystemServices::with(|ss| {
+            ss.check_inspect(pid, target_pid)?;
+            ss.thread_cpu_time(target_pid, target_tid).map(|cpu_time| {
+                let [a1, a2, a3] = cpu_time.to_args();
+                xous_kernel::Result::CpuTime(a1, a2, a3)
+            })
+        }),
+        SysCall::GetProcessCpuTime(target_pid) => SystemServices::with(|ss| {
+            ss.check_inspect(pid, target_pid)?;
+            ss.process_cpu_time(target_pid).map(|cpu_time| {
+                let [a1, a2, a3] = cpu_time.to_args();
+                xous_kernel::Result::CpuTime(a1, a2, a3)
+            })
+        }),
+        SysCall::SetSupervisor(sid, id) => SystemServices::with_mut(|ss| {
+            ss.set_supervisor(pid, sid, id)
+                .and(Ok(xous_kernel::Result::Ok))
+        }),
+        SysCall::ShareMemory(cid, range, flags) => SystemServices::with_mut(|ss| {
+            let sidx = ss
+                .sidx_from_cid(cid)
+                .ok_or(xous_kernel::Error::ServerNotFound)?;
+            let server_pid = ss
+                .server_from_sidx(sidx)
+                .ok_or(xous_kernel::Error::ServerNotFound)?
+                .pid;
+            let virt = ss.share_memory(
+                range.as_mut_ptr(),
+                server_pid,
+                range.len(),
+                flags.contains(MemoryFlags::W),
+            )?;
+            Ok(xous_kernel::Result::MemoryRange(unsafe {
+                MemoryRange::new(virt as usize, range.len())?
+            }))
+        }),
+        SysCall::UnshareMemory(range) => SystemServices::with_mut(|ss| {
+            ss.unshare_memory(pid, range.as_ptr() as usize)
+                .and(Ok(xous_kernel::Result::Ok))
+        }),
+        SysCall::GetSharedMemoryPeer(range) => SystemServices::with(|ss| {
+            ss.shared_memory_peer(pid, range.as_ptr() as usize)
+                .map(xous_kernel::Result::ProcessID)
+        }),
+        SysCall::GetServerStats(cid) => SystemServices::with(|ss| {
+            ss.server_stats(pid, cid).map(|stats| {
+                let [a1, a2, a3, a4, a5, a6] = stats.to_args();
+                xous_kernel::Result::ServerStats(a1, a2, a3, a4, a5, a6)
+            })
+        }),
+        SysCall::GetSenderThread(sender) => SystemServices::with(|ss| {
+            ss.sender_thread(pid, sender)
+                .map(xous_kernel::Result::ThreadID)
+        }),
         _ => Err(xous_kernel::Error::UnhandledSyscall),
     }
 }
@@ -754,6 +754,75 @@ fn send_move_message() {
     main_thread.join().expect("couldn't join kernel process");
 }
 
+#[test]
+fn try_send_move_message_queue_full() {
+    let test_bytes = b"name changed";
+
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let (server_addr_send, server_addr_recv) = unbounded();
+    let (queued_send, queued_recv) = unbounded();
+
+    let xous_server = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "try_send_move_message_queue_full server",
+        move || {
+            let sid = xous_kernel::create_server().expect("couldn't create test server");
+            server_addr_send.send(sid).unwrap();
+
+            // Don't receive anything until the client has filled the queue
+            let queued: usize = queued_recv.recv().unwrap();
+            for _ in 0..queued {
+                let envelope =
+                    xous_kernel::receive_message(sid).expect("couldn't receive messages");
+                if let xous_kernel::Message::Move(m) = envelope.body {
+                    let bt =
+                        unsafe { core::slice::from_raw_parts(m.buf.as_ptr(), test_bytes.len()) };
+                    assert_eq!(*test_bytes, *bt, "message was changed by the kernel");
+                } else {
+                    panic!("unexpected message type");
+                }
+            }
+            // The refused message was never queued
+            assert_eq!(
+                xous_kernel::try_receive_message(sid).map(|m| m.is_some()),
+                Ok(false)
+            );
+        },
+    ))
+    .expect("couldn't start server");
+
+    let xous_client = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "try_send_move_message_queue_full client",
+        move || {
+            let sid = server_addr_recv.recv().unwrap();
+            let conn = xous_kernel::try_connect(sid).expect("couldn't connect to server");
+            let mut queued = 0;
+            let err = loop {
+                let msg = xous_kernel::carton::Carton::from_bytes(test_bytes);
+                match xous_kernel::try_send_message(
+                    conn,
+                    xous_kernel::Message::Move(msg.into_message(0)),
+                ) {
+                    Ok(_) => queued += 1,
+                    Err(e) => break e,
+                }
+            };
+            // The send returns straight away rather than waiting for the server
+            assert_eq!(err, xous_kernel::Error::ServerQueueFull);
+            assert!(queued > 0);
+            queued_send.send(queued).unwrap();
+        },
+    ))
+    .expect("couldn't start client");
+
+    crate::wait_process_as_thread(xous_client).expect("couldn't join client process");
+    crate::wait_process_as_thread(xous_server).expect("couldn't join server process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
 #[test]
 fn send_borrow_message() {
     let main_thread = start_kernel(SERVER_SPEC);
@@ -1170,3 +1239,1113 @@ fn process_restart_server() {
 
     main_thread.join().expect("couldn't join kernel process");
 }
+
+/// Test that threads can change and query their scheduling priority
+#[test]
+fn thread_priority() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let priority_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("thread_priority process", move || {
+            let tid = xous_kernel::current_tid().expect("couldn't get current tid");
+            assert_eq!(
+                xous_kernel::thread_priority(tid).expect("couldn't get priority"),
+                xous_kernel::THREAD_PRIORITY_DEFAULT
+            );
+
+            xous_kernel::set_thread_priority(tid, xous_kernel::THREAD_PRIORITY_MAX)
+                .expect("couldn't set priority");
+            assert_eq!(
+                xous_kernel::thread_priority(tid).expect("couldn't get priority"),
+                xous_kernel::THREAD_PRIORITY_MAX
+            );
+
+            // Priorities above the maximum are rejected without changing anything
+            assert_eq!(
+                xous_kernel::set_thread_priority(tid, xous_kernel::THREAD_PRIORITY_MAX + 1),
+                Err(xous_kernel::Error::InvalidSyscall)
+            );
+            assert_eq!(
+                xous_kernel::thread_priority(tid).expect("couldn't get priority"),
+                xous_kernel::THREAD_PRIORITY_MAX
+            );
+
+            // Thread IDs outside of the thread table are rejected
+            assert_eq!(
+                xous_kernel::thread_priority(crate::arch::process::MAX_THREAD + 1),
+                Err(xous_kernel::Error::InvalidThread)
+            );
+            assert_eq!(
+                xous_kernel::set_thread_priority(0, xous_kernel::THREAD_PRIORITY_IDLE),
+                Err(xous_kernel::Error::InvalidThread)
+            );
+        }),
+    )
+    .expect("couldn't create priority process");
+
+    xous_kernel::wait_process_as_thread(priority_process)
+        .expect("couldn't join priority process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Test that the highest-priority thread is picked, with ties broken round-robin
+#[test]
+fn thread_priority_selection() {
+    use crate::services::next_thread_by_priority;
+    let mut masks = [0usize; xous_kernel::THREAD_PRIORITY_MAX as usize + 1];
+    masks[xous_kernel::THREAD_PRIORITY_DEFAULT as usize] = usize::MAX;
+
+    // With equal priorities, threads take turns
+    let ready = (1 << 2) | (1 << 3) | (1 << 5);
+    assert_eq!(next_thread_by_priority(&masks, ready, 2), Some(3));
+    assert_eq!(next_thread_by_priority(&masks, ready, 3), Some(5));
+    assert_eq!(next_thread_by_priority(&masks, ready, 5), Some(2));
+
+    // Raise thread 5 above everything else
+    masks[xous_kernel::THREAD_PRIORITY_DEFAULT as usize] &= !(1 << 5);
+    masks[xous_kernel::THREAD_PRIORITY_MAX as usize] |= 1 << 5;
+    assert_eq!(next_thread_by_priority(&masks, ready, 5), Some(5));
+    assert_eq!(next_thread_by_priority(&masks, ready, 2), Some(5));
+
+    // Once it is no longer ready, the remaining threads resume
+    assert_eq!(next_thread_by_priority(&masks, ready & !(1 << 5), 2), Some(3));
+
+    // Nothing ready means nothing to run
+    assert_eq!(next_thread_by_priority(&masks, 0, 2), None);
+}
+
+#[test]
+fn thread_stack_checks() {
+    use crate::mem::PAGE_SIZE;
+    use crate::services::check_thread_stack;
+    use xous_kernel::Error;
+
+    // The lowest page becomes the guard page, so there has to be more than one
+    assert_eq!(check_thread_stack(0x4000_0000, 2 * PAGE_SIZE), Ok(()));
+    assert_eq!(check_thread_stack(0x4000_0000, 32 * PAGE_SIZE + 1), Ok(()));
+    assert_eq!(check_thread_stack(0x4000_0000, PAGE_SIZE + 1), Ok(()));
+    assert_eq!(
+        check_thread_stack(0x4000_0000, PAGE_SIZE),
+        Err(Error::BadAddress)
+    );
+    assert_eq!(check_thread_stack(0x4000_0000, 16), Err(Error::BadAddress));
+
+    // The guard page has to be a whole page
+    assert_eq!(
+        check_thread_stack(0x4000_0010, 2 * PAGE_SIZE),
+        Err(Error::BadAlignment)
+    );
+    assert_eq!(
+        check_thread_stack(0x4000_0000 + PAGE_SIZE / 2, 2 * PAGE_SIZE),
+        Err(Error::BadAlignment)
+    );
+}
+
+/// Test that a thread waiting on an address is woken by another thread
+#[test]
+fn wait_for_address() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let futex_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("wait_for_address process", move || {
+            let value = Arc::new(AtomicUsize::new(0));
+            let waiter_value = value.clone();
+            let waiter = xous_kernel::create_thread(move || {
+                while waiter_value.load(Ordering::SeqCst) == 0 {
+                    xous_kernel::wait_for_address(&waiter_value, 0, None)
+                        .expect("couldn't wait for address");
+                }
+                assert_eq!(waiter_value.load(Ordering::SeqCst), 42);
+            })
+            .expect("couldn't spawn waiter thread");
+
+            value.store(42, Ordering::SeqCst);
+            xous_kernel::wake_address(&value, 1).expect("couldn't wake address");
+            xous_kernel::wait_thread(waiter).expect("couldn't join waiter thread");
+
+            // Nobody is waiting any more, so there is nothing to wake
+            assert_eq!(
+                xous_kernel::wake_address(&AtomicUsize::new(0), 1)
+                    .expect("couldn't wake address"),
+                0
+            );
+        }),
+    )
+    .expect("couldn't create futex process");
+
+    xous_kernel::wait_process_as_thread(futex_process).expect("couldn't join futex process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Test that waiting on an address gives up once the timeout expires
+#[test]
+fn wait_for_address_timeout() {
+    use std::sync::atomic::AtomicUsize;
+
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let futex_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("wait_for_address_timeout process", move || {
+            let value = AtomicUsize::new(0);
+            let start = std::time::Instant::now();
+            assert_eq!(
+                xous_kernel::wait_for_address(&value, 0, Some(50)),
+                Err(xous_kernel::Error::Timeout)
+            );
+            assert!(start.elapsed() >= std::time::Duration::from_millis(50));
+
+            // If the value doesn't match, the call returns immediately
+            xous_kernel::wait_for_address(&value, 1, None).expect("couldn't wait for address");
+        }),
+    )
+    .expect("couldn't create futex process");
+
+    xous_kernel::wait_process_as_thread(futex_process).expect("couldn't join futex process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Test that the table of waiting threads doesn't fill up with wakeups that
+/// nobody was waiting for, or with waits that are over
+#[test]
+fn wait_for_address_cycles() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // More than the kernel has room to track at once
+    const CYCLES: usize = 200;
+
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let futex_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("wait_for_address_cycles process", move || {
+            let unwatched: Vec<AtomicUsize> = (0..CYCLES).map(|_| AtomicUsize::new(0)).collect();
+            for value in &unwatched {
+                assert_eq!(
+                    xous_kernel::wake_address(value, 1).expect("couldn't wake address"),
+                    0
+                );
+            }
+
+            let value = AtomicUsize::new(0);
+            for _ in 0..CYCLES {
+                assert_eq!(
+                    xous_kernel::wait_for_address(&value, 0, Some(1)),
+                    Err(xous_kernel::Error::Timeout)
+                );
+            }
+
+            // Take turns with another thread, each waiting for the other to
+            // move its counter on
+            let ping = Arc::new(AtomicUsize::new(0));
+            let pong = Arc::new(AtomicUsize::new(0));
+            let (their_ping, their_pong) = (ping.clone(), pong.clone());
+            let waiter = xous_kernel::create_thread(move || {
+                for cycle in 1..=CYCLES {
+                    while their_ping.load(Ordering::SeqCst) < cycle {
+                        xous_kernel::wait_for_address(&their_ping, cycle - 1, None)
+                            .expect("couldn't wait for address");
+                    }
+                    their_pong.store(cycle, Ordering::SeqCst);
+                    xous_kernel::wake_address(&their_pong, 1).expect("couldn't wake address");
+                }
+            })
+            .expect("couldn't spawn waiter thread");
+            for cycle in 1..=CYCLES {
+                ping.store(cycle, Ordering::SeqCst);
+                xous_kernel::wake_address(&ping, 1).expect("couldn't wake address");
+                while pong.load(Ordering::SeqCst) < cycle {
+                    xous_kernel::wait_for_address(&pong, cycle - 1, None)
+                        .expect("couldn't wait for address");
+                }
+            }
+            xous_kernel::wait_thread(waiter).expect("couldn't join waiter thread");
+        }),
+    )
+    .expect("couldn't create futex process");
+
+    xous_kernel::wait_process_as_thread(futex_process).expect("couldn't join futex process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Test that processes can be enumerated and inspected
+#[test]
+fn process_info() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let info_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("process_info process", move || {
+            let pid = xous_kernel::current_pid().expect("couldn't get current pid");
+            let info = xous_kernel::process_info(pid).expect("couldn't get process info");
+            assert_eq!(info.pid, pid);
+            assert_eq!(info.status, xous_kernel::ProcessStatus::Running);
+            assert!(info.threads >= 1);
+            assert_eq!(info.servers, 0);
+
+            let server = xous_kernel::create_server().expect("couldn't create server");
+            xous_kernel::try_connect(server).expect("couldn't connect to our own server");
+            let info = xous_kernel::process_info(pid).expect("couldn't get process info");
+            assert_eq!(info.servers, 1);
+            assert_eq!(info.connections, 1);
+
+            // Walking the table from PID 1 must find this process
+            let mut next_pid = xous_kernel::PID::new(1);
+            let mut found = false;
+            while let Some(first_pid) = next_pid {
+                let info = match xous_kernel::process_info(first_pid) {
+                    Ok(info) => info,
+                    Err(xous_kernel::Error::ProcessNotFound) => break,
+                    Err(e) => panic!("unexpected error {:?}", e),
+                };
+                found |= info.pid == pid;
+                next_pid = xous_kernel::PID::new(info.pid.get().wrapping_add(1));
+            }
+            assert!(found);
+
+            assert_eq!(
+                xous_kernel::process_info(xous_kernel::PID::new(255).unwrap()),
+                Err(xous_kernel::Error::ProcessNotFound)
+            );
+        }),
+    )
+    .expect("couldn't create info process");
+
+    xous_kernel::wait_process_as_thread(info_process).expect("couldn't join info process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Test that a server can learn which thread sent it a blocking message
+#[test]
+fn sender_thread() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let (server_addr_send, server_addr_recv) = unbounded();
+
+    let xous_server = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "sender_thread server",
+        move || {
+            let sid = xous_kernel::create_server().expect("couldn't create test server");
+            server_addr_send.send(sid).unwrap();
+
+            let envelope = xous_kernel::receive_message(sid).expect("couldn't receive message");
+            let tid = xous_kernel::sender_thread(envelope.sender).expect("couldn't get sender");
+            xous_kernel::return_scalar(envelope.sender, tid).expect("couldn't return scalar");
+
+            // Once answered, the sender no longer refers to a waiting thread
+            assert_eq!(
+                xous_kernel::sender_thread(envelope.sender),
+                Err(xous_kernel::Error::BadAddress)
+            );
+        },
+    ))
+    .expect("couldn't spawn server process");
+
+    let xous_client = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "sender_thread client",
+        move || {
+            let sid = server_addr_recv.recv().unwrap();
+            let conn = xous_kernel::try_connect(sid).expect("couldn't connect to server");
+            let result = xous_kernel::try_send_message(
+                conn,
+                xous_kernel::Message::BlockingScalar(xous_kernel::ScalarMessage {
+                    id: 1,
+                    arg1: 0,
+                    arg2: 0,
+                    arg3: 0,
+                    arg4: 0,
+                }),
+            )
+            .expect("couldn't send message");
+            let tid = xous_kernel::current_tid().expect("couldn't get current tid");
+            assert_eq!(result, xous_kernel::Result::Scalar1(tid));
+        },
+    ))
+    .expect("couldn't spawn client process");
+
+    crate::wait_process_as_thread(xous_server).expect("couldn't join server process");
+    crate::wait_process_as_thread(xous_client).expect("couldn't join client process");
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Test that time spent running is charged to the thread that ran
+#[test]
+fn cpu_time() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let busy_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("cpu_time process", move || {
+            let pid = xous_kernel::current_pid().expect("couldn't get current pid");
+            let tid = xous_kernel::current_tid().expect("couldn't get current tid");
+            let before = xous_kernel::thread_cpu_time(pid, tid).expect("couldn't get cpu time");
+
+            // Spin without making any syscalls
+            let start = std::time::Instant::now();
+            while start.elapsed() < std::time::Duration::from_millis(100) {
+                core::hint::spin_loop();
+            }
+
+            let after = xous_kernel::thread_cpu_time(pid, tid).expect("couldn't get cpu time");
+            assert!(
+                after.run_ms >= before.run_ms + 90,
+                "thread only ran for {} ms",
+                after.run_ms - before.run_ms
+            );
+
+            let total = xous_kernel::process_cpu_time(pid).expect("couldn't get process cpu time");
+            assert!(total.run_ms >= after.run_ms);
+            assert!(total.switches >= after.switches);
+
+            assert_eq!(
+                xous_kernel::thread_cpu_time(pid, 100),
+                Err(xous_kernel::Error::ThreadNotAvailable)
+            );
+            assert_eq!(
+                xous_kernel::process_cpu_time(xous_kernel::PID::new(255).unwrap()),
+                Err(xous_kernel::Error::ProcessNotFound)
+            );
+        }),
+    )
+    .expect("couldn't create cpu_time process");
+
+    xous_kernel::wait_process_as_thread(busy_process).expect("couldn't join cpu_time process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Test that a RAM quota given at process creation is recorded by the kernel,
+/// and that memory moved to the process counts against it
+#[test]
+fn process_quota() {
+    let main_thread = start_kernel(SERVER_SPEC);
+    let (server_addr_send, server_addr_recv) = unbounded();
+
+    let args = xous_kernel::ProcessArgsAsThread::new("quota process", move || {
+        let pid = xous_kernel::current_pid().expect("couldn't get current pid");
+        let info = xous_kernel::process_info(pid).expect("couldn't get process info");
+        assert_eq!(info.ram_quota, 16 * 4096);
+
+        let sid = xous_kernel::create_server().expect("couldn't create server");
+        server_addr_send.send(sid).unwrap();
+        let envelope = xous_kernel::receive_message(sid).expect("couldn't receive message");
+        match envelope.body {
+            xous_kernel::Message::Move(msg) => assert_eq!(msg.id, 2),
+            other => panic!("unexpected message {:?}", other),
+        }
+    });
+    let mut process_init = xous_kernel::arch::create_process_pre_as_thread(&args)
+        .expect("couldn't prepare quota process");
+    process_init.page_quota = 16;
+    let pid = match xous_kernel::rsyscall(xous_kernel::SysCall::CreateProcess(process_init)) {
+        Ok(xous_kernel::Result::ProcessID(pid)) => pid,
+        other => panic!("unexpected result {:?}", other),
+    };
+    let quota_process = xous_kernel::arch::create_process_post_as_thread(args, process_init, pid)
+        .expect("couldn't start quota process");
+
+    let client_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("quota client", move || {
+            let sid = server_addr_recv.recv().unwrap();
+            let conn = xous_kernel::connect(sid).expect("couldn't connect to server");
+
+            // More than the server may own is refused...
+            let too_much = xous_kernel::carton::Carton::from_bytes(&[0u8; 17 * 4096]);
+            let message = xous_kernel::Message::Move(too_much.into_message(1));
+            assert_eq!(
+                xous_kernel::send_message(conn, message),
+                Err(xous_kernel::Error::QuotaExceeded)
+            );
+            // ...while something that fits goes through
+            let enough = xous_kernel::carton::Carton::from_bytes(&[0u8; 4096]);
+            xous_kernel::send_message(conn, xous_kernel::Message::Move(enough.into_message(2)))
+                .expect("couldn't send message");
+        }),
+    )
+    .expect("couldn't start client process");
+
+    xous_kernel::wait_process_as_thread(client_process).expect("couldn't join client process");
+    xous_kernel::wait_process_as_thread(quota_process).expect("couldn't join quota process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Test that the kernel's count of pages follows their owners, and that the
+/// quota check uses it
+#[test]
+fn page_quota_accounting() {
+    let mut mm = crate::mem::MemoryManager::default();
+    let owner = xous_kernel::PID::new(2).unwrap();
+    let other = xous_kernel::PID::new(3).unwrap();
+    mm.set_page_quota(owner, 2);
+
+    mm.note_owner_change(None, Some(owner));
+    assert_eq!(mm.check_page_quota(owner, 1), Ok(()));
+    assert_eq!(
+        mm.check_page_quota(owner, 2),
+        Err(xous_kernel::Error::QuotaExceeded)
+    );
+    mm.note_owner_change(None, Some(owner));
+    assert_eq!(mm.ram_used_by(owner), 2 * crate::mem::PAGE_SIZE);
+    assert_eq!(
+        mm.check_page_quota(owner, 1),
+        Err(xous_kernel::Error::QuotaExceeded)
+    );
+
+    // Moving a page away makes room, and the other process has no limit
+    mm.note_owner_change(Some(owner), Some(other));
+    assert_eq!(mm.ram_used_by(owner), crate::mem::PAGE_SIZE);
+    assert_eq!(mm.ram_used_by(other), crate::mem::PAGE_SIZE);
+    assert_eq!(mm.check_page_quota(owner, 1), Ok(()));
+    assert_eq!(mm.check_page_quota(other, 1000), Ok(()));
+
+    // Releasing pages frees them up, and is not counted twice
+    mm.note_owner_change(Some(owner), None);
+    mm.note_owner_change(None, None);
+    assert_eq!(mm.ram_used_by(owner), 0);
+    assert_eq!(mm.check_page_quota(owner, 2), Ok(()));
+}
+
+/// Test that a supervisor is told when one of its children terminates
+#[test]
+fn supervisor_notification() {
+    const CHILD_TERMINATED: usize = 0x5e;
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let supervisor_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("supervisor process", move || {
+            let sid = xous_kernel::create_server().expect("couldn't create supervisor server");
+            xous_kernel::set_supervisor(sid, CHILD_TERMINATED).expect("couldn't set supervisor");
+
+            // Servers belonging to other processes may not be used
+            assert_eq!(
+                xous_kernel::set_supervisor(
+                    xous_kernel::SID::from_u32(1, 2, 3, 4),
+                    CHILD_TERMINATED
+                ),
+                Err(xous_kernel::Error::ServerNotFound)
+            );
+
+            // Give the child its own key, so the kernel can tell its connection apart
+            xous_kernel::arch::set_process_key(&[0x5e; 16]);
+            let args = xous_kernel::ProcessArgsAsThread::new("supervised process", move || {});
+            let process_init = xous_kernel::arch::create_process_pre_as_thread(&args)
+                .expect("couldn't prepare supervised process");
+            let pid =
+                match xous_kernel::rsyscall(xous_kernel::SysCall::CreateProcess(process_init)) {
+                    Ok(xous_kernel::Result::ProcessID(pid)) => pid,
+                    other => panic!("unexpected result {:?}", other),
+                };
+            let child = xous_kernel::arch::create_process_post_as_thread(args, process_init, pid)
+                .expect("couldn't start supervised process");
+            xous_kernel::wait_process_as_thread(child).expect("couldn't join supervised process");
+
+            let envelope =
+                xous_kernel::receive_message(sid).expect("couldn't receive notification");
+            assert_eq!(envelope.body.id(), CHILD_TERMINATED);
+            assert_eq!(
+                xous_kernel::ChildTerminated::from_message(&envelope.body),
+                Some(xous_kernel::ChildTerminated {
+                    pid,
+                    reason: xous_kernel::ExitReason::Exited(0),
+                })
+            );
+        }),
+    )
+    .expect("couldn't create supervisor process");
+
+    xous_kernel::wait_process_as_thread(supervisor_process)
+        .expect("couldn't join supervisor process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Test that a process still terminates when its supervisor's queue is too
+/// full to take the notification
+#[test]
+fn supervisor_queue_full() {
+    const CHILD_TERMINATED: usize = 0x5e;
+    const FILLER: usize = 0x5f;
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let supervisor_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("supervisor process", move || {
+            let sid = xous_kernel::create_server().expect("couldn't create supervisor server");
+            xous_kernel::set_supervisor(sid, CHILD_TERMINATED).expect("couldn't set supervisor");
+
+            // Fill the supervisor's queue with messages to itself
+            let conn = xous_kernel::connect(sid).expect("couldn't connect to supervisor");
+            let mut queued = 0;
+            while xous_kernel::try_send_message(
+                conn,
+                xous_kernel::Message::new_scalar(FILLER, 0, 0, 0, 0),
+            )
+            .is_ok()
+            {
+                queued += 1;
+            }
+            assert!(queued > 0);
+
+            let run_child = |name: &'static str| {
+                let args = xous_kernel::ProcessArgsAsThread::new(name, move || {});
+                let process_init = xous_kernel::arch::create_process_pre_as_thread(&args)
+                    .expect("couldn't prepare supervised process");
+                let pid =
+                    match xous_kernel::rsyscall(xous_kernel::SysCall::CreateProcess(process_init)) {
+                        Ok(xous_kernel::Result::ProcessID(pid)) => pid,
+                        other => panic!("unexpected result {:?}", other),
+                    };
+                let child =
+                    xous_kernel::arch::create_process_post_as_thread(args, process_init, pid)
+                        .expect("couldn't start supervised process");
+                xous_kernel::wait_process_as_thread(child).expect("couldn't join supervised process");
+                pid
+            };
+
+            // The child exits even though nobody can be told about it
+            xous_kernel::arch::set_process_key(&[0x5e; 16]);
+            let pid = run_child("dropped notification");
+            // The kernel notices the exit once the connection closes, so
+            // wait for the process to go away before draining the queue.
+            while xous_kernel::process_info(pid).map(|info| info.pid) == Ok(pid) {
+                xous_kernel::yield_slice();
+            }
+            for _ in 0..queued {
+                let envelope = xous_kernel::receive_message(sid).expect("couldn't receive message");
+                assert_eq!(envelope.body.id(), FILLER);
+            }
+            assert_eq!(xous_kernel::try_receive_message(sid).map(|m| m.is_some()), Ok(false));
+
+            // Once there's room again, notifications arrive as usual
+            xous_kernel::arch::set_process_key(&[0x60; 16]);
+            let pid = run_child("delivered notification");
+            let envelope =
+                xous_kernel::receive_message(sid).expect("couldn't receive notification");
+            assert_eq!(
+                xous_kernel::ChildTerminated::from_message(&envelope.body),
+                Some(xous_kernel::ChildTerminated {
+                    pid,
+                    reason: xous_kernel::ExitReason::Exited(0),
+                })
+            );
+        }),
+    )
+    .expect("couldn't create supervisor process");
+
+    xous_kernel::wait_process_as_thread(supervisor_process)
+        .expect("couldn't join supervisor process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Test that a ring in shared memory carries data between two processes
+#[test]
+fn shared_memory_ring() {
+    let main_thread = start_kernel(SERVER_SPEC);
+    let (server_addr_send, server_addr_recv) = unbounded();
+    let message = b"Hello, ring!";
+
+    let server_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("shared_memory_ring server", move || {
+            let sid = xous_kernel::create_server().expect("couldn't create server");
+            server_addr_send.send(sid).unwrap();
+
+            let envelope = xous_kernel::receive_message(sid).expect("couldn't receive message");
+            let (addr, len) = match envelope.body {
+                xous_kernel::Message::BlockingScalar(scalar) => (scalar.arg1, scalar.arg2),
+                other => panic!("unexpected message {:?}", other),
+            };
+            let range = unsafe { xous_kernel::MemoryRange::new(addr, len) }.unwrap();
+
+            // Only trust the range after checking who it is shared with
+            assert_eq!(
+                xous_kernel::shared_memory_peer(range),
+                Ok(envelope.sender.pid().unwrap())
+            );
+
+            let mut consumer =
+                unsafe { xous_kernel::ringbuf::consumer(range) }.expect("couldn't attach");
+            let mut buf = [0u8; 32];
+            let count = consumer.read(&mut buf);
+            assert_eq!(&buf[..count], message);
+            assert!(consumer.is_empty());
+
+            xous_kernel::return_scalar(envelope.sender, count).expect("couldn't return");
+        }),
+    )
+    .expect("couldn't start server process");
+
+    let client_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("shared_memory_ring client", move || {
+            let sid = server_addr_recv.recv().unwrap();
+            let conn = xous_kernel::connect(sid).expect("couldn't connect to server");
+
+            let range = xous_kernel::map_memory(
+                None,
+                None,
+                4096,
+                xous_kernel::MemoryFlags::R | xous_kernel::MemoryFlags::W,
+            )
+            .expect("couldn't allocate ring");
+            unsafe { xous_kernel::ringbuf::init(range) }.expect("couldn't init ring");
+            let server_range = xous_kernel::share_memory(
+                conn,
+                range,
+                xous_kernel::MemoryFlags::R | xous_kernel::MemoryFlags::W,
+            )
+            .expect("couldn't share ring");
+
+            // A range may only be shared once
+            assert_eq!(
+                xous_kernel::share_memory(conn, range, xous_kernel::MemoryFlags::R),
+                Err(xous_kernel::Error::ShareViolation)
+            );
+
+            let mut producer =
+                unsafe { xous_kernel::ringbuf::producer(range) }.expect("couldn't attach");
+            assert_eq!(producer.capacity(), 4096 - xous_kernel::ringbuf::HEADER_SIZE - 1);
+            assert_eq!(producer.write(message), message.len());
+
+            let result = xous_kernel::send_message(
+                conn,
+                xous_kernel::Message::new_blocking_scalar(
+                    0,
+                    server_range.as_ptr() as usize,
+                    server_range.len(),
+                    0,
+                    0,
+                ),
+            )
+            .expect("couldn't send message");
+            assert_eq!(result, xous_kernel::Result::Scalar1(message.len()));
+            assert_eq!(producer.free(), producer.capacity());
+
+            xous_kernel::unshare_memory(range).expect("couldn't unshare ring");
+            assert_eq!(
+                xous_kernel::shared_memory_peer(range),
+                Err(xous_kernel::Error::BadAddress)
+            );
+        }),
+    )
+    .expect("couldn't start client process");
+
+    xous_kernel::wait_process_as_thread(client_process).expect("couldn't join client process");
+    xous_kernel::wait_process_as_thread(server_process).expect("couldn't join server process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Test that a server's queue counters track queued and refused messages
+#[test]
+fn server_stats() {
+    let main_thread = start_kernel(SERVER_SPEC);
+    let (server_addr_send, server_addr_recv) = unbounded();
+    let (filled_send, filled_recv) = unbounded();
+
+    let server_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("server_stats server", move || {
+            let sid = xous_kernel::create_server().expect("couldn't create server");
+            server_addr_send.send(sid).unwrap();
+            let sent: usize = filled_recv.recv().unwrap();
+
+            // The owner gets at its own server through a connection
+            let conn = xous_kernel::connect(sid).expect("couldn't connect to own server");
+            let stats = xous_kernel::server_stats(conn).expect("couldn't get stats");
+            assert_eq!(stats.queued, sent);
+            assert_eq!(stats.depth, sent);
+            assert_eq!(stats.max_depth, sent);
+            assert_eq!(stats.full, 1);
+
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            for _ in 0..sent {
+                xous_kernel::receive_message(sid).expect("couldn't receive message");
+            }
+
+            let stats = xous_kernel::server_stats(conn).expect("couldn't get stats");
+            assert_eq!(stats.queued, sent);
+            assert_eq!(stats.depth, 0);
+            assert_eq!(stats.max_depth, sent);
+            assert!(stats.average_wait_ms() >= 10);
+        }),
+    )
+    .expect("couldn't start server process");
+
+    let client_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("server_stats client", move || {
+            let sid = server_addr_recv.recv().unwrap();
+            let conn = xous_kernel::connect(sid).expect("couldn't connect to server");
+
+            // Nobody is receiving, so keep sending until the queue fills up
+            let mut sent = 0;
+            loop {
+                match xous_kernel::try_send_message(
+                    conn,
+                    xous_kernel::Message::new_scalar(0, sent, 0, 0, 0),
+                ) {
+                    Ok(_) => sent += 1,
+                    Err(xous_kernel::Error::ServerQueueFull) => break,
+                    Err(e) => panic!("unexpected error {:?}", e),
+                }
+            }
+            assert!(sent > 0);
+
+            // Clients may not look at somebody else's server
+            assert_eq!(
+                xous_kernel::server_stats(conn),
+                Err(xous_kernel::Error::AccessDenied)
+            );
+            filled_send.send(sent).unwrap();
+        }),
+    )
+    .expect("couldn't start client process");
+
+    xous_kernel::wait_process_as_thread(client_process).expect("couldn't join client process");
+    xous_kernel::wait_process_as_thread(server_process).expect("couldn't join server process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Test that receiving a message gives up once the timeout expires, and that
+/// an expired deadline doesn't affect a later receive
+#[test]
+fn receive_message_timeout() {
+    let main_thread = start_kernel(SERVER_SPEC);
+    let (server_addr_send, server_addr_recv) = unbounded();
+
+    let server_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("receive_message_timeout server", move || {
+            let sid = xous_kernel::create_server().expect("couldn't create server");
+            assert_eq!(
+                xous_kernel::receive_message_timeout(sid, 0).map(|_| ()),
+                Err(xous_kernel::Error::Timeout)
+            );
+
+            let start = std::time::Instant::now();
+            assert_eq!(
+                xous_kernel::receive_message_timeout(sid, 50).map(|_| ()),
+                Err(xous_kernel::Error::Timeout)
+            );
+            assert!(start.elapsed() >= std::time::Duration::from_millis(50));
+
+            server_addr_send.send(sid).unwrap();
+            let envelope =
+                xous_kernel::receive_message_timeout(sid, 5000).expect("couldn't receive message");
+            assert_eq!(envelope.body.id(), 1);
+
+            // The client waits past the earlier deadline before sending this
+            let envelope = xous_kernel::receive_message(sid).expect("couldn't receive message");
+            assert_eq!(envelope.body.id(), 2);
+        }),
+    )
+    .expect("couldn't start server process");
+
+    let client_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("receive_message_timeout client", move || {
+            let sid = server_addr_recv.recv().unwrap();
+            let conn = xous_kernel::connect(sid).expect("couldn't connect to server");
+            xous_kernel::send_message(conn, xous_kernel::Message::new_scalar(1, 0, 0, 0, 0))
+                .expect("couldn't send message");
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            xous_kernel::send_message(conn, xous_kernel::Message::new_scalar(2, 0, 0, 0, 0))
+                .expect("couldn't send message");
+        }),
+    )
+    .expect("couldn't start client process");
+
+    xous_kernel::wait_process_as_thread(client_process).expect("couldn't join client process");
+    xous_kernel::wait_process_as_thread(server_process).expect("couldn't join server process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+mod rpc {
+    #![allow(dead_code)]
+    // `#[xous::rpc]` refers to the library by that name
+    use xous_kernel as xous;
+
+    #[xous::rpc]
+    pub trait Tally {
+        fn add(&mut self, n: u32) -> u32;
+        fn reset(&mut self);
+        fn is_zero(&self) -> bool;
+        /// Destroy the server, so that `serve()` returns
+        fn stop(&mut self);
+    }
+
+    /// `Tally` as a newer client sees it, with a request that the server doesn't know
+    #[xous::rpc]
+    pub trait NewerTally {
+        fn add(&mut self, n: u32) -> u32;
+        fn reset(&mut self);
+        fn is_zero(&self) -> bool;
+        fn stop(&mut self);
+        fn double(&mut self) -> u32;
+    }
+
+    pub struct TallyServer {
+        pub sid: xous::SID,
+        pub total: u32,
+    }
+
+    impl Tally for TallyServer {
+        fn add(&mut self, n: u32) -> u32 {
+            self.total += n;
+            self.total
+        }
+        fn reset(&mut self) {
+            self.total = 0;
+        }
+        fn is_zero(&self) -> bool {
+            self.total == 0
+        }
+        fn stop(&mut self) {
+            xous::destroy_server(self.sid).expect("couldn't destroy server");
+        }
+    }
+}
+
+/// Test that `#[xous::rpc]` clients and servers understand each other, and that a
+/// request the server can't handle gets an error rather than no reply at all
+#[test]
+fn rpc_round_trip() {
+    use rpc::{NewerTallyClient, Tally, TallyClient, TallyOpcode, TallyServer};
+    let main_thread = start_kernel(SERVER_SPEC);
+    let (server_addr_send, server_addr_recv) = unbounded();
+
+    let server_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("rpc_round_trip server", move || {
+            let sid = xous_kernel::create_server().expect("couldn't create server");
+            server_addr_send.send(sid).unwrap();
+            let mut server = TallyServer { sid, total: 0 };
+            assert_eq!(server.serve(sid), xous_kernel::Error::ServerNotFound);
+        }),
+    )
+    .expect("couldn't start server process");
+
+    let client_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("rpc_round_trip client", move || {
+            let sid = server_addr_recv.recv().unwrap();
+            let conn = xous_kernel::connect(sid).expect("couldn't connect to server");
+            let tally = TallyClient::new(conn);
+            assert_eq!(tally.add(2), Ok(2));
+            assert_eq!(tally.add(3), Ok(5));
+            assert_eq!(tally.is_zero(), Ok(false));
+            tally.reset().expect("couldn't reset");
+            assert_eq!(tally.is_zero(), Ok(true));
+
+            // An unknown request, or one of the wrong kind, is answered with an error
+            let unhandled = Ok(xous_kernel::Result::Scalar2(
+                xous_kernel::Error::UnhandledSyscall as usize,
+                0,
+            ));
+            assert_eq!(
+                TallyOpcode::from_usize(TallyOpcode::COUNT - 1),
+                Some(TallyOpcode::Stop)
+            );
+            assert_eq!(TallyOpcode::from_usize(TallyOpcode::COUNT), None);
+            assert_eq!(
+                xous_kernel::send_message(
+                    conn,
+                    xous_kernel::Message::new_blocking_scalar(TallyOpcode::COUNT, 0, 0, 0, 0)
+                ),
+                unhandled
+            );
+            let reset = TallyOpcode::Reset as usize;
+            assert_eq!(
+                xous_kernel::send_message(
+                    conn,
+                    xous_kernel::Message::new_blocking_scalar(reset, 0, 0, 0, 0)
+                ),
+                unhandled
+            );
+            // ...which the generated client passes on
+            assert_eq!(
+                NewerTallyClient::new(conn).double(),
+                Err(xous_kernel::Error::UnhandledSyscall)
+            );
+
+            assert_eq!(tally.add(1), Ok(1));
+            tally.stop().expect("couldn't stop server");
+        }),
+    )
+    .expect("couldn't start client process");
+
+    xous_kernel::wait_process_as_thread(client_process).expect("couldn't join client process");
+    xous_kernel::wait_process_as_thread(server_process).expect("couldn't join server process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Test that an executor runs every task it's given, including the ones its
+/// tasks spawn, that sleeping tasks wake up, and that a task woken from
+/// another thread runs whether the executor is waiting for a message or has
+/// stopped receiving them
+#[test]
+fn executor() {
+    use std::cell::RefCell;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+    use xous_kernel::executor::Executor;
+
+    /// Ready once another thread says so
+    struct Flag(Arc<Mutex<(bool, Option<Waker>)>>);
+
+    impl Future for Flag {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            let mut state = self.0.lock().unwrap();
+            if state.0 {
+                return Poll::Ready(());
+            }
+            state.1 = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let executor_process = xous_kernel::create_process_as_thread(
+        xous_kernel::ProcessArgsAsThread::new("executor process", move || {
+            let sid = xous_kernel::create_server().expect("couldn't create server");
+            let start = std::time::Instant::now();
+            let mut executor = Executor::new(sid, move || start.elapsed().as_millis() as u64);
+            let handle = executor.handle();
+
+            let order = Rc::new(RefCell::new(Vec::new()));
+            let (parent_order, parent_handle) = (order.clone(), handle.clone());
+            executor.spawn(async move {
+                parent_order.borrow_mut().push("parent");
+                let child_order = parent_order.clone();
+                parent_handle.spawn(async move { child_order.borrow_mut().push("child") });
+                parent_handle.sleep_ms(30).await;
+                parent_order.borrow_mut().push("slept");
+            });
+            executor.run();
+            assert_eq!(*order.borrow(), ["parent", "child", "slept"]);
+            assert!(start.elapsed() >= std::time::Duration::from_millis(30));
+
+            // With nothing to receive and no timers, the executor waits for a
+            // message, so the task only runs before the setter sends one if
+            // the waker interrupts that. The wake message isn't one for the
+            // task to receive.
+            let flag = Arc::new(Mutex::new((false, None::<Waker>)));
+            let ran = Arc::new(AtomicBool::new(false));
+            let ran_first = Arc::new(AtomicBool::new(false));
+            let (setter_flag, setter_ran, setter_ran_first) =
+                (flag.clone(), ran.clone(), ran_first.clone());
+            let setter = xous_kernel::create_thread(move || {
+                let conn = xous_kernel::connect(sid).expect("couldn't connect to server");
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                let waker = {
+                    let mut state = setter_flag.lock().unwrap();
+                    state.0 = true;
+                    state.1.take()
+                };
+                waker.expect("task didn't wait for the flag").wake();
+                for _ in 0..100 {
+                    if setter_ran.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                }
+                setter_ran_first.store(setter_ran.load(Ordering::SeqCst), Ordering::SeqCst);
+                xous_kernel::send_message(conn, xous_kernel::Message::new_scalar(7, 0, 0, 0, 0))
+                    .expect("couldn't send message");
+            })
+            .expect("couldn't start setter thread");
+
+            let task_handle = handle.clone();
+            executor.spawn(async move {
+                Flag(flag).await;
+                ran.store(true, Ordering::SeqCst);
+                assert_eq!(task_handle.receive().await.body.id(), 7);
+            });
+            executor.run();
+            assert!(ran_first.load(Ordering::SeqCst));
+
+            xous_kernel::wait_thread(setter).expect("couldn't join setter thread");
+
+            // Nothing receives until the flag is set, so the executor's inbox
+            // fills up and it stops receiving, which leaves only the waker to
+            // get it going again.
+            let flag = Arc::new(Mutex::new((false, None::<Waker>)));
+            let setter_flag = flag.clone();
+            let setter = xous_kernel::create_thread(move || {
+                let conn = xous_kernel::connect(sid).expect("couldn't connect to server");
+                // As many messages as the executor's inbox holds
+                for id in 0..32 {
+                    while let Err(e) = xous_kernel::send_message(
+                        conn,
+                        xous_kernel::Message::new_scalar(id, 0, 0, 0, 0),
+                    ) {
+                        assert_eq!(e, xous_kernel::Error::ServerQueueFull);
+                        xous_kernel::yield_slice();
+                    }
+                }
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                let waker = {
+                    let mut state = setter_flag.lock().unwrap();
+                    state.0 = true;
+                    state.1.take()
+                };
+                waker.expect("task didn't wait for the flag").wake();
+            })
+            .expect("couldn't start setter thread");
+
+            executor.spawn(async move {
+                Flag(flag).await;
+                for id in 0..32 {
+                    assert_eq!(handle.receive().await.body.id(), id);
+                }
+            });
+            executor.run();
+
+            xous_kernel::wait_thread(setter).expect("couldn't join setter thread");
+        }),
+    )
+    .expect("couldn't start executor process");
+
+    xous_kernel::wait_process_as_thread(executor_process).expect("couldn't join executor process");
+
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
@@ -13,10 +13,12 @@ use proc_macro2::Span;
 use rand::Rng;
 use rand::SeedableRng;
 use std::time::{SystemTime, UNIX_EPOCH};
-use syn::{parse, spanned::Spanned, Ident, ItemFn, ReturnType, Type, Visibility};
+use syn::{parse, spanned::Spanned, Ident, ItemFn, ItemTrait, ReturnType, Type, Visibility};
 
 use proc_macro::TokenStream;
 
+mod rpc;
+
 /// Attribute to declare the entry point of the program
 ///
 /// **IMPORTANT**: This attribute must appear exactly *once* in the dependency graph. Also, if you
@@ -100,6 +102,65 @@ pub fn xous_main(args: TokenStream, input: TokenStream) -> TokenStream {
     r.into()
 }
 
+/// Attribute to generate the messaging code for a server from a trait
+///
+/// Each method of the trait becomes a request with its own opcode, numbered in the order the
+/// methods are declared. Methods must take `&self` or `&mut self`, and their arguments and
+/// results must be owned. A method travels as a scalar message if it has at most four
+/// arguments, all of them integers of up to 32 bits or `bool`, and its result is another
+/// such value or `()`. Otherwise its arguments are archived with rkyv and lent to the server
+/// in an `xous_ipc::Buffer`, so they must implement rkyv's `Archive`, `Serialize` and
+/// `Deserialize`.
+///
+/// For a trait `Foo`, this generates:
+///
+/// - `FooOpcode`, an enum with one variant per method
+/// - `FooClient`, which wraps a connection to the server and has one method per request,
+///   returning `Result<_, xous::Error>`
+/// - `Foo::dispatch()` and `Foo::serve()`, provided methods that decode a request, call the
+///   server's implementation and return its result to the client
+///
+/// The crate using this must depend on `xous`, `xous-ipc` and `rkyv`.
+///
+/// # Examples
+///
+/// ``` ignore
+/// #[xous::rpc]
+/// pub trait Counter {
+///     /// Add `n` to the count, returning the new count.
+///     fn add(&mut self, n: u32) -> u32;
+///     /// Set the label shown next to the count.
+///     fn set_label(&mut self, label: xous_ipc::String<64>);
+/// }
+///
+/// // in the server
+/// struct MyCounter { count: u32 }
+/// impl Counter for MyCounter {
+///     fn add(&mut self, n: u32) -> u32 { self.count += n; self.count }
+///     fn set_label(&mut self, label: xous_ipc::String<64>) { log::info!("{}", label) }
+/// }
+/// let error = MyCounter { count: 0 }.serve(sid);
+///
+/// // in the client
+/// let counter = CounterClient::new(conn);
+/// let total = counter.add(3)?;
+/// ```
+#[proc_macro_attribute]
+pub fn rpc(args: TokenStream, input: TokenStream) -> TokenStream {
+    let t = parse_macro_input!(input as ItemTrait);
+
+    if !args.is_empty() {
+        return parse::Error::new(Span::call_site(), "This attribute accepts no arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    match rpc::expand(t) {
+        Ok(r) => r.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
 // Creates a random identifier
 /*
   Historical note -- this identifier was inherited from the Cortex libraries.
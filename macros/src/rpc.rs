@@ -0,0 +1,442 @@
+//! Implementation of `#[xous::rpc]`.
+//!
+//! Every method of the annotated trait becomes one opcode, numbered in declaration order.
+//! A server with opcodes of its own can number them from the generated `COUNT`.
+//! A method whose arguments all fit in a scalar message, and whose result fits in a scalar
+//! return, travels as a scalar: blocking if it returns something, non-blocking if it
+//! doesn't. Any other method has its arguments archived into an `xous_ipc::Buffer`, which
+//! is lent to the server, or mutably lent if the server has a result to write back.
+//!
+//! `serve()` answers a blocking scalar that it can't dispatch with `Scalar2(error, 0)`
+//! rather than leave the client waiting. Results always come back as `Scalar1`, so the
+//! client can tell the two apart.
+
+use proc_macro2::{Span, TokenStream};
+use syn::{
+    parse, spanned::Spanned, FnArg, Ident, ItemTrait, Pat, ReturnType, TraitItem, TraitItemMethod,
+    Type,
+};
+
+/// Types that can be passed in a single word of a scalar message
+const SCALAR_TYPES: &[&str] = &[
+    "u8", "u16", "u32", "usize", "i8", "i16", "i32", "isize", "bool",
+];
+
+/// Most arguments a scalar message can carry
+const SCALAR_ARGS: usize = 4;
+
+enum Kind {
+    /// Non-blocking scalar with no result
+    Scalar,
+    /// Blocking scalar that returns one word
+    BlockingScalar,
+    /// Lend of the archived arguments, with no result
+    Lend,
+    /// Mutable lend of the archived arguments, which the server replaces with the result
+    LendMut,
+}
+
+struct Method {
+    docs: Vec<syn::Attribute>,
+    name: Ident,
+    variant: Ident,
+    args: Vec<(Ident, Type)>,
+    ret: Option<Type>,
+    kind: Kind,
+}
+
+fn is_scalar(ty: &Type) -> bool {
+    match ty {
+        Type::Path(p) => p.qself.is_none() && SCALAR_TYPES.iter().any(|name| p.path.is_ident(name)),
+        _ => false,
+    }
+}
+
+fn is_bool(ty: &Type) -> bool {
+    match ty {
+        Type::Path(p) => p.path.is_ident("bool"),
+        _ => false,
+    }
+}
+
+/// `do_the_thing` => `DoTheThing`
+fn camel_case(name: &Ident) -> Ident {
+    let mut out = String::new();
+    for word in name.to_string().split('_').filter(|w| !w.is_empty()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            out.extend(first.to_uppercase());
+            out.extend(chars);
+        }
+    }
+    Ident::new(&out, name.span())
+}
+
+fn parse_method(method: &TraitItemMethod) -> Result<Method, parse::Error> {
+    let sig = &method.sig;
+    if !sig.generics.params.is_empty() || sig.asyncness.is_some() || sig.variadic.is_some() {
+        return Err(parse::Error::new(
+            sig.span(),
+            "`#[rpc]` methods may not be generic, async or variadic",
+        ));
+    }
+
+    let mut inputs = sig.inputs.iter();
+    match inputs.next() {
+        Some(FnArg::Receiver(r)) if r.reference.is_some() => (),
+        _ => {
+            return Err(parse::Error::new(
+                sig.span(),
+                "`#[rpc]` methods must take `&self` or `&mut self`",
+            ))
+        }
+    }
+
+    let mut args = Vec::new();
+    for input in inputs {
+        let arg = match input {
+            FnArg::Typed(arg) => arg,
+            FnArg::Receiver(r) => return Err(parse::Error::new(r.span(), "unexpected receiver")),
+        };
+        let name = match &*arg.pat {
+            Pat::Ident(p) if p.by_ref.is_none() && p.subpat.is_none() => p.ident.clone(),
+            other => {
+                return Err(parse::Error::new(
+                    other.span(),
+                    "`#[rpc]` arguments must be plain identifiers",
+                ))
+            }
+        };
+        if let Type::Reference(_) = &*arg.ty {
+            return Err(parse::Error::new(
+                arg.ty.span(),
+                "`#[rpc]` arguments are sent to another process, so they must be owned",
+            ));
+        }
+        args.push((name, (*arg.ty).clone()));
+    }
+
+    let ret = match &sig.output {
+        ReturnType::Default => None,
+        ReturnType::Type(_, ty) => match &**ty {
+            Type::Tuple(t) if t.elems.is_empty() => None,
+            Type::Reference(r) => {
+                return Err(parse::Error::new(
+                    r.span(),
+                    "`#[rpc]` results are sent to another process, so they must be owned",
+                ))
+            }
+            ty => Some(ty.clone()),
+        },
+    };
+
+    let scalar_args = args.len() <= SCALAR_ARGS && args.iter().all(|(_, ty)| is_scalar(ty));
+    let kind = match &ret {
+        None if scalar_args => Kind::Scalar,
+        Some(ty) if scalar_args && is_scalar(ty) => Kind::BlockingScalar,
+        None => Kind::Lend,
+        Some(_) => Kind::LendMut,
+    };
+
+    Ok(Method {
+        docs: method
+            .attrs
+            .iter()
+            .filter(|a| a.path.is_ident("doc"))
+            .cloned()
+            .collect(),
+        name: sig.ident.clone(),
+        variant: camel_case(&sig.ident),
+        args,
+        ret,
+        kind,
+    })
+}
+
+/// Convert the scalar argument `argN` back into `ty`.
+fn from_word(word: &Ident, ty: &Type) -> TokenStream {
+    if is_bool(ty) {
+        quote!(#word != 0)
+    } else {
+        quote!(#word as #ty)
+    }
+}
+
+pub(crate) fn expand(input: ItemTrait) -> Result<TokenStream, parse::Error> {
+    if !input.generics.params.is_empty() || input.generics.where_clause.is_some() {
+        return Err(parse::Error::new(
+            input.generics.span(),
+            "`#[rpc]` traits may not be generic",
+        ));
+    }
+
+    let mut methods = Vec::new();
+    for item in input.items.iter() {
+        match item {
+            TraitItem::Method(m) => methods.push(parse_method(m)?),
+            other => {
+                return Err(parse::Error::new(
+                    other.span(),
+                    "`#[rpc]` traits may only contain methods",
+                ))
+            }
+        }
+    }
+
+    let vis = &input.vis;
+    let trait_name = &input.ident;
+    let opcode = format_ident!("{}Opcode", trait_name);
+    let client = format_ident!("{}Client", trait_name);
+
+    let mut variants = Vec::new();
+    let mut from_usize = Vec::new();
+    let mut calls = Vec::new();
+    let mut stubs = Vec::new();
+    let mut arms = Vec::new();
+
+    for (index, m) in methods.iter().enumerate() {
+        let Method {
+            docs,
+            name,
+            variant,
+            args,
+            ret,
+            kind,
+        } = m;
+        let index = proc_macro2::Literal::usize_unsuffixed(index);
+        variants.push(quote!(#variant = #index));
+        from_usize.push(quote!(#index => Some(#opcode::#variant)));
+
+        let arg_names: Vec<&Ident> = args.iter().map(|(name, _)| name).collect();
+        let arg_types: Vec<&Type> = args.iter().map(|(_, ty)| ty).collect();
+        let words: Vec<Ident> = (1..=SCALAR_ARGS)
+            .map(|i| Ident::new(&format!("arg{}", i), Span::call_site()))
+            .collect();
+        let used_words = &words[..args.len().min(SCALAR_ARGS)];
+        let call = format_ident!("{}{}Call", trait_name, variant);
+        let ret_ty = match ret {
+            Some(ty) => quote!(#ty),
+            None => quote!(()),
+        };
+
+        let stub_body;
+        let arm_body;
+        match kind {
+            Kind::Scalar | Kind::BlockingScalar => {
+                let mut scalar_args: Vec<TokenStream> = arg_names
+                    .iter()
+                    .map(|name| quote!(#name as usize))
+                    .collect();
+                scalar_args.resize(SCALAR_ARGS, quote!(0));
+                let decoded: Vec<TokenStream> = used_words
+                    .iter()
+                    .zip(arg_types.iter())
+                    .map(|(word, ty)| from_word(word, ty))
+                    .collect();
+                if let Kind::Scalar = kind {
+                    stub_body = quote! {
+                        xous::send_message(
+                            self.conn,
+                            xous::Message::new_scalar(#opcode::#variant as usize, #(#scalar_args),*),
+                        )
+                        .map(|_| ())
+                    };
+                    arm_body = quote! {
+                        if let xous::Message::Scalar(xous::ScalarMessage { #(#used_words,)* .. }) = msg.body {
+                            self.#name(#(#decoded),*);
+                            None
+                        } else {
+                            Some(msg)
+                        }
+                    };
+                } else {
+                    let result = Ident::new("result", Span::call_site());
+                    let ret_ty = ret.as_ref().unwrap();
+                    let decoded_result = from_word(&result, ret_ty);
+                    stub_body = quote! {
+                        match xous::send_message(
+                            self.conn,
+                            xous::Message::new_blocking_scalar(#opcode::#variant as usize, #(#scalar_args),*),
+                        )? {
+                            xous::Result::Scalar1(#result) => Ok(#decoded_result),
+                            xous::Result::Scalar2(error, _) => Err(xous::Error::from_usize(error)),
+                            _ => Err(xous::Error::InternalError),
+                        }
+                    };
+                    arm_body = quote! {
+                        if let xous::Message::BlockingScalar(xous::ScalarMessage { #(#used_words,)* .. }) = msg.body {
+                            let result = self.#name(#(#decoded),*);
+                            xous::return_scalar(msg.sender, result as usize)
+                                .expect("couldn't return scalar result");
+                            None
+                        } else {
+                            Some(msg)
+                        }
+                    };
+                }
+            }
+            Kind::Lend | Kind::LendMut => {
+                // The result is written over the arguments, so the buffer has to be large
+                // enough for either. Reserving a slot for it in the arguments sees to that.
+                let ret_slot = ret.as_ref().map(|ty| quote!(pub ret: Option<#ty>,));
+                let ret_init = ret.as_ref().map(|_| quote!(ret: None,));
+                calls.push(quote! {
+                    #[doc(hidden)]
+                    #[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+                    #vis struct #call {
+                        #(pub #arg_names: #arg_types,)*
+                        #ret_slot
+                    }
+                });
+                if let Kind::Lend = kind {
+                    stub_body = quote! {
+                        let buf = xous_ipc::Buffer::into_buf(#call { #(#arg_names,)* })
+                            .or(Err(xous::Error::InternalError))?;
+                        buf.lend(self.conn, #opcode::#variant as u32).map(|_| ())
+                    };
+                    arm_body = quote! {
+                        let handled = match msg.body.memory_message() {
+                            Some(mem) => {
+                                let buffer = unsafe { xous_ipc::Buffer::from_memory_message(mem) };
+                                match buffer.to_original::<#call, _>() {
+                                    Ok(call) => {
+                                        self.#name(#(call.#arg_names),*);
+                                        true
+                                    }
+                                    Err(_) => false,
+                                }
+                            }
+                            None => false,
+                        };
+                        if handled { None } else { Some(msg) }
+                    };
+                } else {
+                    stub_body = quote! {
+                        let mut buf = xous_ipc::Buffer::into_buf(#call { #(#arg_names,)* #ret_init })
+                            .or(Err(xous::Error::InternalError))?;
+                        buf.lend_mut(self.conn, #opcode::#variant as u32)?;
+                        buf.to_original::<#ret_ty, _>().or(Err(xous::Error::InternalError))
+                    };
+                    arm_body = quote! {
+                        let mut msg = msg;
+                        let handled = match msg.body.memory_message_mut() {
+                            Some(mem) => {
+                                let mut buffer = unsafe { xous_ipc::Buffer::from_memory_message_mut(mem) };
+                                match buffer.to_original::<#call, _>() {
+                                    Ok(call) => {
+                                        let result = self.#name(#(call.#arg_names),*);
+                                        buffer.replace(result).is_ok()
+                                    }
+                                    Err(_) => false,
+                                }
+                            }
+                            None => false,
+                        };
+                        if handled { None } else { Some(msg) }
+                    };
+                }
+            }
+        }
+
+        stubs.push(quote! {
+            #(#docs)*
+            pub fn #name(&self, #(#arg_names: #arg_types),*) -> core::result::Result<#ret_ty, xous::Error> {
+                #stub_body
+            }
+        });
+        arms.push(quote!(Some(#opcode::#variant) => { #arm_body }));
+    }
+
+    let mut server = input.clone();
+    server.items.push(syn::parse_quote! {
+        /// Handle `msg` if it is one of this trait's requests, and hand it back otherwise,
+        /// so that a server can mix these with opcodes of its own.
+        fn dispatch(&mut self, msg: xous::MessageEnvelope) -> Option<xous::MessageEnvelope>
+        where
+            Self: Sized,
+        {
+            match #opcode::from_usize(msg.body.id()) {
+                #(#arms)*
+                None => Some(msg),
+            }
+        }
+    });
+    server.items.push(syn::parse_quote! {
+        /// Receive and dispatch messages on `sid` until receiving fails. Messages that
+        /// aren't one of this trait's requests are dropped, which hands any memory back to
+        /// the sender, and a blocking scalar is answered with `UnhandledSyscall`.
+        fn serve(&mut self, sid: xous::SID) -> xous::Error
+        where
+            Self: Sized,
+        {
+            loop {
+                match xous::receive_message(sid) {
+                    Ok(msg) => {
+                        if let Some(msg) = self.dispatch(msg) {
+                            if let xous::Message::BlockingScalar(_) = msg.body {
+                                xous::return_scalar2(
+                                    msg.sender,
+                                    xous::Error::UnhandledSyscall as usize,
+                                    0,
+                                )
+                                .ok();
+                            }
+                        }
+                    }
+                    Err(e) => return e,
+                }
+            }
+        }
+    });
+
+    let client_doc = format!(
+        "Client for a server that implements [`{}`]. Every method sends one request and \
+         waits for the server, except for scalar requests without a result, which don't wait.",
+        trait_name
+    );
+    let opcode_doc = format!("Opcodes of the requests in [`{}`]", trait_name);
+    let count = proc_macro2::Literal::usize_unsuffixed(methods.len());
+
+    Ok(quote! {
+        #server
+
+        #[doc = #opcode_doc]
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        #vis enum #opcode {
+            #(#variants,)*
+        }
+
+        impl #opcode {
+            /// How many opcodes there are. They run from 0 to `COUNT - 1`, so a server
+            /// that has opcodes of its own can start numbering them here.
+            pub const COUNT: usize = #count;
+
+            pub fn from_usize(id: usize) -> Option<Self> {
+                match id {
+                    #(#from_usize,)*
+                    _ => None,
+                }
+            }
+        }
+
+        #(#calls)*
+
+        #[doc = #client_doc]
+        #[derive(Debug)]
+        #vis struct #client {
+            conn: xous::CID,
+        }
+
+        impl #client {
+            pub fn new(conn: xous::CID) -> Self {
+                #client { conn }
+            }
+
+            pub fn conn(&self) -> xous::CID {
+                self.conn
+            }
+
+            #(#stubs)*
+        }
+    })
+}
@@ -10,10 +10,17 @@ impl TestStruct {
 
 pub const SERVER_NAME_BENCHMARK: &str = "_Benchmark target_";
 
-#[allow(dead_code)]
+#[xous::rpc]
+pub trait Benchmark {
+    /// Returns `testvar + 1`
+    fn test_scalar(&mut self, testvar: u32) -> u32;
+    /// Returns a `TestStruct` whose first challenge word is one more than `reg`'s
+    fn test_memory(&mut self, reg: TestStruct) -> TestStruct;
+}
+
+/// Requests that `Benchmark` can't express, numbered after its own. `#[xous::rpc]` only
+/// lends memory, and `TestMemorySend` measures sending it.
 #[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
 pub enum Opcode {
-    TestScalar, //(u32),
-    TestMemory, //(TestStruct),
-    TestMemorySend,
+    TestMemorySend = BenchmarkOpcode::COUNT as isize, //(TestStruct)
 }
@@ -4,36 +4,18 @@ pub mod api;
 use api::*;
 
 use num_traits::ToPrimitive;
-use xous::{send_message, CID};
+use xous::CID;
 use xous_ipc::Buffer;
 
 pub fn test_scalar(cid: CID, testvar: u32) -> Result<u32, xous::Error> {
-    let response = send_message(
-        cid,
-        xous::Message::new_blocking_scalar(
-            Opcode::TestScalar.to_usize().unwrap(),
-            testvar as usize,
-            0,
-            0,
-            0,
-        ),
-    )?;
-    if let xous::Result::Scalar1(r) = response {
-        Ok(r as u32)
-    } else {
-        panic!("unexpected return value: {:#?}", response);
-    }
+    BenchmarkClient::new(cid).test_scalar(testvar)
 }
 
 pub fn test_memory(cid: CID, testvar: u32) -> Result<u32, xous::Error> {
     let mut reg = TestStruct::new();
     reg.challenge[0] = testvar;
 
-    let mut buf = Buffer::into_buf(reg).or(Err(xous::Error::InternalError))?;
-    buf.lend_mut(cid, Opcode::TestMemory.to_u32().unwrap())
-        .or(Err(xous::Error::InternalError))?;
-
-    let result = buf.as_flat::<TestStruct, _>().unwrap();
+    let result = BenchmarkClient::new(cid).test_memory(reg)?;
     Ok(result.challenge[0])
 }
 
@@ -41,7 +23,7 @@ pub fn test_memory_send(cid: CID, testvar: u32) -> Result<u32, xous::Error> {
     let mut reg = TestStruct::new();
     reg.challenge[0] = testvar;
 
-    let mut buf = Buffer::into_buf(reg).or(Err(xous::Error::InternalError))?;
+    let buf = Buffer::into_buf(reg).or(Err(xous::Error::InternalError))?;
     buf.send(cid, Opcode::TestMemorySend.to_u32().unwrap())
         .or(Err(xous::Error::InternalError))?;
     Ok(testvar + 2)
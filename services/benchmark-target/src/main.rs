@@ -3,13 +3,26 @@
 
 mod api;
 use api::*;
-use xous::msg_blocking_scalar_unpack;
 
 use log::{error, info};
 
 use num_traits::FromPrimitive;
 use xous_ipc::Buffer;
 
+struct Target;
+
+impl Benchmark for Target {
+    fn test_scalar(&mut self, testvar: u32) -> u32 {
+        testvar + 1
+    }
+
+    fn test_memory(&mut self, reg: TestStruct) -> TestStruct {
+        let mut ret = TestStruct::new();
+        ret.challenge[0] = reg.challenge[0] + 1;
+        ret
+    }
+}
+
 #[xous::xous_main]
 fn xmain() -> ! {
     log_server::init_wait().unwrap();
@@ -21,24 +34,15 @@ fn xmain() -> ! {
         .expect("BENCHTARGET: can't register server");
     info!("BENCHTARGET: registered with NS -- {:?}", bench_sid);
 
+    let mut target = Target;
     let mut state: u32 = 0;
     loop {
-        let mut envelope = xous::receive_message(bench_sid).unwrap();
+        let envelope = xous::receive_message(bench_sid).unwrap();
+        let mut envelope = match target.dispatch(envelope) {
+            Some(envelope) => envelope,
+            None => continue,
+        };
         match FromPrimitive::from_usize(envelope.body.id()) {
-            Some(Opcode::TestScalar) => msg_blocking_scalar_unpack!(envelope, val, _, _, _, {
-                xous::return_scalar(envelope.sender, (val + 1) as usize)
-                    .expect("BENCHTARGET: couldn't return TestScalar request");
-            }),
-            Some(Opcode::TestMemory) => {
-                let mut buffer = unsafe {
-                    Buffer::from_memory_message_mut(envelope.body.memory_message_mut().unwrap())
-                };
-                let reg = buffer.to_original::<TestStruct, _>().unwrap();
-
-                let mut ret = TestStruct::new();
-                ret.challenge[0] = reg.challenge[0] + 1;
-                buffer.replace(ret).unwrap();
-            }
             Some(Opcode::TestMemorySend) => {
                 let mut buffer = unsafe {
                     Buffer::from_memory_message_mut(envelope.body.memory_message_mut().unwrap())
@@ -6,6 +6,9 @@ use api::*;
 
 use api::Opcode;
 use num_traits::{FromPrimitive, ToPrimitive};
+use std::cell::RefCell;
+use std::rc::Rc;
+use xous::executor::{Executor, Handle};
 use xous::{msg_scalar_unpack, CID};
 use xous_ipc::*;
 
@@ -16,85 +19,71 @@ struct ScalarCallback {
     cb_to_client_id: u32,
 }
 
-fn pump_thread() {
-    log::info!("starting pump thread");
-
-    let ticktimer = ticktimer_server::Ticktimer::new().expect("Couldn't connect to Ticktimer");
+struct State {
+    tick_cb: [Option<ScalarCallback>; 32],
+    req_cb: [bool; xous::MAX_CID],
+    ticks: usize,
+    state: usize,
+}
 
-    let xns = xous_names::XousNames::new().unwrap();
-    let server_conn = xns
-        .request_connection_blocking(api::SERVER_NAME)
-        .expect("can't connect to main program");
+async fn pump(handle: Handle, state: Rc<RefCell<State>>) {
+    log::info!("starting pump task");
     loop {
-        xous::send_message(
-            server_conn,
-            xous::Message::new_scalar(Opcode::Tick.to_usize().unwrap(), 0, 0, 0, 0),
-        )
-        .expect("couldn't send Tick message");
-        ticktimer.sleep_ms(100).expect("couldn't sleep");
+        handle.sleep_ms(100).await;
+        tick(&handle, &state).await;
     }
 }
 
-#[xous::xous_main]
-fn shell_main() -> ! {
-    log_server::init_wait().unwrap();
-    log::set_max_level(log::LevelFilter::Info);
-    log::info!("my PID is {}", xous::process::id());
-
-    let xns = xous_names::XousNames::new().unwrap();
-    let server = xns
-        .register_name(api::SERVER_NAME)
-        .expect("can't register server");
-
-    xous::create_thread_0(pump_thread).unwrap();
-    log::info!("pump thread started");
-
-    let mut tick_cb: [Option<ScalarCallback>; 32] = [None; 32];
-    let mut req_cb: [bool; xous::MAX_CID] = [false; xous::MAX_CID];
+/// Pump the tick callbacks. They are copied out first, so that the state isn't borrowed while
+/// waiting for a client's queue to drain.
+async fn tick(handle: &Handle, state: &RefCell<State>) {
+    let tick_cb = state.borrow().tick_cb;
+    for (i, maybe_conn) in tick_cb.iter().enumerate() {
+        if let Some(scb) = maybe_conn {
+            match handle
+                .send(
+                    scb.server_to_cb_cid,
+                    xous::Message::new_scalar(
+                        api::TickCallback::Tick.to_usize().unwrap(),
+                        scb.cb_to_client_cid as usize,
+                        scb.cb_to_client_id as usize,
+                        0,
+                        0,
+                    ),
+                )
+                .await
+            {
+                Err(xous::Error::ServerNotFound) => {
+                    state.borrow_mut().tick_cb[i] = None // automatically de-allocate callbacks for clients that have dropped
+                }
+                Ok(xous::Result::Ok) => {}
+                _ => panic!("unhandled error or result in callback processing"),
+            }
+        }
+    }
+    let mut state = state.borrow_mut();
+    log::trace!("ticks: {}", state.ticks);
+    state.ticks += 1;
+    state.state += 1;
+}
 
-    let mut ticks = 0;
-    let mut state = 0;
+async fn serve(handle: Handle, state: Rc<RefCell<State>>) {
     loop {
-        let msg = xous::receive_message(server).unwrap();
+        let msg = handle.receive().await;
         log::trace!("Message: {:?}", msg);
         match FromPrimitive::from_usize(msg.body.id()) {
-            Some(Opcode::Tick) => {
-                // pump the tick callbacks
-                for maybe_conn in tick_cb.iter_mut() {
-                    if let Some(scb) = maybe_conn {
-                        match xous::send_message(
-                            scb.server_to_cb_cid,
-                            xous::Message::new_scalar(
-                                api::TickCallback::Tick.to_usize().unwrap(),
-                                scb.cb_to_client_cid as usize,
-                                scb.cb_to_client_id as usize,
-                                0,
-                                0,
-                            ),
-                        ) {
-                            Err(xous::Error::ServerNotFound) => {
-                                *maybe_conn = None // automatically de-allocate callbacks for clients that have dropped
-                            }
-                            Ok(xous::Result::Ok) => {}
-                            _ => panic!("unhandled error or result in callback processing"),
-                        }
-                    }
-                }
-                log::trace!("ticks: {}", ticks);
-                ticks += 1;
-                state += 1;
-            }
+            Some(Opcode::Tick) => tick(&handle, &state).await,
             Some(Opcode::RegisterTickListener) => {
                 let buffer =
                     unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
                 let hookdata = buffer.to_original::<ScalarHook, _>().unwrap();
-                do_hook(hookdata, &mut tick_cb);
+                do_hook(hookdata, &mut state.borrow_mut().tick_cb);
             }
             Some(Opcode::RegisterReqListener) => msg_scalar_unpack!(msg, sid0, sid1, sid2, sid3, {
                 let sid = xous::SID::from_u32(sid0 as _, sid1 as _, sid2 as _, sid3 as _);
                 let cid = xous::connect(sid).unwrap();
-                if (cid as usize) < req_cb.len() {
-                    req_cb[cid as usize] = true;
+                if (cid as usize) < xous::MAX_CID {
+                    state.borrow_mut().req_cb[cid as usize] = true;
                 } else {
                     log::error!("cid out of allowable range");
                 }
@@ -104,54 +93,47 @@ fn shell_main() -> ! {
                     let sid = xous::SID::from_u32(sid0 as _, sid1 as _, sid2 as _, sid3 as _);
                     let cid = xous::connect(sid).unwrap(); // if the connection already exists, this just looks it up in the table
                     log::info!("UnregisterReqListener cid {}", cid);
-                    if (cid as usize) < req_cb.len() {
-                        req_cb[cid as usize] = false;
+                    if (cid as usize) < xous::MAX_CID {
+                        state.borrow_mut().req_cb[cid as usize] = false;
                     } else {
                         log::error!("cid out of allowable range");
                     }
                     unsafe { xous::disconnect(cid).unwrap() };
                 })
             }
-            Some(Opcode::Req) => msg_scalar_unpack!(msg, _, _, _, _, {
+            Some(Opcode::Req) => {
+                let (req_cb, current) = {
+                    let state = state.borrow();
+                    (state.req_cb, state.state)
+                };
                 log::debug!("req_cb: {:?}", req_cb);
                 // send results to request listeners
-                for cid in 1..req_cb.len() {
-                    // 0 is not a valid connection
-                    if req_cb[cid as usize] {
-                        match xous::send_message(
-                            cid as u32,
-                            xous::Message::new_scalar(
-                                api::ResultCallback::Result.to_usize().unwrap(),
-                                state as _,
-                                0,
-                                0,
-                                0,
-                            ),
-                        ) {
+                // 0 is not a valid connection
+                for (cid, &listening) in req_cb.iter().enumerate().skip(1) {
+                    if listening {
+                        match handle
+                            .send(
+                                cid as u32,
+                                xous::Message::new_scalar(
+                                    api::ResultCallback::Result.to_usize().unwrap(),
+                                    current,
+                                    0,
+                                    0,
+                                    0,
+                                ),
+                            )
+                            .await
+                        {
                             Err(xous::Error::ServerNotFound) => {
                                 log::info!("de-allocate ReqCallback");
-                                req_cb[cid] = false;
+                                state.borrow_mut().req_cb[cid] = false;
                             }
                             Ok(xous::Result::Ok) => {}
                             _ => panic!("unhandled error or result in callback processing"),
                         }
                     }
                 }
-                /*
-                for maybe_conn in req_cb.iter_mut() {
-                    if let Some(conn) = maybe_conn {
-                        match xous::send_message(*conn,
-                            xous::Message::new_scalar(api::ResultCallback::Result.to_usize().unwrap(), state as _, 0, 0, 0)) {
-                                Err(xous::Error::ServerNotFound) => {
-                                    log::info!("de-allocate ReqCallback");
-                                    *maybe_conn = None // automatically de-allocate callbacks for clients that have dropped
-                                },
-                                Ok(xous::Result::Ok) => {}
-                                _ => panic!("unhandled error or result in callback processing")
-                        }
-                    }
-                }*/
-            }),
+            }
             None => {
                 log::error!("couldn't convert opcode");
             }
@@ -159,6 +141,35 @@ fn shell_main() -> ! {
     }
 }
 
+#[xous::xous_main]
+fn shell_main() -> ! {
+    log_server::init_wait().unwrap();
+    log::set_max_level(log::LevelFilter::Info);
+    log::info!("my PID is {}", xous::process::id());
+
+    let xns = xous_names::XousNames::new().unwrap();
+    let server = xns
+        .register_name(api::SERVER_NAME)
+        .expect("can't register server");
+
+    // The periodic tick and the message loop are two tasks on this one thread, rather than a
+    // pump thread that sends a Tick message to the main loop.
+    let ticktimer = ticktimer_server::Ticktimer::new().expect("Couldn't connect to Ticktimer");
+    let mut executor = Executor::new(server, move || ticktimer.elapsed_ms());
+    let state = Rc::new(RefCell::new(State {
+        tick_cb: [None; 32],
+        req_cb: [false; xous::MAX_CID],
+        ticks: 0,
+        state: 0,
+    }));
+
+    executor.spawn(pump(executor.handle(), state.clone()));
+    log::info!("pump task started");
+    executor.spawn(serve(executor.handle(), state));
+    executor.run();
+    panic!("server tasks exited");
+}
+
 fn do_hook(hookdata: ScalarHook, cb_conns: &mut [Option<ScalarCallback>; 32]) {
     let (s0, s1, s2, s3) = hookdata.sid;
     let sid = xous::SID::from_u32(s0, s1, s2, s3);
@@ -66,7 +66,8 @@ pub struct UxRegistration {
     pub listener: [u32; 4],
     /// opcode ID for redraw messages. This is mandatory.
     pub redraw_id: u32,
-    /// optional opcode ID for inputs. If presented, input Strings are sent to this Ux
+    /// optional opcode ID for inputs. If presented, input lines are sent to this Ux as a
+    /// `xous_ipc::ChunkedBuffer` of UTF-8, to be put back together with a `xous_ipc::Reassembler`
     pub gotinput_id: Option<u32>,
     /// optional opcode ID for audio frames. If presented, audio callbacks requests for more play/rec data will be sent directly to this opcode
     pub audioframe_id: Option<u32>,
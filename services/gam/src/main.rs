@@ -476,14 +476,15 @@ impl ContextManager {
     pub(crate) fn focused_app(&self) -> Option<[u32; 4]> {
         self.focused_context
     }
-    pub(crate) fn forward_input(&self, input: String::<4000>) -> Result<(), xous::Error> {
+    pub(crate) fn forward_input(&self, input: &str) -> Result<(), xous::Error> {
         if let Some(token) = self.focused_app() {
             for maybe_context in self.contexts.iter() {
                 if let Some(context) = maybe_context {
                     if token == context.app_token {
                         if let Some(input_op) = context.gotinput_id {
-                            let buf = Buffer::into_buf(input).or(Err(xous::Error::InternalError)).unwrap();
-                            return buf.send(context.listener, input_op).map(|_| ())
+                            // apps take their input in chunks, so the line isn't capped by a fixed-size message
+                            let line = xous_ipc::ChunkedBuffer::from(input);
+                            return line.send(context.listener, input_op)
                         }
                     }
                 }
@@ -580,10 +581,10 @@ impl ContextManager {
 
 
 static CB_TO_MAIN_CONN: AtomicU32 = AtomicU32::new(0);
-fn imef_cb(s: String::<4000>) {
+fn imef_cb(s: &str) {
     if CB_TO_MAIN_CONN.load(Ordering::Relaxed) != 0 {
         let cb_to_main_conn = CB_TO_MAIN_CONN.load(Ordering::Relaxed);
-        let buf = xous_ipc::Buffer::into_buf(s).or(Err(xous::Error::InternalError)).unwrap();
+        let buf = xous_ipc::ChunkedBuffer::from(s);
         buf.lend(cb_to_main_conn, Opcode::InputLine.to_u32().unwrap()).unwrap();
     }
 }
@@ -606,6 +607,8 @@ fn xmain() -> ! {
     let trng = trng::Trng::new(&xns).expect("can't connect to TRNG");
 
     let mut context_mgr = ContextManager::new(&xns);
+    // input lines from the IMEF come in chunks, and are passed on once they're whole
+    let mut input_lines = xous_ipc::Reassembler::new(ime_plugin_api::MAX_INPUT_LINE_LEN);
 
     // a map of canvases accessable by Gid
     let mut canvases: HashMap<Gid, Canvas> = HashMap::new();
@@ -932,11 +935,16 @@ fn xmain() -> ! {
             },
             Some(Opcode::InputLine) => {
                 // receive the keyboard input and pass it on to the context with focus
-                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
-                let inputline = buffer.to_original::<String::<4000>, _>().unwrap();
-                log::debug!("received input line, forwarding on... {}", inputline);
-                context_mgr.forward_input(inputline).expect("couldn't forward input line to focused app");
-                log::debug!("returned from forward_input");
+                match input_lines.receive(&msg) {
+                    Ok(Some(line)) => {
+                        let inputline = std::string::String::from_utf8_lossy(&line);
+                        log::debug!("received input line, forwarding on... {}", inputline);
+                        context_mgr.forward_input(&inputline).expect("couldn't forward input line to focused app");
+                        log::debug!("returned from forward_input");
+                    }
+                    Ok(None) => (), // more of the line is still to come
+                    Err(e) => log::error!("couldn't receive input line: {:?}", e),
+                }
             },
             Some(Opcode::KeyboardEvent) => msg_scalar_unpack!(msg, k1, k2, k3, k4, {
                 let keys = [
@@ -85,6 +85,12 @@ impl TextEntryPayload {
         self.0.as_str().expect("couldn't convert textentry string")
     }
 }
+/// Lets a received password be held in a `xous_ipc::Zeroizing`, so it is wiped when it goes out of scope.
+impl xous_ipc::VolatileClear for TextEntryPayload {
+    fn volatile_clear(&mut self) {
+        TextEntryPayload::volatile_clear(self)
+    }
+}
 
 #[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 pub struct RadioButtonPayload(ItemName); // returns the name of the item corresponding to the radio button selection
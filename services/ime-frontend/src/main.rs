@@ -717,22 +717,15 @@ fn xmain() -> ! {
                                 for maybe_conn in listeners.iter_mut() {
                                     if let Some(conn) = maybe_conn {
                                         if dbglistener{info!("sending to conn {:?}", conn);}
-                                        let buf = Buffer::into_buf(line).or(Err(xous::Error::InternalError)).unwrap();
+                                        let buf = xous_ipc::ChunkedBuffer::from(line.to_str());
                                         match buf.send(*conn, ImefCallback::GotInputLine.to_u32().unwrap()) {
                                             Err(xous::Error::ServerNotFound) => {
                                                 *maybe_conn = None // automatically de-allocate callbacks for clients that have dropped
                                             },
-                                            Ok(xous::Result::Ok) => {},
-                                            Ok(xous::Result::MemoryReturned(offset, valid)) => {
-                                                // ignore anything that's returned, but note it in case we're debugging
-                                                log::trace!("memory was returned in callback: offset {:?}, valid {:?}", offset, valid);
-                                            },
+                                            Ok(()) => {},
                                             Err(e) => {
                                                 log::error!("unhandled error in callback processing: {:?}", e);
                                             }
-                                            Ok(e) => {
-                                                log::error!("unexpected result in callback processing: {:?}", e);
-                                            }
                                         }
                                     }
                                 }
@@ -212,7 +212,8 @@ pub enum ImefOpcode {
 }
 #[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
 pub enum ImefCallback {
-    GotInputLine, //(String<4000>),
+    /// a finished line, as a `xous_ipc::ChunkedBuffer` of UTF-8
+    GotInputLine,
     Drop,
 }
 
@@ -226,7 +227,7 @@ pub struct ImefDescriptor {
 
 pub trait ImeFrontEndApi {
     fn connect_backend(&self, descriptor: ImefDescriptor) -> Result<(), xous::Error>;
-    fn hook_listener_callback(&mut self, cb: fn(String<4000>)) -> Result<(), xous::Error>;
+    fn hook_listener_callback(&mut self, cb: fn(&str)) -> Result<(), xous::Error>;
     fn redraw(&self, force_all: bool) -> Result<(), xous::Error>;
     fn send_keyevent(&self, keys: [char; 4]) -> Result<(), xous::Error>;
     fn conn(&self) -> xous::CID;
@@ -234,7 +235,9 @@ pub trait ImeFrontEndApi {
 }
 
 pub const SERVER_NAME_IME_FRONT: &str = "_IME front end_";
-static mut INPUT_CB: Option<fn(String<4000>)> = None;
+static mut INPUT_CB: Option<fn(&str)> = None;
+/// longest input line a listener puts back together
+pub const MAX_INPUT_LINE_LEN: usize = 16384;
 
 pub struct ImeFrontEnd {
     cid: CID,
@@ -309,7 +312,7 @@ impl ImeFrontEndApi for ImeFrontEnd {
         .map(|_| ())
     }
 
-    fn hook_listener_callback(&mut self, cb: fn(String<4000>)) -> Result<(), xous::Error> {
+    fn hook_listener_callback(&mut self, cb: fn(&str)) -> Result<(), xous::Error> {
         if unsafe { INPUT_CB }.is_some() {
             return Err(xous::Error::MemoryInUse); // can't hook it twice
         }
@@ -354,19 +357,19 @@ impl ImeFrontEndApi for ImeFrontEnd {
 /// handles callback messages from server, in the library user's process space.
 fn callback_server(sid0: usize, sid1: usize, sid2: usize, sid3: usize) {
     let sid = xous::SID::from_u32(sid0 as u32, sid1 as u32, sid2 as u32, sid3 as u32);
+    let mut lines = xous_ipc::Reassembler::new(MAX_INPUT_LINE_LEN);
     loop {
         let msg = xous::receive_message(sid).unwrap();
         match FromPrimitive::from_usize(msg.body.id()) {
-            Some(ImefCallback::GotInputLine) => {
-                let buffer =
-                    unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
-                let inputline = buffer.to_original::<String<4000>, _>().unwrap();
-                unsafe {
+            Some(ImefCallback::GotInputLine) => match lines.receive(&msg) {
+                Ok(Some(line)) => unsafe {
                     if let Some(cb) = INPUT_CB {
-                        cb(inputline)
+                        cb(&std::string::String::from_utf8_lossy(&line))
                     }
-                }
-            }
+                },
+                Ok(None) => (), // more of the line is still to come
+                Err(e) => log::error!("couldn't receive input line: {:?}", e),
+            },
             Some(ImefCallback::Drop) => {
                 break; // this exits the loop and kills the thread
             }
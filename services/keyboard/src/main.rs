@@ -849,8 +849,9 @@ fn xmain() -> ! {
     let self_cid = xous::connect(kbd_sid).expect("couldn't create suspend callback connection");
     let mut susres = susres::Susres::new(&xns, Opcode::SuspendResume as u32, self_cid).expect("couldn't create suspend/resume object");
 
-    // start a thread that can ping the keyboard loop when a key is held down
+    // the ticktimer pings the keyboard loop while a key is held down
     let ticktimer = ticktimer_server::Ticktimer::new().unwrap();
+    let mut repeat_check: Option<ticktimer_server::TimerCallback> = None;
 
     let mut listener_conn: Option<CID> = None;
     let mut listener_op: Option<usize> = None;
@@ -952,6 +953,13 @@ fn xmain() -> ! {
                 }
             }),
             Some(Opcode::HandlerTrigger) => {
+                // a repeat check comes from the ticktimer, not the interrupt, so nothing has read the keys yet
+                if let Some(check) = repeat_check {
+                    if msg.body.scalar_message().map(|m| m.arg1) == Some(check.to_usize()) {
+                        repeat_check = None;
+                        kbd.poll();
+                    }
+                }
                 let rawstates = kbd.update();
 
                 // interpret scancodes
@@ -986,14 +994,15 @@ fn xmain() -> ! {
                     ).expect("couldn't send key codes to listener");
                 }
                 // as long as we have a keydown, keep pinging the loop at a high rate. this consumes more power, but keydowns are relatively rare.
-                if kbd.is_repeating_key() {
+                if kbd.is_repeating_key() && repeat_check.is_none() {
                     log::trace!("keydowns hold");
                     // fire a second call to check if we should transition to a repeating state
-                    ticktimer.sleep_ms(kbd.get_repeat_check_interval() as _).unwrap();
-                    kbd.poll();
-                    xous::send_message(self_cid,
-                        xous::Message::new_scalar(Opcode::HandlerTrigger.to_usize().unwrap(), 0, 0, 0, 0)
-                    ).unwrap();
+                    repeat_check = Some(ticktimer.schedule_callback(
+                        kbd.get_repeat_check_interval() as _,
+                        false,
+                        self_cid,
+                        Opcode::HandlerTrigger.to_usize().unwrap(),
+                    ).expect("couldn't schedule key repeat check"));
                 }
             },
             None => {log::error!("couldn't convert opcode"); break}
@@ -9,11 +9,105 @@ pub struct LogRecord {
     pub level: u32,
     pub args_length: u32,
     pub args: [u8; 3000],
+    /// Filled in by the log server when the record is lent mutably: the
+    /// generation of level rules, so the logger can tell when to fetch them
+    pub generation: u32,
+}
+
+/// Physical address of the RAM that holds the persistent log. `create-image`
+/// carves this off the start of main RAM as the "plog" region, so that
+/// neither the loader nor the kernel clears it, and what is in it survives a
+/// CPU reboot. `create-image` refuses to build an image whose RAM doesn't
+/// start here.
+#[allow(dead_code)] // only mapped on hardware
+pub const PERSISTENT_LOG_BASE: usize = 0x4000_0000;
+pub const PERSISTENT_LOG_LEN: usize = 0x4000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, num_derive::FromPrimitive, num_derive::ToPrimitive)]
+pub enum LogSource {
+    /// Everything printed since this boot, or as much of it as fits
+    ThisBoot = 0,
+    /// What was in the persistent log when the log server started
+    PreviousBoot = 1,
+}
+
+/// The processes that may read `LogSource::PreviousBoot`.
+///
+/// The loader's `clear_ram` wipes main memory at boot so that nothing from
+/// one boot is visible to the next, but the persistent log is kept out of
+/// main memory precisely so that it isn't wiped. Whatever was logged before
+/// the reboot, secrets included, is therefore still there, so only these
+/// processes get to see it. The names are the ones the loader gives the
+/// processes in the image, which a process can't change.
+pub const PREVIOUS_LOG_READERS: &[&str] = &["shellchat"];
+
+#[repr(C)]
+pub struct LogDump {
+    /// A `LogSource`, filled in by the caller
+    pub source: u32,
+    /// A `xous::Error`, filled in by the log server
+    pub result: u32,
+    pub length: u32,
+    pub data: [u8; PERSISTENT_LOG_LEN],
+}
+
+/// Which records a `LevelRule` applies to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, num_derive::FromPrimitive, num_derive::ToPrimitive)]
+pub enum LevelTarget {
+    /// Every record from the processes with this name
+    Process = 0,
+    /// Records from this module and the modules under it, in any process
+    Module = 1,
+}
+
+/// `LevelRule.level` that removes the rule rather than setting one
+pub const LEVEL_DEFAULT: u32 = u32::MAX;
+
+/// How many rules there can be at once
+pub const MAX_LEVEL_RULES: usize = 16;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct LevelRule {
+    /// A `LevelTarget`
+    pub target: u32,
+    /// A `log::LevelFilter`, or `LEVEL_DEFAULT`
+    pub level: u32,
+    pub name_length: u32,
+    pub name: [u8; 128],
+    /// A `xous::Error`, filled in by the log server when setting a rule
+    pub result: u32,
+}
+
+#[repr(C)]
+pub struct LevelRules {
+    /// Filled in by the caller: nonzero to get every rule, rather than just
+    /// the ones that apply to the caller
+    pub all: u32,
+    pub count: u32,
+    pub rules: [LevelRule; MAX_LEVEL_RULES],
+}
+
+pub fn level_to_u32(level: log::LevelFilter) -> u32 {
+    level as usize as u32
+}
+
+pub fn level_from_u32(level: u32) -> Option<log::LevelFilter> {
+    match level {
+        0 => Some(log::LevelFilter::Off),
+        1 => Some(log::LevelFilter::Error),
+        2 => Some(log::LevelFilter::Warn),
+        3 => Some(log::LevelFilter::Info),
+        4 => Some(log::LevelFilter::Debug),
+        5 => Some(log::LevelFilter::Trace),
+        _ => None,
+    }
 }
 
 #[derive(Debug, PartialEq, num_derive::FromPrimitive, num_derive::ToPrimitive)]
 pub enum Opcode {
-    /// A `LogRecord` message, delivering structured log output
+    /// A `LogRecord` message, delivering structured log output. If it is lent
+    /// mutably, the log server fills in `generation`.
     LogRecord = 0,
 
     /// A `&[u8]` destined for stdout
@@ -25,6 +119,25 @@ pub enum Opcode {
     /// A `xous::StringBuffer` containing this program's name
     ProgramName = 3,
 
+    /// A mutable `Buffer` holding a `LogDump`, which is filled in with the
+    /// contents of the log. Only `PREVIOUS_LOG_READERS` may read the previous
+    /// boot's log; anyone else gets `AccessDenied`.
+    ReadLog = 4,
+
+    /// A mutable `Buffer` holding a `LevelRule` to add, change or remove, with
+    /// `result` filled in by the log server
+    SetLevelRule = 5,
+
+    /// A mutable `Buffer` holding `LevelRules`, which is filled in with the
+    /// rules that apply to the caller
+    GetLevelRules = 6,
+
+    /// Wait until the rules change. This is a blocking scalar whose first
+    /// argument is the generation of rules the caller knows about. The
+    /// reply is the current generation, once it's different. A process may
+    /// have one of these outstanding at a time.
+    WaitLevelChange = 7,
+
     /// A panic occurred, and a panic log is forthcoming
     PanicStarted = 1000,
 
@@ -66,6 +179,10 @@ pub enum Opcode {
     /// End of a panic
     PanicFinished = 1200,
 
+    /// A process took an exception, and a crash report is forthcoming. The
+    /// report is sent as panic messages.
+    CrashStarted = 1300,
+
     /// Enable receiving messages when the system is resumed from sleep.
     EnableRx = 2000,
 }
@@ -0,0 +1,140 @@
+//! Log levels set at runtime, as applied by the logger in each process.
+//!
+//! `log::max_level()` is what the `log` macros check before a record is even
+//! built, so it's raised to the most verbose level that any rule allows, and
+//! the rules themselves are checked for each record that gets that far. The
+//! level the process picked for itself is put back once no rules are left.
+
+use log::{Level, LevelFilter};
+
+#[derive(Default)]
+pub struct Filter {
+    /// The level the process had set before any rules applied to it, which
+    /// goes for anything the rules don't cover
+    default: Option<LevelFilter>,
+    /// The level for this process as a whole
+    process: Option<LevelFilter>,
+    /// Levels by module path
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Replace the rules. `current` is the maximum level as it stands, and
+    /// the return value is what it should be now.
+    pub fn update(
+        &mut self,
+        current: LevelFilter,
+        process: Option<LevelFilter>,
+        modules: Vec<(String, LevelFilter)>,
+    ) -> LevelFilter {
+        if process.is_none() && modules.is_empty() {
+            self.process = None;
+            self.modules.clear();
+            return self.default.take().unwrap_or(current);
+        }
+        let default = *self.default.get_or_insert(current);
+        let most_verbose = modules.iter().map(|(_, level)| *level).max();
+        self.process = process;
+        self.modules = modules;
+        process
+            .unwrap_or(default)
+            .max(most_verbose.unwrap_or(LevelFilter::Off))
+    }
+
+    /// Whether to log a record from `module` at `level`. The module with the
+    /// longest path that `module` falls under has the last word.
+    pub fn enabled(&self, module: &str, level: Level) -> bool {
+        let default = match self.default {
+            Some(default) => default,
+            // `log::max_level()` has already taken care of it.
+            None => return true,
+        };
+        let limit = self
+            .modules
+            .iter()
+            .filter(|(path, _)| within(module, path))
+            .max_by_key(|(path, _)| path.len())
+            .map(|(_, level)| *level)
+            .or(self.process)
+            .unwrap_or(default);
+        level <= limit
+    }
+}
+
+/// Whether `module` is `path` or one of the modules under it
+fn within(module: &str, path: &str) -> bool {
+    match module.strip_prefix(path) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modules(rules: &[(&str, LevelFilter)]) -> Vec<(String, LevelFilter)> {
+        rules.iter().map(|(p, l)| (p.to_string(), *l)).collect()
+    }
+
+    #[test]
+    fn no_rules_leaves_the_process_level_alone() {
+        let mut filter = Filter::new();
+        assert_eq!(
+            filter.update(LevelFilter::Info, None, vec![]),
+            LevelFilter::Info
+        );
+        assert!(filter.enabled("gam", Level::Trace));
+    }
+
+    #[test]
+    fn module_rules_raise_the_maximum() {
+        let mut filter = Filter::new();
+        let max = filter.update(
+            LevelFilter::Info,
+            None,
+            modules(&[("gam::layouts", LevelFilter::Trace)]),
+        );
+        assert_eq!(max, LevelFilter::Trace);
+        assert!(filter.enabled("gam::layouts", Level::Trace));
+        assert!(filter.enabled("gam::layouts::chat", Level::Trace));
+        assert!(!filter.enabled("gam::layoutsx", Level::Debug));
+        assert!(!filter.enabled("gam", Level::Debug));
+        assert!(filter.enabled("gam", Level::Info));
+    }
+
+    #[test]
+    fn the_longest_path_wins() {
+        let mut filter = Filter::new();
+        filter.update(
+            LevelFilter::Info,
+            Some(LevelFilter::Warn),
+            modules(&[
+                ("gam", LevelFilter::Off),
+                ("gam::layouts", LevelFilter::Debug),
+            ]),
+        );
+        assert!(!filter.enabled("gam::api", Level::Error));
+        assert!(filter.enabled("gam::layouts", Level::Debug));
+        assert!(!filter.enabled("shellchat", Level::Info));
+        assert!(filter.enabled("shellchat", Level::Warn));
+    }
+
+    #[test]
+    fn clearing_the_rules_restores_the_process_level() {
+        let mut filter = Filter::new();
+        filter.update(LevelFilter::Info, Some(LevelFilter::Trace), vec![]);
+        // Later updates don't mistake the raised level for the process' own.
+        filter.update(LevelFilter::Trace, Some(LevelFilter::Debug), vec![]);
+        assert!(!filter.enabled("gam", Level::Trace));
+        assert_eq!(
+            filter.update(LevelFilter::Debug, None, vec![]),
+            LevelFilter::Info
+        );
+        assert!(filter.enabled("gam", Level::Trace));
+    }
+}
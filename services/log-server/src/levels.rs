@@ -0,0 +1,199 @@
+//! Log levels set at runtime, for a process by name or for a module path.
+//!
+//! The rules are kept here, but applied by each process' logger, so that a
+//! record that would be dropped never makes it into a message. The reply to
+//! every record carries the generation of the rules, and a logger that sees
+//! a new one fetches the rules that apply to it. A process that wants to hear
+//! sooner keeps a thread waiting on a `WaitLevelChange` request instead,
+//! which is answered whenever the rules change.
+
+use log::LevelFilter;
+use xous::{MessageSender, PID};
+
+use crate::api::{LevelTarget, MAX_LEVEL_RULES};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub target: LevelTarget,
+    pub name: String,
+    pub level: LevelFilter,
+}
+
+pub struct Levels {
+    rules: Vec<Rule>,
+    /// Bumped every time the rules change
+    generation: u32,
+    /// Loggers waiting for the rules to change
+    waiters: Vec<MessageSender>,
+}
+
+impl Levels {
+    pub fn new() -> Self {
+        Levels {
+            rules: Vec::new(),
+            generation: 0,
+            waiters: Vec::new(),
+        }
+    }
+
+    /// Set the level for `target`, or go back to the default if `level` is
+    /// None. Returns the loggers to tell about the new generation of rules.
+    ///
+    /// # Errors
+    ///
+    /// * **QuotaExceeded**: There are already `MAX_LEVEL_RULES` rules
+    pub fn set(
+        &mut self,
+        target: LevelTarget,
+        name: &str,
+        level: Option<LevelFilter>,
+    ) -> Result<Vec<MessageSender>, xous::Error> {
+        let existing = self
+            .rules
+            .iter()
+            .position(|r| r.target == target && r.name == name);
+        match (existing, level) {
+            (Some(index), Some(level)) => self.rules[index].level = level,
+            (Some(index), None) => {
+                self.rules.remove(index);
+            }
+            (None, Some(level)) => {
+                if self.rules.len() >= MAX_LEVEL_RULES {
+                    return Err(xous::Error::QuotaExceeded);
+                }
+                self.rules.push(Rule {
+                    target,
+                    name: name.to_owned(),
+                    level,
+                });
+            }
+            (None, None) => return Ok(Vec::new()),
+        }
+        self.generation = self.generation.wrapping_add(1);
+        Ok(core::mem::take(&mut self.waiters))
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Have `sender` wait for a generation other than `known`, returning the
+    /// current generation straight away if it's already different.
+    pub fn wait(&mut self, sender: MessageSender, known: u32) -> Option<u32> {
+        if known != self.generation {
+            return Some(self.generation);
+        }
+        self.waiters.push(sender);
+        None
+    }
+
+    /// Forget the loggers waiting in processes that `alive` says have gone
+    /// away, so that they neither pile up nor get a reply meant for a process
+    /// that has since taken over the PID.
+    pub fn prune(&mut self, alive: impl Fn(PID) -> bool) {
+        self.waiters
+            .retain(|waiter| waiter.pid().map_or(false, |pid| alive(pid)));
+    }
+
+    pub fn all(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// The rules that apply to the process named `process`: its own, if it
+    /// has one, followed by every module rule.
+    pub fn for_process<'a>(&'a self, process: &'a str) -> impl Iterator<Item = &'a Rule> {
+        self.rules.iter().filter(move |r| match r.target {
+            LevelTarget::Process => r.name == process,
+            LevelTarget::Module => true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_wake_waiters() {
+        let mut levels = Levels::new();
+        let waiter = MessageSender::from_usize(1);
+        assert_eq!(levels.wait(waiter, 0), None);
+        assert_eq!(
+            levels.set(LevelTarget::Module, "gam", Some(LevelFilter::Debug)),
+            Ok(vec![waiter])
+        );
+        assert_eq!(levels.wait(waiter, 0), Some(1));
+
+        // Clearing a rule that isn't there changes nothing.
+        assert_eq!(levels.set(LevelTarget::Module, "com", None), Ok(vec![]));
+        assert_eq!(levels.generation(), 1);
+    }
+
+    #[test]
+    fn departed_waiters_are_forgotten() {
+        let mut levels = Levels::new();
+        let stays = MessageSender::from_usize(2 << 24);
+        let goes = MessageSender::from_usize(3 << 24);
+        assert_eq!(levels.wait(stays, 0), None);
+        assert_eq!(levels.wait(goes, 0), None);
+
+        levels.prune(|pid| pid != goes.pid().unwrap());
+        assert_eq!(
+            levels.set(LevelTarget::Module, "gam", Some(LevelFilter::Debug)),
+            Ok(vec![stays])
+        );
+    }
+
+    #[test]
+    fn rules_are_replaced_and_removed() {
+        let mut levels = Levels::new();
+        levels
+            .set(LevelTarget::Process, "gam", Some(LevelFilter::Debug))
+            .unwrap();
+        levels
+            .set(LevelTarget::Module, "gam", Some(LevelFilter::Warn))
+            .unwrap();
+        levels
+            .set(LevelTarget::Process, "gam", Some(LevelFilter::Trace))
+            .unwrap();
+        assert_eq!(levels.all().len(), 2);
+        assert_eq!(levels.all()[0].level, LevelFilter::Trace);
+
+        levels.set(LevelTarget::Process, "gam", None).unwrap();
+        assert_eq!(levels.all().len(), 1);
+        assert_eq!(levels.all()[0].target, LevelTarget::Module);
+    }
+
+    #[test]
+    fn processes_only_see_their_own_rule() {
+        let mut levels = Levels::new();
+        levels
+            .set(LevelTarget::Process, "gam", Some(LevelFilter::Debug))
+            .unwrap();
+        levels
+            .set(LevelTarget::Process, "com", Some(LevelFilter::Off))
+            .unwrap();
+        levels
+            .set(LevelTarget::Module, "xous_names", Some(LevelFilter::Trace))
+            .unwrap();
+        let rules: Vec<&str> = levels.for_process("com").map(|r| r.name.as_str()).collect();
+        assert_eq!(rules, vec!["com", "xous_names"]);
+    }
+
+    #[test]
+    fn the_table_has_a_limit() {
+        let mut levels = Levels::new();
+        for i in 0..MAX_LEVEL_RULES {
+            let name = format!("module{}", i);
+            levels
+                .set(LevelTarget::Module, &name, Some(LevelFilter::Info))
+                .unwrap();
+        }
+        assert_eq!(
+            levels.set(LevelTarget::Module, "one_more", Some(LevelFilter::Info)),
+            Err(xous::Error::QuotaExceeded)
+        );
+        // Changing an existing rule still works.
+        assert!(levels.set(LevelTarget::Module, "module0", None).is_ok());
+    }
+}
@@ -6,6 +6,7 @@ use xous_ipc::Buffer;
 
 pub mod api;
 mod cursor;
+mod filter;
 
 #[derive(Debug)]
 pub enum LogError {
@@ -27,6 +28,9 @@ static mut XOUS_LOGGER_BACKING: Option<XousLoggerBacking> = None;
 struct XousLoggerBacking<'a> {
     conn: xous::CID,
     buffer: Buffer<'a>,
+    filter: filter::Filter,
+    /// The generation of level rules that `filter` was last updated from
+    generation: u32,
 }
 
 impl<'a> XousLoggerBacking<'a> {
@@ -34,6 +38,8 @@ impl<'a> XousLoggerBacking<'a> {
         Ok(XousLoggerBacking {
             conn: xous::connect(xous::SID::from_bytes(b"xous-log-server ").unwrap())?,
             buffer: Buffer::new(BUFFER_SIZE),
+            filter: filter::Filter::new(),
+            generation: 0,
         })
     }
 }
@@ -43,6 +49,8 @@ impl Default for XousLoggerBacking<'_> {
         XousLoggerBacking {
             conn: xous::connect(xous::SID::from_bytes(b"xous-log-server ").unwrap()).unwrap(),
             buffer: Buffer::new(BUFFER_SIZE),
+            filter: filter::Filter::new(),
+            generation: 0,
         }
     }
 }
@@ -71,11 +79,36 @@ impl XousLoggerBacking<'_> {
             let mut wrapper = cursor::BufferWrapper::new(&mut log_record.args);
             write!(wrapper, "{}", record.args()).unwrap();
             log_record.args_length = wrapper.len() as u32;
+            log_record.generation = self.generation;
         }
 
         self.buffer
-            .lend(self.conn, crate::api::Opcode::LogRecord.to_u32().unwrap())
+            .lend_mut(self.conn, crate::api::Opcode::LogRecord.to_u32().unwrap())
             .unwrap();
+
+        // The reply says whether the level rules have changed since we last looked
+        let generation = unsafe { &*(self.buffer.as_ptr() as *const api::LogRecord) }.generation;
+        if generation != self.generation {
+            self.update_levels(generation);
+        }
+    }
+
+    /// Fetch the level rules that apply to this process, and apply them.
+    fn update_levels(&mut self, generation: u32) {
+        let rules = match get_level_rules(self.conn, false) {
+            Ok(rules) => rules,
+            Err(_) => return,
+        };
+        self.generation = generation;
+        let mut process = None;
+        let mut modules = Vec::new();
+        for (target, name, level) in rules {
+            match target {
+                api::LevelTarget::Process => process = Some(level),
+                api::LevelTarget::Module => modules.push((name, level)),
+            }
+        }
+        log::set_max_level(self.filter.update(log::max_level(), process, modules));
     }
     fn resume(&self) {
         xous::send_message(
@@ -86,12 +119,8 @@ impl XousLoggerBacking<'_> {
     }
 }
 
-impl log::Log for XousLogger {
-    fn enabled(&self, _metadata: &log::Metadata) -> bool {
-        true
-    }
-
-    fn log(&self, record: &log::Record) {
+impl XousLogger {
+    fn with_backing<R>(&self, f: impl FnOnce(&mut XousLoggerBacking) -> R) -> R {
         while self
             .locked
             .compare_exchange(false, true, Ordering::SeqCst, Ordering::Acquire)
@@ -103,10 +132,28 @@ impl log::Log for XousLogger {
         if unsafe { XOUS_LOGGER_BACKING.is_none() } {
             unsafe { XOUS_LOGGER_BACKING = Some(XousLoggerBacking::default()) };
         }
-        unsafe { XOUS_LOGGER_BACKING.as_mut().unwrap().log_impl(record) };
+        let result = f(unsafe { XOUS_LOGGER_BACKING.as_mut().unwrap() });
         self.locked
             .compare_exchange(true, false, Ordering::SeqCst, Ordering::Acquire)
             .expect("LOG: logger became unlocked somehow");
+        result
+    }
+}
+
+impl log::Log for XousLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.with_backing(|backing| backing.filter.enabled(metadata.target(), metadata.level()))
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.with_backing(|backing| {
+            // Filtering here, rather than in the log server, saves sending
+            // records that would only be thrown away.
+            let module = record.module_path().unwrap_or_default();
+            if backing.filter.enabled(module, record.level()) {
+                backing.log_impl(record);
+            }
+        })
     }
     fn flush(&self) {}
 }
@@ -118,6 +165,7 @@ pub fn init() -> Result<(), LogError> {
         }
         log::set_logger(&XOUS_LOGGER).map_err(|_| LogError::LoggerExists)?;
         log::set_max_level(log::LevelFilter::Info);
+        apply_level_rules();
         Ok(())
     } else {
         Err(LogError::NoConnection)
@@ -136,9 +184,157 @@ pub fn init_wait() -> Result<(), log::SetLoggerError> {
     }
     log::set_logger(&XOUS_LOGGER)?;
     log::set_max_level(log::LevelFilter::Info);
+    apply_level_rules();
     Ok(())
 }
 
+/// Apply whatever level rules were set before this process started. Later
+/// changes are noticed when the log server replies to a record.
+fn apply_level_rules() {
+    XOUS_LOGGER.with_backing(|backing| backing.update_levels(backing.generation));
+}
+
+static WATCHING_LEVELS: AtomicBool = AtomicBool::new(false);
+
+/// Apply changes to the level rules as soon as they are made, rather than
+/// with the next record that this process logs. This costs a thread that
+/// waits on the log server for as long as the process runs, so it is only
+/// worth it for a process that might log nothing until its level is raised.
+pub fn watch_levels() -> Result<(), xous::Error> {
+    if WATCHING_LEVELS.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    xous::create_thread_0(wait_for_levels).map(|_| ())
+}
+
+fn wait_for_levels() {
+    let conn = match xous::connect(xous::SID::from_bytes(b"xous-log-server ").unwrap()) {
+        Ok(conn) => conn,
+        Err(_) => return,
+    };
+    loop {
+        let known = XOUS_LOGGER.with_backing(|backing| backing.generation);
+        let generation = match xous::send_message(
+            conn,
+            xous::Message::new_blocking_scalar(
+                api::Opcode::WaitLevelChange.to_usize().unwrap(),
+                known as usize,
+                0,
+                0,
+                0,
+            ),
+        ) {
+            Ok(xous::Result::Scalar1(generation)) => generation as u32,
+            _ => return,
+        };
+        XOUS_LOGGER.with_backing(|backing| {
+            if generation != backing.generation {
+                backing.update_levels(generation);
+            }
+        });
+    }
+}
+
 pub fn resume() {
     unsafe { XOUS_LOGGER_BACKING.as_mut().unwrap().resume() };
 }
+
+/// What the log server has printed since this boot, or as much of the end of
+/// it as it has kept.
+pub fn read_log() -> Result<String, xous::Error> {
+    read_log_source(api::LogSource::ThisBoot)
+}
+
+/// What the log server had printed before the last reboot, if the reboot left
+/// its memory intact.
+///
+/// # Errors
+///
+/// * **AccessDenied**: The caller isn't one of `api::PREVIOUS_LOG_READERS`
+pub fn read_previous_log() -> Result<String, xous::Error> {
+    read_log_source(api::LogSource::PreviousBoot)
+}
+
+fn read_log_source(source: api::LogSource) -> Result<String, xous::Error> {
+    let conn = xous::connect(xous::SID::from_bytes(b"xous-log-server ").unwrap())?;
+    let mut buffer = Buffer::new(core::mem::size_of::<api::LogDump>());
+    {
+        let dump = unsafe { &mut *(buffer.as_mut_ptr() as *mut api::LogDump) };
+        dump.source = source.to_u32().unwrap();
+        dump.result = xous::Error::InternalError as u32;
+        dump.length = 0;
+    }
+    buffer.lend_mut(conn, api::Opcode::ReadLog.to_u32().unwrap())?;
+    let dump = unsafe { &*(buffer.as_ptr() as *const api::LogDump) };
+    match xous::Error::from_usize(dump.result as usize) {
+        xous::Error::NoError => (),
+        e => return Err(e),
+    }
+    let length = (dump.length as usize).min(dump.data.len());
+    Ok(String::from_utf8_lossy(&dump.data[..length]).into_owned())
+}
+
+/// Set the log level for every process named `name`, or for the module at
+/// `name` and those under it, whichever process they're in. A level of None
+/// removes the rule, so the level goes back to what it would otherwise be.
+///
+/// # Errors
+///
+/// * **QuotaExceeded**: There are already `api::MAX_LEVEL_RULES` rules
+/// * **InvalidString**: `name` is too long
+pub fn set_level(
+    target: api::LevelTarget,
+    name: &str,
+    level: Option<log::LevelFilter>,
+) -> Result<(), xous::Error> {
+    let conn = xous::connect(xous::SID::from_bytes(b"xous-log-server ").unwrap())?;
+    let mut buffer = Buffer::new(core::mem::size_of::<api::LevelRule>());
+    {
+        let rule = unsafe { &mut *(buffer.as_mut_ptr() as *mut api::LevelRule) };
+        if name.len() > rule.name.len() {
+            return Err(xous::Error::InvalidString);
+        }
+        rule.target = target.to_u32().unwrap();
+        rule.level = level.map_or(api::LEVEL_DEFAULT, api::level_to_u32);
+        rule.name_length = name.len() as u32;
+        rule.name[..name.len()].copy_from_slice(name.as_bytes());
+        rule.result = xous::Error::InternalError as u32;
+    }
+    buffer.lend_mut(conn, api::Opcode::SetLevelRule.to_u32().unwrap())?;
+    let rule = unsafe { &*(buffer.as_ptr() as *const api::LevelRule) };
+    match xous::Error::from_usize(rule.result as usize) {
+        xous::Error::NoError => Ok(()),
+        e => Err(e),
+    }
+}
+
+/// Every log level rule that has been set
+pub fn level_rules() -> Result<Vec<(api::LevelTarget, String, log::LevelFilter)>, xous::Error> {
+    let conn = xous::connect(xous::SID::from_bytes(b"xous-log-server ").unwrap())?;
+    get_level_rules(conn, true)
+}
+
+fn get_level_rules(
+    conn: xous::CID,
+    all: bool,
+) -> Result<Vec<(api::LevelTarget, String, log::LevelFilter)>, xous::Error> {
+    let mut buffer = Buffer::new(core::mem::size_of::<api::LevelRules>());
+    {
+        let rules = unsafe { &mut *(buffer.as_mut_ptr() as *mut api::LevelRules) };
+        rules.all = all as u32;
+        rules.count = 0;
+    }
+    buffer.lend_mut(conn, api::Opcode::GetLevelRules.to_u32().unwrap())?;
+    let rules = unsafe { &*(buffer.as_ptr() as *const api::LevelRules) };
+    Ok(rules
+        .rules
+        .iter()
+        .take(rules.count as usize)
+        .filter_map(|rule| {
+            let target = num_traits::FromPrimitive::from_u32(rule.target)?;
+            let level = api::level_from_u32(rule.level)?;
+            let name = rule.name.get(..rule.name_length as usize)?;
+            Some((target, String::from_utf8_lossy(name).into_owned(), level))
+        })
+        .collect())
+}
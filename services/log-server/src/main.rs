@@ -3,6 +3,8 @@
 
 mod api;
 use api::*;
+mod levels;
+mod ring;
 
 #[cfg(any(target_os = "none", target_os = "xous"))]
 #[macro_use]
@@ -64,6 +66,12 @@ mod implementation {
         }
     }
 
+    /// There's nothing that would keep the log across runs, so this is just
+    /// the heap.
+    pub fn ring_memory() -> &'static mut [u8] {
+        Box::leak(vec![0u8; crate::api::PERSISTENT_LOG_LEN].into_boxed_slice())
+    }
+
     impl Drop for Output {
         fn drop(&mut self) {
             self.tx.send(ControlMessage::Exit).unwrap();
@@ -166,6 +174,28 @@ mod implementation {
         }
     }
 
+    /// The memory set aside for the log. It's outside of main RAM, so the
+    /// kernel doesn't clear it when it's mapped.
+    pub fn ring_memory() -> &'static mut [u8] {
+        match xous::syscall::map_memory(
+            xous::MemoryAddress::new(crate::api::PERSISTENT_LOG_BASE),
+            None,
+            crate::api::PERSISTENT_LOG_LEN,
+            xous::MemoryFlags::R | xous::MemoryFlags::W,
+        ) {
+            Ok(range) => unsafe {
+                core::slice::from_raw_parts_mut(range.as_mut_ptr(), range.len())
+            },
+            Err(e) => {
+                println!(
+                    "LOG: couldn't map the persistent log ({:?}), it won't survive a reboot",
+                    e
+                );
+                Box::leak(vec![0u8; crate::api::PERSISTENT_LOG_LEN].into_boxed_slice())
+            }
+        }
+    }
+
     fn handle_irq(_irq_no: usize, arg: *mut usize) {
         if cfg!(feature = "logging") {
             let mut inject_csr = CSR::new(arg as *mut u32);
@@ -220,8 +250,57 @@ mod implementation {
     }
 }
 
+/// Where everything the log server prints goes: to the output, and into the
+/// ring buffer log.
+struct Log<'a> {
+    output: &'a mut implementation::OutputWriter,
+    ring: ring::Ring,
+    /// What the ring held when the log server started
+    previous: Vec<u8>,
+}
+
+impl Log<'_> {
+    fn putc(&mut self, c: u8) {
+        self.ring.write(&[c]);
+        self.output.putc(c);
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> core::result::Result<usize, ()> {
+        self.ring.write(buf);
+        self.output.write_all(buf)
+    }
+
+    /// Copy as much of the log as fits into `dump`, keeping the newest part.
+    /// The previous boot's log is only handed over if `previous_allowed`.
+    fn read(&self, dump: &mut LogDump, previous_allowed: bool) -> Result<(), xous::Error> {
+        let this_boot;
+        let log = match FromPrimitive::from_u32(dump.source) {
+            Some(LogSource::PreviousBoot) if !previous_allowed => {
+                dump.length = 0;
+                return Err(xous::Error::AccessDenied);
+            }
+            Some(LogSource::PreviousBoot) => &self.previous,
+            Some(LogSource::ThisBoot) | None => {
+                this_boot = self.ring.contents();
+                &this_boot
+            }
+        };
+        let log = &log[log.len().saturating_sub(dump.data.len())..];
+        dump.data[..log.len()].copy_from_slice(log);
+        dump.length = log.len() as u32;
+        Ok(())
+    }
+}
+
+impl Write for Log<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.ring.write(s.as_bytes());
+        self.output.write_str(s)
+    }
+}
+
 fn handle_scalar(
-    output: &mut implementation::OutputWriter,
+    output: &mut Log,
     sender: xous::MessageSender,
     msg: &xous::ScalarMessage,
     sender_pid: xous::PID,
@@ -257,6 +336,7 @@ fn handle_scalar(
             }
         }
         1200 => writeln!(output, "Terminating process").unwrap(),
+        1300 => writeln!(output, "CRASH in PID {}:", sender_pid).unwrap(),
         2000 => {
             #[cfg(any(target_os = "none", target_os = "xous"))]
             crate::debug::DEFAULT.enable_rx();
@@ -272,11 +352,42 @@ fn handle_scalar(
 }
 
 fn handle_opcode(
-    output: &mut implementation::OutputWriter,
+    output: &mut Log,
+    levels: &mut levels::Levels,
     sender: xous::MessageSender,
     opcode: api::Opcode,
-    message: &xous::Message,
+    message: &mut xous::Message,
 ) {
+    match opcode {
+        // These are lent mutably, so that the reply can be written into them.
+        api::Opcode::ReadLog | api::Opcode::SetLevelRule | api::Opcode::GetLevelRules => {
+            if let Some(mem) = message.memory_message_mut() {
+                handle_mutable(output, levels, sender, opcode, mem);
+            }
+            return;
+        }
+        api::Opcode::WaitLevelChange => {
+            // The reply is held back until the rules change.
+            if let Some(scalar) = message.scalar_message() {
+                levels.prune(process_exists);
+                if let Some(generation) = levels.wait(sender, scalar.arg1 as u32) {
+                    xous::return_scalar(sender, generation as usize).ok();
+                }
+            }
+            return;
+        }
+        api::Opcode::LogRecord => {
+            // Loggers lend records mutably to hear about new level rules
+            if let Some(mem) = message.memory_message_mut() {
+                let mut buffer = unsafe { xous_ipc::Buffer::from_memory_message_mut(mem) };
+                if buffer.len() >= core::mem::size_of::<LogRecord>() {
+                    let lr = unsafe { &mut *(buffer.as_mut_ptr() as *mut LogRecord) };
+                    lr.generation = levels.generation();
+                }
+            }
+        }
+        _ => (),
+    }
     if let Some(mem) = message.memory_message() {
         match opcode {
             api::Opcode::LogRecord => {
@@ -359,13 +470,120 @@ fn handle_opcode(
             }
         }
     } else if let Some(scalar) = message.scalar_message() {
-        // Scalar message
         handle_scalar(output, sender, scalar, sender.pid().unwrap());
     }
 }
 
+fn handle_mutable(
+    output: &mut Log,
+    levels: &mut levels::Levels,
+    sender: xous::MessageSender,
+    opcode: api::Opcode,
+    mem: &mut xous::MemoryMessage,
+) {
+    match opcode {
+        api::Opcode::ReadLog => {
+            let mut buffer = unsafe { xous_ipc::Buffer::from_memory_message_mut(mem) };
+            if buffer.len() < core::mem::size_of::<LogDump>() {
+                return;
+            }
+            let dump = unsafe { &mut *(buffer.as_mut_ptr() as *mut LogDump) };
+            dump.result = match output.read(dump, may_read_previous_log(sender)) {
+                Ok(()) => xous::Error::NoError,
+                Err(e) => e,
+            } as u32;
+        }
+        api::Opcode::SetLevelRule => {
+            let mut buffer = unsafe { xous_ipc::Buffer::from_memory_message_mut(mem) };
+            if buffer.len() < core::mem::size_of::<LevelRule>() {
+                return;
+            }
+            let rule = unsafe { &mut *(buffer.as_mut_ptr() as *mut LevelRule) };
+            let result = set_level_rule(levels, rule);
+            rule.result = match result {
+                Ok(()) => xous::Error::NoError,
+                Err(e) => e,
+            } as u32;
+        }
+        api::Opcode::GetLevelRules => {
+            let mut buffer = unsafe { xous_ipc::Buffer::from_memory_message_mut(mem) };
+            if buffer.len() < core::mem::size_of::<LevelRules>() {
+                return;
+            }
+            let rules = unsafe { &mut *(buffer.as_mut_ptr() as *mut LevelRules) };
+            get_level_rules(levels, rules, sender);
+        }
+        _ => (),
+    }
+}
+
+fn set_level_rule(levels: &mut levels::Levels, rule: &LevelRule) -> Result<(), xous::Error> {
+    let target = FromPrimitive::from_u32(rule.target).ok_or(xous::Error::InternalError)?;
+    let level = match rule.level {
+        LEVEL_DEFAULT => None,
+        level => Some(level_from_u32(level).ok_or(xous::Error::InternalError)?),
+    };
+    let name = rule
+        .name
+        .get(..rule.name_length as usize)
+        .and_then(|name| core::str::from_utf8(name).ok())
+        .ok_or(xous::Error::InvalidString)?;
+    levels.prune(process_exists);
+    let waiters = levels.set(target, name, level)?;
+    for waiter in waiters {
+        xous::return_scalar(waiter, levels.generation() as usize).ok();
+    }
+    Ok(())
+}
+
+/// Whether `sender` is one of the `PREVIOUS_LOG_READERS`.
+fn may_read_previous_log(sender: xous::MessageSender) -> bool {
+    let mut name_buf = [0u8; 64];
+    sender
+        .pid()
+        .and_then(|pid| xous::process_name(pid, &mut name_buf).ok())
+        .map_or(false, |name| PREVIOUS_LOG_READERS.contains(&name))
+}
+
+fn process_exists(pid: xous::PID) -> bool {
+    xous::process_info(pid).map(|info| info.pid) == Ok(pid)
+}
+
+/// Fill in `rules` with every rule, or with the ones for `sender`'s process.
+fn get_level_rules(levels: &levels::Levels, rules: &mut LevelRules, sender: xous::MessageSender) {
+    let mut name_buf = [0u8; 64];
+    let matching: Vec<&levels::Rule> = if rules.all != 0 {
+        levels.all().iter().collect()
+    } else {
+        let process = sender
+            .pid()
+            .and_then(|pid| xous::process_name(pid, &mut name_buf).ok())
+            .unwrap_or("");
+        levels.for_process(process).collect()
+    };
+    let mut count = 0;
+    for (dest, rule) in rules.rules.iter_mut().zip(matching) {
+        let name = rule.name.as_bytes();
+        dest.target = rule.target as u32;
+        dest.level = level_to_u32(rule.level);
+        dest.name_length = name.len().min(dest.name.len()) as u32;
+        dest.name[..dest.name_length as usize].copy_from_slice(&name[..dest.name_length as usize]);
+        count += 1;
+    }
+    rules.count = count;
+}
+
 fn reader_thread(arg: usize) {
-    let output = unsafe { &mut *(arg as *mut implementation::OutputWriter) };
+    let mut ring = ring::Ring::new(implementation::ring_memory());
+    // Keep what was logged before the reboot, and start this boot afresh.
+    let previous = ring.contents();
+    ring.clear();
+    let mut levels = levels::Levels::new();
+    let output = &mut Log {
+        output: unsafe { &mut *(arg as *mut implementation::OutputWriter) },
+        ring,
+        previous,
+    };
     writeln!(output, "LOG: Xous Logging Server starting up...").unwrap();
 
     writeln!(
@@ -399,10 +617,11 @@ fn reader_thread(arg: usize) {
         }
         counter += 1;
         // writeln!(output, "LOG: Waiting for an event...").unwrap();
-        let envelope = xous::syscall::receive_message(server_addr).expect("couldn't get address");
+        let mut envelope =
+            xous::syscall::receive_message(server_addr).expect("couldn't get address");
         let sender = envelope.sender;
         if let Some(opcode) = FromPrimitive::from_usize(envelope.body.id()) {
-            handle_opcode(output, sender, opcode, &envelope.body);
+            handle_opcode(output, &mut levels, sender, opcode, &mut envelope.body);
         } else {
             writeln!(
                 output,
@@ -453,3 +672,92 @@ fn some_main() -> ! {
     output.run();
     panic!("LOG: Exited");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lent_records_reach_the_ring() {
+        let output = implementation::init();
+        let mut writer = output.get_writer();
+        let mut log = Log {
+            output: &mut writer,
+            ring: ring::Ring::new(implementation::ring_memory()),
+            previous: Vec::new(),
+        };
+        let mut levels = levels::Levels::new();
+
+        // `Buffer::lend()` sends the record as a `Borrow`.
+        let page = Box::leak(vec![0u32; 1024].into_boxed_slice());
+        let record = unsafe { &mut *(page.as_mut_ptr() as *mut LogRecord) };
+        record.level = log::Level::Info as u32;
+        record.module[..3].copy_from_slice(b"gam");
+        record.module_length = 3;
+        record.args[..5].copy_from_slice(b"hello");
+        record.args_length = 5;
+        record.file[..7].copy_from_slice(b"main.rs");
+        record.file_length = 7;
+        record.line = Some(12);
+        let mut message = xous::Message::Borrow(xous::MemoryMessage {
+            id: Opcode::LogRecord as usize,
+            buf: unsafe { xous::MemoryRange::new(page.as_ptr() as usize, 4096).unwrap() },
+            offset: None,
+            valid: None,
+        });
+        handle_opcode(
+            &mut log,
+            &mut levels,
+            xous::MessageSender::from_usize(1),
+            Opcode::LogRecord,
+            &mut message,
+        );
+
+        let contents = log.ring.contents();
+        assert_eq!(contents, b"INFO:gam: hello (main.rs:12)\n");
+
+        // Lent mutably, the record comes back with the generation of the rules
+        levels
+            .set(LevelTarget::Module, "gam", Some(log::LevelFilter::Debug))
+            .unwrap();
+        let mut message = xous::Message::MutableBorrow(xous::MemoryMessage {
+            id: Opcode::LogRecord as usize,
+            buf: unsafe { xous::MemoryRange::new(page.as_ptr() as usize, 4096).unwrap() },
+            offset: None,
+            valid: None,
+        });
+        handle_opcode(
+            &mut log,
+            &mut levels,
+            xous::MessageSender::from_usize(1),
+            Opcode::LogRecord,
+            &mut message,
+        );
+        let record = unsafe { &*(page.as_ptr() as *const LogRecord) };
+        assert_eq!(record.generation, 1);
+        assert_eq!(log.ring.contents().len(), 2 * contents.len());
+    }
+
+    #[test]
+    fn previous_boot_is_only_read_when_allowed() {
+        let output = implementation::init();
+        let mut writer = output.get_writer();
+        let log = Log {
+            output: &mut writer,
+            ring: ring::Ring::new(implementation::ring_memory()),
+            previous: b"secret".to_vec(),
+        };
+        let mut dump: Box<LogDump> = Box::new(unsafe { core::mem::zeroed() });
+        dump.source = LogSource::PreviousBoot as u32;
+        dump.length = 99;
+
+        assert_eq!(log.read(&mut dump, false), Err(xous::Error::AccessDenied));
+        assert_eq!(dump.length, 0);
+
+        assert_eq!(log.read(&mut dump, true), Ok(()));
+        assert_eq!(&dump.data[..dump.length as usize], b"secret");
+
+        // Unnamed processes, which is all of them when hosted, aren't readers
+        assert!(!may_read_previous_log(xous::MessageSender::from_usize(1)));
+    }
+}
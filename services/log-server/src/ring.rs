@@ -0,0 +1,134 @@
+//! A ring buffer of everything the log server prints.
+//!
+//! On hardware the ring lives in a block of RAM that `create-image` sets
+//! aside from main memory, so neither the loader nor the kernel clears it.
+//! What was logged before a CPU reboot is therefore still there afterwards.
+//! The memory could hold anything after a power loss, so the header is
+//! checked before the contents are trusted.
+
+/// Marks memory that holds a ring, and the layout it was written with
+const MAGIC: u32 = u32::from_le_bytes(*b"Rng1");
+
+/// Magic number, write position, and number of bytes held, as `u32`s
+const HEADER_LEN: usize = 12;
+
+pub struct Ring {
+    memory: &'static mut [u8],
+}
+
+impl Ring {
+    /// Take over `memory`, keeping whatever ring is already in it.
+    pub fn new(memory: &'static mut [u8]) -> Self {
+        assert!(memory.len() > HEADER_LEN);
+        let mut ring = Ring { memory };
+        if !ring.is_valid() {
+            ring.clear();
+        }
+        ring
+    }
+
+    /// How many bytes the ring can hold
+    pub fn capacity(&self) -> usize {
+        self.memory.len() - HEADER_LEN
+    }
+
+    /// Add `bytes` to the ring, overwriting the oldest ones if it's full.
+    pub fn write(&mut self, bytes: &[u8]) {
+        let capacity = self.capacity();
+        // Only the end of something larger than the ring would survive anyway.
+        let bytes = &bytes[bytes.len().saturating_sub(capacity)..];
+        let head = self.header(1) as usize;
+        // Fill up to the end of the memory, then wrap around to the start.
+        let first = bytes.len().min(capacity - head);
+        self.data()[head..head + first].copy_from_slice(&bytes[..first]);
+        self.data()[..bytes.len() - first].copy_from_slice(&bytes[first..]);
+        let head = (head + bytes.len()) % capacity;
+        let len = (self.header(2) as usize + bytes.len()).min(capacity);
+        self.set_header(1, head as u32);
+        self.set_header(2, len as u32);
+    }
+
+    /// Everything in the ring, oldest first
+    pub fn contents(&self) -> Vec<u8> {
+        let head = self.header(1) as usize;
+        let len = self.header(2) as usize;
+        let data = &self.memory[HEADER_LEN..];
+        let mut contents = Vec::with_capacity(len);
+        if len == data.len() {
+            contents.extend_from_slice(&data[head..]);
+        }
+        contents.extend_from_slice(&data[..head]);
+        contents
+    }
+
+    pub fn clear(&mut self) {
+        self.set_header(0, MAGIC);
+        self.set_header(1, 0);
+        self.set_header(2, 0);
+    }
+
+    fn is_valid(&self) -> bool {
+        let capacity = self.capacity() as u32;
+        let (head, len) = (self.header(1), self.header(2));
+        // Until the ring fills up, it runs from the start of the data up to
+        // the write position.
+        self.header(0) == MAGIC && head < capacity && (len == capacity || len == head)
+    }
+
+    fn data(&mut self) -> &mut [u8] {
+        &mut self.memory[HEADER_LEN..]
+    }
+
+    fn header(&self, index: usize) -> u32 {
+        let mut word = [0u8; 4];
+        word.copy_from_slice(&self.memory[index * 4..index * 4 + 4]);
+        u32::from_le_bytes(word)
+    }
+
+    fn set_header(&mut self, index: usize, value: u32) {
+        self.memory[index * 4..index * 4 + 4].copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(capacity: usize) -> &'static mut [u8] {
+        Box::leak(vec![0u8; HEADER_LEN + capacity].into_boxed_slice())
+    }
+
+    #[test]
+    fn keeps_the_newest_bytes() {
+        let mut ring = Ring::new(memory(8));
+        ring.write(b"abc");
+        assert_eq!(ring.contents(), b"abc");
+        ring.write(b"defgh");
+        assert_eq!(ring.contents(), b"abcdefgh");
+        ring.write(b"ij");
+        assert_eq!(ring.contents(), b"cdefghij");
+        ring.write(b"0123456789");
+        assert_eq!(ring.contents(), b"23456789");
+    }
+
+    #[test]
+    fn survives_being_taken_over_again() {
+        let memory = memory(8);
+        let address = memory.as_mut_ptr();
+        Ring::new(memory).write(b"before reboot");
+
+        let memory = unsafe { core::slice::from_raw_parts_mut(address, HEADER_LEN + 8) };
+        let ring = Ring::new(memory);
+        assert_eq!(ring.contents(), b"e reboot");
+    }
+
+    #[test]
+    fn garbage_is_not_trusted() {
+        let memory = memory(8);
+        for (i, byte) in memory.iter_mut().enumerate() {
+            *byte = (i as u8).wrapping_mul(37);
+        }
+        let ring = Ring::new(memory);
+        assert_eq!(ring.contents(), b"");
+    }
+}
@@ -4,7 +4,7 @@
 mod api;
 use api::*;
 use xous::{msg_scalar_unpack, send_message, msg_blocking_scalar_unpack};
-use xous_ipc::{String, Buffer};
+use xous_ipc::{String, Buffer, Zeroizing};
 
 use num_traits::*;
 
@@ -450,12 +450,11 @@ fn xmain() -> ! {
                 // assume:
                 //   - setup_key_init has also been called (exactly once, before anything happens)
                 //   - set_ux_password_type has been called already
-                let mut buf = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
-                let mut plaintext_pw = buf.to_original::<gam::modal::TextEntryPayload, _>().unwrap();
-
-                keys.hash_and_save_password(plaintext_pw.as_str());
-                plaintext_pw.volatile_clear(); // ensure the data is destroyed after sending to the keys enclave
-                buf.volatile_clear();
+                {
+                    let buf = Zeroizing::new(unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) });
+                    let plaintext_pw = Zeroizing::new(buf.to_original::<gam::modal::TextEntryPayload, _>().unwrap());
+                    keys.hash_and_save_password(plaintext_pw.as_str());
+                } // both copies of the password are wiped here, once it has gone to the keys enclave
 
                 if let Some(pwt) = keys.get_ux_password_type() {
                     match pwt {
@@ -743,12 +742,11 @@ fn xmain() -> ! {
                 }
             }
             Some(Opcode::UxUpdateGwPasswordPolicy)=> {
-                let mut buf = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
-                let mut plaintext_pw = buf.to_original::<gam::modal::TextEntryPayload, _>().unwrap();
-
-                keys.hash_and_save_password(plaintext_pw.as_str());
-                plaintext_pw.volatile_clear(); // ensure the data is destroyed after sending to the keys enclave
-                buf.volatile_clear();
+                {
+                    let buf = Zeroizing::new(unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) });
+                    let plaintext_pw = Zeroizing::new(buf.to_original::<gam::modal::TextEntryPayload, _>().unwrap());
+                    keys.hash_and_save_password(plaintext_pw.as_str());
+                } // both copies of the password are wiped here, once it has gone to the keys enclave
 
                 let mut confirm_radiobox = gam::modal::RadioButtons::new(
                     main_cid,
@@ -851,12 +849,11 @@ fn xmain() -> ! {
                 }
             },
             Some(Opcode::UxSignXousPasswordPolicy) => {
-                let mut buf = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
-                let mut plaintext_pw = buf.to_original::<gam::modal::TextEntryPayload, _>().unwrap();
-
-                keys.hash_and_save_password(plaintext_pw.as_str());
-                plaintext_pw.volatile_clear(); // ensure the data is destroyed after sending to the keys enclave
-                buf.volatile_clear();
+                {
+                    let buf = Zeroizing::new(unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) });
+                    let plaintext_pw = Zeroizing::new(buf.to_original::<gam::modal::TextEntryPayload, _>().unwrap());
+                    keys.hash_and_save_password(plaintext_pw.as_str());
+                } // both copies of the password are wiped here, once it has gone to the keys enclave
 
                 let mut confirm_radiobox = gam::modal::RadioButtons::new(
                     main_cid,
@@ -981,12 +978,11 @@ fn xmain() -> ! {
                 }
             }),
             Some(Opcode::UxAesPasswordPolicy) => {
-                let mut buf = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
-                let mut plaintext_pw = buf.to_original::<gam::modal::TextEntryPayload, _>().unwrap();
-
-                keys.hash_and_save_password(plaintext_pw.as_str());
-                plaintext_pw.volatile_clear(); // ensure the data is destroyed after sending to the keys enclave
-                buf.volatile_clear();
+                {
+                    let buf = Zeroizing::new(unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) });
+                    let plaintext_pw = Zeroizing::new(buf.to_original::<gam::modal::TextEntryPayload, _>().unwrap());
+                    keys.hash_and_save_password(plaintext_pw.as_str());
+                } // both copies of the password are wiped here, once it has gone to the keys enclave
 
                 let mut confirm_radiobox = gam::modal::RadioButtons::new(
                     main_cid,
@@ -1099,12 +1095,11 @@ fn xmain() -> ! {
                 }
             }
             Some(Opcode::UxBbramPasswordReturn) => {
-                let mut buf = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
-                let mut plaintext_pw = buf.to_original::<gam::modal::TextEntryPayload, _>().unwrap();
-
-                keys.hash_and_save_password(plaintext_pw.as_str());
-                plaintext_pw.volatile_clear(); // ensure the data is destroyed after sending to the keys enclave
-                buf.volatile_clear();
+                {
+                    let buf = Zeroizing::new(unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) });
+                    let plaintext_pw = Zeroizing::new(buf.to_original::<gam::modal::TextEntryPayload, _>().unwrap());
+                    keys.hash_and_save_password(plaintext_pw.as_str());
+                } // both copies of the password are wiped here, once it has gone to the keys enclave
                 send_message(main_cid,
                     xous::Message::new_scalar(Opcode::UxBbramRun.to_usize().unwrap(), 0, 0, 0, 0)
                 ).unwrap();
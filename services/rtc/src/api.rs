@@ -28,6 +28,29 @@ pub struct DateTime {
     pub weekday: Weekday,
 }
 
+impl DateTime {
+    /// Seconds since 1970-01-01 00:00:00, treating this as UTC, or None if the fields are out of range
+    /// (e.g. because the RTC was never set).
+    pub fn seconds_since_epoch(&self) -> Option<u64> {
+        if self.months < 1 || self.months > 12 || self.days < 1 || self.days > 31
+        || self.hours > 23 || self.minutes > 59 || self.seconds > 59 {
+            return None;
+        }
+        // days from civil, counting years from March so that the leap day comes last
+        let (months, days) = (self.months as i64, self.days as i64);
+        let year = 2000 + self.years as i64 - if months <= 2 { 1 } else { 0 };
+        let era = year / 400;
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * ((months + 9) % 12) + 2) / 5 + days - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days_since_epoch = era * 146097 + day_of_era - 719468;
+        Some((days_since_epoch * 86400
+            + self.hours as i64 * 3600
+            + self.minutes as i64 * 60
+            + self.seconds as i64) as u64)
+    }
+}
+
 pub(crate) const SERVER_NAME_RTC: &str       = "_Real time clock application server_";
 #[allow(dead_code)]
 pub(crate) const RTC_MODAL_NAME: &'static str = "rtc modal";
@@ -62,6 +85,10 @@ pub(crate) enum Opcode {
     /// clears any RTC alarms that have been set
     ClearRtcAlarm,
 
+    /// sets local time minus UTC to arg1 seconds, which the RTC remembers across reboots.
+    /// Returns a `xous::Error`, or 0 on success.
+    SetTimezone,
+
     /// Ux callbacks
     UxSetTime,
     UxMonth,
@@ -77,7 +104,10 @@ pub(crate) enum Opcode {
     UxQuit,
 
     /// Quit
-    Quit
+    Quit,
+
+    /// Suspend/resume callback
+    SuspendResume,
 }
 
 #[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
@@ -92,3 +122,43 @@ pub(crate) struct ScalarHook {
     pub id: u32,  // ID of the scalar message to send through (e.g. the discriminant of the Enum on the caller's side API)
     pub cid: xous::CID,   // caller-side connection ID for the scalar message to route to. Created by the caller before hooking.
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(years: u8, months: u8, days: u8, hours: u8, minutes: u8, seconds: u8) -> DateTime {
+        DateTime { seconds, minutes, hours, days, months, years, weekday: Weekday::Monday }
+    }
+
+    #[test]
+    fn known_dates() {
+        assert_eq!(dt(0, 1, 1, 0, 0, 0).seconds_since_epoch(), Some(946_684_800));
+        assert_eq!(dt(21, 6, 1, 12, 0, 0).seconds_since_epoch(), Some(1_622_548_800));
+        assert_eq!(dt(99, 12, 31, 23, 59, 59).seconds_since_epoch(), Some(4_102_444_799));
+    }
+
+    #[test]
+    fn leap_years() {
+        // 2000 is a leap year even though it's a century, because it's divisible by 400
+        assert_eq!(dt(0, 2, 29, 0, 0, 0).seconds_since_epoch(), Some(951_782_400));
+        assert_eq!(dt(0, 3, 1, 0, 0, 0).seconds_since_epoch(), Some(951_868_800));
+        assert_eq!(dt(24, 2, 29, 12, 34, 56).seconds_since_epoch(), Some(1_709_210_096));
+        // 2023 isn't, so March follows February 28th
+        assert_eq!(dt(23, 3, 1, 0, 0, 0).seconds_since_epoch(), Some(1_677_628_800));
+        // and neither is 2100
+        assert_eq!(dt(100, 2, 28, 0, 0, 0).seconds_since_epoch(), Some(4_107_456_000));
+        assert_eq!(dt(100, 3, 1, 0, 0, 0).seconds_since_epoch(), Some(4_107_542_400));
+    }
+
+    #[test]
+    fn fields_out_of_range() {
+        assert_eq!(dt(21, 0, 1, 0, 0, 0).seconds_since_epoch(), None);
+        assert_eq!(dt(21, 13, 1, 0, 0, 0).seconds_since_epoch(), None);
+        assert_eq!(dt(21, 1, 0, 0, 0, 0).seconds_since_epoch(), None);
+        assert_eq!(dt(21, 1, 32, 0, 0, 0).seconds_since_epoch(), None);
+        assert_eq!(dt(21, 1, 1, 24, 0, 0).seconds_since_epoch(), None);
+        assert_eq!(dt(21, 1, 1, 0, 60, 0).seconds_since_epoch(), None);
+        assert_eq!(dt(21, 1, 1, 0, 0, 60).seconds_since_epoch(), None);
+    }
+}
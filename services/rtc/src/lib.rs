@@ -97,6 +97,18 @@ impl Rtc {
             Message::new_blocking_scalar(Opcode::ClearRtcAlarm.to_usize().unwrap(), 0, 0, 0, 0)
         ).map(|_|())
     }
+    /// sets how far ahead of UTC the time kept by the RTC is, e.g. 3600 for UTC+1. This is
+    /// kept to whole quarter hours and saved in the RTC, so it survives reboots. Fails with
+    /// ServerQueueFull if the I2C interface stays busy, or InternalError if the write fails.
+    pub fn set_timezone_offset(&self, seconds: i32) -> Result<(), xous::Error> {
+        match send_message(self.conn,
+            Message::new_blocking_scalar(Opcode::SetTimezone.to_usize().unwrap(), seconds as usize, 0, 0, 0)
+        )? {
+            xous::Result::Scalar1(0) => Ok(()),
+            xous::Result::Scalar1(e) => Err(xous::Error::from_usize(e)),
+            _ => Err(xous::Error::InternalError),
+        }
+    }
 }
 
 use core::sync::atomic::{AtomicU32, Ordering};
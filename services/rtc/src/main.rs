@@ -16,6 +16,22 @@ use gam::ActionType::*;
 use core::sync::atomic::{AtomicU32, Ordering};
 static CB_TO_MAIN_CONN: AtomicU32 = AtomicU32::new(0);
 
+/// The RTC has no spare RAM, so the timezone is kept in the minute alarm register, which
+/// nothing uses, and which is on the battery like the time is. The top bit keeps the alarm
+/// disabled, and the rest holds the offset from UTC in quarter hours, which every timezone
+/// is a whole number of. The register resets to 0x80, which reads back as UTC.
+const TIMEZONE_RESET: u8 = 0x80;
+/// How many times SetTimezone waits a millisecond for a busy I2C interface before giving up.
+const SET_TIMEZONE_RETRIES: u32 = 100;
+fn timezone_to_reg(seconds: i32) -> u8 {
+    let quarters = (seconds / 900).clamp(-64, 63) as i8;
+    TIMEZONE_RESET | (quarters as u8 & 0x7F)
+}
+fn timezone_from_reg(reg: u8) -> i32 {
+    let quarters = ((reg << 1) as i8) >> 1; // sign-extend the 7-bit field
+    quarters as i32 * 900
+}
+
 #[cfg(any(target_os = "none", target_os = "xous"))]
 mod implementation {
     #![allow(dead_code)]
@@ -24,6 +40,7 @@ mod implementation {
     use core::sync::atomic::Ordering;
     use llio::{I2cStatus, I2cTransaction, Llio};
     use crate::api::{Opcode, DateTime, Weekday};
+    use crate::{timezone_from_reg, timezone_to_reg};
     use xous_ipc::Buffer;
     use num_traits::ToPrimitive;
 
@@ -329,6 +346,29 @@ mod implementation {
             self.llio.i2c_async_done();
         }
 
+        /// local time minus UTC in seconds, as saved by `set_timezone()`. This blocks, so it
+        /// mustn't be called while an `rtc_get()` is outstanding.
+        pub fn timezone(&mut self) -> Result<i32, xous::Error> {
+            let mut rxbuf = [0; 1];
+            self.llio.i2c_read(ABRTCMC_I2C_ADR, ABRTCMC_MINUTE_ALARM, &mut rxbuf, None)?;
+            Ok(timezone_from_reg(rxbuf[0]))
+        }
+        /// Returns **ServerQueueFull** if the I2C interface is busy, so the caller can retry.
+        pub fn set_timezone(&mut self, seconds: i32) -> Result<(), xous::Error> {
+            match self.llio.i2c_write(ABRTCMC_I2C_ADR, ABRTCMC_MINUTE_ALARM, &[timezone_to_reg(seconds)], None) {
+                Ok(I2cStatus::ResponseWriteOk) => Ok(()),
+                Ok(I2cStatus::ResponseBusy) => Err(xous::Error::ServerQueueFull),
+                Ok(status) => {
+                    log::error!("couldn't set timezone: {:?}", status);
+                    Err(xous::Error::InternalError)
+                }
+                Err(e) => {
+                    log::error!("couldn't set timezone: {:?}", e);
+                    Err(xous::Error::InternalError)
+                }
+            }
+        }
+
         // the awkward array syntax is a legacy of a port from a previous implementation
         // would be fine to clean up method signature as e.g.
         // blocking_i2c_write2(adr: u8, data: u8) -> bool
@@ -452,7 +492,7 @@ mod implementation {
 mod implementation {
     use crate::api::Weekday;
     use chrono::prelude::*;
-    use crate::CB_TO_MAIN_CONN;
+    use crate::{CB_TO_MAIN_CONN, TIMEZONE_RESET, timezone_from_reg, timezone_to_reg};
     use core::sync::atomic::Ordering;
     use num_traits::ToPrimitive;
 
@@ -493,6 +533,7 @@ mod implementation {
 
     pub struct Rtc {
         cb_conn: xous::CID,
+        timezone_reg: u8,
     }
 
     impl Rtc {
@@ -503,6 +544,7 @@ mod implementation {
             xous::create_thread_4(rtc_thread, sid_tuple.0 as usize, sid_tuple.1 as usize, sid_tuple.2 as usize, sid_tuple.3 as usize).unwrap();
             Rtc {
                 cb_conn: cid,
+                timezone_reg: TIMEZONE_RESET,
             }
         }
         pub fn rtc_set(&mut self, _secs: u8, _mins: u8, _hours: u8, _days: u8, _months: u8, _years: u8, _day: Weekday)
@@ -519,6 +561,13 @@ mod implementation {
         pub fn clear_rtc_alarm(&mut self) { }
         pub fn rtc_get_ack(&mut self) {
         }
+        pub fn timezone(&mut self) -> Result<i32, xous::Error> {
+            Ok(timezone_from_reg(self.timezone_reg))
+        }
+        pub fn set_timezone(&mut self, seconds: i32) -> Result<(), xous::Error> {
+            self.timezone_reg = timezone_to_reg(seconds);
+            Ok(())
+        }
     }
 }
 
@@ -568,6 +617,16 @@ fn rtc_ux_validator(input: TextEntryPayload, opcode: u32) -> Option<xous_ipc::St
     None
 }
 
+/// queue up a read of the RTC, from within the main loop's own thread
+fn request_datetime() {
+    // try_send, because waiting on our own queue to drain would deadlock
+    if xous::try_send_message(CB_TO_MAIN_CONN.load(Ordering::Relaxed),
+        xous::Message::new_scalar(Opcode::RequestDateTime.to_usize().unwrap(), 0, 0, 0, 0)
+    ).is_err() {
+        log::error!("couldn't queue up a read of the RTC; wall clock time will be unavailable until the next one");
+    }
+}
+
 #[xous::xous_main]
 fn xmain() -> ! {
     use crate::implementation::Rtc;
@@ -635,6 +694,16 @@ fn xmain() -> ! {
     let mut weekday: Weekday = Weekday::Monday;
 
     let ticktimer = ticktimer_server::Ticktimer::new().expect("can't connect to ticktimer");
+    // nobody else gets to set the wall clock, and it's in the timezone the RTC remembers
+    ticktimer.claim_wall_clock().expect("couldn't claim the wall clock");
+    match rtc.timezone() {
+        Ok(seconds) => ticktimer.set_timezone_offset(seconds).expect("couldn't set timezone"),
+        Err(e) => log::error!("couldn't read the timezone, assuming UTC: {:?}", e),
+    }
+    // the ticktimer keeps wall-clock time based on our readings, so take one now, and another after every resume
+    let sr_cid = xous::connect(rtc_sid).expect("couldn't create suspend callback connection");
+    let mut susres = susres::Susres::new(&xns, Opcode::SuspendResume as u32, sr_cid).expect("couldn't create suspend/resume object");
+    request_datetime();
     let mut dt_cb_conns: [bool; xous::MAX_CID] = [false; xous::MAX_CID];
     log::trace!("ready to accept requests");
     loop {
@@ -695,6 +764,11 @@ fn xmain() -> ! {
                 let incoming_buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
                 let dt = incoming_buffer.to_original::<DateTime, _>().unwrap();
                 log::trace!("ResponseDateTime received: {:?}", dt);
+                if let Some(secs) = dt.seconds_since_epoch() {
+                    ticktimer.set_wall_clock_ms(secs * 1000).expect("couldn't update wall clock");
+                } else {
+                    log::warn!("RTC returned an invalid date and time: {:?}", dt);
+                }
                 for cid in 1..dt_cb_conns.len() { // 0 is not a valid connection
                     if dt_cb_conns[cid as usize] {
                         let outgoing_buf = Buffer::into_buf(dt).or(Err(xous::Error::InternalError)).unwrap();
@@ -731,6 +805,25 @@ fn xmain() -> ! {
                 }
                 log::trace!("RequestDateTime completed");
             }
+            Some(Opcode::SetTimezone) => msg_blocking_scalar_unpack!(msg, seconds, _, _, _, {
+                let seconds = seconds as i32;
+                let mut result = rtc.set_timezone(seconds);
+                let mut retries = 0;
+                while result == Err(xous::Error::ServerQueueFull) && retries < SET_TIMEZONE_RETRIES {
+                    log::trace!("I2C interface was busy setting the timezone, retrying");
+                    retries += 1;
+                    ticktimer.sleep_ms(1).unwrap();
+                    result = rtc.set_timezone(seconds);
+                }
+                if result.is_ok() {
+                    ticktimer.set_timezone_offset(seconds).expect("couldn't set timezone");
+                }
+                let ret = match result {
+                    Ok(()) => 0,
+                    Err(e) => e as usize,
+                };
+                xous::return_scalar(msg.sender, ret).expect("couldn't return to caller");
+            }),
             Some(Opcode::SetWakeupAlarm) => msg_blocking_scalar_unpack!(msg, delay, _, _, _, {
                 rtc.wakeup_alarm(delay as u8); // this will block until finished, no callbacks used
                 xous::return_scalar(msg.sender, 0).expect("couldn't return to caller");
@@ -873,6 +966,11 @@ fn xmain() -> ! {
             Some(Opcode::UxQuit) => {
                 panic!("Modal handler quit unexpectedly");
             }
+            Some(Opcode::SuspendResume) => msg_scalar_unpack!(msg, token, _, _, _, {
+                susres.suspend_until_resume(token).expect("couldn't execute suspend/resume");
+                // time passed while we were suspended, which the ticktimer has to hear about from the RTC
+                request_datetime();
+            }),
             Some(Opcode::Quit) => {
                 log::error!("Quitting RTC server");
                 break;
@@ -904,3 +1002,19 @@ fn xmain() -> ! {
     log::trace!("quitting");
     xous::terminate_process(0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timezone_survives_the_register() {
+        for &seconds in &[0, 3600, -5 * 3600, 5 * 3600 + 1800, -(9 * 3600 + 1800), 5 * 3600 + 2700, 14 * 3600, -12 * 3600] {
+            let reg = timezone_to_reg(seconds);
+            // the alarm stays off
+            assert_eq!(reg & 0x80, 0x80);
+            assert_eq!(timezone_from_reg(reg), seconds);
+        }
+        assert_eq!(timezone_from_reg(TIMEZONE_RESET), 0);
+    }
+}
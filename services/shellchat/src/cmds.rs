@@ -1,5 +1,4 @@
 use xous::{MessageEnvelope};
-use xous_ipc::String;
 use core::fmt::Write;
 
 use std::collections::HashMap;
@@ -7,9 +6,9 @@ use std::collections::HashMap;
 pub trait ShellCmdApi<'a> {
     // user implemented:
     // called to process the command with the remainder of the string attached
-    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error>;
+    fn process(&mut self, args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error>;
     // called to process incoming messages that may have been origniated by the most recently issued command
-    fn callback(&mut self, msg: &MessageEnvelope, _env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn callback(&mut self, msg: &MessageEnvelope, _env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         log::info!("received unhandled message {:?}", msg);
         Ok(None)
     }
@@ -44,12 +43,14 @@ pub struct CommonEnv {
     com: com::Com,
     ticktimer: ticktimer_server::Ticktimer,
     gam: gam::Gam,
-    cb_registrations: HashMap::<u32, String::<256>>,
+    cb_registrations: HashMap::<u32, xous_ipc::String::<256>>,
     trng: Trng,
     xns: xous_names::XousNames,
+    /// our own server, for commands to have the ticktimer call back into
+    callback_conn: xous::CID,
 }
 impl CommonEnv {
-    pub fn register_handler(&mut self, verb: String::<256>) -> u32 {
+    pub fn register_handler(&mut self, verb: xous_ipc::String::<256>) -> u32 {
         let mut key: u32;
         loop {
             key = self.trng.get_u32().unwrap();
@@ -99,6 +100,9 @@ mod memtest;  use memtest::*;
 mod keys;     use keys::*;
 mod wlan;     use wlan::*;
 mod jtag_cmd; use jtag_cmd::*;
+mod ps;       use ps::*;
+mod names;    use names::*;
+mod log_cmd;  use log_cmd::*;
 
 //mod fcc;      use fcc::*;
 //mod pds; // dependency of the FCC file
@@ -106,7 +110,7 @@ mod jtag_cmd; use jtag_cmd::*;
 #[derive(Debug)]
 pub struct CmdEnv {
     common_env: CommonEnv,
-    lastverb: String::<256>,
+    lastverb: xous_ipc::String::<256>,
     ///// 2. declare storage for your command here.
     test_cmd: Test,
     sleep_cmd: Sleep,
@@ -138,6 +142,7 @@ impl CmdEnv {
             cb_registrations: HashMap::new(),
             trng: Trng::new(&xns).unwrap(),
             xns: xous_names::XousNames::new().unwrap(),
+            callback_conn: xns.request_connection_blocking(crate::SERVER_NAME_SHELLCHAT).unwrap(),
         };
         //let fcc = Fcc::new(&mut common);
         let sha = Sha::new(&xns, &mut common);
@@ -145,6 +150,7 @@ impl CmdEnv {
         let ecup = EcUpdate::new(&mut common);
         let engine = Engine::new(&xns, &mut common);
         let memtest = Memtest::new(&xns, &mut common);
+        let ssid = Ssid::new(&mut common);
 
         // print our version info
         let (maj, min, rev, extra, gitrev) = common.llio.soc_gitrev().unwrap();
@@ -158,7 +164,7 @@ impl CmdEnv {
 
         CmdEnv {
             common_env: common,
-            lastverb: String::<256>::new(),
+            lastverb: xous_ipc::String::<256>::new(),
             ///// 3. initialize your storage, by calling new()
             test_cmd: Test::new(),
             sleep_cmd: Sleep::new(&xns),
@@ -166,7 +172,7 @@ impl CmdEnv {
             callback_cmd: CallBack::new(),
             rtc_cmd: RtcCmd::new(&xns),
             vibe_cmd: Vibe::new(),
-            ssid_cmd: Ssid::new(),
+            ssid_cmd: ssid,
             audio_cmd: Audio::new(&xns),
             sha_cmd: sha,
             ecup_cmd: ecup,
@@ -181,8 +187,8 @@ impl CmdEnv {
         }
     }
 
-    pub fn dispatch(&mut self, maybe_cmdline: Option<&mut String::<1024>>, maybe_callback: Option<&MessageEnvelope>) -> Result<Option<String::<1024>>, xous::Error> {
-        let mut ret = String::<1024>::new();
+    pub fn dispatch(&mut self, maybe_cmdline: Option<&mut String>, maybe_callback: Option<&MessageEnvelope>) -> Result<Option<String>, xous::Error> {
+        let mut ret = String::new();
 
         let mut echo_cmd = Echo {}; // this command has no persistent storage, so we can "create" it every time we call dispatch (but it's a zero-cost absraction so this doesn't actually create any instructions)
         let mut wlan_cmd = Wlan {};
@@ -190,6 +196,9 @@ impl CmdEnv {
         let mut backlight_cmd = Backlight{};
         let mut accel_cmd = Accel{};
         let mut console_cmd = Console{};
+        let mut ps_cmd = Ps{};
+        let mut names_cmd = Names{};
+        let mut log_cmd = LogCmd{};
         let commands: &mut [& mut dyn ShellCmdApi] = &mut [
             ///// 4. add your command to this array, so that it can be looked up and dispatched
             &mut echo_cmd,
@@ -214,6 +223,9 @@ impl CmdEnv {
             &mut self.keys_cmd,
             &mut wlan_cmd,
             &mut self.jtag_cmd,
+            &mut ps_cmd,
+            &mut names_cmd,
+            &mut log_cmd,
 
             //&mut self.fcc_cmd,
         ];
@@ -221,9 +233,9 @@ impl CmdEnv {
         if let Some(cmdline) = maybe_cmdline {
             let maybe_verb = tokenize(cmdline);
 
-            let mut cmd_ret: Result<Option<String::<1024>>, xous::Error> = Ok(None);
+            let mut cmd_ret: Result<Option<String>, xous::Error> = Ok(None);
             if let Some(verb_string) = maybe_verb {
-                let verb = verb_string.to_str();
+                let verb = verb_string.as_str();
 
                 // search through the list of commands linearly until one matches,
                 // then run it.
@@ -231,7 +243,7 @@ impl CmdEnv {
                 for cmd in commands.iter_mut() {
                     if cmd.matches(verb) {
                         match_found = true;
-                        cmd_ret = cmd.process(*cmdline, &mut self.common_env);
+                        cmd_ret = cmd.process(cmdline.clone(), &mut self.common_env);
                         self.lastverb.clear();
                         write!(self.lastverb, "{}", verb).expect("SHCH: couldn't record last verb");
                     };
@@ -243,9 +255,9 @@ impl CmdEnv {
                     write!(ret, "Commands: ").unwrap();
                     for cmd in commands.iter() {
                         if !first {
-                            ret.append(", ")?;
+                            ret.push_str(", ");
                         }
-                        ret.append(cmd.verb())?;
+                        ret.push_str(cmd.verb());
                         first = false;
                     }
                     Ok(Some(ret))
@@ -256,7 +268,7 @@ impl CmdEnv {
                 Ok(None)
             }
         } else if let Some(callback) = maybe_callback {
-            let mut cmd_ret: Result<Option<String::<1024>>, xous::Error> = Ok(None);
+            let mut cmd_ret: Result<Option<String>, xous::Error> = Ok(None);
             // first check and see if we have a callback registration; if not, just map to the last verb
             let verb = match self.common_env.cb_registrations.get(&(callback.body.id() as u32)) {
                 Some(verb) => {
@@ -290,29 +302,28 @@ impl CmdEnv {
 /// modifies the incoming line by removing the token and returning the remainder
 /// returns the found token
 /// note: we don't have split() because of nostd
-pub fn tokenize(line: &mut String::<1024>) -> Option<String::<1024>> {
-    let mut token = String::<1024>::new();
-    let mut retline = String::<1024>::new();
+pub fn tokenize(line: &mut String) -> Option<String> {
+    let mut token = String::new();
+    let mut retline = String::new();
 
-    let lineiter = line.as_str().unwrap().chars();
+    let lineiter = line.chars();
     let mut foundspace = false;
     let mut foundrest = false;
     for ch in lineiter {
         if ch != ' ' && !foundspace {
-            token.push(ch).unwrap();
+            token.push(ch);
         } else if foundspace && foundrest {
-            retline.push(ch).unwrap();
+            retline.push(ch);
         } else if foundspace && ch != ' ' {
             // handle case of multiple spaces in a row
             foundrest = true;
-            retline.push(ch).unwrap();
+            retline.push(ch);
         } else {
             foundspace = true;
             // consume the space
         }
     }
-    line.clear();
-    write!(line, "{}", retline.as_str().unwrap()).unwrap();
+    *line = retline;
     if token.len() > 0 {
         Some(token)
     } else {
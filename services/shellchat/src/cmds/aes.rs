@@ -7,7 +7,7 @@ mod aes256tests;
 /// Define block cipher test
 macro_rules! block_cipher_test {
     ($name:ident, $test_name:expr, $test_case_name:ident, $cipher:ty) => {
-        fn $name() -> String::<1024> {
+        fn $name() -> String {
             use cipher::generic_array::{typenum::Unsigned, GenericArray};
             use cipher::{
                 BlockCipher, BlockDecrypt, BlockEncrypt, NewBlockCipher,
@@ -69,7 +69,7 @@ macro_rules! block_cipher_test {
                 true
             }
 
-            let mut ret = String::<1024>::new();
+            let mut ret = String::new();
             write!(ret, "test {} passed", $test_name).unwrap();
             let pb = <$cipher as BlockCipher>::ParBlocks::to_usize();
             let mut i = 0;
@@ -101,7 +101,6 @@ macro_rules! block_cipher_test {
 }
 
 use crate::{ShellCmdApi, CommonEnv};
-use xous_ipc::String;
 
 use aes_xous::*;
 use cipher::{BlockDecrypt, BlockEncrypt, NewBlockCipher};
@@ -245,7 +244,7 @@ impl Aes {
         let sid = xous::create_server().unwrap();
         let sid_tuple = sid.to_u32();
 
-        let cb_id = env.register_handler(String::<256>::from_str("aes"));
+        let cb_id = env.register_handler(xous_ipc::String::<256>::from_str("aes"));
         CB_ID.store(cb_id, Ordering::Relaxed);
 
         xous::create_thread_4(benchmark_thread, sid_tuple.0 as usize, sid_tuple.1 as usize, sid_tuple.2 as usize, sid_tuple.3 as usize).unwrap();
@@ -267,11 +266,11 @@ block_cipher_test!(aes256soft_test, "aes256", AES256_TESTS, Aes256);
 impl<'a> ShellCmdApi<'a> for Aes {
     cmd_api!(aes); // inserts boilerplate for command API
 
-    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
-        let mut ret = String::<1024>::new();
+    fn process(&mut self, args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
+        let mut ret = String::new();
         let helpstring = "Aes [check128] [check128sw] [check256] [check256sw] [hwbench] [swbench] [susres]";
 
-        let mut tokens = args.as_str().unwrap().split(' ');
+        let mut tokens = args.as_str().split(' ');
 
         if let Some(sub_cmd) = tokens.next() {
             match sub_cmd {
@@ -325,9 +324,9 @@ impl<'a> ShellCmdApi<'a> for Aes {
         Ok(Some(ret))
     }
 
-    fn callback(&mut self, msg: &xous::MessageEnvelope, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn callback(&mut self, msg: &xous::MessageEnvelope, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         log::debug!("benchmark callback");
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
 
         xous::msg_scalar_unpack!(msg, pass, hw_mode, keybits, _, {
             let end = env.ticktimer.elapsed_ms();
@@ -1,5 +1,4 @@
 use crate::{ShellCmdApi,CommonEnv};
-use xous_ipc::String;
 
 //use core::convert::TryFrom;
 use codec::*;
@@ -74,13 +73,13 @@ impl Audio {
 impl<'a> ShellCmdApi<'a> for Audio {
     cmd_api!(audio);
 
-    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn process(&mut self, args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         use core::fmt::Write;
 
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
         let helpstring = "audio [play] [info]";
 
-        let mut tokens = args.as_str().unwrap().split(' ');
+        let mut tokens = args.as_str().split(' ');
 
         if let Some(sub_cmd) = tokens.next() {
             match sub_cmd {
@@ -90,7 +89,7 @@ impl<'a> ShellCmdApi<'a> for Audio {
                     env.ticktimer.sleep_ms(50).unwrap();
 
                     if self.callback_id.is_none() {
-                        let cb_id = env.register_handler(String::<256>::from_str(self.verb()));
+                        let cb_id = env.register_handler(xous_ipc::String::<256>::from_str(self.verb()));
                         log::trace!("hooking frame callback with ID {}", cb_id);
                         self.codec.hook_frame_callback(cb_id, self.callback_conn).unwrap(); // any non-handled IDs get routed to our callback port
                         self.callback_id = Some(cb_id);
@@ -152,11 +151,11 @@ impl<'a> ShellCmdApi<'a> for Audio {
         Ok(Some(ret))
     }
 
-    fn callback(&mut self, msg: &MessageEnvelope, _env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn callback(&mut self, msg: &MessageEnvelope, _env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         use core::fmt::Write;
 
         log::debug!("audio callback");
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
         xous::msg_scalar_unpack!(msg, free_play, _avail_rec, _, _, {
             if self.play_ptr_bytes + codec::FIFO_DEPTH *4 < self.raw_len_bytes as usize {
                 log::debug!("{} extending playback", free_play);
@@ -1,5 +1,4 @@
 use crate::{ShellCmdApi, CommonEnv};
-use xous_ipc::String;
 
 #[derive(Debug)]
 pub struct Console {
@@ -8,12 +7,12 @@ pub struct Console {
 impl<'a> ShellCmdApi<'a> for Console {
     cmd_api!(console); // inserts boilerplate for command API
 
-    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn process(&mut self, args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         use core::fmt::Write;
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
         let helpstring = "Serial console options: kernel, log, app";
 
-        let mut tokens = args.as_str().unwrap().split(' ');
+        let mut tokens = args.as_str().split(' ');
 
         if let Some(sub_cmd) = tokens.next() {
             match sub_cmd {
@@ -1,5 +1,4 @@
 use crate::{ShellCmdApi, CommonEnv};
-use xous_ipc::String;
 
 #[derive(Debug)]
 pub struct Echo {
@@ -8,7 +7,7 @@ pub struct Echo {
 impl<'a> ShellCmdApi<'a> for Echo {
     cmd_api!(echo); // inserts boilerplate for command API
 
-    fn process(&mut self, args: String::<1024>, _env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn process(&mut self, args: String, _env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         Ok(Some(args))
     }
 }
@@ -1,5 +1,5 @@
 use crate::{ShellCmdApi, CommonEnv};
-use xous_ipc::{String, Buffer};
+use xous_ipc::Buffer;
 
 use core::sync::atomic::{AtomicU32, Ordering};
 static CB_ID: AtomicU32 = AtomicU32::new(0);
@@ -78,7 +78,7 @@ fn do_update(com: &mut com::Com, callback_conn: xous::CID, package: &[u8], pkg_o
         return false;
     }
     // erase
-    let mut update_str = String::<1024>::new();
+    let mut update_str = xous_ipc::String::<1024>::new();
     write!(update_str, "Erasing {}...", name).unwrap();
     Buffer::into_buf(update_str).unwrap().lend(callback_conn, CB_ID.load(Ordering::Relaxed)).unwrap();
     update_str.clear();
@@ -309,7 +309,7 @@ impl EcUpdate {
         let sid = xous::create_server().unwrap();
         let sid_tuple = sid.to_u32();
 
-        let cb_id = env.register_handler(String::<256>::from_str("ecup"));
+        let cb_id = env.register_handler(xous_ipc::String::<256>::from_str("ecup"));
         CB_ID.store(cb_id, Ordering::Relaxed);
 
         xous::create_thread_4(ecupdate_thread, sid_tuple.0 as usize, sid_tuple.1 as usize, sid_tuple.2 as usize, sid_tuple.3 as usize).unwrap();
@@ -324,12 +324,12 @@ impl EcUpdate {
 impl<'a> ShellCmdApi<'a> for EcUpdate {
     cmd_api!(ecup); // inserts boilerplate for command API
 
-    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
-        let mut ret = String::<1024>::new();
+    fn process(&mut self, args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
+        let mut ret = String::new();
         let helpstring = "ecup [gw] [fw] [wf200] [reset]";
 
-        log::debug!("ecup handling {}", args.as_str().unwrap());
-        let mut tokens = args.as_str().unwrap().split(' ');
+        log::debug!("ecup handling {}", args.as_str());
+        let mut tokens = args.as_str().split(' ');
 
         if self.in_progress {
             log::debug!("Programming already in progress, can't double-initiate!");
@@ -380,15 +380,15 @@ impl<'a> ShellCmdApi<'a> for EcUpdate {
         Ok(Some(ret))
     }
 
-    fn callback(&mut self, msg: &xous::MessageEnvelope, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn callback(&mut self, msg: &xous::MessageEnvelope, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         log::debug!("update callback");
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
 
         if let xous::Message::Borrow(m) = &msg.body {
             // a bit of a hack: we can't route on ID because that's consumed by the outer shell dispatch loop
             // however, if the form of the message is a Borrowed buffer, treat it as a string and print it.
             let update_buf = unsafe { Buffer::from_memory_message(m) };
-            let update_str = update_buf.as_flat::<String::<1024>, _>().unwrap();
+            let update_str = update_buf.as_flat::<xous_ipc::String::<1024>, _>().unwrap();
             write!(ret, "{}", update_str.as_str()).unwrap();
         } else { // otherwise, unpack it and use the first argument as a sub-opcode type
             xous::msg_scalar_unpack!(msg, result_code, progress, _, _, {
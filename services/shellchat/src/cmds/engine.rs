@@ -1,5 +1,4 @@
 use crate::{ShellCmdApi, CommonEnv};
-use xous_ipc::String;
 
 use engine_25519::*;
 
@@ -216,7 +215,7 @@ impl Engine {
         let sid = xous::create_server().unwrap();
         let sid_tuple = sid.to_u32();
 
-        let cb_id = env.register_handler(String::<256>::from_str("engine"));
+        let cb_id = env.register_handler(xous_ipc::String::<256>::from_str("engine"));
         CB_ID.store(cb_id, Ordering::Relaxed);
 
         xous::create_thread_4(benchmark_thread, sid_tuple.0 as usize, sid_tuple.1 as usize, sid_tuple.2 as usize, sid_tuple.3 as usize).unwrap();
@@ -231,12 +230,12 @@ impl Engine {
 impl<'a> ShellCmdApi<'a> for Engine {
     cmd_api!(engine); // inserts boilerplate for command API
 
-    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn process(&mut self, args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         use core::fmt::Write;
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
         let helpstring = "engine [check] [bench] [benchdh] [susres] [dh] [ed]";
 
-        let mut tokens = args.as_str().unwrap().split(' ');
+        let mut tokens = args.as_str().split(' ');
 
         if let Some(sub_cmd) = tokens.next() {
             match sub_cmd {
@@ -460,11 +459,11 @@ impl<'a> ShellCmdApi<'a> for Engine {
         Ok(Some(ret))
     }
 
-    fn callback(&mut self, msg: &xous::MessageEnvelope, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn callback(&mut self, msg: &xous::MessageEnvelope, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         use core::fmt::Write;
 
         log::debug!("benchmark callback");
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
 
         xous::msg_scalar_unpack!(msg, passes, fails, result_type, iters, {
             let end = env.ticktimer.elapsed_ms();
@@ -1,5 +1,4 @@
 use crate::{ShellCmdApi, CommonEnv};
-use xous_ipc::String;
 use com::Com;
 use ticktimer_server::Ticktimer;
 use llio::Llio;
@@ -11,50 +10,31 @@ use crate::cmds::pds::Rate;
 
 use core::fmt::Write;
 
-use xous::{Message, ScalarMessage, MessageEnvelope};
-use core::sync::atomic::{AtomicBool, Ordering};
-static CB_RUN: AtomicBool = AtomicBool::new(false);
-static CB_GO: AtomicBool = AtomicBool::new(false);
-pub fn callback_thread(callback_id: usize) {
-    let ticktimer = ticktimer_server::Ticktimer::new().expect("Couldn't connect to Ticktimer");
-    let xns = xous_names::XousNames::new().unwrap();
-    let callback_conn = xns.request_connection_blocking(crate::SERVER_NAME_SHELLCHAT).unwrap();
-
-    loop {
-        if CB_RUN.load(Ordering::Relaxed) {
-            CB_RUN.store(false, Ordering::Relaxed);
-            ticktimer.sleep_ms(20_000).unwrap();
-            // after we wait, check and see if we still need the callback...
-            if CB_GO.load(Ordering::Relaxed) {
-                // send a message that will get routed to our callback handler, locatable with the `callback_id`
-                xous::send_message(callback_conn, Message::Scalar(ScalarMessage{
-                    id: callback_id, arg1: 0, arg2: 0, arg3: 0, arg4: 0,
-                })).unwrap();
-            }
-        } else {
-            ticktimer.sleep_ms(250).unwrap(); // a little more polite than simply busy-waiting
-        }
-    }
-}
+use xous::MessageEnvelope;
+use ticktimer_server::TimerCallback;
 
 #[derive(Debug)]
 pub struct Fcc {
     channel: Option<u8>,
     rate: Option<Rate>,
-    pds_list: [Option<String::<512>>; 8],
+    pds_list: [Option<xous_ipc::String::<512>>; 8],
     go: bool,
     tx_start_time: u64,
+    callback_id: u32,
+    /// renews the transmission while it's live
+    renewal: Option<TimerCallback>,
 }
 impl Fcc {
     pub fn new(env: &mut CommonEnv) -> Fcc {
-        let callback_id = env.register_handler(String::<256>::from_str("fcc"));
-        xous::create_thread_1(callback_thread, callback_id as usize).expect("couldn't create callback generator thread");
+        let callback_id = env.register_handler(xous_ipc::String::<256>::from_str("fcc"));
         Fcc {
             channel: None, //Some(2), // default to simplify testing, replace with None
             rate: None, //Some(Rate::B1Mbps),  // default to simplify testing, replace with None
             pds_list: [None; 8],
             go: false,
             tx_start_time: 0,
+            callback_id,
+            renewal: None,
         }
     }
     fn send_pds(&self, com: &Com, ticktimer: &Ticktimer) {
@@ -74,8 +54,9 @@ impl Fcc {
     }
     fn stop_tx(&mut self, _com: &com::Com, ticktimer: &Ticktimer, llio: &Llio) {
         self.go = false;
-        CB_GO.store(false, Ordering::Relaxed);
-        CB_RUN.store(false, Ordering::Relaxed); // make sure the callback function is disabled
+        if let Some(renewal) = self.renewal.take() {
+            ticktimer.cancel_callback(renewal).unwrap(); // make sure the callback function is disabled
+        }
         self.clear_pds();
         //self.pds_list[0] = Some(String::<512>::from_str(PDS_STOP_DATA));
         //self.send_pds(&com, &ticktimer);
@@ -87,14 +68,14 @@ impl Fcc {
 impl<'a> ShellCmdApi<'a> for Fcc {
     cmd_api!(fcc); // inserts boilerplate for command API
 
-    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
-        let mut ret = String::<1024>::new();
+    fn process(&mut self, args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
+        let mut ret = String::new();
         let helpstring = "fcc [ch 1-11] [euch 1-13] [rate <code>] [go] [stop] [rev] [res]\nrate code: b[1,2,5.5,11], g[6,9,12,18,24,36,48,54], mcs[0-7]";
 
         // no matter what, we want SSID scanning to be off
         env.com.set_ssid_scanning(false).expect("couldn't turn off SSID scanning");
 
-        let mut tokens = args.as_str().unwrap().split(' ');
+        let mut tokens = args.as_str().split(' ');
 
         if let Some(sub_cmd) = tokens.next() {
             match sub_cmd {
@@ -123,7 +104,7 @@ impl<'a> ShellCmdApi<'a> for Fcc {
                                     if record.rate == rate {
                                         let mut index: usize = 0;
                                         for &line in record.pds_data[(channel-1) as usize].iter() {
-                                            self.pds_list[index] = Some(String::<512>::from_str(line));
+                                            self.pds_list[index] = Some(xous_ipc::String::<512>::from_str(line));
                                             index += 1;
                                         }
                                         found = true;
@@ -134,9 +115,11 @@ impl<'a> ShellCmdApi<'a> for Fcc {
                                     self.send_pds(&env.com, &env.ticktimer);
                                     write!(ret, "TX live: ch {} rate {:?}", channel, rate).unwrap();
                                     self.go = true;
-                                    CB_GO.store(true, Ordering::Relaxed);
                                     self.tx_start_time = env.ticktimer.elapsed_ms();
-                                    CB_RUN.store(true, Ordering::Relaxed); // initiate the callback for the next wakeup
+                                    if self.renewal.is_none() { // initiate the callback for the next wakeups
+                                        self.renewal = Some(env.ticktimer.schedule_callback(
+                                            20_000, true, env.callback_conn, self.callback_id as usize)?);
+                                    }
                                 } else {
                                     write!(ret, "Rate/channel combo not found: ch {} rate {:?}", channel, rate).unwrap();
                                 }
@@ -222,8 +205,8 @@ impl<'a> ShellCmdApi<'a> for Fcc {
         Ok(Some(ret))
     }
 
-    fn callback(&mut self, _msg: &MessageEnvelope, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
-        let mut ret = String::<1024>::new();
+    fn callback(&mut self, _msg: &MessageEnvelope, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
+        let mut ret = String::new();
         if self.go {
             if (env.ticktimer.elapsed_ms() - self.tx_start_time) > (1000 * 60 * 5) {
                 self.stop_tx(&env.com, &env.ticktimer, &env.llio);
@@ -231,7 +214,6 @@ impl<'a> ShellCmdApi<'a> for Fcc {
             } else {
                 self.send_pds(&env.com, &env.ticktimer);
                 write!(ret, "TX renewed: ch {} rate {:?}", self.channel.unwrap(), self.rate.unwrap()).unwrap();
-                CB_RUN.store(true, Ordering::Relaxed); // re-initiate the callback
             }
         } else {
             write!(ret, "Info: passing on TX renewal").unwrap();
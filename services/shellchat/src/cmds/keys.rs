@@ -1,6 +1,5 @@
 use crate::{ShellCmdApi, CommonEnv};
 use root_keys::api::{AesRootkeyType, Block};
-use xous_ipc::String;
 
 #[derive(Debug)]
 pub struct Keys {
@@ -42,12 +41,12 @@ impl Keys {
 impl<'a> ShellCmdApi<'a> for Keys {
     cmd_api!(keys); // inserts boilerplate for command API
 
-    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn process(&mut self, args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         use core::fmt::Write;
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
         let helpstring = "keys [usblock] [usbunlock] [spinortest]";
 
-        let mut tokens = args.as_str().unwrap().split(' ');
+        let mut tokens = args.as_str().split(' ');
 
         if let Some(sub_cmd) = tokens.next() {
             match sub_cmd {
@@ -0,0 +1,171 @@
+use crate::{ShellCmdApi, CommonEnv};
+use log_server::api::LevelTarget;
+
+#[derive(Debug)]
+pub struct LogCmd {
+}
+
+/// room for the log in a response, leaving some for the line that says which page it is
+const PAGE_LEN: usize = 960;
+
+/**
+log shell command:
+- (no args): the newest page of what's been logged since boot
+- prev: the newest page of what was logged before the last reboot
+- a page number after either of these goes that many pages further back
+- levels: list the log levels that have been set at runtime
+- level proc [name] [level]: set the level for every process with this name
+- level mod [path] [level]: set the level for this module and those under it, in every process
+  (the level is one of off, error, warn, info, debug, trace, or default to remove the setting)
+*/
+impl<'a> ShellCmdApi<'a> for LogCmd {
+    cmd_api!(log); // inserts boilerplate for command API
+
+    fn process(&mut self, args: String, _env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
+        use core::fmt::Write;
+        let mut ret = String::new();
+        let helpstring = "log options: [prev] [page], levels, level [proc|mod] [name] [level|default]";
+
+        let mut tokens = args.as_str().split(' ').filter(|t| !t.is_empty()).peekable();
+        match tokens.peek() {
+            Some(&"levels") => {
+                let rules = log_server::level_rules()?;
+                if rules.is_empty() {
+                    write!(ret, "No log levels have been set").unwrap();
+                }
+                for (target, name, level) in rules {
+                    let kind = match target {
+                        LevelTarget::Process => "proc",
+                        LevelTarget::Module => "mod",
+                    };
+                    if write!(ret, "{} {}: {}\n", kind, name, level).is_err() {
+                        break;
+                    }
+                }
+                return Ok(Some(ret));
+            }
+            Some(&"level") => {
+                tokens.next();
+                match parse_level(tokens) {
+                    Some((target, name, level)) => match log_server::set_level(target, name, level) {
+                        Ok(()) => write!(ret, "Log level for {} set", name).unwrap(),
+                        Err(xous::Error::QuotaExceeded) => write!(ret, "Too many log levels are set, remove one first").unwrap(),
+                        Err(e) => return Err(e),
+                    },
+                    None => write!(ret, "{}", helpstring).unwrap(),
+                }
+                return Ok(Some(ret));
+            }
+            _ => (),
+        }
+        let previous = tokens.peek() == Some(&"prev");
+        if previous {
+            tokens.next();
+        }
+        let wanted = match tokens.next().map(|t| t.parse::<usize>()) {
+            None => 0,
+            Some(Ok(page)) => page,
+            Some(Err(_)) => {
+                write!(ret, "{}", helpstring).unwrap();
+                return Ok(Some(ret));
+            }
+        };
+
+        let log = if previous {
+            log_server::read_previous_log()?
+        } else {
+            log_server::read_log()?
+        };
+        let lines: Vec<&str> = log.lines().collect();
+        if lines.is_empty() {
+            write!(ret, "The log is empty").unwrap();
+            return Ok(Some(ret));
+        }
+
+        // pages are counted back from the end, each holding as many whole lines as fit
+        let mut end = lines.len();
+        let mut page = 0;
+        loop {
+            let mut start = end;
+            let mut len = 0;
+            while start > 0 && len + lines[start - 1].len() + 1 <= PAGE_LEN {
+                start -= 1;
+                len += lines[start].len() + 1;
+            }
+            if start == end {
+                // a line that's too long for a page gets one to itself, and is cut short
+                start -= 1;
+            }
+            if page == wanted {
+                write!(ret, "page {}{}:\n", page, if start == 0 { " (oldest)" } else { "" }).unwrap();
+                for line in lines[start..end].iter() {
+                    if write!(ret, "{}\n", tail(line, PAGE_LEN)).is_err() {
+                        break;
+                    }
+                }
+                return Ok(Some(ret));
+            }
+            if start == 0 {
+                write!(ret, "The log only goes back {} pages", page + 1).unwrap();
+                return Ok(Some(ret));
+            }
+            end = start;
+            page += 1;
+        }
+    }
+}
+
+/// parses the arguments to `log level`: proc or mod, a name, and a level or "default"
+fn parse_level<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<(LevelTarget, &'a str, Option<log::LevelFilter>)> {
+    let target = match tokens.next()? {
+        "proc" => LevelTarget::Process,
+        "mod" => LevelTarget::Module,
+        _ => return None,
+    };
+    let name = tokens.next()?;
+    let level = match tokens.next()? {
+        "default" => None,
+        level => Some(level.parse::<log::LevelFilter>().ok()?),
+    };
+    if tokens.next().is_some() {
+        return None;
+    }
+    Some((target, name, level))
+}
+
+/// the last `len` bytes of `line`, or a little less so as not to split a character
+fn tail(line: &str, len: usize) -> &str {
+    let mut cut = line.len().saturating_sub(len);
+    while !line.is_char_boundary(cut) {
+        cut += 1;
+    }
+    &line[cut..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::LevelFilter;
+
+    fn parse(args: &str) -> Option<(LevelTarget, &str, Option<LevelFilter>)> {
+        parse_level(args.split(' ').filter(|t| !t.is_empty()))
+    }
+
+    #[test]
+    fn level_arguments() {
+        assert_eq!(parse("proc gam debug"), Some((LevelTarget::Process, "gam", Some(LevelFilter::Debug))));
+        assert_eq!(parse("mod xous_names::api  TRACE"), Some((LevelTarget::Module, "xous_names::api", Some(LevelFilter::Trace))));
+        assert_eq!(parse("mod com off"), Some((LevelTarget::Module, "com", Some(LevelFilter::Off))));
+        assert_eq!(parse("proc gam default"), Some((LevelTarget::Process, "gam", None)));
+    }
+
+    #[test]
+    fn bad_level_arguments() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("proc"), None);
+        assert_eq!(parse("proc gam"), None);
+        assert_eq!(parse("thread gam info"), None);
+        assert_eq!(parse("proc gam loud"), None);
+        assert_eq!(parse("proc gam info extra"), None);
+    }
+}
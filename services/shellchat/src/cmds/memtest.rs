@@ -1,5 +1,4 @@
 use crate::{ShellCmdApi, CommonEnv};
-use xous_ipc::String;
 use num_traits::*;
 
 #[derive(Debug)]
@@ -12,7 +11,7 @@ impl Memtest {
         let sid = xous::create_server().unwrap();
         let sid_tuple = sid.to_u32();
 
-        let cb_id = env.register_handler(String::<256>::from_str("memtest"));
+        let cb_id = env.register_handler(xous_ipc::String::<256>::from_str("memtest"));
         CB_ID.store(cb_id, Ordering::Relaxed);
 
         xous::create_thread_4(test_thread, sid_tuple.0 as usize, sid_tuple.1 as usize, sid_tuple.2 as usize, sid_tuple.3 as usize).unwrap();
@@ -148,12 +147,12 @@ pub fn test_thread(sid0: usize, sid1: usize, sid2: usize, sid3: usize) {
 impl<'a> ShellCmdApi<'a> for Memtest {
     cmd_api!(memtest); // inserts boilerplate for command API
 
-    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn process(&mut self, args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         use core::fmt::Write;
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
         let helpstring = "memest [test [iters]]";
 
-        let mut tokens = args.as_str().unwrap().split(' ');
+        let mut tokens = args.as_str().split(' ');
 
         if let Some(sub_cmd) = tokens.next() {
             match sub_cmd {
@@ -184,10 +183,10 @@ impl<'a> ShellCmdApi<'a> for Memtest {
         Ok(Some(ret))
     }
 
-    fn callback(&mut self, msg: &xous::MessageEnvelope, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn callback(&mut self, msg: &xous::MessageEnvelope, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         use core::fmt::Write;
 
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
 
         xous::msg_scalar_unpack!(msg, errs, iters, dummy, _, {
             let end = env.ticktimer.elapsed_ms();
@@ -0,0 +1,65 @@
+use crate::{ShellCmdApi, CommonEnv};
+
+#[derive(Debug)]
+pub struct Names {
+}
+
+/**
+names shell command:
+- (no args): list every registered server name with its connection accounting
+- pending: list only the servers whose trusted connections aren't all taken yet, which are
+  the ones holding up `trusted_init_done`
+
+A `*` after the TOKEN column marks a server that still has trusted slots open.
+*/
+impl<'a> ShellCmdApi<'a> for Names {
+    cmd_api!(names); // inserts boilerplate for command API
+
+    fn process(&mut self, args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
+        use core::fmt::Write;
+        let mut ret = String::new();
+        let helpstring = "names options: [pending]";
+
+        let mut tokens = args.as_str().split(' ');
+        let pending_only = match tokens.next() {
+            Some("pending") => true,
+            Some("") | None => false,
+            _ => {
+                write!(ret, "{}", helpstring).unwrap();
+                return Ok(Some(ret));
+            }
+        };
+
+        let servers = match env.xns.list_servers() {
+            Ok(servers) => servers,
+            Err(e) => {
+                write!(ret, "can't list servers: {:?}", e).unwrap();
+                return Ok(Some(ret));
+            }
+        };
+        let trusted_done = env.xns.trusted_init_done().unwrap_or(false);
+        write!(ret, "{} names, trusted init {}\n", servers.len(), if trusted_done { "done" } else { "NOT done" }).unwrap();
+        write!(ret, "CONN  MAX AUTH TOKEN NAME\n").unwrap();
+        for s in servers.iter() {
+            let open = match s.max_conns {
+                Some(max) => s.current_conns < max,
+                None => false,
+            };
+            if pending_only && !open {
+                continue;
+            }
+            let max = s.max_conns.map(|m| m.to_string()).unwrap_or_else(|| "-".to_string());
+            let auth = if s.allow_authenticate { s.auth_conns.to_string() } else { "-".to_string() };
+            // stop once the output buffer is full rather than erroring out
+            if write!(ret, "{:4} {:>4} {:>4} {:>5}{} {}\n",
+                s.current_conns, max, auth,
+                if s.has_token { "yes" } else { "no" },
+                if open { "*" } else { " " },
+                s.name,
+            ).is_err() {
+                break;
+            }
+        }
+        Ok(Some(ret))
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{ShellCmdApi, CommonEnv};
+
+#[derive(Debug)]
+pub struct Ps {
+}
+
+/**
+ps shell command:
+- (no args): list every process in PID order
+- mem: list processes sorted by RAM usage, largest first (a poor man's `top`)
+- cpu: list processes sorted by CPU time, largest first
+*/
+impl<'a> ShellCmdApi<'a> for Ps {
+    cmd_api!(ps); // inserts boilerplate for command API
+
+    fn process(&mut self, args: String, _env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
+        use core::fmt::Write;
+        let mut ret = String::new();
+        let helpstring = "ps options: [mem] [cpu]";
+
+        let mut tokens = args.as_str().split(' ');
+        let sort_by = match tokens.next() {
+            Some("mem") => Some("mem"),
+            Some("cpu") => Some("cpu"),
+            Some("") | None => None,
+            _ => {
+                write!(ret, "{}", helpstring).unwrap();
+                return Ok(Some(ret));
+            }
+        };
+
+        // snapshot the process table first, so the listing is as consistent as we can make it
+        let mut procs: Vec<(xous::ProcessInfo, xous::CpuTime)> = Vec::new();
+        let mut next_pid = 1;
+        while next_pid <= 255 {
+            let info = match xous::process_info(xous::PID::new(next_pid as u8).unwrap()) {
+                Ok(info) => info,
+                Err(_) => break,
+            };
+            next_pid = info.pid.get() as usize + 1;
+            let cpu = xous::process_cpu_time(info.pid).unwrap_or_default();
+            procs.push((info, cpu));
+        }
+        match sort_by {
+            Some("mem") => procs.sort_by(|a, b| b.0.ram_bytes.cmp(&a.0.ram_bytes)),
+            Some("cpu") => procs.sort_by(|a, b| b.1.run_ms.cmp(&a.1.run_ms)),
+            _ => (),
+        }
+
+        let total_ram: usize = procs.iter().map(|(p, _)| p.ram_bytes).sum();
+        write!(ret, "{} procs, {}k RAM\n", procs.len(), total_ram / 1024).unwrap();
+        write!(ret, "PID PPID STATE   THR RDY   RAM   CPU s SRV CON NAME\n").unwrap();
+        let mut name_buf = [0u8; 64];
+        for (p, cpu) in procs.iter() {
+            let name = xous::process_name(p.pid, &mut name_buf).unwrap_or("");
+            // stop once the output buffer is full rather than erroring out
+            if write!(ret, "{:3} {:4} {:7} {:3} {:3} {:4}k {:5}.{} {:3} {:3} {}\n",
+                p.pid, p.ppid, p.status,
+                p.threads, p.ready_threads.count_ones(), p.ram_bytes / 1024,
+                cpu.run_ms / 1000, (cpu.run_ms % 1000) / 100,
+                p.servers, p.connections, name,
+            ).is_err() {
+                break;
+            }
+        }
+        Ok(Some(ret))
+    }
+}
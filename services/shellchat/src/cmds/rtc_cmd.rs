@@ -1,5 +1,4 @@
 use crate::{ShellCmdApi,CommonEnv};
-use xous_ipc::String;
 
 use core::sync::atomic::{AtomicU32, Ordering};
 static SHELLCONN: AtomicU32 = AtomicU32::new(0);
@@ -9,6 +8,32 @@ pub fn dt_callback(dt: rtc::DateTime) {
     buf.send(SHELLCONN.load(Ordering::Relaxed), 0xdead_beef).unwrap(); // send an "unknown ID" so it's routed to the callback handler
 }
 
+/// parses a UTC offset such as "+5:30" or "-8" into seconds
+fn parse_timezone(tz: &str) -> Option<i32> {
+    let (sign, hhmm) = if let Some(rest) = tz.strip_prefix('-') {
+        (-1, rest)
+    } else {
+        (1, tz.strip_prefix('+').unwrap_or(tz))
+    };
+    let mut fields = hhmm.split(':');
+    let hours = fields.next()?.parse::<u32>().ok()? as i32;
+    let mins = match fields.next() {
+        Some(mins) => mins.parse::<u32>().ok()? as i32,
+        None => 0,
+    };
+    if fields.next().is_some() || hours > 14 || mins > 59 || mins % 15 != 0 {
+        return None;
+    }
+    Some(sign * (hours * 3600 + mins * 60))
+}
+
+/// formats an offset in seconds as e.g. "+5:30"
+fn format_timezone(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let mins = seconds.abs() / 60;
+    format!("{}{}:{:02}", sign, mins / 60, mins % 60)
+}
+
 #[derive(Debug)]
 pub struct RtcCmd {
     rtc: rtc::Rtc,
@@ -26,12 +51,12 @@ impl RtcCmd {
 impl<'a> ShellCmdApi<'a> for RtcCmd {
     cmd_api!(rtc);
 
-    fn process(&mut self, args: String::<1024>, _env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn process(&mut self, args: String, _env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         use core::fmt::Write;
-        let mut ret = String::<1024>::new();
-        let helpstring = "rtc options: set, get";
+        let mut ret = String::new();
+        let helpstring = "rtc options: set, get, tz";
 
-        let mut tokens = args.as_str().unwrap().split(' ');
+        let mut tokens = args.as_str().split(' ');
 
         if let Some(sub_cmd) = tokens.next() {
             match sub_cmd {
@@ -120,6 +145,16 @@ impl<'a> ShellCmdApi<'a> for RtcCmd {
                         self.rtc.set_rtc(dt).unwrap();
                     }
                 },
+                "tz" => {
+                    if let Some(seconds) = tokens.next().and_then(parse_timezone) {
+                        match self.rtc.set_timezone_offset(seconds) {
+                            Ok(()) => write!(ret, "Timezone set to UTC{}", format_timezone(seconds)).unwrap(),
+                            Err(e) => write!(ret, "Couldn't set timezone: {:?}", e).unwrap(),
+                        }
+                    } else {
+                        write!(ret, "{}", "usage: rtc tz +hh[:mm]\neg. rtc tz -5, rtc tz +5:30").unwrap();
+                    }
+                }
                 _ => {
                     write!(ret, "{}", helpstring).unwrap();
                 }
@@ -131,13 +166,13 @@ impl<'a> ShellCmdApi<'a> for RtcCmd {
         Ok(Some(ret))
     }
 
-    fn callback(&mut self, msg: &xous::MessageEnvelope, _env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn callback(&mut self, msg: &xous::MessageEnvelope, _env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         use core::fmt::Write;
 
         let buffer = unsafe { xous_ipc::Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
         let dt = buffer.to_original::<rtc::DateTime, _>().unwrap();
 
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
         write!(ret, "{}:{:02}:{:02}, {}/{}/{}, {:?}", dt.hours, dt.minutes, dt.seconds, dt.months, dt.days, dt.years, dt.weekday).unwrap();
 
         self.rtc.unhook_rtc_callback().expect("can't unhook callback after completion");
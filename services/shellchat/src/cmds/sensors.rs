@@ -1,5 +1,4 @@
 use crate::{ShellCmdApi,CommonEnv};
-use xous_ipc::String;
 
 #[derive(Debug)]
 pub struct Sensors {
@@ -14,9 +13,9 @@ impl Sensors {
 impl<'a> ShellCmdApi<'a> for Sensors {
     cmd_api!(sensors);
 
-    fn process(&mut self, _args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn process(&mut self, _args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         use core::fmt::Write;
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
 
         write!(ret, "Vbus {:.2}V\nVint {:.2}V\nVaux {:.2}V\nVbram {:.2}V\nUSB {:.2}|{:.2}V\nTemp {:.1}°C",
            (env.llio.adc_vbus().unwrap() as f64) * 0.005033,
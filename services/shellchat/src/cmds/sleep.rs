@@ -1,5 +1,4 @@
 use crate::{ShellCmdApi,CommonEnv};
-use xous_ipc::String;
 
 #[derive(Debug)]
 pub struct Sleep {
@@ -64,13 +63,13 @@ fn susres_loop() {
 impl<'a> ShellCmdApi<'a> for Sleep {
     cmd_api!(sleep); // inserts boilerplate for command API
 
-    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn process(&mut self, args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         use core::fmt::Write;
 
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
         let helpstring = "sleep [now] [current] [ship] [kill] [coldboot] [killbounce] [sus] [stress] [crypton] [cryptoff] [wfioff] [wfion] [debugwfi]";
 
-        let mut tokens = args.as_str().unwrap().split(' ');
+        let mut tokens = args.as_str().split(' ');
 
         // in all cases, we want the boost to be off to ensure a clean shutdown
         env.com.set_boost(false).unwrap();
@@ -1,69 +1,46 @@
 use crate::{ShellCmdApi, CommonEnv};
-use xous_ipc::String;
-use com::Com;
-use ticktimer_server::Ticktimer;
 
 use core::fmt::Write;
 
-use xous::{Message, ScalarMessage, MessageEnvelope};
-use core::sync::atomic::{AtomicBool, Ordering};
-static CB_RUN: AtomicBool = AtomicBool::new(false);
-pub fn callback_thread() {
-    let ticktimer = Ticktimer::new().expect("Couldn't connect to Ticktimer");
-    let xns = xous_names::XousNames::new().unwrap();
-    let callback_conn = xns.request_connection_blocking(crate::SERVER_NAME_SHELLCHAT).unwrap();
-    let com = Com::new(&xns).unwrap();
-
-    let mut iters = 0; // count how long we've been waiting
-    loop {
-        if CB_RUN.load(Ordering::Relaxed) {
-            log::trace!("checking ssid status");
-            if com.ssid_scan_updated().unwrap() {
-                log::trace!("initiating callback check");
-                // just send a bogus message
-                xous::send_message(callback_conn, Message::Scalar(ScalarMessage{
-                    id: 0xdeadbeef, arg1: 0, arg2: 0, arg3: 0, arg4: 0,
-                })).unwrap();
-                CB_RUN.store(false, Ordering::Relaxed);
-            } else {
-                iters += 1;
-                if iters > 11 { // should have gotten a result within 20 seconds...
-                    // there may have been stale data in the scan cache, clear it
-                    let _ = com.ssid_fetch_as_string().unwrap();
-                }
-            }
-            ticktimer.sleep_ms(2_000).unwrap();
-        } else {
-            ticktimer.sleep_ms(1_000).unwrap();
-            iters = 0;
-        }
-    }
-}
+use xous::MessageEnvelope;
+use ticktimer_server::TimerCallback;
 
 #[derive(Debug)]
 pub struct Ssid {
+    callback_id: u32,
+    /// checks for scan results, while a scan is in progress
+    scan_check: Option<TimerCallback>,
+    /// count how long we've been waiting
+    iters: u32,
 }
 impl Ssid {
-    pub fn new() -> Ssid {
-        xous::create_thread_0(callback_thread).expect("couldn't create callback generator thread");
+    pub fn new(env: &mut CommonEnv) -> Ssid {
+        let callback_id = env.register_handler(xous_ipc::String::<256>::from_str("ssid"));
         Ssid {
+            callback_id,
+            scan_check: None,
+            iters: 0,
         }
     }
 }
 impl<'a> ShellCmdApi<'a> for Ssid {
     cmd_api!(ssid);
 
-    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
-        let mut ret = String::<1024>::new();
+    fn process(&mut self, args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
+        let mut ret = String::new();
         let helpstring = "ssid [scan]";
 
-        let mut tokens = args.as_str().unwrap().split(' ');
+        let mut tokens = args.as_str().split(' ');
 
         if let Some(sub_cmd) = tokens.next() {
             match sub_cmd {
                 "scan" => {
                     env.com.set_ssid_scanning(true).expect("couldn't turn on SSID scanning");
-                    CB_RUN.store(true, Ordering::Relaxed);
+                    if self.scan_check.is_none() {
+                        self.scan_check = Some(env.ticktimer.schedule_callback(
+                            2_000, true, env.callback_conn, self.callback_id as usize)?);
+                    }
+                    self.iters = 0;
                     write!(ret, "SSID scan initiated, please wait...").unwrap();
                 }
                 _ => {
@@ -76,8 +53,24 @@ impl<'a> ShellCmdApi<'a> for Ssid {
         Ok(Some(ret))
     }
 
-    fn callback(&mut self, _msg: &MessageEnvelope, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
-        let mut ret = String::<1024>::new();
+    fn callback(&mut self, msg: &MessageEnvelope, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
+        let scan_check = match self.scan_check {
+            Some(check) if msg.body.scalar_message().map(|m| m.arg1) == Some(check.to_usize()) => check,
+            _ => return Ok(None),
+        };
+        log::trace!("checking ssid status");
+        if !env.com.ssid_scan_updated().unwrap() {
+            self.iters += 1;
+            if self.iters > 11 { // should have gotten a result within 20 seconds...
+                // there may have been stale data in the scan cache, clear it
+                let _ = env.com.ssid_fetch_as_string().unwrap();
+            }
+            return Ok(None);
+        }
+        env.ticktimer.cancel_callback(scan_check)?;
+        self.scan_check = None;
+
+        let mut ret = String::new();
         log::trace!("fetching SSID");
         write!(ret, "{}", env.com.ssid_fetch_as_string().unwrap()).unwrap();
         Ok(Some(ret))
@@ -1,5 +1,4 @@
 use crate::{ShellCmdApi,CommonEnv};
-use xous_ipc::String;
 
 #[derive(Debug)]
 pub struct Test {
@@ -16,16 +15,16 @@ impl Test {
 impl<'a> ShellCmdApi<'a> for Test {
     cmd_api!(test);
 
-    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn process(&mut self, args: String, env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         use core::fmt::Write;
 
         const SENTINEL: &'static str = "|TSTR";
 
         self.state += 1;
-        let mut ret = String::<1024>::new();
+        let mut ret = String::new();
         write!(ret, "Test has run {} times.\n", self.state).unwrap();
 
-        let mut tokens = args.as_str().unwrap().split(' ');
+        let mut tokens = args.as_str().split(' ');
 
         if let Some(sub_cmd) = tokens.next() {
             match sub_cmd {
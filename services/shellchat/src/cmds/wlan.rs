@@ -1,6 +1,5 @@
 use crate::{CommonEnv, ShellCmdApi};
 use core::fmt::Write;
-use xous_ipc::String;
 
 #[derive(Debug)]
 pub struct Wlan {}
@@ -21,14 +20,14 @@ impl<'a> ShellCmdApi<'a> for Wlan {
 
     fn process(
         &mut self,
-        args: String<1024>,
+        args: String,
         env: &mut CommonEnv,
-    ) -> Result<Option<String<1024>>, xous::Error> {
-        let mut ret = String::<1024>::new();
+    ) -> Result<Option<String>, xous::Error> {
+        let mut ret = String::new();
         let helpstring = "wlan [on] [off] [setssid ...] [setpass ...] [join] [leave] [status]";
         let mut show_help = false;
 
-        let mut tokens = args.as_str().unwrap().split(' ');
+        let mut tokens = args.as_str().split(' ');
         if let Some(sub_cmd) = tokens.next() {
             match sub_cmd {
                 "on" => {
@@ -44,7 +43,7 @@ impl<'a> ShellCmdApi<'a> for Wlan {
                     };
                 }
                 "setssid" => {
-                    let mut val = String::<1024>::new();
+                    let mut val = xous_ipc::String::<1024>::new();
                     join_tokens(&mut val, &mut tokens);
                     if val.len() == 0 {
                         let _ = write!(ret, "Error: SSID too short");
@@ -56,7 +55,7 @@ impl<'a> ShellCmdApi<'a> for Wlan {
                     }
                 }
                 "setpass" => {
-                    let mut val = String::<1024>::new();
+                    let mut val = xous_ipc::String::<1024>::new();
                     join_tokens(&mut val, &mut tokens);
                     let _ = match env.com.wlan_set_pass(&val) {
                         Ok(_) => write!(ret, "wlan setpass {}", val),
@@ -102,7 +101,7 @@ This is intended to reverse the effect of .split(' ') in the context of a very s
 command parser. This is a lazy way to avoid building a parser for quoted strings, since
 SSIDs or passwords might include spaces.
 */
-fn join_tokens<'a>(buf: &mut String<1024>, tokens: impl Iterator<Item = &'a str>) {
+fn join_tokens<'a>(buf: &mut xous_ipc::String<1024>, tokens: impl Iterator<Item = &'a str>) {
     for (i, tok) in tokens.enumerate() {
         if i == 0 {
             write!(buf, "{}", tok).unwrap();
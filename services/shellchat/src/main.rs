@@ -9,7 +9,6 @@ it in the services/shellchat/src/cmds/ directory:
 
 ```Rust
 use crate::{ShellCmdApi, CommonEnv};
-use xous_ipc::String;
 
 #[derive(Debug)]
 pub struct Echo {
@@ -18,7 +17,7 @@ pub struct Echo {
 impl<'a> ShellCmdApi<'a> for Echo {
     cmd_api!(echo); // inserts boilerplate for command API
 
-    fn process(&mut self, args: String::<1024>, _env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn process(&mut self, args: String, _env: &mut CommonEnv) -> Result<Option<String>, xous::Error> {
         Ok(Some(rest))
     }
 }
@@ -31,12 +30,10 @@ Check for more detailed docs under Modules/cmds "Shell Chat" below
 */
 use log::info;
 
-use core::fmt::Write;
-
 use gam::UxRegistration;
 use graphics_server::{Gid, Point, Rectangle, TextBounds, TextView, DrawStyle, GlyphStyle, PixelColor};
 use xous::MessageEnvelope;
-use xous_ipc::{String, Buffer};
+use xous_ipc::Reassembler;
 
 #[doc = include_str!("../README.md")]
 mod cmds;
@@ -45,7 +42,7 @@ use cmds::*;
 #[derive(Debug)]
 struct History {
     // the history record
-    pub text: String<1024>,
+    pub text: String,
     // if true, this was input from the user; if false, it's a response from the shell
     pub is_input: bool,
 }
@@ -54,7 +51,7 @@ struct History {
 struct Repl {
     // optional structures that indicate new input to the Repl loop per iteration
     // an input string
-    input: Option<String<1024>>,
+    input: Option<String>,
     // messages from other servers
     msg: Option<MessageEnvelope>,
 
@@ -83,9 +80,9 @@ impl Repl{
         let gam = gam::Gam::new(xns).expect("can't connect to GAM");
 
         let token = gam.register_ux(UxRegistration {
-            app_name: String::<128>::from_str(APP_NAME_SHELLCHAT),
+            app_name: xous_ipc::String::<128>::from_str(APP_NAME_SHELLCHAT),
             ux_type: gam::UxType::Chat,
-            predictor: Some(String::<64>::from_str(ime_plugin_shell::SERVER_NAME_IME_PLUGIN_SHELL)),
+            predictor: Some(xous_ipc::String::<64>::from_str(ime_plugin_shell::SERVER_NAME_IME_PLUGIN_SHELL)),
             listener: sid.to_array(), // note disclosure of our SID to the GAM -- the secret is now shared with the GAM!
             redraw_id: ShellOpcode::Redraw.to_u32().unwrap(),
             gotinput_id: Some(ShellOpcode::Line.to_u32().unwrap()),
@@ -120,10 +117,7 @@ impl Repl{
 
     /// accept a new input string
     fn input(&mut self, line: &str) -> Result<(), xous::Error> {
-        let mut local = String::<1024>::new();
-        write!(local, "{}", line).expect("line too long for history buffer");
-
-        self.input = Some(local);
+        self.input = Some(line.to_string());
 
         Ok(())
     }
@@ -143,9 +137,9 @@ impl Repl{
     fn update(&mut self, was_callback: bool) -> Result<(), xous::Error> {
         let debug1 = false;
         // if we had an input string, do something
-        if let Some(local) = self.input {
+        if let Some(local) = &self.input {
             let input_history = History {
-                text: local,
+                text: local.clone(),
                 is_input: true,
             };
             self.circular_push(input_history);
@@ -162,13 +156,11 @@ impl Repl{
 
         let mut dirty = true;
         // take the input and pass it on to the various command parsers, and attach result
-        if let Some(mut local) = self.input {
+        if let Some(mut local) = self.input.take() {
             log::trace!("processing line: {}", local);
             if let Some(res) = self.env.dispatch(Some(&mut local), None).expect("command dispatch failed") {
-                let mut response = String::<1024>::new();
-                write!(response, "{}", res).expect("can't copy result to history");
                 let output_history = History {
-                    text: response,
+                    text: res,
                     is_input: false
                 };
                 self.circular_push(output_history);
@@ -178,10 +170,8 @@ impl Repl{
         } else if let Some(msg) = &self.msg {
             log::trace!("processing callback msg: {:?}", msg);
             if let Some(res) = self.env.dispatch(None, Some(msg)).expect("callback failed") {
-                let mut response = String::<1024>::new();
-                write!(response, "{}", res).expect("can't copy result to history");
                 let output_history = History {
-                    text: response,
+                    text: res,
                     is_input: false
                 };
                 self.circular_push(output_history);
@@ -250,7 +240,8 @@ impl Repl{
             bubble_tv.style = GlyphStyle::Small;
             bubble_tv.margin = self.bubble_margin;
             bubble_tv.ellipsis = false; bubble_tv.insertion = None;
-            write!(bubble_tv.text, "{}", h.text.as_str().expect("couldn't convert history text")).expect("couldn't write history text to TextView");
+            // the history can hold more than a TextView, so a long bubble shows as much as fits
+            bubble_tv.text.append(&h.text).ok();
             log::trace!("posting {}", bubble_tv.text);
             self.gam.post_textview(&mut bubble_tv).expect("couldn't render bubble textview");
 
@@ -289,10 +280,13 @@ enum ShellOpcode {
 // nothing prevents the two from being the same, other than naming conventions
 pub(crate) const SERVER_NAME_SHELLCHAT: &str = "_Shell chat application_"; // used internally by xous-names
 pub(crate) const APP_NAME_SHELLCHAT: &str = "shellchat"; // the user-facing name
+/// longest input line we accept from the GAM
+const MAX_LINE_LEN: usize = 16384;
 
 #[xous::xous_main]
 fn xmain() -> ! {
     log_server::init_wait().unwrap();
+    xous::crash::install().expect("couldn't install crash reporter"); // faults get a backtrace in the log
     log::set_max_level(log::LevelFilter::Info);
     info!("my PID is {}", xous::process::id());
 
@@ -304,6 +298,7 @@ fn xmain() -> ! {
     let mut repl = Repl::new(&xns, shch_sid);
     let mut update_repl = false;
     let mut was_callback = false;
+    let mut lines = Reassembler::new(MAX_LINE_LEN);
 
     log::trace!("starting main loop");
     loop {
@@ -311,12 +306,17 @@ fn xmain() -> ! {
         log::debug!("got message {:?}", msg);
         match FromPrimitive::from_usize(msg.body.id()) {
             Some(ShellOpcode::Line) => {
-                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
-                let s = buffer.as_flat::<String<4000>, _>().unwrap();
-                log::trace!("shell got input line: {}", s.as_str());
-                repl.input(s.as_str()).expect("REPL couldn't accept input string");
-                update_repl = true; // set a flag, instead of calling here, so message can drop and calling server is released
-                was_callback = false;
+                match lines.receive(&msg) {
+                    Ok(Some(line)) => {
+                        let s = String::from_utf8_lossy(&line);
+                        log::trace!("shell got input line: {}", s);
+                        repl.input(&s).expect("REPL couldn't accept input string");
+                        update_repl = true; // set a flag, instead of calling here, so message can drop and calling server is released
+                        was_callback = false;
+                    }
+                    Ok(None) => (), // more of the line is still to come
+                    Err(e) => log::error!("couldn't receive input line: {:?}", e),
+                }
             }
             Some(ShellOpcode::Redraw) => {
                 log::trace!("got Redraw");
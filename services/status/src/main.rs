@@ -9,7 +9,7 @@ use core::fmt::Write;
 use blitstr_ref as blitstr;
 
 use num_traits::*;
-use xous::{msg_scalar_unpack, send_message, Message, CID};
+use xous::{msg_scalar_unpack, send_message, CID};
 use xous_ipc::String;
 
 use graphics_server::*;
@@ -67,20 +67,6 @@ pub fn dt_callback(dt: rtc::DateTime) {
     }
 }
 
-pub fn pump_thread(conn: usize) {
-    let ticktimer = ticktimer_server::Ticktimer::new().unwrap();
-    loop {
-        match send_message(
-            conn as u32,
-            Message::new_scalar(StatusOpcode::Pump.to_usize().unwrap(), 0, 0, 0, 0),
-        ) {
-            Err(xous::Error::ServerNotFound) => break,
-            Ok(xous::Result::Ok) => {}
-            _ => panic!("unhandled error in status pump thread"),
-        }
-        ticktimer.sleep_ms(1000).unwrap();
-    }
-}
 #[xous::xous_main]
 fn xmain() -> ! {
     log_server::init_wait().unwrap();
@@ -121,11 +107,13 @@ fn xmain() -> ! {
         .expect("|status: can't register server");
     // create a connection for callback hooks
     unsafe { CB_TO_MAIN_CONN = Some(xous::connect(status_sid).unwrap()) };
+    let ticktimer = ticktimer_server::Ticktimer::new().expect("Couldn't connect to Ticktimer");
     let pump_conn = xous::connect(status_sid).unwrap();
-    xous::create_thread_1(pump_thread, pump_conn as _).expect("couldn't create pump thread");
+    let pump = ticktimer
+        .schedule_callback(1000, true, pump_conn, StatusOpcode::Pump.to_usize().unwrap())
+        .expect("couldn't schedule status pump");
 
     let gam = gam::Gam::new(&xns).expect("|status: can't connect to GAM");
-    let ticktimer = ticktimer_server::Ticktimer::new().expect("Couldn't connect to Ticktimer");
     let mut com = com::Com::new(&xns).expect("|status: can't connect to COM");
 
     log::trace!("|status: getting screen size");
@@ -510,6 +498,7 @@ fn xmain() -> ! {
             xous::disconnect(cb).unwrap();
         }
     }
+    ticktimer.cancel_callback(pump).unwrap();
     unsafe {
         xous::disconnect(pump_conn).unwrap();
     }
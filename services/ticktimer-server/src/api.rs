@@ -14,4 +14,71 @@ pub(crate) enum Opcode {
 
     /// force a WDT update
     PingWdt = 4,
+
+    /// Lock the mutex named by arg1 for the calling thread, blocking until
+    /// it's available
+    LockMutex = 5,
+
+    /// Unlock the mutex named by arg1, which the calling thread must hold
+    UnlockMutex = 6,
+
+    /// Unlock the mutex named by arg2, which the calling thread must hold,
+    /// and wait for the condition variable named by arg1 to be notified,
+    /// giving up after arg3 milliseconds unless arg3 is 0. Returns once the
+    /// mutex is locked again.
+    WaitForCondition = 7,
+
+    /// Wake up to arg2 of the threads waiting on the condition variable named
+    /// by arg1, and return how many there were
+    NotifyCondition = 8,
+
+    /// Register the calling process' callback forwarding server, whose SID is
+    /// in arg1-arg4. This replaces any server the process's PID already had,
+    /// along with its callbacks.
+    RegisterCallbackServer = 9,
+
+    /// Send message arg4 to connection arg3 after arg1 milliseconds, repeating
+    /// every arg1 milliseconds if arg2 is nonzero, and return a handle
+    ScheduleCallback = 10,
+
+    /// Cancel the callback whose handle is arg1, returning 1 if it was still
+    /// scheduled
+    CancelCallback = 11,
+
+    /// Get the time in UTC as milliseconds since 1970, or `UseBeforeInit`
+    /// if the RTC hasn't been read yet. `xous::time` sends this directly,
+    /// which is why the number comes from there.
+    GetUtcTimeMs = xous::time::TICKTIMER_GET_UTC_TIME_MS as isize,
+
+    /// Get the local time as milliseconds since 1970, or `UseBeforeInit` if
+    /// the RTC hasn't been read yet
+    GetLocalTimeMs = 13,
+
+    /// The RTC read the local time in arg1 (low word) and arg2 (high word),
+    /// as milliseconds since 1970. Ignored unless sent by the process that
+    /// claimed the wall clock.
+    SetWallClockMs = 14,
+
+    /// Set local time minus UTC to arg1 seconds. Ignored unless sent by the
+    /// process that claimed the wall clock.
+    SetTimezoneOffset = 15,
+
+    /// Get local time minus UTC in seconds
+    GetTimezoneOffset = 16,
+
+    /// Become the only process that may set the wall clock and timezone,
+    /// returning a `xous::Error`, which is `AccessDenied` if another process
+    /// already has
+    ClaimWallClock = 17,
+}
+
+/// Messages to the callback forwarding server within a client process
+#[derive(num_derive::FromPrimitive, num_derive::ToPrimitive, Debug)]
+pub(crate) enum CallbackEvent {
+    /// A timer expired: send message arg2 to connection arg1, passing along
+    /// the handle in arg3
+    Event = 0,
+
+    /// Shut the forwarding server down
+    Drop = 1,
 }
@@ -0,0 +1,170 @@
+//! Timer callbacks scheduled on behalf of other processes.
+//!
+//! A callback is a scalar message sent to a server of the caller's choosing
+//! once its timer expires, and again every period if it is periodic. The
+//! ticktimer doesn't know the caller's server, only a connection the caller
+//! made to it, so the message goes by way of a forwarding server that the
+//! client library starts in each process that uses callbacks. Each callback
+//! is named by a handle, which the caller may use to cancel it.
+
+use std::collections::HashMap;
+
+use xous::{CID, PID};
+
+/// A callback that is waiting for its timer to expire
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Callback {
+    pub pid: PID,
+    /// The connection the forwarding server sends the message to
+    pub cid: CID,
+    /// The ID of the message to send
+    pub opcode: usize,
+    /// How often to repeat, in milliseconds, if this is periodic
+    pub period: Option<u64>,
+}
+
+impl Callback {
+    /// When to fire next, given that the timer for `due` expired at `now`.
+    /// A periodic callback stays on its original schedule, but skips any
+    /// periods that have already gone by rather than firing several times in
+    /// a row.
+    pub fn next_due(&self, due: u64, now: u64) -> Option<u64> {
+        let period = self.period?;
+        let next = due + period;
+        if next > now {
+            Some(next)
+        } else {
+            Some(now + period - (now - due) % period)
+        }
+    }
+}
+
+pub struct Callbacks {
+    next_handle: usize,
+    pending: HashMap<usize, Callback>,
+    /// Each process' forwarding server
+    servers: HashMap<PID, CID>,
+}
+
+impl Callbacks {
+    pub fn new() -> Self {
+        Callbacks {
+            next_handle: 1,
+            pending: HashMap::new(),
+            servers: HashMap::new(),
+        }
+    }
+
+    /// The connection to `pid`'s forwarding server, if it has one.
+    pub fn server(&self, pid: PID) -> Option<CID> {
+        self.servers.get(&pid).copied()
+    }
+
+    /// Record the connection to `pid`'s forwarding server. The client library
+    /// only registers once per process, so a server that `pid` already has
+    /// was left behind by an earlier process with the same PID. It and its
+    /// callbacks are forgotten, and the old connection is returned so that it
+    /// can be closed.
+    pub fn set_server(&mut self, pid: PID, server: CID) -> Option<CID> {
+        let stale = self.remove_process(pid);
+        self.servers.insert(pid, server);
+        stale
+    }
+
+    /// Add a callback and return its handle.
+    pub fn add(&mut self, callback: Callback) -> usize {
+        let handle = self.next_handle;
+        // Skip 0, which tells the client that nothing was scheduled.
+        self.next_handle = self.next_handle.checked_add(1).unwrap_or(1);
+        self.pending.insert(handle, callback);
+        handle
+    }
+
+    pub fn get(&self, handle: usize) -> Option<Callback> {
+        self.pending.get(&handle).copied()
+    }
+
+    /// Forget a callback, on behalf of `pid`. Returns whether `pid` had such a
+    /// callback that hadn't fired for the last time yet.
+    pub fn cancel(&mut self, pid: PID, handle: usize) -> bool {
+        match self.pending.get(&handle) {
+            Some(callback) if callback.pid == pid => self.pending.remove(&handle).is_some(),
+            _ => false,
+        }
+    }
+
+    /// Forget every callback of a process, along with its forwarding server.
+    /// Returns the connection to that server, which is no longer needed.
+    pub fn remove_process(&mut self, pid: PID) -> Option<CID> {
+        self.pending.retain(|_, callback| callback.pid != pid);
+        self.servers.remove(&pid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn callback(pid: u8, period: Option<u64>) -> Callback {
+        Callback {
+            pid: PID::new(pid).unwrap(),
+            cid: 1,
+            opcode: 7,
+            period,
+        }
+    }
+
+    #[test]
+    fn one_shot_fires_once() {
+        assert_eq!(callback(2, None).next_due(100, 100), None);
+    }
+
+    #[test]
+    fn periodic_keeps_its_schedule() {
+        let periodic = callback(2, Some(10));
+        assert_eq!(periodic.next_due(100, 100), Some(110));
+        assert_eq!(periodic.next_due(100, 104), Some(110));
+        // Periods that were missed altogether are skipped.
+        assert_eq!(periodic.next_due(100, 110), Some(120));
+        assert_eq!(periodic.next_due(100, 135), Some(140));
+    }
+
+    #[test]
+    fn only_the_owner_can_cancel() {
+        let mut callbacks = Callbacks::new();
+        let handle = callbacks.add(callback(2, Some(10)));
+        assert_ne!(handle, 0);
+        assert!(!callbacks.cancel(PID::new(3).unwrap(), handle));
+        assert!(callbacks.cancel(PID::new(2).unwrap(), handle));
+        assert!(!callbacks.cancel(PID::new(2).unwrap(), handle));
+        assert_eq!(callbacks.get(handle), None);
+    }
+
+    #[test]
+    fn removing_a_process_forgets_its_callbacks() {
+        let mut callbacks = Callbacks::new();
+        let pid = PID::new(2).unwrap();
+        assert_eq!(callbacks.set_server(pid, 5), None);
+        let mine = callbacks.add(callback(2, None));
+        let theirs = callbacks.add(callback(3, None));
+
+        assert_eq!(callbacks.remove_process(pid), Some(5));
+        assert_eq!(callbacks.server(pid), None);
+        assert_eq!(callbacks.get(mine), None);
+        assert!(callbacks.get(theirs).is_some());
+    }
+
+    #[test]
+    fn registering_again_replaces_a_stale_server() {
+        let mut callbacks = Callbacks::new();
+        let pid = PID::new(2).unwrap();
+        callbacks.set_server(pid, 5);
+        let stale = callbacks.add(callback(2, None));
+        let theirs = callbacks.add(callback(3, None));
+
+        assert_eq!(callbacks.set_server(pid, 6), Some(5));
+        assert_eq!(callbacks.server(pid), Some(6));
+        assert_eq!(callbacks.get(stale), None);
+        assert!(callbacks.get(theirs).is_some());
+    }
+}
@@ -5,6 +5,18 @@ pub mod api;
 use num_traits::ToPrimitive;
 use xous::{send_message, Error, CID};
 
+/// A timer callback scheduled with `Ticktimer::schedule_callback()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimerCallback(usize);
+
+impl TimerCallback {
+    /// The value that arrives in arg1 of each message the callback sends, for
+    /// telling apart callbacks that use the same opcode
+    pub fn to_usize(&self) -> usize {
+        self.0
+    }
+}
+
 #[derive(Debug)]
 pub struct Ticktimer {
     conn: CID,
@@ -60,9 +72,308 @@ impl Ticktimer {
         )
         .expect("Couldn't send WDT ping");
     }
+
+    /// Lock the mutex named by `mutex`, waiting for as long as another thread
+    /// holds it. Any address will do as a name, though it's usually that of
+    /// the data being protected; names are private to each process.
+    pub fn lock_mutex(&self, mutex: usize) -> Result<(), Error> {
+        self.sync_request(api::Opcode::LockMutex, mutex, 0, 0)
+            .map(|_| ())
+    }
+
+    /// Unlock a mutex that was locked with `lock_mutex()`, handing it to the
+    /// longest-waiting thread, if there is one.
+    ///
+    /// # Errors
+    ///
+    /// * **DoubleFree**: The mutex wasn't locked
+    /// * **AccessDenied**: Another thread holds the mutex
+    pub fn unlock_mutex(&self, mutex: usize) -> Result<(), Error> {
+        self.sync_request(api::Opcode::UnlockMutex, mutex, 0, 0)
+            .map(|_| ())
+    }
+
+    /// Unlock `mutex`, wait until `condvar` is notified, and lock `mutex`
+    /// again. Nothing can be notified in between unlocking and waiting, as
+    /// the ticktimer does both at once. Waits forever if `timeout_ms` is None.
+    ///
+    /// # Errors
+    ///
+    /// * **AccessDenied**: `mutex` isn't held by this thread
+    /// * **Timeout**: `condvar` wasn't notified in time. `mutex` is locked
+    ///   again regardless.
+    pub fn wait_condition(
+        &self,
+        condvar: usize,
+        mutex: usize,
+        timeout_ms: Option<usize>,
+    ) -> Result<(), Error> {
+        // A timeout of 0 means forever, so round a real one up.
+        let timeout = timeout_ms.map_or(0, |ms| ms.max(1));
+        self.sync_request(api::Opcode::WaitForCondition, condvar, mutex, timeout)
+            .map(|_| ())
+    }
+
+    /// Wake the thread that has been waiting on `condvar` the longest, and
+    /// return whether there was one.
+    pub fn notify_one(&self, condvar: usize) -> Result<bool, Error> {
+        self.sync_request(api::Opcode::NotifyCondition, condvar, 1, 0)
+            .map(|woken| woken != 0)
+    }
+
+    /// Wake every thread waiting on `condvar`, and return how many there were.
+    pub fn notify_all(&self, condvar: usize) -> Result<usize, Error> {
+        self.sync_request(api::Opcode::NotifyCondition, condvar, usize::MAX, 0)
+    }
+
+    /// Have `opcode` sent to `cid` as a scalar message after `ms`
+    /// milliseconds, and every `ms` milliseconds after that if `periodic` is
+    /// set, until cancelled. The message's arg1 is the callback's
+    /// `to_usize()`.
+    ///
+    /// The first callback in a process starts a thread that passes the
+    /// messages on, since the ticktimer can't use `cid` itself.
+    pub fn schedule_callback(
+        &self,
+        ms: usize,
+        periodic: bool,
+        cid: CID,
+        opcode: usize,
+    ) -> Result<TimerCallback, Error> {
+        self.start_callback_server()?;
+        let response = send_message(
+            self.conn,
+            xous::Message::new_blocking_scalar(
+                api::Opcode::ScheduleCallback.to_usize().unwrap(),
+                ms,
+                periodic as usize,
+                cid as usize,
+                opcode,
+            ),
+        )?;
+        match response {
+            xous::Result::Scalar1(0) => Err(Error::UseBeforeInit),
+            xous::Result::Scalar1(handle) => Ok(TimerCallback(handle)),
+            _ => Err(Error::InternalError),
+        }
+    }
+
+    /// Stop a callback from firing again, and return whether it was still
+    /// scheduled. A one-shot callback that has already fired is not.
+    pub fn cancel_callback(&self, callback: TimerCallback) -> Result<bool, Error> {
+        let response = send_message(
+            self.conn,
+            xous::Message::new_blocking_scalar(
+                api::Opcode::CancelCallback.to_usize().unwrap(),
+                callback.0,
+                0,
+                0,
+                0,
+            ),
+        )?;
+        if let xous::Result::Scalar1(cancelled) = response {
+            Ok(cancelled != 0)
+        } else {
+            Err(Error::InternalError)
+        }
+    }
+
+    /// The time in UTC, in milliseconds since 1970-01-01 00:00:00.
+    ///
+    /// # Errors
+    ///
+    /// * **UseBeforeInit**: The RTC hasn't been read yet, which happens
+    ///   shortly after boot and after every resume
+    pub fn now_utc(&self) -> Result<u64, Error> {
+        self.time_request(api::Opcode::GetUtcTimeMs)
+    }
+
+    /// The local time, in milliseconds since 1970-01-01 00:00:00, which is
+    /// what the RTC keeps. Fails as `now_utc()` does.
+    pub fn now_local(&self) -> Result<u64, Error> {
+        self.time_request(api::Opcode::GetLocalTimeMs)
+    }
+
+    /// Tell the ticktimer that the RTC reads `local_ms` milliseconds since
+    /// 1970-01-01 00:00:00 local time. This is ignored unless this process
+    /// has claimed the wall clock with `claim_wall_clock()`.
+    pub fn set_wall_clock_ms(&self, local_ms: u64) -> Result<(), Error> {
+        send_message(
+            self.conn,
+            xous::Message::new_scalar(
+                api::Opcode::SetWallClockMs.to_usize().unwrap(),
+                (local_ms & 0xFFFF_FFFF) as usize,
+                ((local_ms >> 32) & 0xFFFF_FFFF) as usize,
+                0,
+                0,
+            ),
+        )
+        .map(|_| ())
+    }
+
+    /// Set how far ahead of UTC local time is, in seconds, e.g. 3600 for
+    /// UTC+1. This changes `now_utc()`, as the RTC keeps local time. Like
+    /// `set_wall_clock_ms()`, it's ignored unless this process has claimed
+    /// the wall clock.
+    pub fn set_timezone_offset(&self, seconds: i32) -> Result<(), Error> {
+        send_message(
+            self.conn,
+            xous::Message::new_scalar(
+                api::Opcode::SetTimezoneOffset.to_usize().unwrap(),
+                seconds as usize,
+                0,
+                0,
+                0,
+            ),
+        )
+        .map(|_| ())
+    }
+
+    /// How far ahead of UTC local time is, in seconds
+    pub fn timezone_offset(&self) -> Result<i32, Error> {
+        let response = send_message(
+            self.conn,
+            xous::Message::new_blocking_scalar(
+                api::Opcode::GetTimezoneOffset.to_usize().unwrap(),
+                0,
+                0,
+                0,
+                0,
+            ),
+        )?;
+        if let xous::Result::Scalar1(seconds) = response {
+            Ok(seconds as i32)
+        } else {
+            Err(Error::InternalError)
+        }
+    }
+
+    /// Become the only process that may set the wall clock and timezone. The
+    /// RTC server does this as it starts, before anyone else can.
+    ///
+    /// # Errors
+    ///
+    /// * **AccessDenied**: Another process already claimed the wall clock
+    pub fn claim_wall_clock(&self) -> Result<(), Error> {
+        let response = send_message(
+            self.conn,
+            xous::Message::new_blocking_scalar(
+                api::Opcode::ClaimWallClock.to_usize().unwrap(),
+                0,
+                0,
+                0,
+                0,
+            ),
+        )?;
+        match response {
+            xous::Result::Scalar1(0) => Ok(()),
+            xous::Result::Scalar1(e) => Err(Error::from_usize(e)),
+            _ => Err(Error::InternalError),
+        }
+    }
+
+    fn time_request(&self, opcode: api::Opcode) -> Result<u64, Error> {
+        let response = send_message(
+            self.conn,
+            xous::Message::new_blocking_scalar(opcode.to_usize().unwrap(), 0, 0, 0, 0),
+        )?;
+        match response {
+            xous::Result::Scalar2(lower, upper) => Ok(lower as u64 | ((upper as u64) << 32)),
+            xous::Result::Scalar1(e) => Err(Error::from_usize(e)),
+            _ => Err(Error::InternalError),
+        }
+    }
+
+    fn start_callback_server(&self) -> Result<(), Error> {
+        // Only one thread gets to register, and the others wait to see how
+        // it went, so that the ticktimer hears from each process only once.
+        loop {
+            match CALLBACK_SERVER.compare_exchange(
+                CALLBACK_SERVER_NONE,
+                CALLBACK_SERVER_STARTING,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(CALLBACK_SERVER_STARTED) => return Ok(()),
+                Err(_) => xous::yield_slice(),
+            }
+        }
+        let result = self.register_callback_server();
+        CALLBACK_SERVER.store(
+            if result.is_ok() {
+                CALLBACK_SERVER_STARTED
+            } else {
+                CALLBACK_SERVER_NONE
+            },
+            Ordering::Release,
+        );
+        result
+    }
+
+    fn register_callback_server(&self) -> Result<(), Error> {
+        let sid = xous::create_server()?;
+        let sid_tuple = sid.to_u32();
+        xous::create_thread_4(
+            callback_server,
+            sid_tuple.0 as usize,
+            sid_tuple.1 as usize,
+            sid_tuple.2 as usize,
+            sid_tuple.3 as usize,
+        )?;
+        let response = send_message(
+            self.conn,
+            xous::Message::new_blocking_scalar(
+                api::Opcode::RegisterCallbackServer.to_usize().unwrap(),
+                sid_tuple.0 as usize,
+                sid_tuple.1 as usize,
+                sid_tuple.2 as usize,
+                sid_tuple.3 as usize,
+            ),
+        );
+        match response {
+            Ok(xous::Result::Scalar1(0)) => Ok(()),
+            Ok(xous::Result::Scalar1(e)) => {
+                stop_callback_server(sid);
+                Err(Error::from_usize(e))
+            }
+            Ok(_) => {
+                stop_callback_server(sid);
+                Err(Error::InternalError)
+            }
+            Err(e) => {
+                stop_callback_server(sid);
+                Err(e)
+            }
+        }
+    }
+
+    fn sync_request(
+        &self,
+        opcode: api::Opcode,
+        arg1: usize,
+        arg2: usize,
+        arg3: usize,
+    ) -> Result<usize, Error> {
+        let response = send_message(
+            self.conn,
+            xous::Message::new_blocking_scalar(opcode.to_usize().unwrap(), arg1, arg2, arg3, 0),
+        )?;
+        match (opcode, response) {
+            (api::Opcode::NotifyCondition, xous::Result::Scalar1(woken)) => Ok(woken),
+            (_, xous::Result::Scalar1(0)) => Ok(0),
+            (_, xous::Result::Scalar1(e)) => Err(Error::from_usize(e)),
+            _ => Err(Error::InternalError),
+        }
+    }
 }
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::sync::atomic::{AtomicU32, AtomicU8, Ordering};
 static REFCOUNT: AtomicU32 = AtomicU32::new(0);
+/// Whether the ticktimer knows this process' callback forwarding server
+static CALLBACK_SERVER: AtomicU8 = AtomicU8::new(CALLBACK_SERVER_NONE);
+const CALLBACK_SERVER_NONE: u8 = 0;
+const CALLBACK_SERVER_STARTING: u8 = 1;
+const CALLBACK_SERVER_STARTED: u8 = 2;
 impl Drop for Ticktimer {
     fn drop(&mut self) {
         // de-allocate myself. It's unsafe because we are responsible to make sure nobody else is using the connection.
@@ -73,3 +384,39 @@ impl Drop for Ticktimer {
         }
     }
 }
+
+/// Passes timer callbacks on to the connection they were scheduled for, in
+/// the library user's process space.
+fn callback_server(sid0: usize, sid1: usize, sid2: usize, sid3: usize) {
+    let sid = xous::SID::from_u32(sid0 as u32, sid1 as u32, sid2 as u32, sid3 as u32);
+    loop {
+        let msg = xous::receive_message(sid).unwrap();
+        match num_traits::FromPrimitive::from_usize(msg.body.id()) {
+            Some(api::CallbackEvent::Event) => {
+                xous::msg_scalar_unpack!(msg, cid, opcode, handle, _, {
+                    if let Err(e) = send_message(
+                        cid as CID,
+                        xous::Message::new_scalar(opcode, handle, 0, 0, 0),
+                    ) {
+                        log::warn!("couldn't deliver timer callback {}: {:?}", handle, e);
+                    }
+                })
+            }
+            Some(api::CallbackEvent::Drop) => break,
+            None => (),
+        }
+    }
+    xous::destroy_server(sid).unwrap();
+}
+
+fn stop_callback_server(sid: xous::SID) {
+    let cid = xous::connect(sid).unwrap();
+    send_message(
+        cid,
+        xous::Message::new_scalar(api::CallbackEvent::Drop.to_usize().unwrap(), 0, 0, 0, 0),
+    )
+    .ok();
+    unsafe {
+        xous::disconnect(cid).ok();
+    }
+}
@@ -3,27 +3,49 @@
 #![cfg_attr(target_os = "none", no_main)]
 
 mod api;
+mod callback;
+mod sync;
+mod wallclock;
 
 use std::cmp::Reverse;
 use std::collections::BinaryHeap;
 
 use log::{error, info};
 
+/// What to do once a `SleepRequest` expires
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Expiry {
+    /// Reply to a thread that's blocked in `SleepMs`
+    Wake(xous::MessageSender),
+    /// Deliver the callback with this handle. That's up to the main loop, so
+    /// the timer leaves these in place for it to collect.
+    Callback(usize),
+}
+
+impl core::fmt::Display for Expiry {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Expiry::Wake(sender) => write!(f, "{}", sender),
+            Expiry::Callback(handle) => write!(f, "callback {}", handle),
+        }
+    }
+}
+
 #[derive(Eq)]
 pub struct SleepRequest {
     msec: i64,
-    sender: xous::MessageSender,
+    expiry: Expiry,
 }
 
 impl core::fmt::Display for SleepRequest {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "SleepRequest {{ msec: {}, {} }}", self.msec, self.sender)
+        write!(f, "SleepRequest {{ msec: {}, {} }}", self.msec, self.expiry)
     }
 }
 
 impl core::fmt::Debug for SleepRequest {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "SleepRequest {{ msec: {}, {} }}", self.msec, self.sender)
+        write!(f, "SleepRequest {{ msec: {}, {} }}", self.msec, self.expiry)
     }
 }
 
@@ -34,7 +56,7 @@ impl core::cmp::Ord for SleepRequest {
         } else if self.msec > other.msec {
             core::cmp::Ordering::Greater
         } else {
-            self.sender.cmp(&other.sender)
+            self.expiry.cmp(&other.expiry)
         }
     }
 }
@@ -47,14 +69,14 @@ impl core::cmp::PartialOrd for SleepRequest {
 
 impl core::cmp::PartialEq for SleepRequest {
     fn eq(&self, other: &Self) -> bool {
-        self.msec == other.msec && self.sender == other.sender
+        self.msec == other.msec && self.expiry == other.expiry
     }
 }
 
 #[cfg(any(target_os = "none", target_os = "xous"))]
 mod implementation {
     const TICKS_PER_MS: u64 = 1;
-    use super::SleepRequest;
+    use super::{Expiry, SleepRequest};
     use susres::{RegManager, RegOrField, SuspendResume};
     use utralib::generated::*;
 
@@ -75,7 +97,11 @@ mod implementation {
         // enabled when this value is not None.
         let response = xtt.current_response.take().unwrap();
 
-        xous::return_scalar(response.sender, 0).expect("couldn't send response");
+        match response.expiry {
+            Expiry::Wake(sender) => xous::return_scalar(sender, 0).expect("couldn't send response"),
+            // Leave it for `stop_interrupt()` to hand back to the main loop.
+            Expiry::Callback(_) => xtt.current_response = Some(response),
+        }
 
         // Disable the timer
         xtt.csr.wfo(utra::ticktimer::EV_ENABLE_ALARM, 0);
@@ -275,18 +301,14 @@ mod implementation {
 
 #[cfg(not(any(target_os = "none", target_os = "xous")))]
 mod implementation {
-    use super::SleepRequest;
+    use super::{Expiry, SleepRequest};
     use num_traits::ToPrimitive;
     use std::convert::TryInto;
 
     #[derive(Debug)]
     enum SleepComms {
         InterruptSleep,
-        StartSleep(
-            xous::MessageSender,
-            i64, /* ms */
-            u64, /* elapsed */
-        ),
+        StartSleep(Expiry, i64 /* ms */, u64 /* elapsed */),
     }
     pub struct XousTickTimer {
         start: std::time::Instant,
@@ -310,10 +332,16 @@ mod implementation {
                     };
                     match result {
                         Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
-                            let sender = current_response.take().unwrap().sender;
-                            #[cfg(feature = "debug-print")]
-                            log::info!("Returning scalar to {}", sender);
-                            xous::return_scalar(sender, 0).expect("couldn't send response");
+                            let response = current_response.take().unwrap();
+                            match response.expiry {
+                                Expiry::Wake(sender) => {
+                                    #[cfg(feature = "debug-print")]
+                                    log::info!("Returning scalar to {}", sender);
+                                    xous::return_scalar(sender, 0).expect("couldn't send response");
+                                }
+                                // Leave it for `stop_interrupt()` to hand back to the main loop.
+                                Expiry::Callback(_) => current_response = Some(response),
+                            }
 
                             // This is dangerous and may panic if the queue is full.
                             xous::try_send_message(
@@ -336,21 +364,21 @@ mod implementation {
                             timeout = None;
                             time_remaining_sender.send(current_response.take()).unwrap()
                         }
-                        Ok(SleepComms::StartSleep(new_sender, expiry, elapsed)) => {
-                            let mut duration = expiry - (elapsed as i64);
+                        Ok(SleepComms::StartSleep(new_expiry, msec, elapsed)) => {
+                            let mut duration = msec - (elapsed as i64);
                             if duration > 0 {
                                 #[cfg(feature = "debug-print")]
                                 log::info!(
                                     "Starting sleep for {} ms, returning to {}",
                                     duration,
-                                    new_sender
+                                    new_expiry
                                 );
                             } else {
                                 #[cfg(feature = "debug-print")]
                                 log::info!(
                                     "Clamping duration to 0 (was: {})m returning to {}",
                                     duration,
-                                    new_sender
+                                    new_expiry
                                 );
                                 duration = 0;
                             }
@@ -358,8 +386,8 @@ mod implementation {
                                 duration.try_into().unwrap(),
                             ));
                             current_response = Some(SleepRequest {
-                                sender: new_sender,
-                                msec: expiry,
+                                expiry: new_expiry,
+                                msec,
                             });
                         }
                     }
@@ -393,11 +421,11 @@ mod implementation {
                 "request.msec: {}  self.elapsed_ms: {}  returning to: {}",
                 request.msec,
                 self.elapsed_ms(),
-                request.sender
+                request.expiry
             );
             self.sleep_comms
                 .send(SleepComms::StartSleep(
-                    request.sender,
+                    request.expiry,
                     request.msec as i64,
                     self.elapsed_ms(),
                 ))
@@ -425,6 +453,7 @@ use implementation::*;
 fn recalculate_sleep(
     ticktimer: &mut XousTickTimer,
     sleep_heap: &mut BinaryHeap<Reverse<SleepRequest>>, // min-heap with Reverse
+    callbacks: &mut callback::Callbacks,
     new: Option<SleepRequest>,
 ) {
     // If there's a sleep request ongoing now, grab it.
@@ -452,20 +481,155 @@ fn recalculate_sleep(
         info!("No new sleep request");
     }
 
+    // Deliver any callbacks that are due, and put the periodic ones back.
+    let now = ticktimer.elapsed_ms();
+    while let Some(&Reverse(SleepRequest {
+        msec,
+        expiry: Expiry::Callback(handle),
+    })) = sleep_heap.peek()
+    {
+        if msec > now as i64 {
+            break;
+        }
+        sleep_heap.pop();
+        if let Some(next) = deliver_callback(callbacks, handle, msec as u64, now) {
+            sleep_heap.push(Reverse(SleepRequest {
+                msec: next as i64,
+                expiry: Expiry::Callback(handle),
+            }));
+        }
+    }
+
     // If there are items in the sleep heap, take the next item that will expire.
     if let Some(Reverse(next_response)) = sleep_heap.pop() {
         #[cfg(feature = "debug-print")]
         info!(
             "scheduling a response at {} to {} (heap: {:?})",
-            next_response.msec, next_response.sender, sleep_heap
+            next_response.msec, next_response.expiry, sleep_heap
         );
         ticktimer.schedule_response(next_response);
     }
 }
 
+/// How long to wait before trying again to deliver a callback to a process
+/// whose forwarding server is busy
+const CALLBACK_RETRY_MS: u64 = 10;
+
+/// Send a callback whose timer expired at `due` to its process' forwarding
+/// server. Returns when it should fire next, if ever.
+fn deliver_callback(
+    callbacks: &mut callback::Callbacks,
+    handle: usize,
+    due: u64,
+    now: u64,
+) -> Option<u64> {
+    // Cancelled callbacks stay in the sleep heap until they come up.
+    let cb = callbacks.get(handle)?;
+    let server = callbacks.server(cb.pid)?;
+    match xous::try_send_message(
+        server,
+        xous::Message::new_scalar(
+            api::CallbackEvent::Event as usize,
+            cb.cid as usize,
+            cb.opcode,
+            handle,
+            0,
+        ),
+    ) {
+        Ok(_) => {
+            let next = cb.next_due(due, now);
+            if next.is_none() {
+                callbacks.cancel(cb.pid, handle);
+            }
+            next
+        }
+        Err(xous::Error::ServerQueueFull) => Some(now + CALLBACK_RETRY_MS),
+        Err(e) => {
+            // The process is most likely gone, so nothing else of its will get through either.
+            log::warn!("couldn't deliver callback to PID {}: {:?}", cb.pid, e);
+            if let Some(conn) = callbacks.remove_process(cb.pid) {
+                unsafe { xous::disconnect(conn).ok() };
+            }
+            None
+        }
+    }
+}
+
+/// Make sure the sync objects filed under `pid` belong to the process that
+/// has it now, rather than to one that has gone away.
+fn sync_generation(
+    sync_objects: &mut sync::SyncObjects,
+    pid: xous::PID,
+) -> Result<(), xous::Error> {
+    let info = xous::process_info(pid)?;
+    if info.pid != pid {
+        return Err(xous::Error::ProcessNotFound);
+    }
+    sync_objects.set_generation(pid, info.generation);
+    Ok(())
+}
+
+/// Find out which thread sent a request for a mutex or condition variable,
+/// and check that the sync objects of its PID are its own. If the kernel won't
+/// say, the sender is told why and there's nothing more to do.
+fn sync_client(
+    sync_objects: &mut sync::SyncObjects,
+    sender: xous::MessageSender,
+) -> Option<xous::TID> {
+    let result = xous::sender_thread(sender).and_then(|tid| {
+        let pid = sender.pid().ok_or(xous::Error::ProcessNotFound)?;
+        sync_generation(sync_objects, pid).map(|_| tid)
+    });
+    match result {
+        Ok(tid) => Some(tid),
+        Err(e) => {
+            send_replies(Some((sender, e as usize)));
+            None
+        }
+    }
+}
+
+/// Reply with a time in milliseconds, split up the same way as `ElapsedMs`.
+fn return_time(sender: xous::MessageSender, time: u64) {
+    if let Err(e) = xous::return_scalar2(
+        sender,
+        (time & 0xFFFF_FFFF) as usize,
+        ((time >> 32) & 0xFFFF_FFFF) as usize,
+    ) {
+        log::warn!("couldn't return time to {}: {:?}", sender, e);
+    }
+}
+
+/// Reply with the wall-clock time, or with `UseBeforeInit` as a `Scalar1` if
+/// the RTC hasn't been read yet.
+fn return_wall_clock(sender: xous::MessageSender, now: Option<u64>) {
+    match now {
+        Some(time) => return_time(sender, time),
+        None => {
+            if let Err(e) = xous::return_scalar(sender, xous::Error::UseBeforeInit as usize) {
+                log::warn!("couldn't return time to {}: {:?}", sender, e);
+            }
+        }
+    }
+}
+
+/// Reply to threads that were waiting on a mutex or condition variable. A
+/// thread that has gone away in the meantime can't be replied to, and doesn't
+/// need to be.
+fn send_replies<I: IntoIterator<Item = sync::Reply>>(replies: I) {
+    for (sender, result) in replies {
+        if let Err(e) = xous::return_scalar(sender, result) {
+            log::warn!("couldn't reply to {}: {:?}", sender, e);
+        }
+    }
+}
+
 #[xous::xous_main]
 fn xmain() -> ! {
     let mut sleep_heap: BinaryHeap<Reverse<SleepRequest>> = BinaryHeap::new(); // Reverse wrapping makes this a min-heap
+    let mut sync_objects = sync::SyncObjects::new();
+    let mut callbacks = callback::Callbacks::new();
+    let mut wall_clock = wallclock::WallClock::new();
 
     log_server::init_wait().unwrap();
     log::set_max_level(log::LevelFilter::Info);
@@ -496,7 +660,19 @@ fn xmain() -> ! {
         //#[cfg(feature = "watchdog")] // for debugging the watchdog
         //ticktimer.check_wdt();
 
-        let msg = xous::receive_message(ticktimer_server).unwrap();
+        // Condition variable timeouts are handled here rather than with the
+        // sleep timer, as they need the mutex to be taken back.
+        let now = ticktimer.elapsed_ms();
+        send_replies(sync_objects.expire(now));
+        let msg = match sync_objects.next_deadline() {
+            Some(deadline) => {
+                match xous::receive_message_timeout(ticktimer_server, (deadline - now) as usize) {
+                    Err(xous::Error::Timeout) => continue,
+                    result => result.unwrap(),
+                }
+            }
+            None => xous::receive_message(ticktimer_server).unwrap(),
+        };
         log::trace!("msg: {:?}", msg);
         match num_traits::FromPrimitive::from_usize(msg.body.id()) {
             Some(api::Opcode::ElapsedMs) => {
@@ -512,16 +688,20 @@ fn xmain() -> ! {
                 recalculate_sleep(
                     &mut ticktimer,
                     &mut sleep_heap,
+                    &mut callbacks,
                     Some(SleepRequest {
                         msec: ms as i64,
-                        sender: msg.sender,
+                        expiry: Expiry::Wake(msg.sender),
                     }),
                 )
             }),
             Some(api::Opcode::RecalculateSleep) => {
-                recalculate_sleep(&mut ticktimer, &mut sleep_heap, None);
+                recalculate_sleep(&mut ticktimer, &mut sleep_heap, &mut callbacks, None);
             }
             Some(api::Opcode::SuspendResume) => xous::msg_scalar_unpack!(msg, token, _, _, _, {
+                // Elapsed time doesn't include the time spent suspended, so the
+                // wall clock is off until the RTC is read again after resuming.
+                wall_clock.invalidate();
                 ticktimer.suspend();
                 susres
                     .suspend_until_resume(token)
@@ -531,6 +711,148 @@ fn xmain() -> ! {
             Some(api::Opcode::PingWdt) => {
                 ticktimer.reset_wdt();
             }
+            Some(api::Opcode::LockMutex) => {
+                xous::msg_blocking_scalar_unpack!(msg, mutex, _, _, _, {
+                    if let Some(tid) = sync_client(&mut sync_objects, msg.sender) {
+                        send_replies(sync_objects.lock(msg.sender, tid, mutex))
+                    }
+                })
+            }
+            Some(api::Opcode::UnlockMutex) => {
+                xous::msg_blocking_scalar_unpack!(msg, mutex, _, _, _, {
+                    if let Some(tid) = sync_client(&mut sync_objects, msg.sender) {
+                        send_replies(sync_objects.unlock(msg.sender, tid, mutex))
+                    }
+                })
+            }
+            Some(api::Opcode::WaitForCondition) => {
+                xous::msg_blocking_scalar_unpack!(msg, condvar, mutex, timeout, _, {
+                    let deadline = if timeout == 0 {
+                        None
+                    } else {
+                        Some(ticktimer.elapsed_ms() + timeout as u64)
+                    };
+                    if let Some(tid) = sync_client(&mut sync_objects, msg.sender) {
+                        send_replies(sync_objects.wait(msg.sender, tid, condvar, mutex, deadline))
+                    }
+                })
+            }
+            Some(api::Opcode::NotifyCondition) => {
+                xous::msg_blocking_scalar_unpack!(msg, condvar, count, _, _, {
+                    // The reply is a count rather than an error code, and a
+                    // sender that the kernel knows nothing about has gone anyway.
+                    if let Some(pid) = msg.sender.pid() {
+                        sync_generation(&mut sync_objects, pid).ok();
+                    }
+                    let (woken, replies) = sync_objects.notify(msg.sender, condvar, count);
+                    xous::return_scalar(msg.sender, woken).expect("couldn't return notify count");
+                    send_replies(replies)
+                })
+            }
+            Some(api::Opcode::RegisterCallbackServer) => {
+                xous::msg_blocking_scalar_unpack!(msg, sid0, sid1, sid2, sid3, {
+                    let sid =
+                        xous::SID::from_u32(sid0 as u32, sid1 as u32, sid2 as u32, sid3 as u32);
+                    let result = match msg.sender.pid() {
+                        Some(pid) => xous::try_connect(sid).map(|conn| {
+                            if let Some(stale) = callbacks.set_server(pid, conn) {
+                                unsafe { xous::disconnect(stale).ok() };
+                            }
+                        }),
+                        None => Err(xous::Error::InvalidPID),
+                    };
+                    xous::return_scalar(msg.sender, result.err().map_or(0, |e| e as usize))
+                        .expect("couldn't return callback server registration");
+                })
+            }
+            Some(api::Opcode::ScheduleCallback) => {
+                xous::msg_blocking_scalar_unpack!(msg, ms, periodic, cid, opcode, {
+                    let handle = match msg.sender.pid() {
+                        // Clients register their forwarding server first.
+                        Some(pid) if callbacks.server(pid).is_some() => {
+                            callbacks.add(callback::Callback {
+                                pid,
+                                cid: cid as xous::CID,
+                                opcode,
+                                period: if periodic != 0 {
+                                    Some((ms as u64).max(1))
+                                } else {
+                                    None
+                                },
+                            })
+                        }
+                        _ => 0,
+                    };
+                    xous::return_scalar(msg.sender, handle)
+                        .expect("couldn't return callback handle");
+                    if handle != 0 {
+                        recalculate_sleep(
+                            &mut ticktimer,
+                            &mut sleep_heap,
+                            &mut callbacks,
+                            Some(SleepRequest {
+                                msec: ms as i64,
+                                expiry: Expiry::Callback(handle),
+                            }),
+                        );
+                    }
+                })
+            }
+            Some(api::Opcode::CancelCallback) => {
+                xous::msg_blocking_scalar_unpack!(msg, handle, _, _, _, {
+                    let cancelled = match msg.sender.pid() {
+                        Some(pid) => callbacks.cancel(pid, handle),
+                        None => false,
+                    };
+                    xous::return_scalar(msg.sender, cancelled as usize)
+                        .expect("couldn't return callback cancellation");
+                })
+            }
+            Some(api::Opcode::GetUtcTimeMs) => {
+                xous::msg_blocking_scalar_unpack!(msg, _, _, _, _, {
+                    let now = wall_clock.now(wallclock::Zone::Utc, ticktimer.elapsed_ms());
+                    return_wall_clock(msg.sender, now);
+                })
+            }
+            Some(api::Opcode::GetLocalTimeMs) => {
+                xous::msg_blocking_scalar_unpack!(msg, _, _, _, _, {
+                    let now = wall_clock.now(wallclock::Zone::Local, ticktimer.elapsed_ms());
+                    return_wall_clock(msg.sender, now);
+                })
+            }
+            Some(api::Opcode::SetWallClockMs) => xous::msg_scalar_unpack!(msg, lo, hi, _, _, {
+                if wall_clock.may_set(msg.sender.pid()) {
+                    let local_ms = lo as u64 | ((hi as u64) << 32);
+                    wall_clock.set(local_ms, ticktimer.elapsed_ms());
+                } else {
+                    log::warn!("{} may not set the wall clock", msg.sender);
+                }
+            }),
+            Some(api::Opcode::SetTimezoneOffset) => {
+                xous::msg_scalar_unpack!(msg, seconds, _, _, _, {
+                    if wall_clock.may_set(msg.sender.pid()) {
+                        wall_clock.set_timezone(seconds as i32);
+                    } else {
+                        log::warn!("{} may not set the timezone", msg.sender);
+                    }
+                })
+            }
+            Some(api::Opcode::GetTimezoneOffset) => {
+                xous::msg_blocking_scalar_unpack!(msg, _, _, _, _, {
+                    xous::return_scalar(msg.sender, wall_clock.timezone() as usize)
+                        .expect("couldn't return timezone offset");
+                })
+            }
+            Some(api::Opcode::ClaimWallClock) => {
+                xous::msg_blocking_scalar_unpack!(msg, _, _, _, _, {
+                    let result = match msg.sender.pid() {
+                        Some(pid) if wall_clock.claim(pid) => xous::Error::NoError,
+                        _ => xous::Error::AccessDenied,
+                    };
+                    xous::return_scalar(msg.sender, result as usize)
+                        .expect("couldn't return wall clock claim");
+                })
+            }
             None => {
                 error!("couldn't convert opcode");
                 break;
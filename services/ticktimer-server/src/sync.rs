@@ -0,0 +1,415 @@
+//! Mutexes and condition variables hosted on behalf of other processes.
+//!
+//! Each object is named by the address the client passes in, and belongs to
+//! the process that sent it, so two processes can use the same address
+//! without getting in each other's way. Nothing has to be created or freed: a
+//! mutex exists only while it is locked, and a condition variable only while
+//! something waits on it. A mutex remembers which thread holds it, as the
+//! kernel reports it, and only that thread may unlock it or wait with it.
+//! Objects of a process that has gone away are forgotten once another process
+//! takes over its PID (see `set_generation()`), so the newcomer doesn't find
+//! them still locked.
+//!
+//! Requests are blocking scalars. A request that can't be satisfied yet keeps
+//! its sender here until it can, and the reply is a `xous::Error` code, with
+//! `NoError` meaning success. None of this makes syscalls itself; each call
+//! returns the replies that the server should send.
+
+use std::collections::{HashMap, VecDeque};
+
+use xous::{Error, MessageSender, PID, TID};
+
+/// A sender to reply to, and what to reply with
+pub type Reply = (MessageSender, usize);
+
+type Key = (Option<PID>, usize);
+
+fn key(sender: MessageSender, address: usize) -> Key {
+    (sender.pid(), address)
+}
+
+fn reply(sender: MessageSender, result: Error) -> Reply {
+    (sender, result as usize)
+}
+
+/// A locked mutex
+struct Mutex {
+    /// The thread that holds it
+    owner: TID,
+    /// The threads waiting to lock it, and what to tell each of them once
+    /// they have it
+    waiters: VecDeque<(TID, Reply)>,
+}
+
+/// A thread that is waiting on a condition variable
+struct Waiter {
+    sender: MessageSender,
+    tid: TID,
+    /// The mutex to take back before replying
+    mutex: usize,
+    /// When to give up, in ticktimer milliseconds
+    deadline: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct SyncObjects {
+    mutexes: HashMap<Key, Mutex>,
+    condvars: HashMap<Key, VecDeque<Waiter>>,
+    /// The generation of the process that each PID's objects belong to
+    generations: HashMap<PID, u8>,
+}
+
+impl SyncObjects {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Lock a mutex for thread `tid` of `sender`, or queue it up if the
+    /// mutex is already locked.
+    pub fn lock(&mut self, sender: MessageSender, tid: TID, mutex: usize) -> Option<Reply> {
+        self.acquire(key(sender, mutex), tid, reply(sender, Error::NoError))
+    }
+
+    /// Unlock a mutex on behalf of thread `tid` of `sender`. The reply to
+    /// `sender` comes first, followed by one for the thread that gets the
+    /// mutex next, if any.
+    pub fn unlock(&mut self, sender: MessageSender, tid: TID, mutex: usize) -> Vec<Reply> {
+        match self.release(key(sender, mutex), tid) {
+            Ok(next) => core::iter::once(reply(sender, Error::NoError))
+                .chain(next)
+                .collect(),
+            Err(e) => vec![reply(sender, e)],
+        }
+    }
+
+    /// Unlock `mutex` and wait on `condvar` until notified or until
+    /// `deadline`. Either way, the reply is held back until `sender` has the
+    /// mutex again.
+    pub fn wait(
+        &mut self,
+        sender: MessageSender,
+        tid: TID,
+        condvar: usize,
+        mutex: usize,
+        deadline: Option<u64>,
+    ) -> Vec<Reply> {
+        let next = match self.release(key(sender, mutex), tid) {
+            Ok(next) => next,
+            // Waiting without holding the mutex would miss notifications.
+            Err(_) => return vec![reply(sender, Error::AccessDenied)],
+        };
+        self.condvars
+            .entry(key(sender, condvar))
+            .or_default()
+            .push_back(Waiter {
+                sender,
+                tid,
+                mutex,
+                deadline,
+            });
+        next.into_iter().collect()
+    }
+
+    /// Wake up to `count` of the threads waiting on `condvar`, oldest first.
+    /// Returns how many were woken, along with replies for any that got their
+    /// mutex back straight away.
+    pub fn notify(
+        &mut self,
+        sender: MessageSender,
+        condvar: usize,
+        count: usize,
+    ) -> (usize, Vec<Reply>) {
+        let condvar = key(sender, condvar);
+        let woken: Vec<Waiter> = match self.condvars.get_mut(&condvar) {
+            Some(waiters) => {
+                let count = count.min(waiters.len());
+                waiters.drain(..count).collect()
+            }
+            None => return (0, Vec::new()),
+        };
+        if self.condvars[&condvar].is_empty() {
+            self.condvars.remove(&condvar);
+        }
+        let count = woken.len();
+        let replies = woken
+            .into_iter()
+            .filter_map(|w| {
+                self.acquire(
+                    key(w.sender, w.mutex),
+                    w.tid,
+                    reply(w.sender, Error::NoError),
+                )
+            })
+            .collect();
+        (count, replies)
+    }
+
+    /// Stop waiting for every thread whose deadline is at or before `now`.
+    /// Each of them is told `Timeout` once it has its mutex back.
+    pub fn expire(&mut self, now: u64) -> Vec<Reply> {
+        let mut expired = Vec::new();
+        self.condvars.retain(|_, waiters| {
+            let mut i = 0;
+            while i < waiters.len() {
+                if matches!(waiters[i].deadline, Some(d) if d <= now) {
+                    expired.extend(waiters.remove(i));
+                } else {
+                    i += 1;
+                }
+            }
+            !waiters.is_empty()
+        });
+        expired
+            .into_iter()
+            .filter_map(|w| {
+                self.acquire(
+                    key(w.sender, w.mutex),
+                    w.tid,
+                    reply(w.sender, Error::Timeout),
+                )
+            })
+            .collect()
+    }
+
+    /// The earliest deadline of any waiting thread
+    pub fn next_deadline(&self) -> Option<u64> {
+        self.condvars
+            .values()
+            .flatten()
+            .filter_map(|w| w.deadline)
+            .min()
+    }
+
+    /// Note that `pid` now belongs to the process of the given generation (see
+    /// `xous::ProcessInfo`). If it used to belong to another process, that
+    /// process' objects are forgotten, along with any of its threads that
+    /// were waiting. Nothing is left to reply to, as only a process' own
+    /// threads can wait on its objects.
+    ///
+    /// Call this before acting on a request, so that a newcomer can neither
+    /// find nor disturb the objects of the process it replaced.
+    pub fn set_generation(&mut self, pid: PID, generation: u8) {
+        match self.generations.insert(pid, generation) {
+            Some(previous) if previous != generation => {
+                let other = |(owner, _): &Key| *owner != Some(pid);
+                self.mutexes.retain(|key, _| other(key));
+                self.condvars.retain(|key, _| other(key));
+            }
+            _ => {}
+        }
+    }
+
+    /// Give the mutex to thread `tid`, whom `then` replies to, returning that
+    /// reply if the mutex was free.
+    fn acquire(&mut self, mutex: Key, tid: TID, then: Reply) -> Option<Reply> {
+        match self.mutexes.get_mut(&mutex) {
+            Some(locked) => {
+                locked.waiters.push_back((tid, then));
+                None
+            }
+            None => {
+                self.mutexes.insert(
+                    mutex,
+                    Mutex {
+                        owner: tid,
+                        waiters: VecDeque::new(),
+                    },
+                );
+                Some(then)
+            }
+        }
+    }
+
+    /// Take the mutex away from thread `tid` and hand it to the next thread
+    /// waiting for it, returning the reply for that thread, or unlock it if
+    /// there is none.
+    ///
+    /// # Errors
+    ///
+    /// * **DoubleFree**: The mutex isn't locked
+    /// * **AccessDenied**: Another thread holds the mutex
+    fn release(&mut self, mutex: Key, tid: TID) -> Result<Option<Reply>, Error> {
+        let locked = self.mutexes.get_mut(&mutex).ok_or(Error::DoubleFree)?;
+        if locked.owner != tid {
+            return Err(Error::AccessDenied);
+        }
+        match locked.waiters.pop_front() {
+            Some((next, then)) => {
+                locked.owner = next;
+                Ok(Some(then))
+            }
+            None => {
+                self.mutexes.remove(&mutex);
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OK: usize = Error::NoError as usize;
+    const MUTEX: usize = 0x2000_1000;
+    const CONDVAR: usize = 0x2000_1004;
+
+    /// A sender for a message from thread `tid` of process `pid`. The kernel
+    /// only puts the PID in the sender, so the thread goes in the message
+    /// index here, where `tid()` finds it again.
+    fn sender(pid: usize, tid: usize) -> MessageSender {
+        MessageSender::from_usize(pid << 24 | tid)
+    }
+
+    fn tid(sender: MessageSender) -> TID {
+        sender.to_usize() & 0xffff
+    }
+
+    #[test]
+    fn mutex_is_handed_over_in_order() {
+        let mut sync = SyncObjects::new();
+        let (a, b, c) = (sender(2, 1), sender(2, 2), sender(2, 3));
+        assert_eq!(sync.lock(a, tid(a), MUTEX), Some((a, OK)));
+        assert_eq!(sync.lock(b, tid(b), MUTEX), None);
+        assert_eq!(sync.lock(c, tid(c), MUTEX), None);
+        assert_eq!(sync.unlock(a, tid(a), MUTEX), vec![(a, OK), (b, OK)]);
+        assert_eq!(sync.unlock(b, tid(b), MUTEX), vec![(b, OK), (c, OK)]);
+        assert_eq!(sync.unlock(c, tid(c), MUTEX), vec![(c, OK)]);
+        assert_eq!(
+            sync.unlock(c, tid(c), MUTEX),
+            vec![(c, Error::DoubleFree as usize)]
+        );
+    }
+
+    #[test]
+    fn processes_do_not_share_objects() {
+        let mut sync = SyncObjects::new();
+        let (a, b) = (sender(2, 1), sender(3, 1));
+        assert_eq!(sync.lock(a, tid(a), MUTEX), Some((a, OK)));
+        assert_eq!(sync.lock(b, tid(b), MUTEX), Some((b, OK)));
+
+        assert_eq!(sync.wait(a, tid(a), CONDVAR, MUTEX, None), vec![]);
+        assert_eq!(sync.notify(b, CONDVAR, usize::MAX), (0, vec![]));
+        assert_eq!(sync.notify(a, CONDVAR, usize::MAX), (1, vec![(a, OK)]));
+    }
+
+    #[test]
+    fn wait_requires_the_mutex() {
+        let mut sync = SyncObjects::new();
+        let a = sender(2, 1);
+        assert_eq!(
+            sync.wait(a, tid(a), CONDVAR, MUTEX, None),
+            vec![(a, Error::AccessDenied as usize)]
+        );
+        assert_eq!(sync.notify(a, CONDVAR, 1), (0, vec![]));
+    }
+
+    #[test]
+    fn notified_waiter_gets_the_mutex_back() {
+        let mut sync = SyncObjects::new();
+        let (a, b) = (sender(2, 1), sender(2, 2));
+        sync.lock(a, tid(a), MUTEX);
+        sync.lock(b, tid(b), MUTEX);
+
+        // Waiting hands the mutex to `b`, which notifies while holding it.
+        assert_eq!(sync.wait(a, tid(a), CONDVAR, MUTEX, None), vec![(b, OK)]);
+        assert_eq!(sync.notify(b, CONDVAR, 1), (1, vec![]));
+        assert_eq!(sync.unlock(b, tid(b), MUTEX), vec![(b, OK), (a, OK)]);
+        assert_eq!(sync.unlock(a, tid(a), MUTEX), vec![(a, OK)]);
+    }
+
+    #[test]
+    fn notify_one_and_all() {
+        let mut sync = SyncObjects::new();
+        let waiters = [sender(2, 1), sender(2, 2), sender(2, 3)];
+        for &w in waiters.iter() {
+            sync.lock(w, tid(w), MUTEX);
+            sync.wait(w, tid(w), CONDVAR, MUTEX, None);
+        }
+        let notifier = sender(2, 4);
+        sync.lock(notifier, tid(notifier), MUTEX);
+
+        assert_eq!(sync.notify(notifier, CONDVAR, 1), (1, vec![]));
+        assert_eq!(sync.notify(notifier, CONDVAR, usize::MAX), (2, vec![]));
+        assert_eq!(sync.notify(notifier, CONDVAR, usize::MAX), (0, vec![]));
+
+        // The mutex goes to each of them in the order they were notified.
+        let mut holder = notifier;
+        for &w in waiters.iter() {
+            assert_eq!(
+                sync.unlock(holder, tid(holder), MUTEX),
+                vec![(holder, OK), (w, OK)]
+            );
+            holder = w;
+        }
+        assert_eq!(sync.unlock(holder, tid(holder), MUTEX), vec![(holder, OK)]);
+    }
+
+    #[test]
+    fn wait_times_out() {
+        let mut sync = SyncObjects::new();
+        let (a, b) = (sender(2, 1), sender(2, 2));
+        sync.lock(a, tid(a), MUTEX);
+        sync.wait(a, tid(a), CONDVAR, MUTEX, Some(100));
+        sync.lock(b, tid(b), MUTEX);
+        sync.wait(b, tid(b), CONDVAR, MUTEX, Some(50));
+        assert_eq!(sync.next_deadline(), Some(50));
+
+        assert_eq!(sync.expire(49), vec![]);
+        assert_eq!(sync.expire(50), vec![(b, Error::Timeout as usize)]);
+        assert_eq!(sync.next_deadline(), Some(100));
+
+        // `a` times out while `b` holds the mutex, so it has to wait for it.
+        assert_eq!(sync.expire(100), vec![]);
+        assert_eq!(sync.next_deadline(), None);
+        assert_eq!(
+            sync.unlock(b, tid(b), MUTEX),
+            vec![(b, OK), (a, Error::Timeout as usize)]
+        );
+        assert_eq!(sync.notify(a, CONDVAR, usize::MAX), (0, vec![]));
+    }
+
+    #[test]
+    fn only_the_owner_unlocks() {
+        let mut sync = SyncObjects::new();
+        let (a, b) = (sender(2, 1), sender(2, 2));
+        sync.lock(a, tid(a), MUTEX);
+
+        let denied = Error::AccessDenied as usize;
+        assert_eq!(sync.unlock(b, tid(b), MUTEX), vec![(b, denied)]);
+        assert_eq!(
+            sync.wait(b, tid(b), CONDVAR, MUTEX, None),
+            vec![(b, denied)]
+        );
+        assert_eq!(sync.unlock(a, tid(a), MUTEX), vec![(a, OK)]);
+    }
+
+    #[test]
+    fn departed_processes_are_forgotten() {
+        let mut sync = SyncObjects::new();
+        let pid = |pid| PID::new(pid).unwrap();
+        let (gone, waiter, other) = (sender(2, 1), sender(2, 2), sender(3, 1));
+        sync.set_generation(pid(2), 0);
+        sync.lock(gone, tid(gone), MUTEX);
+        sync.lock(waiter, tid(waiter), MUTEX);
+        sync.set_generation(pid(3), 0);
+        sync.lock(other, tid(other), MUTEX);
+        sync.wait(other, tid(other), CONDVAR, MUTEX, Some(10));
+
+        // The same process carries on where it left off.
+        sync.set_generation(pid(2), 0);
+        assert_eq!(
+            sync.unlock(gone, tid(gone), MUTEX),
+            vec![(gone, OK), (waiter, OK)]
+        );
+
+        // A process that takes over the PID starts with nothing locked.
+        sync.set_generation(pid(2), 1);
+        assert_eq!(sync.next_deadline(), Some(10));
+        let newcomer = sender(2, 2);
+        assert_eq!(
+            sync.lock(newcomer, tid(newcomer), MUTEX),
+            Some((newcomer, OK))
+        );
+    }
+}
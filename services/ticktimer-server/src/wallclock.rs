@@ -0,0 +1,154 @@
+//! Wall-clock time, kept as an offset from `elapsed_ms()`.
+//!
+//! The ticktimer has no idea what time it is until the RTC service tells it,
+//! which happens at boot, after every resume, and whenever else someone reads
+//! the RTC. The RTC keeps local time, as that's what it is set to and what
+//! gets displayed, so UTC is found by subtracting the timezone offset.
+//!
+//! Requests for the time that arrive while it's unknown are refused, rather
+//! than held until the RTC is read, as that may never happen.
+//!
+//! Only one process may set the clock or the timezone: the first one to claim
+//! the clock, which is the RTC server.
+
+use xous::PID;
+
+/// The RTC only counts whole seconds, so a reading that's this close to what
+/// we already have doesn't tell us anything new. Ignoring it keeps the clock
+/// from jumping back and forth with every reading.
+const RESYNC_MS: i64 = 2000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Zone {
+    Utc,
+    Local,
+}
+
+pub struct WallClock {
+    /// Local time minus `elapsed_ms()`, if the RTC has been read since boot
+    /// or since the last suspend
+    offset: Option<i64>,
+    /// Local time minus UTC, in milliseconds
+    timezone: i64,
+    /// The process that may set the clock
+    owner: Option<PID>,
+}
+
+impl WallClock {
+    pub fn new() -> Self {
+        WallClock {
+            offset: None,
+            timezone: 0,
+            owner: None,
+        }
+    }
+
+    /// Make `pid` the only process that may set the clock, unless another
+    /// process got there first. Returns whether `pid` owns the clock.
+    pub fn claim(&mut self, pid: PID) -> bool {
+        *self.owner.get_or_insert(pid) == pid
+    }
+
+    /// Whether `pid` may set the clock and the timezone
+    pub fn may_set(&self, pid: Option<PID>) -> bool {
+        self.owner.is_some() && self.owner == pid
+    }
+
+    /// The time in milliseconds since 1970-01-01 00:00:00 in `zone`, or None
+    /// if it isn't known yet.
+    pub fn now(&self, zone: Zone, elapsed: u64) -> Option<u64> {
+        let local = elapsed as i64 + self.offset?;
+        let time = match zone {
+            Zone::Utc => local - self.timezone,
+            Zone::Local => local,
+        };
+        Some(time.max(0) as u64)
+    }
+
+    /// Record that the RTC read `local_ms` when `elapsed_ms()` was `elapsed`.
+    pub fn set(&mut self, local_ms: u64, elapsed: u64) {
+        let offset = local_ms as i64 - elapsed as i64;
+        match self.offset {
+            Some(current) if (offset - current).abs() < RESYNC_MS => (),
+            _ => self.offset = Some(offset),
+        }
+    }
+
+    /// Forget the time, e.g. because `elapsed_ms()` is about to stop counting
+    /// while the system is suspended.
+    pub fn invalidate(&mut self) {
+        self.offset = None;
+    }
+
+    /// Local time minus UTC, in seconds
+    pub fn timezone(&self) -> i32 {
+        (self.timezone / 1000) as i32
+    }
+
+    pub fn set_timezone(&mut self, seconds: i32) {
+        self.timezone = seconds as i64 * 1000;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2021-06-01 12:00:00
+    const JUNE_1: u64 = 1_622_548_800_000;
+
+    #[test]
+    fn time_follows_elapsed() {
+        let mut clock = WallClock::new();
+        assert_eq!(clock.now(Zone::Utc, 500), None);
+        clock.set(JUNE_1, 1000);
+        assert_eq!(clock.now(Zone::Utc, 1000), Some(JUNE_1));
+        assert_eq!(clock.now(Zone::Utc, 61_000), Some(JUNE_1 + 60_000));
+    }
+
+    #[test]
+    fn timezone_separates_local_from_utc() {
+        let mut clock = WallClock::new();
+        clock.set_timezone(-5 * 3600);
+        assert_eq!(clock.timezone(), -5 * 3600);
+        clock.set(JUNE_1, 0);
+        assert_eq!(clock.now(Zone::Local, 0), Some(JUNE_1));
+        assert_eq!(clock.now(Zone::Utc, 0), Some(JUNE_1 + 5 * 3600 * 1000));
+    }
+
+    #[test]
+    fn only_the_first_claimant_may_set_the_clock() {
+        let mut clock = WallClock::new();
+        let (rtc, other) = (PID::new(4).unwrap(), PID::new(9).unwrap());
+        assert!(!clock.may_set(Some(rtc)));
+        assert!(!clock.may_set(None));
+        assert!(clock.claim(rtc));
+        assert!(clock.claim(rtc));
+        assert!(!clock.claim(other));
+        assert!(clock.may_set(Some(rtc)));
+        assert!(!clock.may_set(Some(other)));
+        assert!(!clock.may_set(None));
+    }
+
+    #[test]
+    fn small_corrections_are_ignored() {
+        let mut clock = WallClock::new();
+        clock.set(JUNE_1, 0);
+        // A reading taken just before the RTC ticked over looks a second slow.
+        clock.set(JUNE_1 + 4000, 5000);
+        assert_eq!(clock.now(Zone::Utc, 5000), Some(JUNE_1 + 5000));
+        // Anything further off is the RTC being set, or drift.
+        clock.set(JUNE_1 + 60_000, 6000);
+        assert_eq!(clock.now(Zone::Utc, 6000), Some(JUNE_1 + 60_000));
+    }
+
+    #[test]
+    fn suspend_forgets_the_time() {
+        let mut clock = WallClock::new();
+        clock.set(JUNE_1, 0);
+        clock.invalidate();
+        assert_eq!(clock.now(Zone::Utc, 100), None);
+        clock.set(JUNE_1 + 3_600_000, 100);
+        assert_eq!(clock.now(Zone::Utc, 100), Some(JUNE_1 + 3_600_000));
+    }
+}
@@ -1,5 +1,12 @@
 #[allow(dead_code)]
 pub const AUTHENTICATE_TIMEOUT: u32 = 10_000; // time in ms that a process has to respond to an authentication request
+/// Outstanding challenges beyond this number push out the oldest one, so abandoned
+/// authentication attempts can't grow the table without bound.
+#[allow(dead_code)]
+pub const MAX_PENDING_CHALLENGES: usize = 32;
+/// Every subscriber takes up one of the name server's connections, so keep some headroom
+#[allow(dead_code)]
+pub const MAX_SUBSCRIBERS: usize = 16;
 
 #[derive(num_derive::FromPrimitive, num_derive::ToPrimitive)]
 pub(crate) enum Opcode {
@@ -7,7 +14,8 @@ pub(crate) enum Opcode {
     Register,
     /// Create a connection to the target server.
     Lookup,
-    /// Create an authenticated connection to the target server.
+    /// Create an authenticated connection to the target server. If the response does not
+    /// answer an outstanding challenge, a new challenge is returned instead.
     AuthenticatedLookup,
     /// unregister a server, given its cryptographically unique SID.
     Unregister,
@@ -15,6 +23,38 @@ pub(crate) enum Opcode {
     Disconnect,
     /// indicates if all inherentely trusted slots have been occupied. Should not run untrusted code until this is the case.
     TrustedInitDone,
+    /// unregister every server belonging to a child process of the caller, so a restarted child can claim the names again.
+    UnregisterChild,
+    /// send a `NameChange` to the given callback server whenever a name is registered or unregistered.
+    /// The reply is 0, or the `xous::Error` that stopped the subscription.
+    Subscribe,
+    /// stop sending `NameChange`s to the given callback server.
+    Unsubscribe,
+    /// return one entry of the name table, for diagnostics. Only processes from the boot image may ask.
+    ListServers,
+    /// return one of the names at or below a prefix in the name hierarchy. Only processes from the boot image may ask.
+    FindNames,
+}
+
+/// Messages sent to a subscriber's callback server
+#[derive(num_derive::FromPrimitive, num_derive::ToPrimitive)]
+pub(crate) enum NameCallback {
+    /// A `NameChange`
+    Event,
+    /// Sent by the subscriber to itself, to shut down its callback server
+    Drop,
+}
+
+/// What happened to a name, as reported to subscribers
+#[derive(Debug, Copy, Clone, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub enum NameEvent {
+    /// A server registered the name for the first time
+    Registered,
+    /// The name was released, and connections made through it are stale
+    Unregistered,
+    /// A name that had been released was registered again, typically by a restarted server.
+    /// It now leads to a different SID, so connections made through it must be replaced.
+    SidChanged,
 }
 
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
@@ -29,12 +69,59 @@ pub(crate) enum Return {
     CID((xous::CID, Option<[u32; 4]>)),
     /// Operation requested was otherwise successful (currently only used by disconnect to ack the disconnect)
     Success,
+    /// One entry of the name table, in response to a ListServers request
+    Listing(ServerListing),
+    /// One name under a prefix, in response to a FindNames request
+    Match(NameQuery),
+}
+
+/// The connection accounting of one registered name
+#[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct ServerInfo {
+    pub name: xous_ipc::String<64>,
+    /// Connections handed out through a plain lookup
+    pub current_conns: u32,
+    /// The number of plain lookups allowed, or `None` if unlimited
+    pub max_conns: Option<u32>,
+    /// Whether clients may connect past `max_conns` by authenticating
+    pub allow_authenticate: bool,
+    /// Connections handed out to authenticated clients
+    pub auth_conns: u32,
+    /// Whether the server has a one-time disconnect token
+    pub has_token: bool,
+}
+
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub(crate) struct ServerListing {
+    /// Position in the table, which is sorted by name
+    pub index: u32,
+    /// Number of names in the table
+    pub total: u32,
+    /// `None` once `index` is past the end
+    pub info: Option<ServerInfo>,
+}
+
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub(crate) struct NameQuery {
+    pub prefix: xous_ipc::String<64>,
+    /// Position among the matching names, which are sorted
+    pub index: u32,
+    /// `None` once `index` is past the last match
+    pub name: Option<xous_ipc::String<64>>,
 }
 
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 pub(crate) struct Registration {
     pub name: xous_ipc::String<64>,
     pub conn_limit: Option<u32>,
+    /// Shared secret that lets clients connect past `conn_limit` by authenticating
+    pub auth_key: Option<[u8; 32]>,
+}
+
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub(crate) struct NameChange {
+    pub name: xous_ipc::String<64>,
+    pub event: NameEvent,
 }
 
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
@@ -47,7 +134,7 @@ pub(crate) struct Disconnect {
 pub(crate) struct AuthenticatedLookup {
     pub name: xous_ipc::String<64>,
     pub pubkey_id: [u8; 20], // 160-bit pubkey ID encoded in network order (big endian)
-    pub response: [u32; 8],
+    pub response: [u32; 8],  // HMAC-SHA256 over the challenge, server name and client PID
 }
 
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
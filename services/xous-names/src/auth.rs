@@ -0,0 +1,132 @@
+//! Challenge-response for authenticated connections.
+//!
+//! A server that wants to accept connections beyond its trusted `max_conns` registers a
+//! 256-bit shared secret along with its name. A client proves that it knows the secret by
+//! returning HMAC-SHA256(secret, challenge || name || client PID), which ties the response
+//! to one challenge, one server and one process.
+//!
+//! This uses the software `sha2` rather than the hardware hash engine, because the engine is
+//! itself a server that needs xous-names in order to be found.
+
+use hmac::{Hmac, Mac, NewMac};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// Length of a shared secret, in bytes
+pub const KEY_LEN: usize = 32;
+
+fn hmac_sha256(key: &[u8], message: &[&[u8]]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("HMAC takes keys of any length");
+    for part in message {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+/// Identify a secret without revealing it, so that a client holding several secrets knows
+/// which one a challenge is asking for.
+pub fn key_id(key: &[u8; KEY_LEN]) -> [u8; 20] {
+    let mut id = [0u8; 20];
+    id.copy_from_slice(&Sha256::digest(key)[..20]);
+    id
+}
+
+/// Compute the response to `challenge` for a connection from `pid` to the server `name`.
+pub fn response(key: &[u8; KEY_LEN], challenge: &[u32; 4], name: &str, pid: xous::PID) -> [u32; 8] {
+    let mut challenge_bytes = [0u8; 16];
+    for (bytes, word) in challenge_bytes.chunks_exact_mut(4).zip(challenge.iter()) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    let mac = hmac_sha256(key, &[&challenge_bytes, name.as_bytes(), &[pid.get()]]);
+
+    let mut response = [0u32; 8];
+    for (word, bytes) in response.iter_mut().zip(mac.chunks_exact(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    response
+}
+
+/// Compare two responses in constant time, so that how long it takes to turn away a wrong
+/// response says nothing about how much of it was right.
+pub fn responses_match(expected: &[u32; 8], actual: &[u32; 8]) -> bool {
+    expected[..].ct_eq(&actual[..]).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(digits: &str) -> Vec<u8> {
+        (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn hmac_sha256_rfc_4231() {
+        let cases: [(Vec<u8>, Vec<u8>, &str); 6] = [
+            (
+                vec![0x0b; 20],
+                b"Hi There".to_vec(),
+                "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            ),
+            (
+                b"Jefe".to_vec(),
+                b"what do ya want for nothing?".to_vec(),
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                vec![0xaa; 20],
+                vec![0xdd; 50],
+                "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+            ),
+            (
+                (1..=25).collect(),
+                vec![0xcd; 50],
+                "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+            ),
+            (
+                vec![0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First".to_vec(),
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+            (
+                vec![0xaa; 131],
+                b"This is a test using a larger than block-size key and a larger than \
+                  block-size data. The key needs to be hashed before being used by the HMAC \
+                  algorithm."
+                    .to_vec(),
+                "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+            ),
+        ];
+        for (key, data, mac) in cases.iter() {
+            assert_eq!(hmac_sha256(key, &[data]).to_vec(), hex(mac));
+        }
+        // test case 5 is truncated to 128 bits
+        assert_eq!(
+            hmac_sha256(&[0x0c; 20], &[b"Test With Truncation"])[..16].to_vec(),
+            hex("a3b6167473100ee06e0c796c2955552b")
+        );
+    }
+
+    #[test]
+    fn responses_match_only_when_equal() {
+        let key = [7u8; KEY_LEN];
+        let pid = xous::PID::new(5).unwrap();
+        let expected = response(&key, &[1, 2, 3, 4], "gam", pid);
+        assert!(responses_match(&expected, &expected));
+        for word in 0..8 {
+            let mut wrong = expected;
+            wrong[word] ^= 1 << 31;
+            assert!(!responses_match(&expected, &wrong));
+        }
+        // the response is tied to the challenge, the server and the process
+        assert_ne!(response(&key, &[1, 2, 3, 5], "gam", pid), expected);
+        assert_ne!(response(&key, &[1, 2, 3, 4], "gan", pid), expected);
+        assert_ne!(
+            response(&key, &[1, 2, 3, 4], "gam", xous::PID::new(6).unwrap()),
+            expected
+        );
+    }
+}
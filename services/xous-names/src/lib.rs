@@ -3,23 +3,30 @@
 //! Detailed docs are parked under Structs/XousNames down below
 
 pub mod api;
+pub mod auth;
 
-use api::Disconnect;
+use api::{Disconnect, NameCallback, NameChange, NameQuery, ServerListing};
+pub use api::{NameEvent, ServerInfo};
 use core::fmt::Write;
-use num_traits::ToPrimitive;
+use num_traits::{FromPrimitive, ToPrimitive};
 use xous_ipc::{Buffer, String};
 
 #[doc = include_str!("../README.md")]
 #[derive(Debug)]
 pub struct XousNames {
     conn: xous::CID,
+    callback_sid: Option<xous::SID>,
 }
+static mut NAME_CB: Option<fn(NameEvent, &str)> = None;
 impl XousNames {
     pub fn new() -> Result<Self, xous::Error> {
         REFCOUNT.store(REFCOUNT.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
         let conn = xous::connect(xous::SID::from_bytes(b"xous-name-server").unwrap())
             .expect("Couldn't connect to XousNames");
-        Ok(XousNames { conn })
+        Ok(XousNames {
+            conn,
+            callback_sid: None,
+        })
     }
 
     pub fn unregister_server(&self, sid: xous::SID) -> Result<(), xous::Error> {
@@ -52,14 +59,59 @@ impl XousNames {
         }
     }
 
+    /// Unregister every server that was registered by `pid`, which must be a child of this
+    /// process. A supervisor calls this after it is told that a child has terminated (see
+    /// `xous::set_supervisor()`), so that the restarted child can register the same names
+    /// again and get fresh SIDs. Returns the number of names that were released.
+    pub fn unregister_child(&self, pid: xous::PID) -> Result<usize, xous::Error> {
+        let response = xous::send_message(
+            self.conn,
+            xous::Message::new_blocking_scalar(
+                api::Opcode::UnregisterChild.to_usize().unwrap(),
+                pid.get() as usize,
+                0,
+                0,
+                0,
+            ),
+        )?;
+        if let xous::Result::Scalar1(released) = response {
+            Ok(released)
+        } else {
+            Err(xous::Error::InternalError)
+        }
+    }
+
     pub fn register_name(
         &self,
         name: &str,
         max_conns: Option<u32>,
+    ) -> Result<xous::SID, xous::Error> {
+        self.register(name, max_conns, None)
+    }
+
+    /// Register a name that, once its `max_conns` trusted connections are taken, still
+    /// accepts connections from any process that can prove it knows `auth_key`. See
+    /// `request_authenticated_connection()`. Authenticated connections are counted
+    /// separately and never use up the trusted slots.
+    pub fn register_name_with_auth_key(
+        &self,
+        name: &str,
+        max_conns: Option<u32>,
+        auth_key: &[u8; auth::KEY_LEN],
+    ) -> Result<xous::SID, xous::Error> {
+        self.register(name, max_conns, Some(*auth_key))
+    }
+
+    fn register(
+        &self,
+        name: &str,
+        max_conns: Option<u32>,
+        auth_key: Option<[u8; auth::KEY_LEN]>,
     ) -> Result<xous::SID, xous::Error> {
         let mut registration = api::Registration {
             name: String::<64>::new(),
             conn_limit: max_conns,
+            auth_key,
         };
         // could also do String::from_str() but in this case we want things to fail if the string is too long.
         write!(registration.name, "{}", name).expect("name probably too long");
@@ -93,7 +145,7 @@ impl XousNames {
 
         match buf.to_original().unwrap() {
             api::Return::CID((cid, token)) => Ok((cid, token)),
-            // api::Return::AuthenticateRequest(_) => Err(xous::Error::AccessDenied),
+            api::Return::AuthenticateRequest(_) => Err(xous::Error::AccessDenied),
             _ => Err(xous::Error::ServerNotFound),
         }
     }
@@ -112,6 +164,12 @@ impl XousNames {
         }
     }
 
+    /// Connect to the server registered as `name`.
+    ///
+    /// Names may be hierarchical and versioned, as in `ime/plugin/shell@2.1.0`. A lookup
+    /// for just a path, like `ime/plugin/shell`, or for a path and a version requirement,
+    /// like `ime/plugin/shell@^2` or `ime/plugin/shell@>=2.1`, connects to the newest
+    /// registered version that fits. A bare version like `@2.1` means `@^2.1`.
     pub fn request_connection(&self, name: &str) -> Result<xous::CID, xous::Error> {
         let mut lookup_name = xous_ipc::String::<64>::new();
         write!(lookup_name, "{}", name).expect("name problably too long");
@@ -123,11 +181,65 @@ impl XousNames {
 
         match buf.to_original().unwrap() {
             api::Return::CID((cid, _)) => Ok(cid),
-            // api::Return::AuthenticateRequest(_) => Err(xous::Error::AccessDenied),
+            api::Return::AuthenticateRequest(_) => Err(xous::Error::AccessDenied),
             _ => Err(xous::Error::ServerNotFound),
         }
     }
 
+    /// Connect to a server that was registered with `register_name_with_auth_key()` by
+    /// answering a challenge with `auth_key`. This works even after all of the server's
+    /// trusted connections have been handed out, and does not take one of them.
+    ///
+    /// Returns `AccessDenied` if the key is not the one the server registered.
+    pub fn request_authenticated_connection(
+        &self,
+        name: &str,
+        auth_key: &[u8; auth::KEY_LEN],
+    ) -> Result<xous::CID, xous::Error> {
+        let pid = xous::PID::new(xous::process::id() as u8).ok_or(xous::Error::InternalError)?;
+        let mut lookup = api::AuthenticatedLookup {
+            name: String::<64>::new(),
+            pubkey_id: auth::key_id(auth_key),
+            response: [0; 8],
+        };
+        write!(lookup.name, "{}", name).expect("name problably too long");
+
+        // The first request has nothing to answer yet and comes back with a challenge. If
+        // the answer to that is rejected, the server hands out another challenge instead
+        // of a connection.
+        for _ in 0..2 {
+            let mut buf = Buffer::into_buf(lookup).or(Err(xous::Error::InternalError))?;
+            buf.lend_mut(
+                self.conn,
+                api::Opcode::AuthenticatedLookup.to_u32().unwrap(),
+            )
+            .or(Err(xous::Error::InternalError))?;
+
+            match buf.to_original().unwrap() {
+                api::Return::CID((cid, _)) => return Ok(cid),
+                api::Return::AuthenticateRequest(request) => {
+                    if request.pubkey_id != auth::key_id(auth_key) {
+                        return Err(xous::Error::AccessDenied);
+                    }
+                    // the server may have resolved a versioned name, so answer for the one it chose
+                    let response = auth::response(
+                        auth_key,
+                        &request.challenge,
+                        request.name.as_str().or(Err(xous::Error::InternalError))?,
+                        pid,
+                    );
+                    lookup = api::AuthenticatedLookup {
+                        name: request.name,
+                        pubkey_id: request.pubkey_id,
+                        response,
+                    };
+                }
+                _ => return Err(xous::Error::ServerNotFound),
+            }
+        }
+        Err(xous::Error::AccessDenied)
+    }
+
     /// note: you probably want to use this one, to avoid synchronization issues on startup as servers register asynhcronously
     pub fn request_connection_blocking(&self, name: &str) -> Result<xous::CID, xous::Error> {
         loop {
@@ -141,6 +253,133 @@ impl XousNames {
         }
     }
 
+    /// Have `cb` called with the name whenever any server registers or unregisters. A
+    /// `SidChanged` event means that a server that went away has registered again, so
+    /// connections made to it earlier should be replaced with a fresh
+    /// `request_connection()`.
+    ///
+    /// Only one callback may be hooked per process, and the name server only accepts a
+    /// limited number of subscribers.
+    pub fn hook_name_callback(&mut self, cb: fn(NameEvent, &str)) -> Result<(), xous::Error> {
+        if unsafe { NAME_CB }.is_some() {
+            return Err(xous::Error::MemoryInUse);
+        }
+        let sid_tuple: (u32, u32, u32, u32);
+        if let Some(sid) = self.callback_sid {
+            sid_tuple = sid.to_u32();
+        } else {
+            let sid = xous::create_server().expect("Couldn't create name callback server");
+            self.callback_sid = Some(sid);
+            sid_tuple = sid.to_u32();
+            xous::create_thread_4(
+                name_cb_server,
+                sid_tuple.0 as usize,
+                sid_tuple.1 as usize,
+                sid_tuple.2 as usize,
+                sid_tuple.3 as usize,
+            )
+            .unwrap();
+        }
+        unsafe { NAME_CB = Some(cb) };
+        let response = xous::send_message(
+            self.conn,
+            xous::Message::new_blocking_scalar(
+                api::Opcode::Subscribe.to_usize().unwrap(),
+                sid_tuple.0 as usize,
+                sid_tuple.1 as usize,
+                sid_tuple.2 as usize,
+                sid_tuple.3 as usize,
+            ),
+        )?;
+        let result = match response {
+            xous::Result::Scalar1(0) => Ok(()),
+            xous::Result::Scalar1(e) => Err(xous::Error::from_usize(e)),
+            _ => Err(xous::Error::InternalError),
+        };
+        if result.is_err() {
+            unsafe { NAME_CB = None };
+        }
+        result
+    }
+
+    pub fn unhook_name_callback(&mut self) -> Result<(), xous::Error> {
+        unsafe { NAME_CB = None };
+        if let Some(sid) = self.callback_sid {
+            let sid_tuple = sid.to_u32();
+            xous::send_message(
+                self.conn,
+                xous::Message::new_blocking_scalar(
+                    api::Opcode::Unsubscribe.to_usize().unwrap(),
+                    sid_tuple.0 as usize,
+                    sid_tuple.1 as usize,
+                    sid_tuple.2 as usize,
+                    sid_tuple.3 as usize,
+                ),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Return every registered name at or below `prefix` in the name hierarchy, sorted. A
+    /// prefix matches whole path segments: `ime/plugin` finds `ime/plugin/shell@1.0.0` but
+    /// not `ime/plugins`. Use this to discover the alternatives registered under a path. Only
+    /// processes in the boot image may search.
+    ///
+    /// Names are read one at a time, so a name registered or unregistered in the middle of
+    /// the search may be missed or reported twice.
+    pub fn find_names(&self, prefix: &str) -> Result<Vec<String<64>>, xous::Error> {
+        let mut names = Vec::new();
+        loop {
+            let mut query = NameQuery {
+                prefix: String::<64>::new(),
+                index: names.len() as u32,
+                name: None,
+            };
+            write!(query.prefix, "{}", prefix).or(Err(xous::Error::InvalidString))?;
+            let mut buf = Buffer::into_buf(query).or(Err(xous::Error::InternalError))?;
+            buf.lend_mut(self.conn, api::Opcode::FindNames.to_u32().unwrap())
+                .or(Err(xous::Error::InternalError))?;
+
+            match buf.to_original().unwrap() {
+                api::Return::Match(NameQuery {
+                    name: Some(name), ..
+                }) => names.push(name),
+                api::Return::Match(_) => return Ok(names),
+                api::Return::Failure => return Err(xous::Error::AccessDenied),
+                _ => return Err(xous::Error::InternalError),
+            }
+        }
+    }
+
+    /// Return the connection accounting of every registered name, sorted by name. This is
+    /// meant for diagnosing a boot that never reaches `trusted_init_done()`, and is only
+    /// available to processes in the boot image.
+    ///
+    /// The table is read one name at a time, so a name registered or unregistered in the
+    /// middle of the listing may be missed or reported twice.
+    pub fn list_servers(&self) -> Result<Vec<ServerInfo>, xous::Error> {
+        let mut servers = Vec::new();
+        loop {
+            let request = ServerListing {
+                index: servers.len() as u32,
+                total: 0,
+                info: None,
+            };
+            let mut buf = Buffer::into_buf(request).or(Err(xous::Error::InternalError))?;
+            buf.lend_mut(self.conn, api::Opcode::ListServers.to_u32().unwrap())
+                .or(Err(xous::Error::InternalError))?;
+
+            match buf.to_original().unwrap() {
+                api::Return::Listing(ServerListing {
+                    info: Some(info), ..
+                }) => servers.push(info),
+                api::Return::Listing(_) => return Ok(servers),
+                api::Return::Failure => return Err(xous::Error::AccessDenied),
+                _ => return Err(xous::Error::InternalError),
+            }
+        }
+    }
+
     pub fn trusted_init_done(&self) -> Result<bool, xous::Error> {
         let response = xous::send_message(
             self.conn,
@@ -163,17 +402,28 @@ impl XousNames {
             Err(xous::Error::InternalError)
         }
     }
-
-    // todo:
-    // pub fn authenticated_connection(&self, name: &str, key: Authkey)
-    // this function will create an authenticated connection, if such are allowed
-    // it's intended for use by dynamically-loaded third-party apps. As of Xous 0.8 this isn't supported, so it's just a "todo"
 }
 
 use core::sync::atomic::{AtomicU32, Ordering};
 static REFCOUNT: AtomicU32 = AtomicU32::new(0);
 impl Drop for XousNames {
     fn drop(&mut self) {
+        // if we have callbacks, stop the name server from sending more and shut down our handler
+        if self.callback_sid.is_some() {
+            self.unhook_name_callback().ok();
+        }
+        if let Some(sid) = self.callback_sid.take() {
+            let cid = xous::connect(sid).unwrap();
+            xous::send_message(
+                cid,
+                xous::Message::new_scalar(NameCallback::Drop.to_usize().unwrap(), 0, 0, 0, 0),
+            )
+            .unwrap();
+            unsafe {
+                xous::disconnect(cid).unwrap();
+            }
+        }
+
         // de-allocate myself. It's unsafe because we are responsible to make sure nobody else is using the connection.
         if REFCOUNT.load(Ordering::Relaxed) == 0 {
             unsafe {
@@ -182,3 +432,25 @@ impl Drop for XousNames {
         }
     }
 }
+
+fn name_cb_server(sid0: usize, sid1: usize, sid2: usize, sid3: usize) {
+    let sid = xous::SID::from_u32(sid0 as u32, sid1 as u32, sid2 as u32, sid3 as u32);
+    log::trace!("name callback server started");
+    loop {
+        let msg = xous::receive_message(sid).unwrap();
+        match FromPrimitive::from_usize(msg.body.id()) {
+            Some(NameCallback::Event) => {
+                let buffer =
+                    unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let change = buffer.to_original::<NameChange, _>().unwrap();
+                // a change may still arrive after we unhooked; it's harmless to ignore it
+                if let Some(cb) = unsafe { NAME_CB } {
+                    cb(change.event, change.name.as_str().unwrap_or(""))
+                }
+            }
+            Some(NameCallback::Drop) => break,
+            None => log::error!("got unrecognized message in name callback server, ignoring"),
+        }
+    }
+    xous::destroy_server(sid).unwrap();
+}
@@ -3,8 +3,12 @@
 
 mod api;
 use api::*;
+mod auth;
+mod namespace;
+mod subscribers;
+use subscribers::Subscribers;
 
-use num_traits::FromPrimitive;
+use num_traits::{FromPrimitive, ToPrimitive};
 use xous::msg_blocking_scalar_unpack;
 use xous_ipc::{Buffer, String};
 
@@ -73,23 +77,45 @@ I'll take safety over speed in this case.
 Eventually, we shall endeavor to remove Heapless entirely, once we have a `libstd` in place
 and we can use heap-allocated Rust primitives...
 */
+/// A server's shared secret. It is printed by its key ID only, so that dumping the
+/// table into the log doesn't give the secret away.
+#[derive(Copy, Clone)]
+struct AuthKey([u8; auth::KEY_LEN]);
+impl core::fmt::Debug for AuthKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "AuthKey({:02x?})", auth::key_id(&self.0))
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 struct Connection {
     pub sid: xous::SID,
     pub current_conns: u32, // number of unauthenticated (inherentely trusted) connections
     pub max_conns: Option<u32>, // if None, unlimited connections allowed
-    pub allow_authenticate: bool,
-    pub auth_conns: u32,         // number of authenticated connections
+    pub auth_key: Option<AuthKey>, // if Some, clients that know the key may connect beyond max_conns
+    pub auth_conns: u32,           // number of authenticated connections
     pub token: Option<[u32; 4]>, // a random number that must be presented to allow for disconnection for single-connection servers
+    pub owner: Option<xous::PID>, // the process that registered the name
+    pub supervisor: Option<xous::PID>, // the parent of the owner at registration time, which may unregister the name on its behalf
+}
+/// A challenge handed to `pid` for a connection to `name`, along with the only response
+/// that will be accepted for it.
+#[derive(Debug)]
+struct PendingChallenge {
+    pub name: XousServerName,
+    pub pid: xous::PID,
+    pub expected: [u32; 8],
 }
 #[derive(Debug)]
 struct CheckedHashMap {
     pub map: HashMap<XousServerName, Connection>,
+    pub challenges: Vec<PendingChallenge>,
 }
 impl CheckedHashMap {
     pub fn new() -> Self {
         CheckedHashMap {
             map: HashMap::new(),
+            challenges: Vec::new(),
         }
     }
     pub fn insert(
@@ -97,6 +123,8 @@ impl CheckedHashMap {
         name: XousServerName,
         sid: xous::SID,
         max_conns: Option<u32>,
+        auth_key: Option<[u8; auth::KEY_LEN]>,
+        owner: Option<xous::PID>,
     ) -> Result<(), xous::Error> {
         let token = if max_conns == Some(1) {
             // for the special case of 1-connection servers, provision a one-time use token for disconnects
@@ -114,13 +142,34 @@ impl CheckedHashMap {
                 sid,
                 current_conns: 0,
                 max_conns,
-                allow_authenticate: false, // for now, we don't support authenticated connections
+                auth_key: auth_key.map(AuthKey),
                 auth_conns: 0,
                 token,
+                owner,
+                supervisor: owner.and_then(parent_of),
             },
         );
         Ok(())
     }
+    /// Remove every name registered by `owner`, provided that `supervisor` was its parent when
+    /// the name was registered. This lets a supervisor clear out the names of a child that
+    /// crashed, so that the replacement process can register them again.
+    pub fn remove_child(&mut self, owner: xous::PID, supervisor: xous::PID) -> Vec<XousServerName> {
+        let names: Vec<XousServerName> = self
+            .map
+            .iter()
+            .filter(|(_name, mapping)| {
+                mapping.owner == Some(owner) && mapping.supervisor == Some(supervisor)
+            })
+            .map(|(name, _mapping)| *name)
+            .collect();
+        for name in names.iter() {
+            self.map.remove(name);
+        }
+        self.challenges
+            .retain(|pending| !names.contains(&pending.name));
+        names
+    }
     pub fn remove(&mut self, sid: xous::SID) -> Option<XousServerName> {
         // remove is expensive, because we have to do a full search for the sid, which is not our usual key
         // however, for security reasons, you have to let us know your sid (which is a secret) in order to delete
@@ -134,6 +183,7 @@ impl CheckedHashMap {
         }
         if let Some(name) = removed_name {
             self.map.remove(&name);
+            self.challenges.retain(|pending| pending.name != name);
         }
 
         removed_name
@@ -168,6 +218,83 @@ impl CheckedHashMap {
         }
         (None, None)
     }
+    /// Issue a challenge to `pid` for a connection to `name`, replacing any challenge it
+    /// was given before. Returns `None` if the server does not accept authenticated
+    /// connections.
+    pub fn challenge(
+        &mut self,
+        name: &XousServerName,
+        pid: xous::PID,
+    ) -> Option<AuthenticateRequest> {
+        let key = self.map.get(name)?.auth_key?;
+        let challenge = xous::create_server_id().ok()?.to_array();
+
+        self.challenges
+            .retain(|pending| !(pending.name == *name && pending.pid == pid));
+        if self.challenges.len() >= MAX_PENDING_CHALLENGES {
+            self.challenges.remove(0);
+        }
+        self.challenges.push(PendingChallenge {
+            name: *name,
+            pid,
+            expected: auth::response(&key.0, &challenge, name.to_str(), pid),
+        });
+        Some(AuthenticateRequest {
+            name: String::<64>::from_str(name.to_str()),
+            pubkey_id: auth::key_id(&key.0),
+            challenge,
+        })
+    }
+    /// Check `response` against the challenge that was given to `pid` for `name`. Each
+    /// challenge can only be answered once, right or wrong. Returns `None` if there was
+    /// no challenge outstanding.
+    pub fn check_response(
+        &mut self,
+        name: &XousServerName,
+        pid: xous::PID,
+        response: &[u32; 8],
+    ) -> Option<bool> {
+        let index = self
+            .challenges
+            .iter()
+            .position(|pending| pending.name == *name && pending.pid == pid)?;
+        let pending = self.challenges.remove(index);
+        Some(auth::responses_match(&pending.expected, response))
+    }
+    /// Count a connection that was made by answering a challenge. These don't use up any
+    /// of the server's `max_conns` slots.
+    pub fn count_authenticated(&mut self, name: &XousServerName) {
+        if let Some(entry) = self.map.get_mut(name) {
+            entry.auth_conns += 1;
+        }
+    }
+    pub fn allows_authentication(&self, name: &XousServerName) -> bool {
+        self.map
+            .get(name)
+            .map(|entry| entry.auth_key.is_some())
+            .unwrap_or(false)
+    }
+    /// Describe the `index`th name in alphabetical order, along with the number of names.
+    pub fn listing(&self, index: u32) -> ServerListing {
+        let mut names: Vec<&XousServerName> = self.map.keys().collect();
+        names.sort_by(|a, b| a.to_str().cmp(b.to_str()));
+        let info = names.get(index as usize).map(|name| {
+            let entry = &self.map[*name];
+            ServerInfo {
+                name: String::<64>::from_str(name.to_str()),
+                current_conns: entry.current_conns,
+                max_conns: entry.max_conns,
+                allow_authenticate: entry.auth_key.is_some(),
+                auth_conns: entry.auth_conns,
+                has_token: entry.token.is_some(),
+            }
+        });
+        ServerListing {
+            index,
+            total: names.len() as u32,
+            info,
+        }
+    }
     pub fn trusted_init_done(&self) -> bool {
         let mut trusted_done = true;
         for (name, entry) in self.map.iter() {
@@ -204,8 +331,15 @@ impl CheckedHashMap {
     }
     // this is a safer version of disconnect. we track servers that allow exactly one connection at a time
     // and give them a one-time-use token that a connector can use to disconnect.
+    // `name` may be a versioned lookup, and the server it resolved to may no longer be the
+    // newest by now, so check every version of the path. Tokens are random, so only the right
+    // one can match.
     pub fn disconnect_with_token(&mut self, name: &XousServerName, token: [u32; 4]) -> bool {
-        if let Some(entry) = self.map.get_mut(name) {
+        let (path, _) = namespace::split(name.to_str());
+        for (entry_name, entry) in self.map.iter_mut() {
+            if namespace::split(entry_name.to_str()).0 != path {
+                continue;
+            }
             if let Some(old_token) = entry.token {
                 if (token == old_token) && (entry.current_conns == 1) {
                     (*entry).current_conns = 0;
@@ -221,6 +355,88 @@ impl CheckedHashMap {
         }
         false
     }
+    /// Find the name that a lookup for `requested` refers to: the name itself if it is
+    /// registered, or else the newest registered version of its path that meets its
+    /// version requirement.
+    pub fn resolve(&self, requested: &XousServerName) -> Option<XousServerName> {
+        if self.map.contains_key(requested) {
+            return Some(*requested);
+        }
+        let (path, requirement) = namespace::split(requested.to_str());
+        let requirement = match requirement {
+            Some(requirement) => namespace::Requirement::parse(requirement)?,
+            None => namespace::Requirement::Any,
+        };
+        self.map
+            .keys()
+            .filter_map(|name| {
+                let (candidate, version) = namespace::split(name.to_str());
+                let version = namespace::Version::parse(version?)?;
+                if candidate == path && requirement.matches(&version) {
+                    Some((version, *name))
+                } else {
+                    None
+                }
+            })
+            .max_by_key(|(version, _name)| *version)
+            .map(|(_version, name)| name)
+    }
+    /// Return the `index`th name at or below `prefix`, in alphabetical order.
+    pub fn find(&self, prefix: &str, index: u32) -> Option<XousServerName> {
+        let mut names: Vec<&XousServerName> = self
+            .map
+            .keys()
+            .filter(|name| namespace::is_under(name.to_str(), prefix))
+            .collect();
+        names.sort_by(|a, b| a.to_str().cmp(b.to_str()));
+        names.get(index as usize).map(|name| **name)
+    }
+}
+
+/// Reaches subscribers through the kernel
+/// The parent of `pid`, or `None` if it has already gone. `process_info()` moves on to the
+/// next live process when `pid` is gone, so its answer has to be checked.
+fn parent_of(pid: xous::PID) -> Option<xous::PID> {
+    xous::process_info(pid)
+        .ok()
+        .filter(|info| info.pid == pid)
+        .map(|info| info.ppid)
+}
+
+/// Whether `sender` is part of the boot image, which the kernel starts directly.
+fn from_boot_image(sender: xous::MessageSender) -> bool {
+    sender
+        .pid()
+        .and_then(parent_of)
+        .map(|ppid| ppid.get() == 1)
+        .unwrap_or(false)
+}
+
+struct XousNotifier;
+impl subscribers::Notifier for XousNotifier {
+    fn connect(&mut self, sid: xous::SID) -> Result<xous::CID, xous::Error> {
+        xous::try_connect(sid)
+    }
+    /// The memory is moved rather than lent, and without waiting for room in the subscriber's
+    /// queue, so a subscriber that is slow or stuck misses the event rather than holding up
+    /// the name server.
+    fn send(
+        &mut self,
+        cid: xous::CID,
+        name: &XousServerName,
+        event: NameEvent,
+    ) -> Result<(), xous::Error> {
+        let change = NameChange {
+            name: String::<64>::from_str(name.to_str()),
+            event,
+        };
+        let buf = Buffer::into_buf(change).expect("couldn't serialize name change");
+        buf.try_send(cid, NameCallback::Event.to_u32().unwrap())
+            .map(|_| ())
+    }
+    fn disconnect(&mut self, cid: xous::CID) {
+        unsafe { xous::disconnect(cid).ok() };
+    }
 }
 
 #[xous::xous_main]
@@ -238,6 +454,7 @@ fn xmain() -> ! {
     // this limits the number of available servers to be requested to 128...!
     //let mut name_table = FnvIndexMap::<XousServerName, xous::SID, 128>::new();
     let mut name_table = CheckedHashMap::new();
+    let mut subscribers = Subscribers::new(XousNotifier);
 
     info!("started");
     loop {
@@ -258,13 +475,20 @@ fn xmain() -> ! {
                 let response: api::Return;
 
                 log::trace!("registration request for '{}'", name);
-                if !name_table.contains_key(&name) {
+                if !name_table.contains_key(&name) && namespace::is_registrable(name.to_str()) {
                     let new_sid =
                         xous::create_server_id().expect("create server failed, maybe OOM?");
                     name_table
-                        .insert(name, new_sid, registration.conn_limit)
+                        .insert(
+                            name,
+                            new_sid,
+                            registration.conn_limit,
+                            registration.auth_key,
+                            msg.sender.pid(),
+                        )
                         .expect("register name failure, maybe out of HashMap capacity?");
                     log::trace!("request successful, SID is {:?}", new_sid);
+                    subscribers.registered(&name);
 
                     response = api::Return::SID(new_sid.into());
                 } else {
@@ -281,6 +505,7 @@ fn xmain() -> ! {
                 let gid = xous::SID::from_u32(s0 as u32, s1 as u32, s2 as u32, s3 as u32);
                 if let Some(name) = name_table.remove(gid) {
                     info!("{} server has unregistered", name);
+                    subscribers.unregistered(&name);
                     xous::return_scalar(msg.sender, 1).unwrap();
                 } else {
                     log::error!("couldn't unregister {:?}", gid);
@@ -298,6 +523,7 @@ fn xmain() -> ! {
                         .expect("couldn't convert server name to string"),
                 );
                 log::trace!("Lookup request for '{}'", name);
+                let name = name_table.resolve(&name).unwrap_or(name);
                 let response: api::Return;
                 if let (Some(server_sid), token) = name_table.connect(&name) {
                     let sender_pid = msg
@@ -320,24 +546,24 @@ fn xmain() -> ! {
                             response = api::Return::Failure
                         }
                     }
+                } else if name_table.allows_authentication(&name) {
+                    // The trusted slots are all taken, but the caller may still get in by
+                    // proving that it knows the server's key.
+                    let sender_pid = msg
+                        .sender
+                        .pid()
+                        .expect("can't extract sender PID on Lookup");
+                    response = match name_table.challenge(&name, sender_pid) {
+                        Some(auth_request) => api::Return::AuthenticateRequest(auth_request),
+                        None => api::Return::Failure,
+                    };
                 } else {
                     log::debug!("Can't find request '{}' in table, dumping table:", name);
                     for (_name, conn) in name_table.map.iter() {
                         log::debug!("{:?}", conn);
                     }
-                    // no authenticate remedy currently supported, but we'd put that code somewhere around here eventually.
-                    let (c1, c2, c3, c4) = xous::create_server_id().unwrap().to_u32();
-                    let auth_request = AuthenticateRequest {
-                        name: String::<64>::from_str(
-                            name_string
-                                .as_str()
-                                .expect("couldn't convert server name to string"),
-                        ),
-                        pubkey_id: [0; 20], // placeholder
-                        challenge: [c1, c2, c3, c4],
-                    };
                     d11ctimeout.hosted_delay();
-                    response = api::Return::AuthenticateRequest(auth_request) // this code just exists to exercise the return path
+                    response = api::Return::Failure
                 }
                 buffer
                     .replace(response)
@@ -345,11 +571,60 @@ fn xmain() -> ! {
             }
             Some(api::Opcode::AuthenticatedLookup) => {
                 let mem = msg.body.memory_message_mut().unwrap();
-                let buffer = unsafe { Buffer::from_memory_message_mut(mem) };
-                let auth_lookup: AuthenticatedLookup = buffer.to_original().unwrap();
-                info!("AuthenticatedLookup request {:?}", auth_lookup);
-                error!("AuthenticatedLookup not yet implemented");
-                unimplemented!("AuthenticatedLookup not yet implemented");
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(mem) };
+                let auth_lookup = buffer.to_original::<AuthenticatedLookup, _>().unwrap();
+                let name = XousServerName::from_str(
+                    auth_lookup
+                        .name
+                        .as_str()
+                        .expect("couldn't convert server name to string"),
+                );
+                let sender_pid = msg
+                    .sender
+                    .pid()
+                    .expect("can't extract sender PID on AuthenticatedLookup");
+                log::trace!("AuthenticatedLookup request for '{}'", name);
+                let name = name_table.resolve(&name).unwrap_or(name);
+
+                let response: api::Return;
+                match name_table.check_response(&name, sender_pid, &auth_lookup.response) {
+                    Some(true) => {
+                        let server_sid = name_table.map[&name].sid;
+                        response = match xous::connect_for_process(sender_pid, server_sid)
+                            .expect("can't broker connection")
+                        {
+                            xous::Result::ConnectionID(connection_id) => {
+                                name_table.count_authenticated(&name);
+                                log::trace!(
+                                    "authenticated lookup success, returning connection {}",
+                                    connection_id
+                                );
+                                api::Return::CID((connection_id, None))
+                            }
+                            _ => api::Return::Failure,
+                        };
+                    }
+                    checked => {
+                        if checked == Some(false) {
+                            // rate limit guessing, the same way failed registrations are
+                            info!(
+                                "authentication for '{}' failed, waiting for deterministic timeout",
+                                name
+                            );
+                            d11ctimeout.deterministic_busy_wait();
+                        }
+                        response = match name_table.challenge(&name, sender_pid) {
+                            Some(auth_request) => api::Return::AuthenticateRequest(auth_request),
+                            None => {
+                                d11ctimeout.hosted_delay();
+                                api::Return::Failure
+                            }
+                        };
+                    }
+                }
+                buffer
+                    .replace(response)
+                    .expect("AuthenticatedLookup can't serialize return value");
             }
             Some(api::Opcode::TrustedInitDone) => {
                 if name_table.trusted_init_done() {
@@ -358,6 +633,66 @@ fn xmain() -> ! {
                     xous::return_scalar(msg.sender, 0).expect("couldn't return trusted_init_done");
                 }
             }
+            Some(api::Opcode::UnregisterChild) => msg_blocking_scalar_unpack!(msg, pid, _, _, _, {
+                let removed = match (xous::PID::new(pid as u8), msg.sender.pid()) {
+                    (Some(child), Some(sender)) => name_table.remove_child(child, sender),
+                    _ => Vec::new(),
+                };
+                for name in removed.iter() {
+                    subscribers.unregistered(name);
+                    info!(
+                        "{} server of terminated PID {} has been unregistered",
+                        name, pid
+                    );
+                }
+                xous::return_scalar(msg.sender, removed.len()).unwrap();
+            }),
+            Some(api::Opcode::Subscribe) => msg_blocking_scalar_unpack!(msg, s0, s1, s2, s3, {
+                let sid = xous::SID::from_u32(s0 as u32, s1 as u32, s2 as u32, s3 as u32);
+                let result = match subscribers.subscribe(sid) {
+                    Ok(()) => 0,
+                    Err(e) => e as usize,
+                };
+                xous::return_scalar(msg.sender, result).unwrap();
+            }),
+            Some(api::Opcode::Unsubscribe) => msg_blocking_scalar_unpack!(msg, s0, s1, s2, s3, {
+                let sid = xous::SID::from_u32(s0 as u32, s1 as u32, s2 as u32, s3 as u32);
+                let unsubscribed = subscribers.unsubscribe(sid);
+                xous::return_scalar(msg.sender, unsubscribed as usize).unwrap();
+            }),
+            Some(api::Opcode::ListServers) => {
+                let mem = msg.body.memory_message_mut().unwrap();
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(mem) };
+                let request = buffer.to_original::<ServerListing, _>().unwrap();
+                // The table shows which servers still have trusted slots open, which is none
+                // of a third-party app's business.
+                let response = if from_boot_image(msg.sender) {
+                    api::Return::Listing(name_table.listing(request.index))
+                } else {
+                    api::Return::Failure
+                };
+                buffer
+                    .replace(response)
+                    .expect("ListServers can't serialize return value");
+            }
+            Some(api::Opcode::FindNames) => {
+                let mem = msg.body.memory_message_mut().unwrap();
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(mem) };
+                let mut query = buffer.to_original::<NameQuery, _>().unwrap();
+                // Names alone would tell a third-party app which services, and which versions
+                // of them, the device runs.
+                let response = if from_boot_image(msg.sender) {
+                    query.name = name_table
+                        .find(query.prefix.as_str().unwrap_or(""), query.index)
+                        .map(|name| String::<64>::from_str(name.to_str()));
+                    api::Return::Match(query)
+                } else {
+                    api::Return::Failure
+                };
+                buffer
+                    .replace(response)
+                    .expect("FindNames can't serialize return value");
+            }
             Some(api::Opcode::Disconnect) => {
                 let mem = msg.body.memory_message_mut().unwrap();
                 let mut buffer = unsafe { Buffer::from_memory_message_mut(mem) };
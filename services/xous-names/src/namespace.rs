@@ -0,0 +1,205 @@
+//! Hierarchical, versioned server names.
+//!
+//! A name is a path of `/`-separated segments, optionally followed by `@` and a version, for
+//! example `ime/plugin/shell@2.1.0`. Two versions of the same path are simply two names, so
+//! they can be registered side by side.
+//!
+//! A lookup may name a path alone, or a path and a version requirement such as
+//! `ime/plugin/shell@^2`. If no server is registered under exactly that name, it resolves to
+//! the newest registered version of the path that meets the requirement. Names without an
+//! `@`, like every name that predates this scheme, behave exactly as they always have.
+
+use core::cmp::Ordering;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    major: u32,
+    minor: u32,
+    patch: u32,
+}
+
+impl Version {
+    /// Parse `MAJOR[.MINOR[.PATCH]]`, taking missing parts as 0.
+    pub fn parse(s: &str) -> Option<Version> {
+        let mut parts = [0u32; 3];
+        for (count, part) in s.split('.').enumerate() {
+            if count == parts.len() || part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit())
+            {
+                return None;
+            }
+            parts[count] = part.parse().ok()?;
+        }
+        Some(Version {
+            major: parts[0],
+            minor: parts[1],
+            patch: parts[2],
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum Requirement {
+    /// `*`, or no requirement at all
+    Any,
+    /// `=1.2.3`
+    Exact(Version),
+    /// `>=1.2.3`
+    AtLeast(Version),
+    /// `^1.2.3` or just `1.2.3`: at least this version, without a breaking change. As with
+    /// Cargo, the leftmost nonzero part of the version may not change.
+    Compatible(Version),
+}
+
+impl Requirement {
+    pub fn parse(s: &str) -> Option<Requirement> {
+        if s == "*" {
+            Some(Requirement::Any)
+        } else if let Some(v) = s.strip_prefix(">=") {
+            Version::parse(v).map(Requirement::AtLeast)
+        } else if let Some(v) = s.strip_prefix('=') {
+            Version::parse(v).map(Requirement::Exact)
+        } else {
+            Version::parse(s.strip_prefix('^').unwrap_or(s)).map(Requirement::Compatible)
+        }
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        match self {
+            Requirement::Any => true,
+            Requirement::Exact(v) => version == v,
+            Requirement::AtLeast(v) => version >= v,
+            Requirement::Compatible(v) => {
+                let same_series = if v.major != 0 {
+                    version.major == v.major
+                } else if v.minor != 0 {
+                    version.major == 0 && version.minor == v.minor
+                } else {
+                    version.major == 0 && version.minor == 0 && version.patch == v.patch
+                };
+                same_series && version.cmp(v) != Ordering::Less
+            }
+        }
+    }
+}
+
+/// Split a name into its path and the part after the last `@`, if any.
+pub fn split(name: &str) -> (&str, Option<&str>) {
+    match name.rfind('@') {
+        Some(at) => (&name[..at], Some(&name[at + 1..])),
+        None => (name, None),
+    }
+}
+
+/// A name may be registered if it has no version, or a well-formed one.
+pub fn is_registrable(name: &str) -> bool {
+    match split(name) {
+        (path, Some(version)) => !path.is_empty() && Version::parse(version).is_some(),
+        (_, None) => true,
+    }
+}
+
+/// Whether `name` lies at or below `prefix` in the hierarchy. A prefix only matches whole
+/// segments, so `ime/plugin` matches `ime/plugin/shell@2` but not `ime/plugins`, while a
+/// trailing `/` matches only names strictly below it.
+pub fn is_under(name: &str, prefix: &str) -> bool {
+    let (path, _) = split(name);
+    if prefix.is_empty() || prefix.ends_with('/') {
+        path.starts_with(prefix)
+    } else {
+        path == prefix || (path.starts_with(prefix) && path[prefix.len()..].starts_with('/'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(major: u32, minor: u32, patch: u32) -> Version {
+        Version {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    fn matches(requirement: &str, v: &str) -> bool {
+        Requirement::parse(requirement)
+            .unwrap()
+            .matches(&Version::parse(v).unwrap())
+    }
+
+    #[test]
+    fn versions_parse() {
+        assert_eq!(Version::parse("2.1.0"), Some(version(2, 1, 0)));
+        assert_eq!(Version::parse("2.1"), Some(version(2, 1, 0)));
+        assert_eq!(Version::parse("2"), Some(version(2, 0, 0)));
+        assert!(Version::parse("1.10.0") > Version::parse("1.9.0"));
+    }
+
+    #[test]
+    fn malformed_versions_are_refused() {
+        for bad in [
+            "",
+            "1.",
+            ".1",
+            "1..2",
+            "1.2.3.4",
+            "1.x",
+            "-1",
+            "+1",
+            " 1",
+            "99999999999",
+        ]
+        .iter()
+        {
+            assert_eq!(Version::parse(bad), None, "{:?}", bad);
+        }
+        for bad in ["^", ">=", "=", "~1", ">=1.x", "**"].iter() {
+            assert!(Requirement::parse(bad).is_none(), "{:?}", bad);
+        }
+        assert!(!is_registrable("shell@2.x"));
+        assert!(!is_registrable("@2.1"));
+        assert!(is_registrable("shell@2.1"));
+        assert!(is_registrable("shell"));
+    }
+
+    #[test]
+    fn requirements_match() {
+        assert!(matches("*", "0.0.1"));
+        assert!(matches("=1.2.3", "1.2.3"));
+        assert!(!matches("=1.2.3", "1.2.4"));
+        assert!(matches(">=1.2", "3.0.0"));
+        assert!(!matches(">=1.2", "1.1.9"));
+
+        // A bare version is the same as `^`
+        assert!(matches("1.2", "1.9.0"));
+        assert!(matches("^1.2", "1.2.0"));
+        assert!(!matches("^1.2", "1.1.9"));
+        assert!(!matches("^1.2", "2.0.0"));
+
+        // Below 1.0, the leftmost nonzero part is the one that may not change
+        assert!(matches("^0.2.3", "0.2.9"));
+        assert!(!matches("^0.2.3", "0.3.0"));
+        assert!(!matches("^0.2.3", "0.2.2"));
+        assert!(matches("^0.0.3", "0.0.3"));
+        assert!(!matches("^0.0.3", "0.0.4"));
+    }
+
+    #[test]
+    fn prefixes_match_whole_segments() {
+        assert!(is_under("ime/plugin/shell@2", "ime/plugin"));
+        assert!(is_under("ime/plugin", "ime/plugin"));
+        assert!(!is_under("ime/plugins", "ime/plugin"));
+        assert!(!is_under("a/bc", "a/b"));
+        assert!(!is_under("a.bc", "a.b"));
+        assert!(is_under("a.b/c", "a.b"));
+
+        // A trailing `/` only matches what is below it
+        assert!(is_under("ime/plugin/shell", "ime/plugin/"));
+        assert!(!is_under("ime/plugin", "ime/plugin/"));
+
+        // The version isn't part of the path
+        assert!(!is_under("ime@2/x", "ime@2"));
+        assert!(is_under("anything", ""));
+    }
+}
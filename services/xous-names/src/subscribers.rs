@@ -0,0 +1,248 @@
+//! Processes that asked to hear about names coming and going, tracked by the connection to
+//! their callback server.
+
+use crate::api::{NameEvent, XousServerName, MAX_SUBSCRIBERS};
+use std::collections::HashSet;
+
+/// How the name server reaches the subscribers' callback servers
+pub trait Notifier {
+    /// Connect to a callback server. This must not wait for the server to appear, because
+    /// a subscriber that passes a bogus SID would then hang the name server.
+    fn connect(&mut self, sid: xous::SID) -> Result<xous::CID, xous::Error>;
+    /// Send an event to a callback server. For the same reason, this must not wait for room
+    /// in the server's queue; it returns **ServerQueueFull** instead.
+    fn send(
+        &mut self,
+        cid: xous::CID,
+        name: &XousServerName,
+        event: NameEvent,
+    ) -> Result<(), xous::Error>;
+    fn disconnect(&mut self, cid: xous::CID);
+}
+
+pub struct Subscribers<N: Notifier> {
+    notifier: N,
+    conns: Vec<(xous::SID, xous::CID)>,
+    /// Names that were unregistered at some point, so that registering one of them again is
+    /// reported as a new SID rather than a new name
+    released: HashSet<XousServerName>,
+}
+
+impl<N: Notifier> Subscribers<N> {
+    pub fn new(notifier: N) -> Self {
+        Subscribers {
+            notifier,
+            conns: Vec::new(),
+            released: HashSet::new(),
+        }
+    }
+
+    /// Start sending events to the callback server `sid`. Subscribing again is harmless.
+    ///
+    /// # Errors
+    ///
+    /// * **OutOfMemory**: There are already `MAX_SUBSCRIBERS` subscribers
+    /// * Anything that connecting to `sid` fails with, such as **ServerNotFound**
+    pub fn subscribe(&mut self, sid: xous::SID) -> Result<(), xous::Error> {
+        if self.conns.iter().any(|&(subscriber, _)| subscriber == sid) {
+            return Ok(());
+        }
+        if self.conns.len() >= MAX_SUBSCRIBERS {
+            return Err(xous::Error::OutOfMemory);
+        }
+        let cid = self.notifier.connect(sid)?;
+        self.conns.push((sid, cid));
+        Ok(())
+    }
+
+    /// Stop sending events to `sid`, returning `false` if it wasn't subscribed.
+    pub fn unsubscribe(&mut self, sid: xous::SID) -> bool {
+        match self
+            .conns
+            .iter()
+            .position(|&(subscriber, _)| subscriber == sid)
+        {
+            Some(index) => {
+                let (_, cid) = self.conns.remove(index);
+                self.notifier.disconnect(cid);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn registered(&mut self, name: &XousServerName) {
+        if self.released.remove(name) {
+            self.notify(name, NameEvent::SidChanged);
+        } else {
+            self.notify(name, NameEvent::Registered);
+        }
+    }
+
+    pub fn unregistered(&mut self, name: &XousServerName) {
+        self.released.insert(*name);
+        self.notify(name, NameEvent::Unregistered);
+    }
+
+    /// Tell every subscriber about `event`, forgetting the ones that have gone away. A
+    /// subscriber whose queue is full misses the event, but stays subscribed.
+    fn notify(&mut self, name: &XousServerName, event: NameEvent) {
+        let notifier = &mut self.notifier;
+        self.conns
+            .retain(|&(_, cid)| match notifier.send(cid, name, event) {
+                Ok(()) => true,
+                Err(xous::Error::ServerQueueFull) => {
+                    log::warn!(
+                        "subscriber {} is behind, dropping {:?} for {}",
+                        cid,
+                        event,
+                        name
+                    );
+                    true
+                }
+                Err(_) => {
+                    // the subscriber has gone away
+                    notifier.disconnect(cid);
+                    false
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hands out connection IDs in order, and remembers what was sent to each.
+    #[derive(Default)]
+    struct FakeNotifier {
+        missing: Vec<xous::SID>,
+        gone: Vec<xous::CID>,
+        full: Vec<xous::CID>,
+        next_cid: xous::CID,
+        sent: Vec<(xous::CID, String, NameEvent)>,
+        disconnected: Vec<xous::CID>,
+    }
+
+    impl Notifier for FakeNotifier {
+        fn connect(&mut self, sid: xous::SID) -> Result<xous::CID, xous::Error> {
+            if self.missing.contains(&sid) {
+                return Err(xous::Error::ServerNotFound);
+            }
+            self.next_cid += 1;
+            Ok(self.next_cid)
+        }
+        fn send(
+            &mut self,
+            cid: xous::CID,
+            name: &XousServerName,
+            event: NameEvent,
+        ) -> Result<(), xous::Error> {
+            if self.gone.contains(&cid) {
+                return Err(xous::Error::ServerNotFound);
+            }
+            if self.full.contains(&cid) {
+                return Err(xous::Error::ServerQueueFull);
+            }
+            self.sent.push((cid, name.to_str().to_owned(), event));
+            Ok(())
+        }
+        fn disconnect(&mut self, cid: xous::CID) {
+            self.disconnected.push(cid);
+        }
+    }
+
+    fn sid(n: u32) -> xous::SID {
+        xous::SID::from_u32(n, 0, 0, 0)
+    }
+
+    #[test]
+    fn subscribers_hear_about_names() {
+        let mut subscribers = Subscribers::new(FakeNotifier::default());
+        assert_eq!(subscribers.subscribe(sid(1)), Ok(()));
+        assert_eq!(subscribers.subscribe(sid(2)), Ok(()));
+        // subscribing twice doesn't mean hearing everything twice
+        assert_eq!(subscribers.subscribe(sid(1)), Ok(()));
+
+        let name = XousServerName::from_str("gam");
+        subscribers.registered(&name);
+        subscribers.unregistered(&name);
+        subscribers.registered(&name);
+        let events: Vec<_> = subscribers
+            .notifier
+            .sent
+            .iter()
+            .filter(|(cid, ..)| *cid == 1)
+            .map(|(_, name, event)| (name.as_str(), *event))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                ("gam", NameEvent::Registered),
+                ("gam", NameEvent::Unregistered),
+                ("gam", NameEvent::SidChanged),
+            ]
+        );
+        assert_eq!(subscribers.notifier.sent.len(), 6);
+    }
+
+    #[test]
+    fn bad_sids_are_refused() {
+        let mut notifier = FakeNotifier::default();
+        notifier.missing.push(sid(9));
+        let mut subscribers = Subscribers::new(notifier);
+        assert_eq!(
+            subscribers.subscribe(sid(9)),
+            Err(xous::Error::ServerNotFound)
+        );
+        subscribers.registered(&XousServerName::from_str("gam"));
+        assert!(subscribers.notifier.sent.is_empty());
+    }
+
+    #[test]
+    fn the_number_of_subscribers_is_limited() {
+        let mut subscribers = Subscribers::new(FakeNotifier::default());
+        for n in 0..MAX_SUBSCRIBERS as u32 {
+            assert_eq!(subscribers.subscribe(sid(n)), Ok(()));
+        }
+        assert_eq!(
+            subscribers.subscribe(sid(MAX_SUBSCRIBERS as u32)),
+            Err(xous::Error::OutOfMemory)
+        );
+        assert!(subscribers.unsubscribe(sid(0)));
+        assert_eq!(subscribers.subscribe(sid(MAX_SUBSCRIBERS as u32)), Ok(()));
+    }
+
+    #[test]
+    fn unsubscribing_stops_events() {
+        let mut subscribers = Subscribers::new(FakeNotifier::default());
+        subscribers.subscribe(sid(1)).unwrap();
+        assert!(subscribers.unsubscribe(sid(1)));
+        assert!(!subscribers.unsubscribe(sid(1)));
+        assert_eq!(subscribers.notifier.disconnected, vec![1]);
+
+        subscribers.registered(&XousServerName::from_str("gam"));
+        assert!(subscribers.notifier.sent.is_empty());
+    }
+
+    #[test]
+    fn departed_subscribers_are_dropped() {
+        let mut notifier = FakeNotifier::default();
+        notifier.gone.push(1);
+        notifier.full.push(2);
+        let mut subscribers = Subscribers::new(notifier);
+        subscribers.subscribe(sid(1)).unwrap();
+        subscribers.subscribe(sid(2)).unwrap();
+
+        subscribers.registered(&XousServerName::from_str("gam"));
+        assert_eq!(subscribers.notifier.disconnected, vec![1]);
+
+        // a subscriber that was only behind is still there once it catches up
+        subscribers.notifier.full.clear();
+        subscribers.registered(&XousServerName::from_str("com"));
+        assert_eq!(
+            subscribers.notifier.sent,
+            vec![(2, "com".to_owned(), NameEvent::Registered)]
+        );
+    }
+}
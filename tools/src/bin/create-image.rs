@@ -11,12 +11,19 @@ use tools::tags::bflg::Bflg;
 use tools::tags::inie::IniE;
 use tools::tags::memory::{MemoryRegion, MemoryRegions};
 use tools::tags::pnam::ProcessNames;
+use tools::tags::pquo::ProcessQuotas;
 use tools::tags::xkrn::XousKernel;
 use tools::utils::{parse_csr_csv, parse_u32};
 use tools::xous_arguments::XousArguments;
 
 use clap::{App, Arg};
 
+/// RAM at the start of main memory that is set aside for the log server's
+/// persistent log. These must match `PERSISTENT_LOG_BASE` and
+/// `PERSISTENT_LOG_LEN` in the log server, which maps this address directly.
+const PERSISTENT_LOG_BASE: u32 = 0x4000_0000;
+const PERSISTENT_LOG_LEN: u32 = 0x4000;
+
 struct RamConfig {
     offset: u32,
     size: u32,
@@ -87,6 +94,16 @@ fn main() {
                 .number_of_values(1)
                 .help("Initial program to load"),
         )
+        .arg(
+            Arg::with_name("quota")
+                .short("q")
+                .long("quota")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("NAME:BYTES")
+                .help("Limit the RAM that the named initial program may use"),
+        )
         .arg(
             Arg::with_name("csv")
                 .short("c")
@@ -140,6 +157,25 @@ fn main() {
     };
 
     let mut process_names = ProcessNames::new();
+    let mut process_quotas = ProcessQuotas::new();
+    let mut quotas = std::collections::BTreeMap::new();
+    if let Some(quota_args) = matches.values_of("quota") {
+        for quota_arg in quota_args {
+            let quota_parts: Vec<&str> = quota_arg.split(':').collect();
+            if quota_parts.len() != 2 {
+                eprintln!("Error: --quota argument should be of the form [name]:[bytes]");
+                return;
+            }
+            let bytes = match parse_u32(quota_parts[1]) {
+                Ok(o) => o,
+                Err(e) => {
+                    eprintln!("Error: Unable to parse {}: {:?}", quota_parts[1], e);
+                    return;
+                }
+            };
+            quotas.insert(quota_parts[0].to_owned(), ((bytes as u64 + 4095) / 4096) as u32);
+        }
+    }
 
     if let Some(val) = matches.value_of("ram") {
         let ram_parts: Vec<&str> = val.split(':').collect();
@@ -210,6 +246,26 @@ fn main() {
         csr_to_config(tools::utils::CsrConfig { regions: map }, &mut ram_config);
     }
 
+    // Hand the start of RAM over as a region of its own. It's no longer main
+    // memory, so the loader doesn't clear it at boot and the kernel doesn't
+    // clear it when the log server maps it, which is what lets the log
+    // survive a reboot. The log server has the address built in, so refuse
+    // to make an image where the region would end up anywhere else.
+    if ram_config.offset != PERSISTENT_LOG_BASE || ram_config.size <= PERSISTENT_LOG_LEN {
+        eprintln!(
+            "Error: RAM must start at {:08x} and be larger than {:x} bytes to hold the persistent log",
+            PERSISTENT_LOG_BASE, PERSISTENT_LOG_LEN
+        );
+        return;
+    }
+    ram_config.regions.add(MemoryRegion::new(
+        PERSISTENT_LOG_BASE,
+        PERSISTENT_LOG_LEN,
+        MemoryRegion::make_name("plog"),
+    ));
+    ram_config.offset += PERSISTENT_LOG_LEN;
+    ram_config.size -= PERSISTENT_LOG_LEN;
+
     let mut args = XousArguments::new(ram_config.offset, ram_config.size, ram_config.name);
 
     if !ram_config.regions.is_empty() {
@@ -231,15 +287,15 @@ fn main() {
     if let Some(init_paths) = matches.values_of("init") {
         let mut pid = 2;
         for init_path in init_paths {
-            let program_name = std::path::Path::new(init_path);
-            process_names.set(
-                pid,
-                program_name
-                    .file_stem()
-                    .expect("program had no name")
-                    .to_str()
-                    .expect("program name is not valid utf-8"),
-            );
+            let program_name = std::path::Path::new(init_path)
+                .file_stem()
+                .expect("program had no name")
+                .to_str()
+                .expect("program name is not valid utf-8");
+            process_names.set(pid, program_name);
+            if let Some(pages) = quotas.remove(program_name) {
+                process_quotas.set(pid, pages);
+            }
             pid += 1;
             let init = read_minielf(init_path).expect("couldn't parse init file");
             args.add(IniE::new(init.entry_point, init.sections, init.program));
@@ -259,6 +315,13 @@ fn main() {
 
     args.add(process_names);
 
+    for name in quotas.keys() {
+        eprintln!("Warning: --quota given for {}, which is not an initial program", name);
+    }
+    if !process_quotas.is_empty() {
+        args.add(process_quotas);
+    }
+
     // Add tags for init and kernel.  These point to the actual data, which should
     // immediately follow the tags.  Therefore, we must know the length of the tags
     // before we create them.
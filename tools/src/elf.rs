@@ -5,7 +5,8 @@ use std::fs::File;
 use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 use xmas_elf::program::Type as ProgramType;
-use xmas_elf::sections::ShType;
+use xmas_elf::sections::{SectionData, ShType};
+use xmas_elf::symbol_table::{Entry, Entry32, Type as SymbolType};
 use xmas_elf::ElfFile;
 
 // Normal ELF flags
@@ -20,6 +21,14 @@ bitflags! {
     }
 }
 
+/// Where a program's symbol table gets loaded. This is above anything the
+/// kernel hands out, and below the kernel's own mappings.
+pub const SYMBOL_TABLE_BASE: u32 = 0xfe00_0000;
+
+/// A program that defines this static gets a symbol table, and the static is
+/// patched to hold the table's address. See `xous::crash`.
+const SYMBOL_TABLE_POINTER: &str = "XOUS_SYMBOL_TABLE";
+
 pub struct ProgramDescription {
     /// Virtual address of .text section in RAM
     pub text_offset: u32,
@@ -281,6 +290,8 @@ pub fn read_minielf<P: AsRef<Path>>(filename: P) -> Result<MiniElf, ElfReadError
     let mut program_data = Cursor::new(Vec::new());
 
     let mut sections = vec![];
+    // The address, length and program offset of each section that gets copied
+    let mut copied = vec![];
 
     debug!("ELF: {:?}", elf.header);
     for ph in elf.program_iter() {
@@ -346,6 +357,7 @@ pub fn read_minielf<P: AsRef<Path>>(filename: P) -> Result<MiniElf, ElfReadError
 
         // If this section gets copied, add it to the program stream.
         if s.get_type() != Ok(ShType::NoBits) {
+            copied.push((s.address(), section_data.len() as u64, program_offset));
             program_data
                 .seek(SeekFrom::Start(program_offset))
                 .map_err(ElfReadError::FileSeekError)?;
@@ -361,6 +373,35 @@ pub fn read_minielf<P: AsRef<Path>>(filename: P) -> Result<MiniElf, ElfReadError
             flags,
         });
     }
+    // Programs that report their own crashes get a table of their functions
+    // appended, and a pointer to it.
+    if let Some(pointer) = find_symbol(&elf, SYMBOL_TABLE_POINTER)? {
+        let table = encode_symbol_table(&read_functions(&elf)?);
+        debug!("Adding a {} byte symbol table", table.len());
+        let (virt, _, offset) = copied
+            .iter()
+            .find(|(virt, len, _)| pointer >= *virt && pointer + 4 <= virt + len)
+            .ok_or(ElfReadError::SectionRangeError)?;
+        program_data
+            .seek(SeekFrom::Start(offset + pointer - virt))
+            .map_err(ElfReadError::FileSeekError)?;
+        program_data
+            .write(&SYMBOL_TABLE_BASE.to_le_bytes())
+            .map_err(ElfReadError::WriteSectionError)?;
+        program_data
+            .seek(SeekFrom::Start(program_offset))
+            .map_err(ElfReadError::FileSeekError)?;
+        program_data
+            .write(&table)
+            .map_err(ElfReadError::WriteSectionError)?;
+        sections.push(MiniElfSection {
+            virt: SYMBOL_TABLE_BASE,
+            size: table.len() as u32,
+            name: ".xous_symbols".to_string(),
+            flags: MiniElfFlags::NONE,
+        });
+    }
+
     let observed_size = program_data
         .seek(SeekFrom::End(0))
         .map_err(ElfReadError::SeekFromEndError)?;
@@ -372,3 +413,112 @@ pub fn read_minielf<P: AsRef<Path>>(filename: P) -> Result<MiniElf, ElfReadError
         program: program_data.into_inner(),
     })
 }
+
+fn symbols<'a>(elf: &ElfFile<'a>) -> Result<&'a [Entry32], ElfReadError> {
+    let symtab = match elf.find_section_by_name(".symtab") {
+        Some(symtab) => symtab,
+        None => return Ok(&[]),
+    };
+    match symtab.get_data(elf).map_err(ElfReadError::ParseElfError)? {
+        SectionData::SymbolTable32(entries) => Ok(entries),
+        _ => Err(ElfReadError::ParseElfError("symbol table wasn't 32-bit")),
+    }
+}
+
+/// Look up the address of a symbol by name.
+fn find_symbol(elf: &ElfFile, name: &str) -> Result<Option<u64>, ElfReadError> {
+    Ok(symbols(elf)?
+        .iter()
+        .find(|entry| entry.get_name(elf) == Ok(name))
+        .map(|entry| entry.value()))
+}
+
+/// A function in a program, named for humans
+#[derive(Debug, PartialEq)]
+pub struct Function {
+    pub addr: u32,
+    pub size: u32,
+    pub name: String,
+}
+
+/// List the functions in a program by address, with their names demangled.
+pub fn read_functions(elf: &ElfFile) -> Result<Vec<Function>, ElfReadError> {
+    let mut functions = vec![];
+    for entry in symbols(elf)? {
+        if entry.get_type() != Ok(SymbolType::Func) || entry.value() == 0 || entry.size() == 0 {
+            continue;
+        }
+        let name = entry.get_name(elf).map_err(ElfReadError::ParseElfError)?;
+        functions.push(Function {
+            addr: entry.value() as u32,
+            size: entry.size() as u32,
+            // `{:#}` leaves off the hash at the end of each name
+            name: format!("{:#}", rustc_demangle::demangle(name)),
+        });
+    }
+    functions.sort_by_key(|f| f.addr);
+    functions.dedup_by_key(|f| f.addr);
+    Ok(functions)
+}
+
+/// Lay out a symbol table the way `xous::crash` reads it: the magic `XSym`,
+/// the number of entries and the length of the table, then an entry per
+/// function holding its address, its size and the offset and length of its
+/// name, then the names. Every field is a little-endian `u32`.
+pub fn encode_symbol_table(functions: &[Function]) -> Vec<u8> {
+    let names_offset = 12 + functions.len() * 16;
+    let names_len: usize = functions.iter().map(|f| f.name.len()).sum();
+    let mut len = names_offset + names_len;
+    len += (4 - (len & 3)) & 3;
+
+    let mut table = Vec::with_capacity(len);
+    table.extend_from_slice(b"XSym");
+    table.extend_from_slice(&(functions.len() as u32).to_le_bytes());
+    table.extend_from_slice(&(len as u32).to_le_bytes());
+    let mut name_offset = names_offset;
+    for function in functions {
+        table.extend_from_slice(&function.addr.to_le_bytes());
+        table.extend_from_slice(&function.size.to_le_bytes());
+        table.extend_from_slice(&(name_offset as u32).to_le_bytes());
+        table.extend_from_slice(&(function.name.len() as u32).to_le_bytes());
+        name_offset += function.name.len();
+    }
+    for function in functions {
+        table.extend_from_slice(function.name.as_bytes());
+    }
+    table.resize(len, 0);
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(name: &str, addr: u32, size: u32) -> Function {
+        Function {
+            addr,
+            size,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn symbol_table_round_trip() {
+        let table = encode_symbol_table(&[
+            function("first", 0x1000, 0x20),
+            function("second", 0x1020, 0x10),
+            function("third", 0x1100, 0x8),
+        ]);
+        assert_eq!(table.len() % 4, 0);
+        let symbols = xous::crash::SymbolTable::from_bytes(Box::leak(table.into_boxed_slice()))
+            .expect("table wasn't recognised");
+
+        assert_eq!(symbols.lookup(0x1020), Some(("second", 0)));
+        assert_eq!(symbols.lookup(0x1004), Some(("first", 4)));
+        assert_eq!(symbols.lookup(0x1107), Some(("third", 7)));
+        assert_eq!(symbols.lookup(0xfff), None);
+        assert_eq!(symbols.lookup(0x1108), None);
+        // Between the end of one function and the start of the next
+        assert_eq!(symbols.lookup(0x1030), None);
+    }
+}
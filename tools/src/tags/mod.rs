@@ -2,4 +2,5 @@ pub mod bflg;
 pub mod inie;
 pub mod memory;
 pub mod pnam;
+pub mod pquo;
 pub mod xkrn;
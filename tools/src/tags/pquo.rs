@@ -0,0 +1,55 @@
+use crate::xous_arguments::{XousArgument, XousArgumentCode, XousSize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+
+#[derive(Debug, Default)]
+pub struct ProcessQuotas {
+    /// The maximum number of RAM pages each PID may own
+    quotas: BTreeMap<u32, u32>,
+}
+
+impl fmt::Display for ProcessQuotas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "    process quotas:")?;
+        for (pid, pages) in self.quotas.iter() {
+            writeln!(f, "        PID {}: {} pages", pid, pages)?;
+        }
+        Ok(())
+    }
+}
+
+impl ProcessQuotas {
+    pub fn new() -> ProcessQuotas {
+        ProcessQuotas {
+            quotas: BTreeMap::new(),
+        }
+    }
+
+    pub fn set(&mut self, pid: u32, pages: u32) {
+        self.quotas.insert(pid, pages);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quotas.is_empty()
+    }
+}
+
+impl XousArgument for ProcessQuotas {
+    fn code(&self) -> XousArgumentCode {
+        u32::from_le_bytes(*b"PQuo")
+    }
+
+    fn length(&self) -> XousSize {
+        (self.quotas.len() * 8) as XousSize
+    }
+
+    fn serialize(&self, output: &mut dyn io::Write) -> io::Result<usize> {
+        let mut written = 0;
+        for (pid, pages) in self.quotas.iter() {
+            written += output.write(&pid.to_le_bytes())?;
+            written += output.write(&pages.to_le_bytes())?;
+        }
+        Ok(written)
+    }
+}
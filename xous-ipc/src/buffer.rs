@@ -2,10 +2,100 @@ use core::convert::TryInto;
 
 use rkyv::{ser::Serializer, Fallible};
 use xous::{
-    map_memory, send_message, unmap_memory, Error, MemoryAddress, MemoryFlags, MemoryMessage,
-    MemoryRange, MemorySize, Message, Result, CID,
+    map_memory, send_message, try_send_message, unmap_memory, Error, MemoryAddress, MemoryFlags,
+    MemoryMessage, MemoryRange, MemorySize, Message, Result, CID,
 };
 
+/// Every serialized `Buffer` starts with a small header describing what it holds, so that
+/// the receiving side can refuse to read a type other than the one that was written, for
+/// example when a client was built against an older version of a server's `api.rs`.
+///
+/// | Bytes  | Contents                                               |
+/// | ------ | ------------------------------------------------------ |
+/// | 0..4   | `HEADER_MAGIC`, which includes the header format       |
+/// | 4..8   | schema version chosen by the sender, 0 if unspecified  |
+/// | 8..16  | fingerprint of the serialized type                     |
+const HEADER_LEN: usize = 16;
+const HEADER_MAGIC: u32 = 0x5842_0001;
+
+/// Errors that may occur when reading a value back out of a `Buffer`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BufferError {
+    /// The buffer has no header, so it was either never serialized into or was filled in
+    /// some other way.
+    NoHeader,
+    /// The buffer holds a different type, or a different layout of the same type, than the
+    /// one that was asked for.
+    TypeMismatch { expected: u64, found: u64 },
+    /// The archived value would extend beyond the end of the buffer.
+    OutOfBounds,
+}
+
+/// Compute a fingerprint for `T` from its name and the layout of its archived form.
+///
+/// Module paths are left out of the name, because the same `api.rs` is usually compiled
+/// into both a server and its client library under different crate names. A change that
+/// keeps both the name and the archived layout intact, such as swapping two fields of the
+/// same type, can't be seen here; bump the schema version for those instead.
+fn fingerprint<T: rkyv::Archive>() -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    fn hash(mut h: u64, bytes: &[u8]) -> u64 {
+        for b in bytes {
+            h ^= *b as u64;
+            h = h.wrapping_mul(FNV_PRIME);
+        }
+        h
+    }
+
+    let name = core::any::type_name::<T>().as_bytes();
+    let mut h = FNV_OFFSET;
+    // Start of the identifier currently being scanned. It is only hashed once it turns out
+    // not to be a path segment.
+    let mut start = 0;
+    let mut i = 0;
+    while i < name.len() {
+        let c = name[i];
+        if c.is_ascii_alphanumeric() || c == b'_' {
+            i += 1;
+        } else if c == b':' && name.get(i + 1) == Some(&b':') {
+            i += 2;
+            start = i;
+        } else {
+            h = hash(h, &name[start..=i]);
+            i += 1;
+            start = i;
+        }
+    }
+    h = hash(h, &name[start..]);
+    let size = core::mem::size_of::<T::Archived>() as u32;
+    let align = core::mem::align_of::<T::Archived>() as u32;
+    h = hash(h, &size.to_le_bytes());
+    hash(h, &align.to_le_bytes())
+}
+
+/// Serialize `src` into `inner` behind a header. Returns the storage along with the position
+/// of the archived value.
+fn serialize_with_header<T, S>(
+    inner: T,
+    src: &S,
+    schema: u32,
+) -> core::result::Result<(T, usize), ()>
+where
+    T: AsMut<[u8]>,
+    S: rkyv::Serialize<rkyv::ser::serializers::BufferSerializer<T>>,
+{
+    let mut ser = rkyv::ser::serializers::BufferSerializer::new(inner);
+    ser.write(&[0u8; HEADER_LEN]).or(Err(()))?;
+    let pos = ser.serialize_value(src).or(Err(()))?;
+    let mut inner = ser.into_inner();
+    let header = &mut inner.as_mut()[..HEADER_LEN];
+    header[0..4].copy_from_slice(&HEADER_MAGIC.to_le_bytes());
+    header[4..8].copy_from_slice(&schema.to_le_bytes());
+    header[8..16].copy_from_slice(&fingerprint::<S>().to_le_bytes());
+    Ok((inner, pos))
+}
+
 #[derive(Debug)]
 pub struct Buffer<'a> {
     range: MemoryRange,
@@ -178,15 +268,41 @@ impl<'a> Buffer<'a> {
         Ok(result)
     }
 
+    /// Like `send()`, but returns `ServerQueueFull` rather than waiting for room in the
+    /// server's queue. The memory stays with this `Buffer` if the send fails, and is freed
+    /// when it is dropped.
+    #[allow(dead_code)]
+    pub fn try_send(mut self, connection: CID, id: u32) -> core::result::Result<Result, Error> {
+        let msg = MemoryMessage {
+            id: id as usize,
+            buf: self.valid,
+            offset: self.offset,
+            valid: MemorySize::new(self.slice.len()),
+        };
+        let result = try_send_message(connection, Message::Move(msg))?;
+
+        // prevents it from being Dropped.
+        self.should_drop = false;
+        Ok(result)
+    }
+
     #[allow(dead_code)]
     pub fn into_buf<S>(src: S) -> core::result::Result<Self, ()>
     where
         S: rkyv::Serialize<rkyv::ser::serializers::BufferSerializer<Buffer<'a>>>,
     {
-        let buf = Self::new(core::mem::size_of::<S>());
-        let mut ser = rkyv::ser::serializers::BufferSerializer::new(buf);
-        let pos = ser.serialize_value(&src).or(Err(()))?;
-        let mut buf = ser.into_inner();
+        Self::into_buf_with_schema(src, 0)
+    }
+
+    /// Like `into_buf()`, but also record a schema version that the receiver can check with
+    /// `schema()`. Use this when the meaning of a type changes without its layout changing.
+    #[allow(dead_code)]
+    pub fn into_buf_with_schema<S>(src: S, schema: u32) -> core::result::Result<Self, ()>
+    where
+        S: rkyv::Serialize<rkyv::ser::serializers::BufferSerializer<Buffer<'a>>>,
+    {
+        let buf = Self::new(HEADER_LEN + core::mem::size_of::<S>());
+        let (mut buf, pos) = serialize_with_header(buf, &src, schema)?;
         buf.offset = MemoryAddress::new(pos);
         Ok(buf)
     }
@@ -199,19 +315,32 @@ impl<'a> Buffer<'a> {
     {
         let copied_slice =
             unsafe { core::slice::from_raw_parts_mut(self.slice.as_mut_ptr(), self.slice.len()) };
-        // zeroize the slice before using it
-        /*for &mut s in copied_slice {
-            s = 0;
-        }*/
-        let mut ser = rkyv::ser::serializers::BufferSerializer::new(copied_slice);
-        let pos = ser.serialize_value(&src).or(Err(())).unwrap();
-        self.slice = ser.into_inner();
+        // zeroize the slice before using it, so nothing of the previous contents is left
+        // behind in the padding
+        for b in copied_slice.iter_mut() {
+            *b = 0;
+        }
+        let (slice, pos) = serialize_with_header(copied_slice, &src, 0).unwrap();
+        self.slice = slice;
         self.offset = MemoryAddress::new(pos);
         Ok(())
     }
 
     #[allow(dead_code)]
     pub fn replace<S>(&mut self, src: S) -> core::result::Result<(), &'static str>
+    where
+        S: rkyv::Serialize<rkyv::ser::serializers::BufferSerializer<&'a mut [u8]>>,
+    {
+        self.replace_with_schema(src, 0)
+    }
+
+    /// Like `replace()`, but also record a schema version. See `into_buf_with_schema()`.
+    #[allow(dead_code)]
+    pub fn replace_with_schema<S>(
+        &mut self,
+        src: S,
+        schema: u32,
+    ) -> core::result::Result<(), &'static str>
     where
         S: rkyv::Serialize<rkyv::ser::serializers::BufferSerializer<&'a mut [u8]>>,
     {
@@ -229,8 +358,12 @@ impl<'a> Buffer<'a> {
         // for ourselves.
         let copied_slice =
             unsafe { core::slice::from_raw_parts_mut(self.slice.as_mut_ptr(), self.slice.len()) };
-        let mut ser = rkyv::ser::serializers::BufferSerializer::new(copied_slice);
-        let pos = ser.serialize_value(&src).or(Err("couldn't serialize"))?;
+        // As with `rewrite()`, don't let the request linger around the reply.
+        for b in copied_slice.iter_mut() {
+            *b = 0;
+        }
+        let (_, pos) =
+            serialize_with_header(copied_slice, &src, schema).or(Err("couldn't serialize"))?;
         self.offset = MemoryAddress::new(pos);
         if let Some(ref mut msg) = self.memory_message.as_mut() {
             msg.offset = MemoryAddress::new(pos);
@@ -238,25 +371,58 @@ impl<'a> Buffer<'a> {
         Ok(())
     }
 
+    /// The schema version the sender recorded, or 0 if it didn't specify one.
+    #[allow(dead_code)]
+    pub fn schema(&self) -> core::result::Result<u32, BufferError> {
+        self.header().map(|(schema, _)| schema)
+    }
+
+    fn header(&self) -> core::result::Result<(u32, u64), BufferError> {
+        if self.slice.len() < HEADER_LEN {
+            return Err(BufferError::NoHeader);
+        }
+        let magic = u32::from_le_bytes(self.slice[0..4].try_into().unwrap());
+        if magic != HEADER_MAGIC {
+            return Err(BufferError::NoHeader);
+        }
+        let schema = u32::from_le_bytes(self.slice[4..8].try_into().unwrap());
+        let fingerprint = u64::from_le_bytes(self.slice[8..16].try_into().unwrap());
+        Ok((schema, fingerprint))
+    }
+
+    /// Make sure the buffer holds a `T`, and return the position of the archived value.
+    fn check<T: rkyv::Archive>(&self) -> core::result::Result<usize, BufferError> {
+        let (_, found) = self.header()?;
+        let expected = fingerprint::<T>();
+        if found != expected {
+            return Err(BufferError::TypeMismatch { expected, found });
+        }
+        let pos = self.offset.map(|o| o.get()).unwrap_or_default();
+        if pos < HEADER_LEN || pos + core::mem::size_of::<T::Archived>() > self.slice.len() {
+            return Err(BufferError::OutOfBounds);
+        }
+        Ok(pos)
+    }
+
     /// Zero-copy representation of the data on the receiving side, wrapped in an "Archived" trait and left in the heap. Cheap so uses "as_" prefix.
     #[allow(dead_code)]
-    pub fn as_flat<T, U>(&self) -> core::result::Result<&U, ()>
+    pub fn as_flat<T, U>(&self) -> core::result::Result<&U, BufferError>
     where
         T: rkyv::Archive<Archived = U>,
     {
-        let pos = self.offset.map(|o| o.get()).unwrap_or_default();
+        let pos = self.check::<T>()?;
         let r = unsafe { rkyv::archived_value::<T>(self.slice, pos) };
         Ok(r)
     }
 
     /// A representation identical to the original, but reequires copying to the stack. More expensive so uses "to_" prefix.
     #[allow(dead_code)]
-    pub fn to_original<T, U>(&self) -> core::result::Result<T, ()>
+    pub fn to_original<T, U>(&self) -> core::result::Result<T, BufferError>
     where
         T: rkyv::Archive<Archived = U>,
         U: rkyv::Deserialize<T, dyn Fallible<Error = XousUnreachable>>,
     {
-        let pos = self.offset.map(|o| o.get()).unwrap_or_default();
+        let pos = self.check::<T>()?;
         let r = unsafe { rkyv::archived_value::<T>(self.slice, pos) };
         Ok(r.deserialize(&mut XousDeserializer {}).unwrap())
     }
@@ -295,3 +461,142 @@ impl<'a> Drop for Buffer<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two revisions of the same message, as a server and an out-of-date client might see it.
+    mod v1 {
+        #[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, PartialEq)]
+        pub struct Settings {
+            pub volume: u32,
+        }
+    }
+    mod v2 {
+        #[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, PartialEq)]
+        pub struct Settings {
+            pub volume: u32,
+            pub balance: i32,
+        }
+    }
+    // The same revision compiled into a different crate
+    mod client {
+        #[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Debug, PartialEq)]
+        pub struct Settings {
+            pub volume: u32,
+            pub balance: i32,
+        }
+    }
+
+    #[repr(C, align(4096))]
+    struct Page([u8; 4096]);
+
+    fn buffer(page: &mut Page) -> Buffer {
+        unsafe { Buffer::from_raw_parts(page.0.as_mut_ptr() as usize, page.0.len(), 0) }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut page = Page([0; 4096]);
+        let mut buf = buffer(&mut page);
+        buf.rewrite(v2::Settings {
+            volume: 3,
+            balance: -1,
+        })
+        .unwrap();
+        assert_eq!(buf.schema(), Ok(0));
+        assert_eq!(
+            buf.to_original::<v2::Settings, _>(),
+            Ok(v2::Settings {
+                volume: 3,
+                balance: -1
+            })
+        );
+        let flat = buf.as_flat::<v2::Settings, _>().unwrap();
+        assert_eq!((flat.volume, flat.balance), (3, -1));
+    }
+
+    #[test]
+    fn module_path_is_ignored() {
+        let mut page = Page([0; 4096]);
+        let mut buf = buffer(&mut page);
+        buf.rewrite(v2::Settings {
+            volume: 3,
+            balance: -1,
+        })
+        .unwrap();
+        assert_eq!(
+            buf.to_original::<client::Settings, _>(),
+            Ok(client::Settings {
+                volume: 3,
+                balance: -1
+            })
+        );
+    }
+
+    #[test]
+    fn old_reader_rejects_new_layout() {
+        let mut page = Page([0; 4096]);
+        let mut buf = buffer(&mut page);
+        buf.rewrite(v2::Settings {
+            volume: 3,
+            balance: -1,
+        })
+        .unwrap();
+        let expected = fingerprint::<v1::Settings>();
+        let found = fingerprint::<v2::Settings>();
+        assert_ne!(expected, found);
+        assert_eq!(
+            buf.to_original::<v1::Settings, _>(),
+            Err(BufferError::TypeMismatch { expected, found })
+        );
+        assert!(buf.as_flat::<v1::Settings, _>().is_err());
+    }
+
+    #[test]
+    fn new_reader_rejects_old_layout() {
+        let mut page = Page([0; 4096]);
+        let mut buf = buffer(&mut page);
+        buf.rewrite(v1::Settings { volume: 3 }).unwrap();
+        assert!(matches!(
+            buf.to_original::<v2::Settings, _>(),
+            Err(BufferError::TypeMismatch { .. })
+        ));
+        assert!(buf.as_flat::<v2::Settings, _>().is_err());
+    }
+
+    #[test]
+    fn replace_records_schema() {
+        let mut page = Page([0; 4096]);
+        let range =
+            unsafe { MemoryRange::new(page.0.as_mut_ptr() as usize, page.0.len()).unwrap() };
+        let mut msg = MemoryMessage {
+            id: 0,
+            buf: range,
+            offset: None,
+            valid: None,
+        };
+        let mut buf = unsafe { Buffer::from_memory_message_mut(&mut msg) };
+        buf.replace_with_schema(v1::Settings { volume: 7 }, 2)
+            .unwrap();
+        assert_eq!(buf.schema(), Ok(2));
+        assert_eq!(
+            buf.to_original::<v1::Settings, _>(),
+            Ok(v1::Settings { volume: 7 })
+        );
+        drop(buf);
+        assert!(msg.offset.is_some());
+    }
+
+    #[test]
+    fn missing_header() {
+        let mut page = Page([0; 4096]);
+        let buf = buffer(&mut page);
+        assert_eq!(buf.schema(), Err(BufferError::NoHeader));
+        assert_eq!(
+            buf.to_original::<v1::Settings, _>(),
+            Err(BufferError::NoHeader)
+        );
+    }
+}
@@ -0,0 +1,446 @@
+//! Payloads that don't fit in a single message.
+//!
+//! A `Buffer` is a fixed-size lend, so callers have historically picked a fixed capacity such
+//! as `String<4000>` and lost anything beyond it. A `ChunkedBuffer` grows like a `Vec<u8>`
+//! and is sent as a series of page-sized chunks, all under the same message ID. The server
+//! feeds every such message to a `Reassembler`, which hands back the whole payload once the
+//! last chunk has arrived.
+//!
+//! Chunks from a single sender arrive in order, so reassembly only has to keep the partial
+//! payloads of concurrent senders apart.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use std::collections::VecDeque;
+
+use xous::{Error, MessageEnvelope, CID, PID};
+
+use crate::Buffer;
+
+/// Payload bytes carried per chunk, which leaves room for the headers within one page
+pub const CHUNK_LEN: usize = 4000;
+
+/// Partial payloads a `Reassembler` keeps before it gives up on the oldest
+const MAX_PENDING: usize = 8;
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+struct Chunk {
+    /// identifies the payload among those in flight from the same process
+    transfer: u32,
+    /// length of the whole payload
+    total: u32,
+    /// where `data` goes in the payload
+    offset: u32,
+    /// how much of `data` is used
+    len: u32,
+    data: [u8; CHUNK_LEN],
+}
+
+static NEXT_TRANSFER: AtomicU32 = AtomicU32::new(0);
+
+/// A growable byte buffer that can be sent to a server in more than one message. It
+/// dereferences to a `Vec<u8>`, and text may be added with `write!`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChunkedBuffer {
+    data: Vec<u8>,
+}
+
+impl ChunkedBuffer {
+    pub fn new() -> Self {
+        ChunkedBuffer { data: Vec::new() }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        ChunkedBuffer {
+            data: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+
+    fn chunks(&self) -> Result<impl Iterator<Item = Chunk> + '_, Error> {
+        if self.data.len() > u32::MAX as usize {
+            return Err(Error::OutOfMemory);
+        }
+        let transfer = NEXT_TRANSFER.fetch_add(1, Ordering::Relaxed);
+        let total = self.data.len() as u32;
+        // An empty payload is still sent as one empty chunk, so that the server sees it.
+        let count = core::cmp::max(1, (self.data.len() + CHUNK_LEN - 1) / CHUNK_LEN);
+        Ok((0..count).map(move |i| {
+            let start = i * CHUNK_LEN;
+            let piece = &self.data[start..core::cmp::min(start + CHUNK_LEN, self.data.len())];
+            let mut chunk = Chunk {
+                transfer,
+                total,
+                offset: start as u32,
+                len: piece.len() as u32,
+                data: [0; CHUNK_LEN],
+            };
+            chunk.data[..piece.len()].copy_from_slice(piece);
+            chunk
+        }))
+    }
+
+    /// Lend the contents to `connection`, one chunk at a time. Each chunk blocks until the
+    /// server is done with it.
+    pub fn lend(&self, connection: CID, id: u32) -> Result<(), Error> {
+        let mut chunks = self.chunks()?;
+        // Every chunk is the same size, so one page can be reused for all of them.
+        let mut buf = Buffer::into_buf(chunks.next().unwrap()).or(Err(Error::InternalError))?;
+        buf.lend(connection, id)?;
+        for chunk in chunks {
+            buf.rewrite(chunk)?;
+            buf.lend(connection, id)?;
+        }
+        Ok(())
+    }
+
+    /// Move the contents to `connection` without waiting for the server.
+    pub fn send(&self, connection: CID, id: u32) -> Result<(), Error> {
+        for chunk in self.chunks()? {
+            let buf = Buffer::into_buf(chunk).or(Err(Error::InternalError))?;
+            buf.send(connection, id)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::ops::Deref for ChunkedBuffer {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl core::ops::DerefMut for ChunkedBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+impl From<Vec<u8>> for ChunkedBuffer {
+    fn from(data: Vec<u8>) -> Self {
+        ChunkedBuffer { data }
+    }
+}
+
+impl From<&[u8]> for ChunkedBuffer {
+    fn from(data: &[u8]) -> Self {
+        ChunkedBuffer {
+            data: data.to_vec(),
+        }
+    }
+}
+
+impl From<&str> for ChunkedBuffer {
+    fn from(s: &str) -> Self {
+        ChunkedBuffer::from(s.as_bytes())
+    }
+}
+
+impl core::fmt::Write for ChunkedBuffer {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.data.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+struct Pending {
+    sender: Option<PID>,
+    transfer: u32,
+    total: usize,
+    data: Vec<u8>,
+}
+
+/// Puts payloads sent with a `ChunkedBuffer` back together on the server side.
+pub struct Reassembler {
+    max_len: usize,
+    pending: Vec<Pending>,
+    /// payloads given up on to make room for newer ones, whose later chunks are refused
+    dropped: VecDeque<(Option<PID>, u32)>,
+}
+
+impl Reassembler {
+    /// Create a reassembler that refuses payloads longer than `max_len` bytes.
+    pub fn new(max_len: usize) -> Self {
+        Reassembler {
+            max_len,
+            pending: Vec::new(),
+            dropped: VecDeque::new(),
+        }
+    }
+
+    /// Take in one chunk. Returns the complete payload once its last chunk has arrived, and
+    /// `None` while more are still to come.
+    ///
+    /// A chunk that arrives twice is ignored. Otherwise, once a chunk has been refused, every
+    /// later chunk of its payload is refused as well.
+    ///
+    /// # Errors
+    ///
+    /// * **OutOfMemory**: The payload is longer than this reassembler accepts
+    /// * **QuotaExceeded**: Too many payloads were in flight at once, and this one was given
+    ///   up on to make room for a newer one
+    /// * **InternalError**: The message isn't a chunk, the chunk arrived out of order, or it
+    ///   disagrees with the other chunks of its payload about where it fits
+    pub fn receive(&mut self, envelope: &MessageEnvelope) -> Result<Option<Vec<u8>>, Error> {
+        let mem = envelope.body.memory_message().ok_or(Error::InternalError)?;
+        let buffer = unsafe { Buffer::from_memory_message(mem) };
+        let chunk = buffer.as_flat::<Chunk, _>().or(Err(Error::InternalError))?;
+        let sender = envelope.sender.pid();
+        let len = chunk.len as usize;
+        if len > CHUNK_LEN {
+            self.forget(sender, chunk.transfer);
+            return Err(Error::InternalError);
+        }
+        self.add(
+            sender,
+            chunk.transfer,
+            chunk.total as usize,
+            chunk.offset as usize,
+            &chunk.data[..len],
+        )
+    }
+
+    /// Add `data`, which belongs at `offset` in a payload of `total` bytes.
+    fn add(
+        &mut self,
+        sender: Option<PID>,
+        transfer: u32,
+        total: usize,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<Option<Vec<u8>>, Error> {
+        if total > self.max_len {
+            self.forget(sender, transfer);
+            return Err(Error::OutOfMemory);
+        }
+        if offset > total || data.len() > total - offset {
+            self.forget(sender, transfer);
+            return Err(Error::InternalError);
+        }
+
+        let index = match self
+            .pending
+            .iter()
+            .position(|p| p.sender == sender && p.transfer == transfer)
+        {
+            Some(index) => {
+                let received = self.pending[index].data.len();
+                if self.pending[index].total != total {
+                    self.pending.remove(index);
+                    return Err(Error::InternalError);
+                }
+                if offset + data.len() <= received {
+                    return Ok(None);
+                }
+                if offset != received {
+                    self.pending.remove(index);
+                    return Err(Error::InternalError);
+                }
+                index
+            }
+            None if offset == 0 => {
+                if self.pending.len() >= MAX_PENDING {
+                    let oldest = self.pending.remove(0);
+                    if self.dropped.len() >= MAX_PENDING {
+                        self.dropped.pop_front();
+                    }
+                    self.dropped.push_back((oldest.sender, oldest.transfer));
+                }
+                self.pending.push(Pending {
+                    sender,
+                    transfer,
+                    total,
+                    data: Vec::with_capacity(total),
+                });
+                self.pending.len() - 1
+            }
+            None if self.dropped.contains(&(sender, transfer)) => return Err(Error::QuotaExceeded),
+            None => return Err(Error::InternalError),
+        };
+
+        self.pending[index].data.extend_from_slice(data);
+        if offset + data.len() == total {
+            Ok(Some(self.pending.remove(index).data))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn forget(&mut self, sender: Option<PID>, transfer: u32) {
+        self.pending
+            .retain(|p| !(p.sender == sender && p.transfer == transfer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(len: usize) -> ChunkedBuffer {
+        (0..len).map(|i| i as u8).collect::<Vec<u8>>().into()
+    }
+
+    /// Feed `chunks` to `reassembler` as if `sender` had sent them, returning each result.
+    fn feed<'a>(
+        reassembler: &mut Reassembler,
+        sender: Option<PID>,
+        chunks: impl IntoIterator<Item = &'a Chunk>,
+    ) -> Vec<Result<Option<Vec<u8>>, Error>> {
+        chunks
+            .into_iter()
+            .map(|c| {
+                reassembler.add(
+                    sender,
+                    c.transfer,
+                    c.total as usize,
+                    c.offset as usize,
+                    &c.data[..c.len as usize],
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut reassembler = Reassembler::new(16384);
+        let sent = payload(10_000);
+        let chunks: Vec<Chunk> = sent.chunks().unwrap().collect();
+        assert_eq!(chunks.len(), 3);
+        let results = feed(&mut reassembler, None, &chunks);
+        assert_eq!(results[..2], [Ok(None), Ok(None)]);
+        assert_eq!(results[2], Ok(Some(sent.into_vec())));
+
+        let empty: Vec<Chunk> = ChunkedBuffer::new().chunks().unwrap().collect();
+        assert_eq!(feed(&mut reassembler, None, &empty), [Ok(Some(Vec::new()))]);
+    }
+
+    #[test]
+    fn senders_are_kept_apart() {
+        let mut reassembler = Reassembler::new(16384);
+        let first: Vec<Chunk> = payload(5000).chunks().unwrap().collect();
+        let mut second: Vec<Chunk> = payload(6000).chunks().unwrap().collect();
+        // the same transfer number from another process is a different payload
+        for chunk in second.iter_mut() {
+            chunk.transfer = first[0].transfer;
+        }
+        let a = PID::new(2);
+        let b = PID::new(3);
+        assert_eq!(feed(&mut reassembler, a, &first[..1]), [Ok(None)]);
+        assert_eq!(feed(&mut reassembler, b, &second[..1]), [Ok(None)]);
+        assert_eq!(
+            feed(&mut reassembler, a, &first[1..]),
+            [Ok(Some(payload(5000).into_vec()))]
+        );
+        assert_eq!(
+            feed(&mut reassembler, b, &second[1..]),
+            [Ok(Some(payload(6000).into_vec()))]
+        );
+    }
+
+    #[test]
+    fn out_of_order_chunks_are_refused() {
+        let mut reassembler = Reassembler::new(16384);
+        let chunks: Vec<Chunk> = payload(10_000).chunks().unwrap().collect();
+        assert_eq!(
+            feed(&mut reassembler, None, [&chunks[0], &chunks[2], &chunks[1]]),
+            [
+                Ok(None),
+                Err(Error::InternalError),
+                Err(Error::InternalError)
+            ]
+        );
+        // a payload whose start never arrived can't be started partway through
+        let chunks: Vec<Chunk> = payload(10_000).chunks().unwrap().collect();
+        assert_eq!(
+            feed(&mut reassembler, None, &chunks[1..]),
+            [Err(Error::InternalError), Err(Error::InternalError)]
+        );
+    }
+
+    #[test]
+    fn duplicate_chunks_are_ignored() {
+        let mut reassembler = Reassembler::new(16384);
+        let sent = payload(10_000);
+        let chunks: Vec<Chunk> = sent.chunks().unwrap().collect();
+        let order = [
+            &chunks[0], &chunks[0], &chunks[1], &chunks[0], &chunks[1], &chunks[2],
+        ];
+        assert_eq!(
+            feed(&mut reassembler, None, order),
+            [
+                Ok(None),
+                Ok(None),
+                Ok(None),
+                Ok(None),
+                Ok(None),
+                Ok(Some(sent.into_vec()))
+            ]
+        );
+    }
+
+    #[test]
+    fn chunks_must_agree_on_the_total() {
+        let mut reassembler = Reassembler::new(16384);
+        let mut chunks: Vec<Chunk> = payload(10_000).chunks().unwrap().collect();
+        // claiming the payload ends early would otherwise hand back a truncated one
+        chunks[1].total = chunks[1].offset + chunks[1].len;
+        assert_eq!(
+            feed(&mut reassembler, None, &chunks),
+            [
+                Ok(None),
+                Err(Error::InternalError),
+                Err(Error::InternalError)
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_payloads_are_refused() {
+        let mut reassembler = Reassembler::new(5000);
+        let chunks: Vec<Chunk> = payload(5001).chunks().unwrap().collect();
+        assert_eq!(
+            feed(&mut reassembler, None, &chunks),
+            [Err(Error::OutOfMemory), Err(Error::OutOfMemory)]
+        );
+        let chunks: Vec<Chunk> = payload(5000).chunks().unwrap().collect();
+        assert_eq!(
+            feed(&mut reassembler, None, &chunks),
+            [Ok(None), Ok(Some(payload(5000).into_vec()))]
+        );
+
+        // chunks that run past the end of their own payload
+        assert_eq!(
+            reassembler.add(None, 7, 100, 0, &[0; 200]),
+            Err(Error::InternalError)
+        );
+        assert_eq!(
+            reassembler.add(None, 8, 100, 200, &[]),
+            Err(Error::InternalError)
+        );
+    }
+
+    #[test]
+    fn too_many_pending_payloads_drop_the_oldest() {
+        let mut reassembler = Reassembler::new(16384);
+        let payloads: Vec<Vec<Chunk>> = (0..=MAX_PENDING)
+            .map(|_| payload(5000).chunks().unwrap().collect())
+            .collect();
+        for chunks in payloads.iter() {
+            assert_eq!(feed(&mut reassembler, None, &chunks[..1]), [Ok(None)]);
+        }
+        assert_eq!(
+            feed(&mut reassembler, None, &payloads[0][1..]),
+            [Err(Error::QuotaExceeded)]
+        );
+        for chunks in payloads[1..].iter() {
+            assert_eq!(
+                feed(&mut reassembler, None, &chunks[1..]),
+                [Ok(Some(payload(5000).into_vec()))]
+            );
+        }
+    }
+}
@@ -6,3 +6,11 @@ pub use buffer::*;
 
 mod string;
 pub use string::*;
+
+mod zeroize;
+pub use zeroize::*;
+
+#[cfg(not(target_os = "none"))]
+mod chunked;
+#[cfg(not(target_os = "none"))]
+pub use chunked::*;
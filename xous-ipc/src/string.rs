@@ -132,39 +132,17 @@ impl<const N: usize> String<N> {
         unsafe { core::str::from_utf8_unchecked(&self.bytes[0..self.len()]) }
     }
 
-    /// awful, O(N) implementation because we have to iterate through the entire string
-    /// and decode variable-length utf8 characters, until we can't.
+    /// Remove the last character and return it. The bytes it occupied are zeroed, so no copy
+    /// of it is left behind.
     pub fn pop(&mut self) -> Option<char> {
-        if self.is_empty() {
-            return None;
-        }
-        // first, make a copy of the string
-        let tempbytes: [u8; N] = self.bytes;
-        let tempstr = &(*unsafe { core::str::from_utf8_unchecked(&tempbytes[0..self.len()]) });
-        // clear our own string
-        self.len = 0;
-        self.bytes = [0; N];
-
-        // now copy over character by character, until just before the last character
-        let mut char_iter = tempstr.chars();
-        let mut maybe_c = char_iter.next();
-        loop {
-            match maybe_c {
-                Some(c) => {
-                    let next_c = char_iter.next();
-                    match next_c {
-                        Some(_thing) => {
-                            self.push(c).unwrap(); // always succeeds because we're re-encoding our string
-                            maybe_c = next_c;
-                        }
-                        None => return next_c,
-                    }
-                }
-                None => {
-                    return None; // we should actually never get here because len() == 0 case already covered
-                }
-            }
+        let c = self.to_str().chars().next_back()?;
+        let len = self.len();
+        let new_len = len - c.len_utf8();
+        for b in self.bytes[new_len..len].iter_mut() {
+            *b = 0;
         }
+        self.len = new_len as u32;
+        Some(c)
     }
 
     pub fn push(&mut self, ch: char) -> core::result::Result<usize, Error> {
@@ -329,3 +307,31 @@ pub struct XousDeserializer;
 impl rkyv::Fallible for XousDeserializer {
     type Error = xous::Error;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pop_takes_whole_characters() {
+        let mut s = String::<16>::from_str("aé€");
+        assert_eq!(s.len(), 6);
+
+        assert_eq!(s.pop(), Some('€'));
+        assert_eq!(s.to_str(), "aé");
+        assert_eq!(s.pop(), Some('é'));
+        assert_eq!(s.to_str(), "a");
+        assert_eq!(s.pop(), Some('a'));
+        assert!(s.is_empty());
+
+        // Nothing of what was popped is left behind
+        assert_eq!(s.as_bytes(), [0; 16]);
+    }
+
+    #[test]
+    fn pop_from_empty() {
+        let mut s = String::<4>::new();
+        assert_eq!(s.pop(), None);
+        assert!(s.is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+//! Wiping secrets when they go out of scope.
+//!
+//! `String` is `Copy` and `Buffer` may point at memory that belongs to another process, so
+//! neither wipes itself on drop. Wrapping one in `Zeroizing` opts in: the contents are
+//! overwritten with zeroes, in a way the compiler can't optimize out, when the wrapper is
+//! dropped. A `Buffer` built from a message is dropped before its `MessageEnvelope`, so lent
+//! memory is wiped before it is returned to the lender.
+//!
+//! Don't wrap a `Buffer` that carries a reply back to the lender, as the reply would be wiped
+//! along with everything else. Memory that was lent immutably can't be wiped by the borrower;
+//! the lender has to wrap its own `Buffer` instead.
+
+use core::ops::{Deref, DerefMut};
+
+use crate::{Buffer, String};
+
+/// Something whose contents can be overwritten with zeroes, e.g. because it holds a password.
+pub trait VolatileClear {
+    fn volatile_clear(&mut self);
+}
+
+impl<const N: usize> VolatileClear for String<N> {
+    fn volatile_clear(&mut self) {
+        String::volatile_clear(self)
+    }
+}
+
+impl<'a> VolatileClear for Buffer<'a> {
+    fn volatile_clear(&mut self) {
+        Buffer::volatile_clear(self)
+    }
+}
+
+/// Owns a `T` and wipes it when dropped.
+#[derive(Debug)]
+pub struct Zeroizing<T: VolatileClear>(T);
+
+impl<T: VolatileClear> Zeroizing<T> {
+    pub fn new(value: T) -> Self {
+        Zeroizing(value)
+    }
+}
+
+impl<T: VolatileClear> Deref for Zeroizing<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: VolatileClear> DerefMut for Zeroizing<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: VolatileClear> Drop for Zeroizing<T> {
+    fn drop(&mut self) {
+        self.0.volatile_clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    /// Counts how often it is cleared
+    struct Tally<'a>(&'a Cell<usize>);
+
+    impl VolatileClear for Tally<'_> {
+        fn volatile_clear(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn cleared_once_on_drop() {
+        let clears = Cell::new(0);
+        let tally = Zeroizing::new(Tally(&clears));
+        assert_eq!(clears.get(), 0);
+        drop(tally);
+        assert_eq!(clears.get(), 1);
+    }
+
+    #[test]
+    fn dropping_wipes_a_string() {
+        let mut slot =
+            core::mem::MaybeUninit::new(Zeroizing::new(String::<16>::from_str("hunter2")));
+        // `String` is `Copy`, so it's still there to look at once the wrapper has been dropped.
+        let wiped = unsafe {
+            core::ptr::drop_in_place(slot.as_mut_ptr());
+            (*slot.as_ptr()).0
+        };
+        assert!(wiped.is_empty());
+        assert_eq!(wiped.as_bytes(), [0; 16]);
+    }
+}
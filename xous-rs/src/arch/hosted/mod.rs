@@ -120,6 +120,9 @@ impl ProcessKey {
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct ProcessInit {
     pub key: ProcessKey,
+
+    /// The most RAM pages this process may own, or 0 for no limit
+    pub page_quota: usize,
 }
 
 pub struct ProcessArgs {
@@ -217,7 +220,7 @@ pub fn process_to_args(call: usize, init: &ProcessInit) -> [usize; 8] {
         u32::from_le_bytes(init.key.0[4..8].try_into().unwrap()) as _,
         u32::from_le_bytes(init.key.0[8..12].try_into().unwrap()) as _,
         u32::from_le_bytes(init.key.0[12..16].try_into().unwrap()) as _,
-        0,
+        init.page_quota,
         0,
         0,
     ]
@@ -228,7 +231,7 @@ pub fn args_to_process(
     a2: usize,
     a3: usize,
     a4: usize,
-    _a5: usize,
+    a5: usize,
     _a6: usize,
     _a7: usize,
 ) -> core::result::Result<ProcessInit, crate::Error> {
@@ -241,6 +244,7 @@ pub fn args_to_process(
     key.copy_from_slice(&v);
     Ok(ProcessInit {
         key: ProcessKey(key),
+        page_quota: a5,
     })
 }
 
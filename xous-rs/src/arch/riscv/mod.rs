@@ -70,6 +70,9 @@ impl ProcessKey {
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct ProcessInit {
     pub key: ProcessKey,
+
+    /// The most RAM pages this process may own, or 0 for no limit
+    pub page_quota: usize,
 }
 
 pub struct WaitHandle<T> {
@@ -145,7 +148,7 @@ pub fn process_to_args(call: usize, init: &ProcessInit) -> [usize; 8] {
         call,
         u32::from_le_bytes(init.key.0[0..4].try_into().unwrap()) as _,
         u32::from_le_bytes(init.key.0[4..8].try_into().unwrap()) as _,
-        0,
+        init.page_quota,
         0,
         0,
         0,
@@ -355,7 +358,9 @@ pub fn create_thread_n_pre(
     #[cfg(not(feature = "bit-flags"))]
     let flags = 0b0000_0010 | 0b0000_0100 | 0b0000_0001;
 
-    let stack = crate::map_memory(None, None, 131_072, flags)?;
+    // The kernel turns the lowest page into a guard page, so add one on top
+    // of the usable stack.
+    let stack = crate::map_memory(None, None, 131_072 + 4096, flags)?;
     Ok(ThreadInit::new(start, stack, *arg1, *arg2, *arg3, *arg4))
 }
 
@@ -26,6 +26,9 @@ pub struct ThreadInit {}
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct ProcessInit {
     pub key: ProcessKey,
+
+    /// The most RAM pages this process may own, or 0 for no limit
+    pub page_quota: usize,
 }
 
 pub struct ProcessArgsAsThread<F: FnOnce()> {
@@ -63,6 +66,7 @@ where
         key: PROCESS_KEY
             .with(|pk| *pk.borrow())
             .unwrap_or_else(default_process_key),
+        page_quota: 0,
     })
 }
 
@@ -140,6 +144,7 @@ pub fn create_process_pre(_args: &ProcessArgs) -> core::result::Result<ProcessIn
         key: PROCESS_KEY
             .with(|pk| *pk.borrow())
             .unwrap_or_else(default_process_key),
+        page_quota: 0,
     })
 }
 
@@ -210,7 +215,7 @@ pub fn process_to_args(call: usize, init: &ProcessInit) -> [usize; 8] {
         u32::from_le_bytes(init.key.0[4..8].try_into().unwrap()) as _,
         u32::from_le_bytes(init.key.0[8..12].try_into().unwrap()) as _,
         u32::from_le_bytes(init.key.0[12..16].try_into().unwrap()) as _,
-        0,
+        init.page_quota,
         0,
         0,
     ]
@@ -233,7 +238,7 @@ pub fn args_to_process(
     a2: usize,
     a3: usize,
     a4: usize,
-    _a5: usize,
+    a5: usize,
     _a6: usize,
     _a7: usize,
 ) -> core::result::Result<ProcessInit, crate::Error> {
@@ -246,6 +251,7 @@ pub fn args_to_process(
     key.copy_from_slice(&v);
     Ok(ProcessInit {
         key: ProcessKey(key),
+        page_quota: a5,
     })
 }
 
@@ -0,0 +1,262 @@
+//! Crash reports for processes that fault.
+//!
+//! A process that calls `install()` no longer just halts when it takes an
+//! exception such as a page fault. Instead, the exception handler walks the
+//! frame pointers of the faulting thread, looks each return address up in
+//! the process' symbol table, sends the result to the log server, and then
+//! terminates the process.
+//!
+//! The symbol table is added by `create-image`, which looks for the
+//! `XOUS_SYMBOL_TABLE` static in each program. Only programs that call
+//! `install()` link that static, so only they pay for the table. Frames are
+//! found by following `fp`, so code has to be built with
+//! `-C force-frame-pointers=yes` for the backtrace to get past the faulting
+//! function.
+
+use core::fmt::{self, Write};
+
+use crate::{Exception, FaultContext, Message, ScalarMessage, CID, SID};
+
+/// Marks the start of a symbol table
+const SYMBOL_TABLE_MAGIC: u32 = u32::from_le_bytes(*b"XSym");
+
+/// Bytes of header before the first entry: the magic, the number of entries,
+/// and the length of the whole table
+const SYMBOL_TABLE_HEADER: usize = 12;
+
+/// Bytes in each entry: the address, the size, and the offset and length of
+/// the name, as little-endian `u32`s. Entries are sorted by address.
+const SYMBOL_ENTRY_LEN: usize = 16;
+
+/// The value of `XOUS_SYMBOL_TABLE` until `create-image` patches it
+const NO_SYMBOLS: usize = usize::MAX;
+
+/// Where this process' symbol table was loaded. `create-image` finds this by
+/// name and overwrites the value when it appends the table to the program.
+#[no_mangle]
+static XOUS_SYMBOL_TABLE: usize = NO_SYMBOLS;
+
+/// How many frames to walk before giving up
+const MAX_FRAMES: usize = 32;
+
+/// A frame pointer more than this far above the stack pointer is taken to be
+/// garbage
+const MAX_STACK_DEPTH: usize = 1024 * 1024;
+
+/// Log server message that begins a crash report. The report itself follows
+/// as panic text, and ends with `PanicFinished`.
+const LOG_CRASH_STARTED: usize = 1300;
+const LOG_PANIC_TEXT: usize = 1100;
+const LOG_PANIC_FINISHED: usize = 1200;
+
+/// A table of a program's functions, laid out the way `create-image` writes
+/// it
+pub struct SymbolTable {
+    data: &'static [u8],
+}
+
+impl SymbolTable {
+    /// This process' own symbol table, if `create-image` gave it one
+    fn get() -> Option<SymbolTable> {
+        let base = unsafe { core::ptr::read_volatile(&XOUS_SYMBOL_TABLE) };
+        if base == NO_SYMBOLS {
+            return None;
+        }
+        let header = unsafe { core::slice::from_raw_parts(base as *const u8, SYMBOL_TABLE_HEADER) };
+        let len = SymbolTable::from_bytes(header)?.len()?;
+        SymbolTable::from_bytes(unsafe { core::slice::from_raw_parts(base as *const u8, len) })
+    }
+
+    /// Read a symbol table out of `data`, which has to begin with one.
+    pub fn from_bytes(data: &'static [u8]) -> Option<SymbolTable> {
+        let table = SymbolTable { data };
+        if table.word(0)? != SYMBOL_TABLE_MAGIC {
+            return None;
+        }
+        Some(table)
+    }
+
+    /// The length of the table in bytes, according to its header
+    fn len(&self) -> Option<usize> {
+        Some(self.word(8)? as usize)
+    }
+
+    fn word(&self, offset: usize) -> Option<u32> {
+        let bytes = self.data.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn entry(&self, index: usize) -> Option<[u32; 4]> {
+        let offset = SYMBOL_TABLE_HEADER + index * SYMBOL_ENTRY_LEN;
+        Some([
+            self.word(offset)?,
+            self.word(offset + 4)?,
+            self.word(offset + 8)?,
+            self.word(offset + 12)?,
+        ])
+    }
+
+    /// Find the function containing `addr`, returning its name and how far
+    /// into it `addr` is.
+    pub fn lookup(&self, addr: usize) -> Option<(&'static str, usize)> {
+        let count = self.word(4)? as usize;
+        // Find the last entry that starts at or before `addr`.
+        let (mut low, mut high) = (0, count);
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.entry(mid)?[0] as usize <= addr {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let [start, size, name_offset, name_len] = self.entry(low.checked_sub(1)?)?;
+        let offset = addr - start as usize;
+        if offset >= size as usize {
+            return None;
+        }
+        let name = self
+            .data
+            .get(name_offset as usize..name_offset as usize + name_len as usize)?;
+        Some((core::str::from_utf8(name).ok()?, offset))
+    }
+}
+
+/// Walks a stack by following saved frame pointers. With frame pointers, the
+/// return address is saved just below the address that `fp` points to, and
+/// the caller's `fp` just below that.
+struct Frames {
+    fp: usize,
+    sp: usize,
+    depth: usize,
+}
+
+impl Iterator for Frames {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let word = core::mem::size_of::<usize>();
+        // Only follow pointers that land within the stack we're walking, so
+        // that garbage can't fault the exception handler.
+        if self.depth >= MAX_FRAMES
+            || self.fp % word != 0
+            || self.fp < self.sp.saturating_add(2 * word)
+            || self.fp - self.sp > MAX_STACK_DEPTH
+        {
+            return None;
+        }
+        let (ra, caller_fp) = unsafe {
+            let frame = self.fp as *const usize;
+            (frame.sub(1).read_volatile(), frame.sub(2).read_volatile())
+        };
+        if ra == 0 {
+            return None;
+        }
+        // Callers' frames are always further up the stack.
+        self.fp = if caller_fp > self.fp { caller_fp } else { 0 };
+        self.depth += 1;
+        Some(ra)
+    }
+}
+
+/// What is known about an exception, formatted as a human-readable report
+/// with a symbolized backtrace.
+pub struct CrashReport {
+    exception: Exception,
+    context: FaultContext,
+}
+
+impl CrashReport {
+    pub fn new(exception: Exception, context: FaultContext) -> Self {
+        CrashReport { exception, context }
+    }
+
+    /// The program counters of the faulting function and each of its
+    /// callers, innermost first.
+    pub fn backtrace(&self) -> impl Iterator<Item = usize> {
+        core::iter::once(self.exception.pc()).chain(Frames {
+            fp: self.context.fp,
+            sp: self.context.sp,
+            depth: 0,
+        })
+    }
+}
+
+impl fmt::Display for CrashReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:x?}", self.exception)?;
+        writeln!(
+            f,
+            "ra {:08x}  sp {:08x}  fp {:08x}",
+            self.context.ra, self.context.sp, self.context.fp
+        )?;
+        let symbols = SymbolTable::get();
+        if symbols.is_none() {
+            writeln!(f, "(no symbol table)")?;
+        }
+        for (index, pc) in self.backtrace().enumerate() {
+            write!(f, "  #{:<2} {:08x}", index, pc)?;
+            // A return address points past the call, which may be the first
+            // instruction of the next function. Look up the call instead.
+            let lookup = if index == 0 { pc } else { pc - 1 };
+            match symbols.as_ref().and_then(|s| s.lookup(lookup)) {
+                Some((name, offset)) => writeln!(f, "  {} + {:#x}", name, offset + (pc - lookup))?,
+                None => writeln!(f, "  ???")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sends text to the log server as panic messages, which is something that
+/// still works from within an exception handler.
+struct LogWriter {
+    conn: CID,
+}
+
+impl LogWriter {
+    fn send(&self, id: usize, args: [usize; 4]) {
+        let message = Message::Scalar(ScalarMessage {
+            id,
+            arg1: args[0],
+            arg2: args[1],
+            arg3: args[2],
+            arg4: args[3],
+        });
+        crate::try_send_message(self.conn, message).ok();
+    }
+}
+
+impl fmt::Write for LogWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let word = core::mem::size_of::<usize>();
+        for chunk in s.as_bytes().chunks(word * 4) {
+            let mut args = [0usize; 4];
+            for (arg, bytes) in args.iter_mut().zip(chunk.chunks(word)) {
+                let mut padded = [0u8; core::mem::size_of::<usize>()];
+                padded[..bytes.len()].copy_from_slice(bytes);
+                *arg = usize::from_le_bytes(padded);
+            }
+            self.send(LOG_PANIC_TEXT + chunk.len(), args);
+        }
+        Ok(())
+    }
+}
+
+fn report_and_exit(exception: Exception) -> isize {
+    let report = CrashReport::new(exception, crate::fault_context());
+    if let Ok(conn) = crate::try_connect(SID::from_bytes(b"xous-log-server ").unwrap()) {
+        let mut writer = LogWriter { conn };
+        writer.send(LOG_CRASH_STARTED, [0; 4]);
+        write!(writer, "{}", report).ok();
+        writer.send(LOG_PANIC_FINISHED, [0; 4]);
+    }
+    crate::terminate_process(1)
+}
+
+/// Send a crash report to the log server and terminate this process whenever
+/// it takes an exception. This replaces any exception handler that was
+/// already set.
+pub fn install() -> core::result::Result<(), crate::Error> {
+    crate::set_exception_handler(report_and_exit)
+}
@@ -146,6 +146,19 @@ pub type CID = u32;
 /// Thread ID
 pub type TID = usize;
 
+/// Scheduling priority of a thread. Higher values are scheduled first.
+pub type ThreadPriority = u8;
+
+/// The lowest priority a thread may have. Threads at this level only run
+/// when nothing else in the system is runnable.
+pub const THREAD_PRIORITY_IDLE: ThreadPriority = 0;
+
+/// The priority every thread starts out with.
+pub const THREAD_PRIORITY_DEFAULT: ThreadPriority = 4;
+
+/// The highest priority a thread may have.
+pub const THREAD_PRIORITY_MAX: ThreadPriority = 7;
+
 /// Equivalent to a RISC-V Hart ID
 pub type CpuID = usize;
 
@@ -236,6 +249,7 @@ pub enum Error {
     UseBeforeInit = 24,
     DoubleFree = 25,
     DebugInProgress = 26,
+    QuotaExceeded = 27,
 }
 
 impl Error {
@@ -268,6 +282,7 @@ impl Error {
             24 => UseBeforeInit,
             25 => DoubleFree,
             26 => DebugInProgress,
+            27 => QuotaExceeded,
             _ => UnknownError,
         }
     }
@@ -300,6 +315,7 @@ impl Error {
             UseBeforeInit => 24,
             DoubleFree => 25,
             DebugInProgress => 26,
+            QuotaExceeded => 27,
             UnknownError => usize::MAX,
         }
     }
@@ -743,6 +759,309 @@ impl core::fmt::Display for MemoryType {
     }
 }
 
+/// The scheduling state of a process, as reported by `GetProcessInfo`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ProcessStatus {
+    /// The process has been created but has no threads yet
+    Allocated,
+
+    /// The process has been set up and is waiting to run for the first time
+    Setup,
+
+    /// The process has threads that are ready to run
+    Ready,
+
+    /// The process is currently running
+    Running,
+
+    /// Every thread in the process is blocked
+    Sleeping,
+
+    /// The process has been stopped by the debugger
+    Debug,
+
+    /// The process is running its exception handler
+    Exception,
+
+    /// The exception handler of the process is blocked
+    BlockedException,
+
+    /// The kernel reported a state that this library does not know about
+    Unknown,
+}
+
+impl ProcessStatus {
+    pub fn from_usize(arg: usize) -> Self {
+        use ProcessStatus::*;
+        match arg {
+            1 => Allocated,
+            2 => Setup,
+            3 => Ready,
+            4 => Running,
+            5 => Sleeping,
+            6 => Debug,
+            7 => Exception,
+            8 => BlockedException,
+            _ => Unknown,
+        }
+    }
+
+    pub fn to_usize(&self) -> usize {
+        use ProcessStatus::*;
+        match *self {
+            Allocated => 1,
+            Setup => 2,
+            Ready => 3,
+            Running => 4,
+            Sleeping => 5,
+            Debug => 6,
+            Exception => 7,
+            BlockedException => 8,
+            Unknown => 0,
+        }
+    }
+}
+
+impl core::fmt::Display for ProcessStatus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use ProcessStatus::*;
+        let s = match self {
+            Allocated => "alloc",
+            Setup => "setup",
+            Ready => "ready",
+            Running => "run",
+            Sleeping => "sleep",
+            Debug => "debug",
+            Exception => "exc",
+            BlockedException => "exc-blk",
+            Unknown => "?",
+        };
+        f.pad(s)
+    }
+}
+
+/// A snapshot of a single process, as returned by `GetProcessInfo`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ProcessInfo {
+    /// The ID of this process
+    pub pid: PID,
+
+    /// The process that created this one
+    pub ppid: PID,
+
+    /// How many processes have had this PID before this one, modulo 256.
+    /// A change means that the process that had the PID has gone.
+    pub generation: u8,
+
+    /// What the process is currently doing
+    pub status: ProcessStatus,
+
+    /// A bitmask of the threads that are ready to run
+    pub ready_threads: usize,
+
+    /// The number of threads that exist in this process
+    pub threads: usize,
+
+    /// The number of bytes of RAM owned by this process
+    pub ram_bytes: usize,
+
+    /// The number of servers this process has created
+    pub servers: usize,
+
+    /// The number of servers this process is connected to
+    pub connections: usize,
+
+    /// The most RAM this process may own, in bytes, or 0 if it has no limit
+    pub ram_quota: usize,
+}
+
+impl ProcessInfo {
+    /// Decode the arguments of a `Result::ProcessInfo`
+    pub fn from_args(args: [usize; 7]) -> Option<Self> {
+        Some(ProcessInfo {
+            pid: PID::new(args[0] as u8)?,
+            ppid: PID::new((args[0] >> 8) as u8)?,
+            generation: (args[0] >> 24) as u8,
+            status: ProcessStatus::from_usize((args[0] >> 16) & 0xff),
+            ready_threads: args[1],
+            threads: args[2],
+            ram_bytes: args[3],
+            servers: args[4],
+            connections: args[5],
+            ram_quota: args[6],
+        })
+    }
+
+    /// Encode this as the arguments of a `Result::ProcessInfo`
+    pub fn to_args(&self) -> [usize; 7] {
+        [
+            self.pid.get() as usize
+                | (self.ppid.get() as usize) << 8
+                | self.status.to_usize() << 16
+                | (self.generation as usize) << 24,
+            self.ready_threads,
+            self.threads,
+            self.ram_bytes,
+            self.servers,
+            self.connections,
+            self.ram_quota,
+        ]
+    }
+}
+
+/// How much CPU time a thread or process has used, as returned by
+/// `GetThreadCpuTime` and `GetProcessCpuTime`
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct CpuTime {
+    /// Milliseconds spent running, as counted by the system timer
+    pub run_ms: u64,
+
+    /// The number of times the kernel switched to this thread or process
+    pub switches: usize,
+}
+
+impl CpuTime {
+    /// Decode the arguments of a `Result::CpuTime`
+    pub fn from_args(args: [usize; 3]) -> Self {
+        CpuTime {
+            run_ms: args[0] as u64 | (args[1] as u64) << 32,
+            switches: args[2],
+        }
+    }
+
+    /// Encode this as the arguments of a `Result::CpuTime`
+    pub fn to_args(&self) -> [usize; 3] {
+        [
+            self.run_ms as u32 as usize,
+            (self.run_ms >> 32) as usize,
+            self.switches,
+        ]
+    }
+}
+
+/// Counters describing how busy a server's message queue is, as returned by
+/// `GetServerStats`. Only messages that arrive while no server thread is
+/// waiting are queued; the rest are handed over directly and are not counted.
+/// The counters wrap around on overflow.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct ServerStats {
+    /// The number of messages that have been placed in the queue
+    pub queued: usize,
+
+    /// The number of messages in the queue right now
+    pub depth: usize,
+
+    /// The largest number of messages that have been in the queue at once
+    pub max_depth: usize,
+
+    /// The number of messages that were refused with `ServerQueueFull`.
+    /// Blocking senders retry, so one message may be counted several times.
+    pub full: usize,
+
+    /// The sum of the time that messages have spent in the queue, in
+    /// milliseconds. This is accumulated from the queue depth whenever it
+    /// changes, so time spent by messages still in the queue is only partly
+    /// included.
+    pub wait_ms: u64,
+}
+
+impl ServerStats {
+    /// The average number of milliseconds a message spent in the queue
+    /// before the server received it
+    pub fn average_wait_ms(&self) -> u64 {
+        // `queued` may have wrapped around
+        match self.queued.saturating_sub(self.depth) {
+            0 => 0,
+            delivered => self.wait_ms / delivered as u64,
+        }
+    }
+
+    /// Decode the arguments of a `Result::ServerStats`
+    pub fn from_args(args: [usize; 6]) -> Self {
+        ServerStats {
+            queued: args[0],
+            depth: args[1],
+            max_depth: args[2],
+            full: args[3],
+            wait_ms: args[4] as u64 | (args[5] as u64) << 32,
+        }
+    }
+
+    /// Encode this as the arguments of a `Result::ServerStats`
+    pub fn to_args(&self) -> [usize; 6] {
+        [
+            self.queued,
+            self.depth,
+            self.max_depth,
+            self.full,
+            self.wait_ms as u32 as usize,
+            (self.wait_ms >> 32) as usize,
+        ]
+    }
+}
+
+/// Why a process stopped running
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ExitReason {
+    /// The process called `TerminateProcess` with the given exit code
+    Exited(u32),
+
+    /// The process took an exception that it had no handler for. The values
+    /// are architecture-specific: on RISC-V these are `scause`, `sepc`
+    /// and `stval`.
+    Exception {
+        cause: usize,
+        pc: usize,
+        addr: usize,
+    },
+}
+
+/// The notification that is sent to a supervisor server, as registered with
+/// `SetSupervisor`, when one of its process' children terminates. It arrives
+/// as a nonblocking Scalar message with the ID given at registration.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ChildTerminated {
+    /// The PID that the child had. This PID is free, and may be reused by the
+    /// next process that gets created.
+    pub pid: PID,
+
+    /// Why the child stopped
+    pub reason: ExitReason,
+}
+
+impl ChildTerminated {
+    /// Decode a notification message. Returns `None` if this is not a
+    /// Scalar message.
+    pub fn from_message(message: &Message) -> Option<Self> {
+        let msg = match message {
+            Message::Scalar(msg) => msg,
+            _ => return None,
+        };
+        let pid = PID::new(msg.arg1 as u8)?;
+        let reason = match msg.arg1 >> 8 {
+            0 => ExitReason::Exited(msg.arg2 as u32),
+            1 => ExitReason::Exception {
+                cause: msg.arg2,
+                pc: msg.arg3,
+                addr: msg.arg4,
+            },
+            _ => return None,
+        };
+        Some(ChildTerminated { pid, reason })
+    }
+
+    /// Encode this notification as a message with the given ID
+    pub fn to_message(&self, id: MessageId) -> Message {
+        let pid = self.pid.get() as usize;
+        match self.reason {
+            ExitReason::Exited(code) => Message::new_scalar(id, pid, code as usize, 0, 0),
+            ExitReason::Exception { cause, pc, addr } => {
+                Message::new_scalar(id, pid | 1 << 8, cause, pc, addr)
+            }
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, PartialEq)]
 pub enum Result {
@@ -794,6 +1113,15 @@ pub enum Result {
         Option<MemorySize>, /* valid */
     ),
 
+    /// Information about a process. Decode this with `ProcessInfo::from_args()`.
+    ProcessInfo(usize, usize, usize, usize, usize, usize, usize),
+
+    /// CPU time used by a thread or process. Decode this with `CpuTime::from_args()`.
+    CpuTime(usize, usize, usize),
+
+    /// Message queue counters for a server. Decode this with `ServerStats::from_args()`.
+    ServerStats(usize, usize, usize, usize, usize, usize),
+
     UnknownResult(usize, usize, usize, usize, usize, usize, usize),
 }
 
@@ -850,6 +1178,13 @@ impl Result {
                 0,
                 0,
             ],
+            Result::ProcessInfo(arg1, arg2, arg3, arg4, arg5, arg6, arg7) => {
+                [19, *arg1, *arg2, *arg3, *arg4, *arg5, *arg6, *arg7]
+            }
+            Result::CpuTime(arg1, arg2, arg3) => [20, *arg1, *arg2, *arg3, 0, 0, 0, 0],
+            Result::ServerStats(arg1, arg2, arg3, arg4, arg5, arg6) => {
+                [21, *arg1, *arg2, *arg3, *arg4, *arg5, *arg6, 0]
+            }
             Result::UnknownResult(arg1, arg2, arg3, arg4, arg5, arg6, arg7) => {
                 [usize::MAX, *arg1, *arg2, *arg3, *arg4, *arg5, *arg6, *arg7]
             }
@@ -926,6 +1261,9 @@ impl Result {
             16 => Result::RetryCall,
             17 => Result::None,
             18 => Result::MemoryReturned(MemorySize::new(src[1]), MemorySize::new(src[2])),
+            19 => Result::ProcessInfo(src[1], src[2], src[3], src[4], src[5], src[6], src[7]),
+            20 => Result::CpuTime(src[1], src[2], src[3]),
+            21 => Result::ServerStats(src[1], src[2], src[3], src[4], src[5], src[6]),
             _ => Result::UnknownResult(src[0], src[1], src[2], src[3], src[4], src[5], src[6]),
         }
     }
@@ -1,3 +1,5 @@
+use crate::TID;
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 #[repr(usize)]
 pub enum ExceptionType {
@@ -11,6 +13,7 @@ pub enum ExceptionType {
     InstructionPageFault = 7,
     LoadPageFault = 8,
     StorePageFault = 9,
+    StackOverflow = 10,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -25,9 +28,24 @@ pub enum Exception {
     InstructionPageFault(usize /* epc */, usize /* addr */),
     LoadPageFault(usize /* epc */, usize /* addr */),
     StorePageFault(usize /* epc */, usize /* addr */),
+
+    /// A thread ran off the end of its stack and touched the guard page below it
+    StackOverflow(usize /* epc */, TID /* tid */),
     Unknown(usize, usize, usize),
 }
 
+/// Registers of the thread that caused an exception, which are what it takes to
+/// walk its stack
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct FaultContext {
+    /// Return address
+    pub ra: usize,
+    /// Stack pointer
+    pub sp: usize,
+    /// Frame pointer, i.e. `s0`
+    pub fp: usize,
+}
+
 impl Exception {
     pub fn new(a0: usize, a1: usize, a2: usize) -> Exception {
         match a0 {
@@ -47,6 +65,7 @@ impl Exception {
             7 /*ExceptionType::InstructionPageFault as usize*/ => Exception::InstructionPageFault(a1, a2),
             8 /*ExceptionType::LoadPageFault as usize*/ => Exception::LoadPageFault(a1, a2),
             9 /*ExceptionType::StorePageFault as usize*/ => Exception::StorePageFault(a1, a2),
+            10 /*ExceptionType::StackOverflow as usize*/ => Exception::StackOverflow(a1, a2),
             _ => Exception::Unknown(a0, a1, a2),
         }
     }
@@ -63,6 +82,7 @@ impl Exception {
             | Exception::InstructionPageFault(pc, _)
             | Exception::LoadPageFault(pc, _)
             | Exception::StorePageFault(pc, _)
+            | Exception::StackOverflow(pc, _)
             | Exception::Unknown(_, pc, _) => pc,
         }
     }
@@ -82,3 +102,21 @@ impl Exception {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_guard_page_fault_names_the_thread() {
+        let epc = 0x2000_1234;
+        let ex = Exception::new(ExceptionType::StackOverflow as usize, epc, 3);
+        assert_eq!(ex, Exception::StackOverflow(epc, 3));
+        assert_eq!(ex.pc(), epc);
+
+        // Other page faults still carry the faulting address
+        let ex = Exception::new(ExceptionType::StorePageFault as usize, epc, 0x4000_0ff8);
+        assert_eq!(ex, Exception::StorePageFault(epc, 0x4000_0ff8));
+        assert_eq!(Exception::new(11, epc, 0), Exception::Unknown(11, epc, 0));
+    }
+}
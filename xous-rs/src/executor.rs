@@ -0,0 +1,461 @@
+//! A single-threaded executor for servers written with `async`/`await`.
+//!
+//! A server has traditionally been a `receive_message()` loop, with a helper
+//! thread for anything that has to happen alongside it, such as a periodic
+//! tick. An `Executor` instead runs any number of tasks on the server's own
+//! thread. A task can wait for a message to the server, for a timeout, or for
+//! room in another server's queue, and the executor sleeps in the kernel
+//! whenever none of them can make progress.
+//!
+//! Messages are handed out in the order they arrive, to whichever task asks
+//! first. A server will usually have one task that receives and dispatches
+//! messages, and more tasks for timers and outgoing traffic.
+//!
+//! Everything a task can wait on here is driven from the executor's thread,
+//! but a `Waker` may also be used from another thread. To rouse an executor
+//! that's waiting for a message, it sends the server a scalar message with
+//! the id `WAKE_ID`, which the executor keeps to itself.
+
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use crate::{Error, Message, MessageEnvelope, ScalarMessage, CID, PID, SID};
+
+/// The id of the message that a waker on another thread sends to rouse an
+/// executor waiting for messages. Tasks never see these, so a server run by
+/// an `Executor` shouldn't use this id for anything else.
+pub const WAKE_ID: usize = usize::MAX;
+
+/// How long to wait before trying again to send to a server whose queue was
+/// full
+const RETRY_MS: u64 = 1;
+
+/// How many received messages may wait for a task before the executor leaves
+/// the rest in the kernel's queue
+const INBOX_LEN: usize = 32;
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+struct TaskWaker {
+    woken: AtomicBool,
+    signal: Arc<Signal>,
+}
+
+/// Lets a waker on another thread rouse the executor, whether it's waiting
+/// for a message or has stopped receiving them and is parked
+struct Signal {
+    /// Bumped on every wake, and waited on while parked
+    count: AtomicUsize,
+    parked: AtomicBool,
+    receiving: AtomicBool,
+    /// A wake message is on its way, so there's no need for another
+    wake_sent: AtomicBool,
+    /// The executor's connection to its own server
+    conn: CID,
+}
+
+impl Signal {
+    /// Whether `envelope` is a wake message from a waker on another thread of
+    /// `pid`, the executor's own process
+    fn is_wake(&self, envelope: &MessageEnvelope, pid: Option<PID>) -> bool {
+        match envelope.body {
+            Message::Scalar(ScalarMessage { id, arg1, .. }) => {
+                id == WAKE_ID
+                    && arg1 == self as *const Self as usize
+                    && envelope.sender.pid() == pid
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let signal = &self.signal;
+        self.woken.store(true, Ordering::SeqCst);
+        signal.count.fetch_add(1, Ordering::SeqCst);
+        if signal.parked.load(Ordering::SeqCst) {
+            crate::wake_address(&signal.count, 1).ok();
+        }
+        if signal.receiving.load(Ordering::SeqCst) && !signal.wake_sent.swap(true, Ordering::SeqCst)
+        {
+            let token = Arc::as_ptr(signal) as usize;
+            // If the queue is full, the executor has a message to wake up to
+            // anyway.
+            if crate::try_send_message(signal.conn, Message::new_scalar(WAKE_ID, token, 0, 0, 0))
+                .is_err()
+            {
+                signal.wake_sent.store(false, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+/// State shared between the executor and the futures it hands out
+struct Shared {
+    clock: Box<dyn Fn() -> u64>,
+    inbox: RefCell<VecDeque<MessageEnvelope>>,
+    receivers: RefCell<Vec<Waker>>,
+    timers: RefCell<Vec<(u64, Waker)>>,
+    retries: RefCell<Vec<Waker>>,
+    spawned: RefCell<Vec<Task>>,
+}
+
+impl Shared {
+    fn now(&self) -> u64 {
+        (self.clock)()
+    }
+
+    /// Wake every task whose timer has expired, and return whether there were
+    /// any.
+    fn fire_timers(&self) -> bool {
+        let now = self.now();
+        let mut fired = false;
+        self.timers.borrow_mut().retain(|(deadline, waker)| {
+            if *deadline > now {
+                return true;
+            }
+            waker.wake_by_ref();
+            fired = true;
+            false
+        });
+        fired
+    }
+
+    /// How long until the earliest timer expires, if there is one.
+    fn next_timeout(&self) -> Option<u64> {
+        let now = self.now();
+        self.timers
+            .borrow()
+            .iter()
+            .map(|(deadline, _)| deadline.saturating_sub(now))
+            .min()
+    }
+}
+
+fn register(wakers: &RefCell<Vec<Waker>>, waker: &Waker) {
+    let mut wakers = wakers.borrow_mut();
+    if !wakers.iter().any(|w| w.will_wake(waker)) {
+        wakers.push(waker.clone());
+    }
+}
+
+/// Runs tasks on behalf of one server.
+pub struct Executor {
+    sid: SID,
+    shared: Rc<Shared>,
+    signal: Arc<Signal>,
+    tasks: Vec<(Task, Arc<TaskWaker>)>,
+}
+
+impl Executor {
+    /// Create an executor that receives messages sent to `sid`. `clock`
+    /// returns the current time in milliseconds, and is normally the
+    /// ticktimer's `elapsed_ms()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the executor can't connect to `sid`, which it does so that
+    /// wakers on other threads can reach it.
+    pub fn new<F: Fn() -> u64 + 'static>(sid: SID, clock: F) -> Self {
+        let conn = crate::connect(sid).expect("couldn't connect executor to its server");
+        Executor {
+            sid,
+            shared: Rc::new(Shared {
+                clock: Box::new(clock),
+                inbox: RefCell::new(VecDeque::new()),
+                receivers: RefCell::new(Vec::new()),
+                timers: RefCell::new(Vec::new()),
+                retries: RefCell::new(Vec::new()),
+                spawned: RefCell::new(Vec::new()),
+            }),
+            signal: Arc::new(Signal {
+                count: AtomicUsize::new(0),
+                parked: AtomicBool::new(false),
+                receiving: AtomicBool::new(false),
+                wake_sent: AtomicBool::new(false),
+                conn,
+            }),
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Get a handle that tasks can use to wait on events and spawn more tasks.
+    pub fn handle(&self) -> Handle {
+        Handle {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Add a task. It starts running once `run()` is called.
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, future: F) {
+        self.handle().spawn(future)
+    }
+
+    /// Run tasks until all of them have finished.
+    ///
+    /// # Panics
+    ///
+    /// Panics if receiving from the server fails, for example because the
+    /// server has been destroyed.
+    pub fn run(&mut self) {
+        loop {
+            let spawned = core::mem::take(&mut *self.shared.spawned.borrow_mut());
+            for task in spawned {
+                let waker = Arc::new(TaskWaker {
+                    woken: AtomicBool::new(true),
+                    signal: self.signal.clone(),
+                });
+                self.tasks.push((task, waker));
+            }
+            if self.tasks.is_empty() {
+                return;
+            }
+
+            let mut progress = false;
+            let mut i = 0;
+            while i < self.tasks.len() {
+                let (task, task_waker) = &mut self.tasks[i];
+                if task_waker.woken.swap(false, Ordering::SeqCst) {
+                    progress = true;
+                    let waker = Waker::from(task_waker.clone());
+                    if task.as_mut().poll(&mut Context::from_waker(&waker)).is_ready() {
+                        drop(self.tasks.swap_remove(i));
+                        continue;
+                    }
+                }
+                i += 1;
+            }
+
+            if !progress {
+                self.wait();
+            }
+        }
+    }
+
+    /// Block until something happens that a task may be waiting for.
+    fn wait(&mut self) {
+        if self.shared.fire_timers() {
+            return;
+        }
+        let mut timeout = self.shared.next_timeout();
+        if !self.shared.retries.borrow().is_empty() {
+            timeout = Some(timeout.map_or(RETRY_MS, |t| t.min(RETRY_MS)));
+        }
+        let timeout = timeout.map(|t| t as usize);
+
+        if self.shared.inbox.borrow().len() >= INBOX_LEN {
+            // Leave further messages in the kernel until a task catches up,
+            // and only wait for the next timer or a wake from another thread.
+            self.park(timeout);
+        } else {
+            match self.receive(timeout) {
+                Ok(envelope) if self.signal.is_wake(&envelope, crate::current_pid().ok()) => {
+                    self.signal.wake_sent.store(false, Ordering::SeqCst);
+                }
+                Ok(envelope) => {
+                    self.shared.inbox.borrow_mut().push_back(envelope);
+                    for waker in self.shared.receivers.borrow_mut().drain(..) {
+                        waker.wake();
+                    }
+                }
+                Err(Error::Timeout) => {}
+                Err(e) => panic!("couldn't receive message: {:?}", e),
+            }
+        }
+
+        for waker in self.shared.retries.borrow_mut().drain(..) {
+            waker.wake();
+        }
+        self.shared.fire_timers();
+    }
+
+    /// Receive the next message, unless a task is woken first, in which case
+    /// this returns `Timeout`, as it does if `timeout_ms` passes.
+    fn receive(&self, timeout_ms: Option<usize>) -> core::result::Result<MessageEnvelope, Error> {
+        let signal = &self.signal;
+        signal.receiving.store(true, Ordering::SeqCst);
+        // A waker that ran before `receiving` was set didn't send a message,
+        // so look for one here. One that runs after will send one.
+        let result = if self
+            .tasks
+            .iter()
+            .any(|(_, w)| w.woken.load(Ordering::SeqCst))
+        {
+            Err(Error::Timeout)
+        } else {
+            match timeout_ms {
+                Some(ms) => crate::receive_message_timeout(self.sid, ms),
+                None => crate::receive_message(self.sid),
+            }
+        };
+        signal.receiving.store(false, Ordering::SeqCst);
+        result
+    }
+
+    /// Sleep until a task is woken, or for `timeout_ms` if it's given.
+    fn park(&self, timeout_ms: Option<usize>) {
+        let signal = &self.signal;
+        signal.parked.store(true, Ordering::SeqCst);
+        let seen = signal.count.load(Ordering::SeqCst);
+        // A wake from before `parked` was set didn't touch the address, so
+        // look for one before sleeping. A wake from after `seen` was read
+        // changes `count`, so the kernel won't let this thread sleep through
+        // it.
+        let woken = || {
+            signal.count.load(Ordering::SeqCst) != seen
+                || self
+                    .tasks
+                    .iter()
+                    .any(|(_, w)| w.woken.load(Ordering::SeqCst))
+        };
+        while !woken() {
+            match crate::wait_for_address(&signal.count, seen, timeout_ms) {
+                // Wakeups may be spurious, so check again, unless there's a
+                // timer to see to.
+                Ok(()) if timeout_ms.is_none() => {}
+                Ok(()) | Err(Error::Timeout) => break,
+                Err(e) => panic!("couldn't wait for timer: {:?}", e),
+            }
+        }
+        signal.parked.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Lets a task wait on events, and spawn more tasks, from inside an
+/// `Executor`.
+#[derive(Clone)]
+pub struct Handle {
+    shared: Rc<Shared>,
+}
+
+impl Handle {
+    /// Add a task. It starts running once the current task yields.
+    pub fn spawn<F: Future<Output = ()> + 'static>(&self, future: F) {
+        self.shared.spawned.borrow_mut().push(Box::pin(future));
+    }
+
+    /// The current time according to the executor's clock, in milliseconds.
+    pub fn elapsed_ms(&self) -> u64 {
+        self.shared.now()
+    }
+
+    /// Wait for the next message sent to the server.
+    pub fn receive(&self) -> Receive {
+        Receive {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Wait for `ms` milliseconds to pass.
+    pub fn sleep_ms(&self, ms: usize) -> Sleep {
+        Sleep {
+            deadline: self.shared.now() + ms as u64,
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Send `message` to `connection`. A Scalar or BlockingScalar is retried
+    /// for as long as the server's queue is full, without blocking the
+    /// executor.
+    ///
+    /// A Scalar or a Move completes as soon as it is queued. A message that
+    /// blocks, i.e. a lend or a BlockingScalar, is sent as with
+    /// `send_message()` and holds up every task until the server replies, so
+    /// don't use one with a server that may take its time. A message with
+    /// memory attached is only tried once, and returns `ServerQueueFull` as
+    /// `try_send_message()` would.
+    pub fn send(&self, connection: CID, message: Message) -> SendMessage {
+        SendMessage {
+            shared: self.shared.clone(),
+            connection,
+            message: Some(message),
+        }
+    }
+}
+
+/// The future returned by `Handle::receive()`
+pub struct Receive {
+    shared: Rc<Shared>,
+}
+
+impl Future for Receive {
+    type Output = MessageEnvelope;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<MessageEnvelope> {
+        if let Some(envelope) = self.shared.inbox.borrow_mut().pop_front() {
+            return Poll::Ready(envelope);
+        }
+        register(&self.shared.receivers, cx.waker());
+        Poll::Pending
+    }
+}
+
+/// The future returned by `Handle::sleep_ms()`
+pub struct Sleep {
+    shared: Rc<Shared>,
+    deadline: u64,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.shared.now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        let mut timers = self.shared.timers.borrow_mut();
+        if !timers
+            .iter()
+            .any(|(deadline, waker)| *deadline == self.deadline && waker.will_wake(cx.waker()))
+        {
+            timers.push((self.deadline, cx.waker().clone()));
+        }
+        Poll::Pending
+    }
+}
+
+/// The future returned by `Handle::send()`
+pub struct SendMessage {
+    shared: Rc<Shared>,
+    connection: CID,
+    message: Option<Message>,
+}
+
+impl Future for SendMessage {
+    type Output = core::result::Result<crate::Result, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        // Scalars are kept so they can be sent again, while memory belongs to
+        // the kernel once it has been sent.
+        let message = match this.message.as_ref() {
+            Some(Message::Scalar(s)) => Message::Scalar(*s),
+            Some(Message::BlockingScalar(s)) => Message::BlockingScalar(*s),
+            _ => this.message.take().expect("message was already sent"),
+        };
+        match crate::try_send_message(this.connection, message) {
+            Err(Error::ServerQueueFull) if this.message.is_some() => {
+                register(&this.shared.retries, cx.waker());
+                Poll::Pending
+            }
+            result => {
+                this.message = None;
+                Poll::Ready(result)
+            }
+        }
+    }
+}
@@ -7,18 +7,26 @@ extern crate bitflags;
 #[cfg(not(feature = "rustc-dep-of-std"))]
 extern crate xous_macros as macros;
 #[cfg(not(feature = "rustc-dep-of-std"))]
-pub use macros::xous_main;
+pub use macros::{rpc, xous_main};
+
+#[cfg(feature = "executor")]
+extern crate alloc;
 
 pub mod arch;
 
 pub mod carton;
+pub mod crash;
 pub mod definitions;
+#[cfg(feature = "executor")]
+pub mod executor;
 mod messages;
 
 pub mod process;
+pub mod ringbuf;
 pub mod string;
 pub mod stringbuffer;
 pub mod syscall;
+pub mod time;
 
 pub use arch::{ProcessArgs, ProcessInit, ProcessKey, ThreadInit};
 pub use definitions::*;
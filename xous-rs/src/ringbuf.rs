@@ -0,0 +1,323 @@
+//! A single-producer, single-consumer byte ring that lives in memory shared
+//! between two processes with `share_memory()`. Once both sides have
+//! attached, data moves without any syscalls: the producer copies bytes in
+//! and advances the write index, and the consumer copies them out and
+//! advances the read index.
+//!
+//! The first `HEADER_SIZE` bytes of the range hold the indices, and the rest
+//! holds the data. One byte is always left empty so that a full ring can be
+//! told apart from an empty one.
+//!
+//! The ring does not wake anybody up. Pair it with a message, for example a
+//! nonblocking Scalar sent when the ring goes from empty to non-empty, if the
+//! consumer should not poll.
+//!
+//! The other process can write to the header at any time. A misbehaving
+//! peer can corrupt the data in the ring, but indices are checked before use
+//! so it can never cause an access outside of the range.
+
+use crate::{Error, MemoryRange};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of bytes at the start of the range used for bookkeeping
+pub const HEADER_SIZE: usize = 64;
+
+/// Written by `init()` so that attaching to garbage fails
+const RING_MAGIC: usize = 0x5269_6e67;
+
+#[repr(C)]
+struct Header {
+    magic: AtomicUsize,
+
+    /// Size of the data area, in bytes
+    capacity: AtomicUsize,
+
+    /// Offset of the next byte to write. Only the producer modifies this.
+    head: AtomicUsize,
+
+    /// Offset of the next byte to read. Only the consumer modifies this.
+    tail: AtomicUsize,
+}
+
+struct Ring {
+    header: *const Header,
+    data: *mut u8,
+    capacity: usize,
+}
+
+impl Ring {
+    unsafe fn attach(range: MemoryRange) -> Result<Ring, Error> {
+        if range.len() <= HEADER_SIZE {
+            return Err(Error::BadAddress);
+        }
+        let header = range.as_ptr() as *const Header;
+        let capacity = range.len() - HEADER_SIZE;
+        if (*header).magic.load(Ordering::Acquire) != RING_MAGIC
+            || (*header).capacity.load(Ordering::Relaxed) != capacity
+        {
+            return Err(Error::BadAddress);
+        }
+        Ok(Ring {
+            header,
+            data: range.as_mut_ptr().add(HEADER_SIZE),
+            capacity,
+        })
+    }
+
+    fn header(&self) -> &Header {
+        unsafe { &*self.header }
+    }
+
+    /// Load an index, returning `None` if the peer has set it out of range.
+    fn load(&self, index: &AtomicUsize) -> Option<usize> {
+        let value = index.load(Ordering::Acquire);
+        if value < self.capacity {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// Return the number of bytes waiting to be read, along with the
+    /// current `head` and `tail`.
+    fn used(&self) -> Option<(usize, usize, usize)> {
+        let head = self.load(&self.header().head)?;
+        let tail = self.load(&self.header().tail)?;
+        Some(((head + self.capacity - tail) % self.capacity, head, tail))
+    }
+}
+
+/// Prepare `range` to be used as a ring. One side must do this before
+/// either side attaches, usually right before calling `share_memory()`.
+///
+/// # Safety
+///
+/// `range` must be valid, writable memory, and nobody may be using it as a
+/// ring at the time.
+pub unsafe fn init(range: MemoryRange) -> Result<(), Error> {
+    if range.len() <= HEADER_SIZE {
+        return Err(Error::BadAddress);
+    }
+    let header = range.as_mut_ptr() as *mut Header;
+    header.write(Header {
+        magic: AtomicUsize::new(0),
+        capacity: AtomicUsize::new(range.len() - HEADER_SIZE),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+    });
+    (*header).magic.store(RING_MAGIC, Ordering::Release);
+    Ok(())
+}
+
+/// Attach to the writing end of a ring that was set up with `init()`.
+///
+/// # Safety
+///
+/// `range` must stay mapped for as long as the `Producer` exists, and there
+/// must only be one `Producer` for a given ring.
+pub unsafe fn producer(range: MemoryRange) -> Result<Producer, Error> {
+    Ring::attach(range).map(|ring| Producer { ring })
+}
+
+/// Attach to the reading end of a ring that was set up with `init()`.
+///
+/// # Safety
+///
+/// `range` must stay mapped for as long as the `Consumer` exists, and there
+/// must only be one `Consumer` for a given ring.
+pub unsafe fn consumer(range: MemoryRange) -> Result<Consumer, Error> {
+    Ring::attach(range).map(|ring| Consumer { ring })
+}
+
+/// The writing end of a ring
+pub struct Producer {
+    ring: Ring,
+}
+
+unsafe impl Send for Producer {}
+
+impl Producer {
+    /// The largest number of bytes the ring can hold at once
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity - 1
+    }
+
+    /// The number of bytes that can be written without overwriting unread data
+    pub fn free(&self) -> usize {
+        self.ring
+            .used()
+            .map(|(used, _, _)| self.capacity() - used)
+            .unwrap_or(0)
+    }
+
+    /// Copy as much of `data` into the ring as will fit, and return the
+    /// number of bytes written.
+    pub fn write(&mut self, data: &[u8]) -> usize {
+        let (used, head, _tail) = match self.ring.used() {
+            Some(indices) => indices,
+            None => return 0,
+        };
+        let count = data.len().min(self.capacity() - used);
+
+        // Copy in two pieces, in case the data wraps around the end.
+        let first = count.min(self.ring.capacity - head);
+        unsafe {
+            core::ptr::copy_nonoverlapping(data.as_ptr(), self.ring.data.add(head), first);
+            core::ptr::copy_nonoverlapping(
+                data.as_ptr().add(first),
+                self.ring.data,
+                count - first,
+            );
+        }
+        self.ring
+            .header()
+            .head
+            .store((head + count) % self.ring.capacity, Ordering::Release);
+        count
+    }
+}
+
+/// The reading end of a ring
+pub struct Consumer {
+    ring: Ring,
+}
+
+unsafe impl Send for Consumer {}
+
+impl Consumer {
+    /// The number of bytes waiting to be read
+    pub fn len(&self) -> usize {
+        self.ring.used().map(|(used, _, _)| used).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copy as many bytes as are available, up to the length of `buf`, and
+    /// return the number of bytes read.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let (used, _head, tail) = match self.ring.used() {
+            Some(indices) => indices,
+            None => return 0,
+        };
+        let count = buf.len().min(used);
+
+        let first = count.min(self.ring.capacity - tail);
+        unsafe {
+            core::ptr::copy_nonoverlapping(self.ring.data.add(tail), buf.as_mut_ptr(), first);
+            core::ptr::copy_nonoverlapping(
+                self.ring.data,
+                buf.as_mut_ptr().add(first),
+                count - first,
+            );
+        }
+        self.ring
+            .header()
+            .tail
+            .store((tail + count) % self.ring.capacity, Ordering::Release);
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Memory for a ring with room for `N` bytes of data, including the one
+    /// that is always left empty
+    #[repr(C, align(4096))]
+    struct Backing<const N: usize>([u8; N]);
+
+    fn ring<const N: usize>(backing: &mut Backing<N>) -> (Producer, Consumer) {
+        unsafe {
+            let range = MemoryRange::new(backing.0.as_mut_ptr() as usize, N).unwrap();
+            init(range).unwrap();
+            (producer(range).unwrap(), consumer(range).unwrap())
+        }
+    }
+
+    #[test]
+    fn full_and_empty() {
+        let mut backing = Backing([0u8; HEADER_SIZE + 8]);
+        let (mut producer, mut consumer) = ring(&mut backing);
+        assert_eq!(producer.capacity(), 7);
+        assert!(consumer.is_empty());
+        assert_eq!(consumer.read(&mut [0u8; 4]), 0);
+
+        assert_eq!(producer.write(b"0123456789"), 7);
+        assert_eq!(producer.free(), 0);
+        assert_eq!(producer.write(b"x"), 0);
+        assert_eq!(consumer.len(), 7);
+
+        let mut buf = [0u8; 16];
+        assert_eq!(consumer.read(&mut buf), 7);
+        assert_eq!(&buf[..7], b"0123456");
+        assert!(consumer.is_empty());
+        assert_eq!(producer.free(), 7);
+    }
+
+    #[test]
+    fn data_wraps_around_the_end() {
+        let mut backing = Backing([0u8; HEADER_SIZE + 8]);
+        let (mut producer, mut consumer) = ring(&mut backing);
+        let mut buf = [0u8; 8];
+        assert_eq!(producer.write(b"abcde"), 5);
+        assert_eq!(consumer.read(&mut buf[..5]), 5);
+
+        // Both indices are at 5 of 8, so this is split across the end.
+        assert_eq!(producer.write(b"fghijk"), 6);
+        assert_eq!(consumer.len(), 6);
+        assert_eq!(consumer.read(&mut buf[..2]), 2);
+        assert_eq!(&buf[..2], b"fg");
+        assert_eq!(consumer.read(&mut buf), 4);
+        assert_eq!(&buf[..4], b"hijk");
+        assert!(consumer.is_empty());
+    }
+
+    #[test]
+    fn garbage_is_refused() {
+        let mut backing = Backing([0u8; HEADER_SIZE + 8]);
+        let range =
+            unsafe { MemoryRange::new(backing.0.as_mut_ptr() as usize, HEADER_SIZE + 8) }.unwrap();
+        assert!(unsafe { consumer(range) }.is_err());
+
+        let (mut producer, mut consumer) = ring(&mut backing);
+        producer.write(b"abc");
+        // A peer that sets an index out of range gets nothing more through.
+        consumer.ring.header().head.store(100, Ordering::Release);
+        assert_eq!(consumer.len(), 0);
+        assert_eq!(consumer.read(&mut [0u8; 8]), 0);
+        assert_eq!(producer.free(), 0);
+        assert_eq!(producer.write(b"d"), 0);
+    }
+
+    #[test]
+    fn bytes_arrive_in_order() {
+        const TOTAL: usize = 100_000;
+        let mut backing = Backing([0u8; HEADER_SIZE + 61]);
+        let (mut producer, mut consumer) = ring(&mut backing);
+
+        let writer = std::thread::spawn(move || {
+            let mut sent = 0;
+            while sent < TOTAL {
+                let chunk: Vec<u8> = (sent..(sent + 13).min(TOTAL)).map(|n| n as u8).collect();
+                sent += producer.write(&chunk);
+                std::thread::yield_now();
+            }
+        });
+
+        let mut received = 0;
+        let mut buf = [0u8; 17];
+        while received < TOTAL {
+            let count = consumer.read(&mut buf);
+            for &byte in &buf[..count] {
+                assert_eq!(byte, received as u8);
+                received += 1;
+            }
+            std::thread::yield_now();
+        }
+        writer.join().unwrap();
+        assert!(consumer.is_empty());
+    }
+}
@@ -1,7 +1,8 @@
 use crate::{
     pid_from_usize, CpuID, Error, Exception, MemoryAddress, MemoryFlags, MemoryMessage,
-    MemoryRange, MemorySize, MemoryType, Message, MessageEnvelope, MessageSender, ProcessArgs,
-    ProcessInit, Result, ScalarMessage, SysCallResult, ThreadInit, CID, PID, SID, TID,
+    MemoryRange, MemorySize, MemoryType, Message, MessageEnvelope, MessageId, MessageSender,
+    ProcessArgs, ProcessInit, Result, ScalarMessage, SysCallResult, ThreadInit, ThreadPriority,
+    CID, PID, SID, TID,
 };
 use core::convert::{TryFrom, TryInto};
 
@@ -266,7 +267,15 @@ pub enum SysCall {
     /// Return two scalars to the sender
     ReturnScalar2(MessageSender, usize, usize),
 
-    /// Spawn a new thread
+    /// Spawn a new thread. On hardware, the lowest page of the stack becomes
+    /// a guard page: it is unmapped, and touching it raises a
+    /// `StackOverflow` exception instead of corrupting whatever lies below.
+    ///
+    /// # Errors
+    ///
+    /// * **BadAlignment**: The stack is not page-aligned
+    /// * **BadAddress**: The stack is not mapped, or is not larger than one page
+    /// * **ThreadNotAvailable**: The process has no free thread slots
     CreateThread(ThreadInit),
 
     /// Create a new process, setting the current process as the parent ID.
@@ -328,6 +337,222 @@ pub enum SysCall {
         usize, /* stack pointer */
     ),
 
+    /// Set the scheduling priority of the given thread in the current process.
+    /// The scheduler always runs the highest-priority thread that is ready,
+    /// and round-robins between threads of equal priority.
+    ///
+    /// # Errors
+    ///
+    /// * **InvalidThread**: The thread ID is not valid
+    /// * **InvalidSyscall**: The priority exceeds `THREAD_PRIORITY_MAX`
+    SetThreadPriority(TID, ThreadPriority),
+
+    /// Get the scheduling priority of the given thread in the current process.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar1**: The priority of the thread
+    ///
+    /// # Errors
+    ///
+    /// * **InvalidThread**: The thread ID is not valid
+    GetThreadPriority(TID),
+
+    /// Put the current thread to sleep as long as the word at the given
+    /// address contains the expected value. This is the building block for
+    /// userspace mutexes and condition variables. If the value has already
+    /// changed, the call returns immediately. Wakeups may be spurious, so the
+    /// caller must check the value again after this returns.
+    ///
+    /// If the timeout is nonzero, the thread is woken after that many
+    /// milliseconds even if nobody calls `WakeAddress`.
+    ///
+    /// # Errors
+    ///
+    /// * **BadAlignment**: The address is not word-aligned
+    /// * **BadAddress**: The address is not mapped in this process
+    /// * **Timeout**: The timeout expired before the thread was woken
+    /// * **OutOfMemory**: The kernel has no room to record another waiter
+    WaitForAddress(
+        MemoryAddress, /* address */
+        usize,         /* expected value */
+        usize,         /* timeout in ms, or 0 for none */
+    ),
+
+    /// Wake up to the given number of threads in the current process that
+    /// are waiting on the given address with `WaitForAddress`.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar1**: The number of threads that were woken
+    WakeAddress(MemoryAddress /* address */, usize /* count */),
+
+    /// Get a snapshot of the first process whose PID is equal to or greater
+    /// than the given PID. Call this repeatedly with the returned PID plus one
+    /// to walk the whole process table. Only PID 1 and the processes of the
+    /// boot image may look at processes other than themselves.
+    ///
+    /// # Returns
+    ///
+    /// * **ProcessInfo**: Information about the process
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: There are no processes at or after this PID
+    /// * **AccessDenied**: The PID isn't the caller's, and the caller may not
+    ///   look at other processes
+    GetProcessInfo(PID),
+
+    /// Copy the name of the given process into the buffer. Names are only
+    /// known for processes that were started by the loader.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar1**: The number of bytes that were copied
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: The process does not exist
+    /// * **BadAddress**: The buffer is not valid
+    /// * **AccessDenied**: As for `GetProcessInfo`
+    GetProcessName(
+        PID,
+        MemoryAddress, /* buffer */
+        usize,         /* buffer length */
+    ),
+
+    /// Return how much CPU time the given thread has used, and how many
+    /// times the kernel has switched to it.
+    ///
+    /// # Returns
+    ///
+    /// * **CpuTime**: The accumulated CPU time
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: The process does not exist
+    /// * **ThreadNotAvailable**: The thread ID is out of range
+    /// * **AccessDenied**: As for `GetProcessInfo`
+    GetThreadCpuTime(PID, TID),
+
+    /// Return the total CPU time used by every thread in the given process.
+    ///
+    /// # Returns
+    ///
+    /// * **CpuTime**: The accumulated CPU time
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: The process does not exist
+    /// * **AccessDenied**: As for `GetProcessInfo`
+    GetProcessCpuTime(PID),
+
+    /// Make the given server the supervisor of all processes that this
+    /// process creates. Whenever one of them terminates, the server receives
+    /// a nonblocking Scalar message with the given ID describing which child
+    /// stopped and why. See `ChildTerminated` for the message layout.
+    ///
+    /// Supervision stops when the server is destroyed. Calling this again
+    /// replaces the previous supervisor.
+    ///
+    /// # Errors
+    ///
+    /// * **ServerNotFound**: The server does not exist or belongs to another process
+    SetSupervisor(SID, MessageId),
+
+    /// Map the given range into the process that owns the server behind the
+    /// connection, while keeping it mapped here as well. Unlike a lend, the
+    /// mapping persists across messages until either side calls
+    /// `UnshareMemory` or terminates. The server is only given write access
+    /// if `MemoryFlags::W` is specified.
+    ///
+    /// The range is not announced to the server. Send it the returned
+    /// address, and have it check the address with `GetSharedMemoryPeer`.
+    ///
+    /// In hosted mode this only works between processes that are threads of
+    /// the same program.
+    ///
+    /// # Returns
+    ///
+    /// * **MemoryRange**: The range as seen by the server process
+    ///
+    /// # Errors
+    ///
+    /// * **ServerNotFound**: The connection is not valid
+    /// * **BadAlignment**: The range is not page-aligned
+    /// * **ShareViolation**: Part of the range is already lent or shared, or
+    ///   the server belongs to this process
+    /// * **OutOfMemory**: Too many ranges are shared
+    /// * **UnhandledSyscall**: The system is running in hosted mode, where
+    ///   processes don't share any memory
+    ShareMemory(CID, MemoryRange, MemoryFlags),
+
+    /// Stop sharing a range that was set up with `ShareMemory`. Either side
+    /// may call this with the range as it appears in its own address space.
+    ///
+    /// # Errors
+    ///
+    /// * **BadAddress**: No shared range starts at the given address
+    UnshareMemory(MemoryRange),
+
+    /// Return the process on the other side of a shared range, given the
+    /// range as it appears in the caller's address space.
+    ///
+    /// # Returns
+    ///
+    /// * **ProcessID**: The other process
+    ///
+    /// # Errors
+    ///
+    /// * **BadAddress**: No shared range starts at the given address
+    GetSharedMemoryPeer(MemoryRange),
+
+    /// Return counters describing the message queue of the server behind the
+    /// given connection. The server owner can get a connection to its own
+    /// server with `Connect`.
+    ///
+    /// # Returns
+    ///
+    /// * **ServerStats**: The counters for the server
+    ///
+    /// # Errors
+    ///
+    /// * **ServerNotFound**: The connection is not valid
+    /// * **AccessDenied**: The caller is neither the owner of the server nor
+    ///   the owner's parent
+    GetServerStats(CID),
+
+    /// Wait for a message to arrive for the given server, like
+    /// `ReceiveMessage`, but give up after the given number of milliseconds.
+    /// With a timeout of zero this only returns a message that is already
+    /// queued. The deadline is checked whenever the kernel schedules, so the
+    /// call may return slightly late.
+    ///
+    /// # Returns
+    ///
+    /// * **Message**: The message that arrived
+    ///
+    /// # Errors
+    ///
+    /// * **ServerNotFound**: The server does not exist or belongs to another process
+    /// * **Timeout**: No message arrived before the timeout expired
+    /// * **OutOfMemory**: The kernel has no room to record another deadline
+    ReceiveMessageTimeout(SID, usize /* timeout in ms */),
+
+    /// Return the thread that sent a blocking message which the caller has
+    /// received and not yet replied to. Unlike anything the message itself
+    /// says, this can't be forged by the client.
+    ///
+    /// # Returns
+    ///
+    /// * **ThreadID**: The thread that is waiting for the reply
+    ///
+    /// # Errors
+    ///
+    /// * **ServerNotFound**: The message wasn't sent to one of the caller's servers
+    /// * **BadAddress**: No thread is waiting for a reply to this message
+    GetSenderThread(MessageSender),
+
     /// This syscall does not exist. It captures all possible
     /// arguments so detailed analysis can be performed.
     Invalid(usize, usize, usize, usize, usize, usize, usize),
@@ -371,6 +596,21 @@ pub enum SysCallNumber {
     Disconnect = 35,
     JoinThread = 36,
     SetExceptionHandler = 37,
+    SetThreadPriority = 38,
+    GetThreadPriority = 39,
+    WaitForAddress = 40,
+    WakeAddress = 41,
+    GetProcessInfo = 42,
+    GetProcessName = 43,
+    GetThreadCpuTime = 44,
+    GetProcessCpuTime = 45,
+    SetSupervisor = 46,
+    ShareMemory = 47,
+    UnshareMemory = 48,
+    GetSharedMemoryPeer = 49,
+    GetServerStats = 50,
+    ReceiveMessageTimeout = 51,
+    GetSenderThread = 52,
     Invalid,
 }
 
@@ -414,6 +654,21 @@ impl SysCallNumber {
             35 => Disconnect,
             36 => JoinThread,
             37 => SetExceptionHandler,
+            38 => SetThreadPriority,
+            39 => GetThreadPriority,
+            40 => WaitForAddress,
+            41 => WakeAddress,
+            42 => GetProcessInfo,
+            43 => GetProcessName,
+            44 => GetThreadCpuTime,
+            45 => GetProcessCpuTime,
+            46 => SetSupervisor,
+            47 => ShareMemory,
+            48 => UnshareMemory,
+            49 => GetSharedMemoryPeer,
+            50 => GetServerStats,
+            51 => ReceiveMessageTimeout,
+            52 => GetSenderThread,
             _ => Invalid,
         }
     }
@@ -755,6 +1010,162 @@ impl SysCall {
                 0,
                 0,
             ],
+            SysCall::SetThreadPriority(tid, priority) => [
+                SysCallNumber::SetThreadPriority as usize,
+                *tid as usize,
+                *priority as usize,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetThreadPriority(tid) => [
+                SysCallNumber::GetThreadPriority as usize,
+                *tid as usize,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::WaitForAddress(addr, expected, timeout) => [
+                SysCallNumber::WaitForAddress as usize,
+                addr.get(),
+                *expected,
+                *timeout,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::WakeAddress(addr, count) => [
+                SysCallNumber::WakeAddress as usize,
+                addr.get(),
+                *count,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetProcessInfo(pid) => [
+                SysCallNumber::GetProcessInfo as usize,
+                pid.get() as usize,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetProcessName(pid, buf, len) => [
+                SysCallNumber::GetProcessName as usize,
+                pid.get() as usize,
+                buf.get(),
+                *len,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetThreadCpuTime(pid, tid) => [
+                SysCallNumber::GetThreadCpuTime as usize,
+                pid.get() as usize,
+                *tid,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetProcessCpuTime(pid) => [
+                SysCallNumber::GetProcessCpuTime as usize,
+                pid.get() as usize,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::SetSupervisor(sid, id) => {
+                let s = sid.to_u32();
+                [
+                    SysCallNumber::SetSupervisor as usize,
+                    s.0 as _,
+                    s.1 as _,
+                    s.2 as _,
+                    s.3 as _,
+                    *id,
+                    0,
+                    0,
+                ]
+            }
+            SysCall::ShareMemory(cid, range, flags) => [
+                SysCallNumber::ShareMemory as usize,
+                *cid as usize,
+                range.as_ptr() as usize,
+                range.len(),
+                crate::get_bits(flags),
+                0,
+                0,
+                0,
+            ],
+            SysCall::UnshareMemory(range) => [
+                SysCallNumber::UnshareMemory as usize,
+                range.as_ptr() as usize,
+                range.len(),
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetSharedMemoryPeer(range) => [
+                SysCallNumber::GetSharedMemoryPeer as usize,
+                range.as_ptr() as usize,
+                range.len(),
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetServerStats(cid) => [
+                SysCallNumber::GetServerStats as usize,
+                *cid as usize,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::ReceiveMessageTimeout(sid, timeout_ms) => {
+                let s = sid.to_u32();
+                [
+                    SysCallNumber::ReceiveMessageTimeout as usize,
+                    s.0 as _,
+                    s.1 as _,
+                    s.2 as _,
+                    s.3 as _,
+                    *timeout_ms,
+                    0,
+                    0,
+                ]
+            }
+            SysCall::GetSenderThread(sender) => [
+                SysCallNumber::GetSenderThread as usize,
+                sender.to_usize(),
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
             SysCall::Invalid(a1, a2, a3, a4, a5, a6, a7) => [
                 SysCallNumber::Invalid as usize,
                 *a1,
@@ -918,6 +1329,58 @@ impl SysCall {
             SysCallNumber::Disconnect => SysCall::Disconnect(a1 as _),
             SysCallNumber::JoinThread => SysCall::JoinThread(a1 as _),
             SysCallNumber::SetExceptionHandler => SysCall::SetExceptionHandler(a1 as _, a2 as _),
+            SysCallNumber::SetThreadPriority => {
+                if a2 > crate::THREAD_PRIORITY_MAX as usize {
+                    return Err(Error::InvalidSyscall);
+                }
+                SysCall::SetThreadPriority(a1 as _, a2 as _)
+            }
+            SysCallNumber::GetThreadPriority => SysCall::GetThreadPriority(a1 as _),
+            SysCallNumber::WaitForAddress => SysCall::WaitForAddress(
+                MemoryAddress::new(a1).ok_or(Error::InvalidSyscall)?,
+                a2,
+                a3,
+            ),
+            SysCallNumber::WakeAddress => {
+                SysCall::WakeAddress(MemoryAddress::new(a1).ok_or(Error::InvalidSyscall)?, a2)
+            }
+            SysCallNumber::GetProcessInfo => {
+                SysCall::GetProcessInfo(PID::new(a1 as _).ok_or(Error::InvalidSyscall)?)
+            }
+            SysCallNumber::GetProcessName => SysCall::GetProcessName(
+                PID::new(a1 as _).ok_or(Error::InvalidSyscall)?,
+                MemoryAddress::new(a2).ok_or(Error::InvalidSyscall)?,
+                a3,
+            ),
+            SysCallNumber::GetThreadCpuTime => {
+                SysCall::GetThreadCpuTime(PID::new(a1 as _).ok_or(Error::InvalidSyscall)?, a2)
+            }
+            SysCallNumber::GetProcessCpuTime => {
+                SysCall::GetProcessCpuTime(PID::new(a1 as _).ok_or(Error::InvalidSyscall)?)
+            }
+            SysCallNumber::SetSupervisor => SysCall::SetSupervisor(
+                SID::from_u32(a1 as _, a2 as _, a3 as _, a4 as _),
+                a5,
+            ),
+            SysCallNumber::ShareMemory => SysCall::ShareMemory(
+                a1 as _,
+                unsafe { MemoryRange::new(a2, a3).or(Err(Error::InvalidSyscall)) }?,
+                crate::from_bits(a4).ok_or(Error::InvalidSyscall)?,
+            ),
+            SysCallNumber::UnshareMemory => SysCall::UnshareMemory(unsafe {
+                MemoryRange::new(a1, a2).or(Err(Error::InvalidSyscall))
+            }?),
+            SysCallNumber::GetSharedMemoryPeer => SysCall::GetSharedMemoryPeer(unsafe {
+                MemoryRange::new(a1, a2).or(Err(Error::InvalidSyscall))
+            }?),
+            SysCallNumber::GetServerStats => SysCall::GetServerStats(a1 as _),
+            SysCallNumber::ReceiveMessageTimeout => SysCall::ReceiveMessageTimeout(
+                SID::from_u32(a1 as _, a2 as _, a3 as _, a4 as _),
+                a5,
+            ),
+            SysCallNumber::GetSenderThread => {
+                SysCall::GetSenderThread(MessageSender::from_usize(a1))
+            }
             SysCallNumber::Invalid => SysCall::Invalid(a1, a2, a3, a4, a5, a6, a7),
         })
     }
@@ -1017,6 +1480,7 @@ impl SysCall {
                 | SysCall::ReturnScalar2(_, _, _)
                 | SysCall::ReturnScalar1(_, _)
                 | SysCall::ReturnMemory(_, _, _, _)
+                | SysCall::WakeAddress(_, _)
         )
     }
 }
@@ -1273,8 +1737,9 @@ pub fn try_connect(server: SID) -> core::result::Result<CID, Error> {
 ///
 /// # Errors
 ///
+/// * **ServerNotFound**: The server doesn't exist, or was destroyed
 pub fn receive_message(server: SID) -> core::result::Result<MessageEnvelope, Error> {
-    let result = rsyscall(SysCall::ReceiveMessage(server)).expect("Couldn't call ReceiveMessage");
+    let result = rsyscall(SysCall::ReceiveMessage(server))?;
     if let Result::Message(envelope) = result {
         Ok(envelope)
     } else if let Result::Error(e) = result {
@@ -1303,6 +1768,24 @@ pub fn try_receive_message(server: SID) -> core::result::Result<Option<MessageEn
     }
 }
 
+/// Wait up to `timeout_ms` milliseconds for a message to arrive for the
+/// provided server. The kernel notices an expired timeout at the next
+/// pre-emption tick, so it may run up to one tick long.
+///
+/// # Errors
+///
+/// * **Timeout**: No message arrived in time
+pub fn receive_message_timeout(
+    server: SID,
+    timeout_ms: usize,
+) -> core::result::Result<MessageEnvelope, Error> {
+    match rsyscall(SysCall::ReceiveMessageTimeout(server, timeout_ms))? {
+        Result::Message(envelope) => Ok(envelope),
+        Result::Error(e) => Err(e),
+        _ => Err(Error::InternalError),
+    }
+}
+
 /// Send a message to a server.  Depending on the mesage type (move or borrow), it
 /// will either block (borrow) or return immediately (move).
 /// If the message type is `borrow`, then the memory addresses pointed to will be
@@ -1312,6 +1795,7 @@ pub fn try_receive_message(server: SID) -> core::result::Result<Option<MessageEn
 ///
 /// * **ServerNotFound**: The server does not exist so the connection is now invalid
 /// * **BadAddress**: The client tried to pass a Memory message using an address it doesn't own
+/// * **QuotaExceeded**: The message moves memory to a server that may not own any more
 /// * **ServerQueueFull**: The queue in the server is full, and this call would block
 /// * **Timeout**: The timeout limit has been reached
 pub fn try_send_message(connection: CID, message: Message) -> core::result::Result<Result, Error> {
@@ -1355,6 +1839,7 @@ pub fn connect_for_process(pid: PID, sid: SID) -> core::result::Result<Result, E
 ///
 /// * **ServerNotFound**: The server does not exist so the connection is now invalid
 /// * **BadAddress**: The client tried to pass a Memory message using an address it doesn't own
+/// * **QuotaExceeded**: The message moves memory to a server that may not own any more
 /// * **Timeout**: The timeout limit has been reached
 pub fn send_message(connection: CID, message: Message) -> core::result::Result<Result, Error> {
     let result = rsyscall(SysCall::SendMessage(connection, message));
@@ -1580,6 +2065,23 @@ pub fn current_tid() -> core::result::Result<TID, Error> {
     })
 }
 
+/// Return the thread that sent `sender`, a blocking message that this
+/// process has received and not yet replied to.
+///
+/// # Errors
+///
+/// * **ServerNotFound**: The message wasn't sent to one of this process' servers
+/// * **BadAddress**: No thread is waiting for a reply to this message
+pub fn sender_thread(sender: MessageSender) -> core::result::Result<TID, Error> {
+    rsyscall(SysCall::GetSenderThread(sender)).and_then(|result| {
+        if let Result::ThreadID(tid) = result {
+            Ok(tid)
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
 pub fn destroy_server(sid: SID) -> core::result::Result<(), Error> {
     rsyscall(SysCall::DestroyServer(sid)).and_then(|result| {
         if let Result::Ok = result {
@@ -1629,13 +2131,39 @@ pub fn join_thread(tid: TID) -> core::result::Result<usize, Error> {
 }
 
 static EXCEPTION_HANDLER: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
-fn handle_exception(exception_type: usize, arg1: usize, arg2: usize) -> isize {
+static FAULT_CONTEXT: [core::sync::atomic::AtomicUsize; 3] = [
+    core::sync::atomic::AtomicUsize::new(0),
+    core::sync::atomic::AtomicUsize::new(0),
+    core::sync::atomic::AtomicUsize::new(0),
+];
+fn handle_exception(
+    exception_type: usize,
+    arg1: usize,
+    arg2: usize,
+    ra: usize,
+    sp: usize,
+    fp: usize,
+) -> isize {
     let exception = crate::exceptions::Exception::new(exception_type, arg1, arg2);
+    for (slot, value) in FAULT_CONTEXT.iter().zip([ra, sp, fp].iter()) {
+        slot.store(*value, core::sync::atomic::Ordering::SeqCst);
+    }
     let f = EXCEPTION_HANDLER.load(core::sync::atomic::Ordering::SeqCst);
     let f = unsafe { core::mem::transmute::<usize, fn(Exception) -> isize>(f) };
     f(exception)
 }
 
+/// The registers of the thread that caused the exception being handled. This
+/// is only meaningful when called from within an exception handler.
+pub fn fault_context() -> crate::exceptions::FaultContext {
+    let load = |i: usize| FAULT_CONTEXT[i].load(core::sync::atomic::Ordering::SeqCst);
+    crate::exceptions::FaultContext {
+        ra: load(0),
+        sp: load(1),
+        fp: load(2),
+    }
+}
+
 /// Sets the given function as this process' Exception handler. This function
 /// will be called whenever an Exception occurs such as a memory fault,
 /// illegal instruction, or a child process terminating.
@@ -1664,6 +2192,223 @@ pub fn set_exception_handler(
     })
 }
 
+/// Set the scheduling priority of a thread within the current process. Use
+/// `current_tid()` to change the priority of the calling thread.
+///
+/// # Errors
+///
+/// * **InvalidThread**: The thread does not exist in this process
+/// * **InvalidSyscall**: The priority is greater than `THREAD_PRIORITY_MAX`
+pub fn set_thread_priority(tid: TID, priority: ThreadPriority) -> core::result::Result<(), Error> {
+    if priority > crate::THREAD_PRIORITY_MAX {
+        return Err(Error::InvalidSyscall);
+    }
+    rsyscall(SysCall::SetThreadPriority(tid, priority)).and_then(|result| {
+        if let Result::Ok = result {
+            Ok(())
+        } else if let Result::Error(e) = result {
+            Err(e)
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Get the scheduling priority of a thread within the current process.
+///
+/// # Errors
+///
+/// * **InvalidThread**: The thread does not exist in this process
+pub fn thread_priority(tid: TID) -> core::result::Result<ThreadPriority, Error> {
+    rsyscall(SysCall::GetThreadPriority(tid)).and_then(|result| {
+        if let Result::Scalar1(priority) = result {
+            Ok(priority as ThreadPriority)
+        } else if let Result::Error(e) = result {
+            Err(e)
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Block the current thread for as long as `addr` contains `expected`, or
+/// until `timeout_ms` milliseconds have passed. Returns as soon as another
+/// thread calls `wake_address()` on the same address. Wakeups may be
+/// spurious, so callers should re-check the value in a loop. As with
+/// `receive_message_timeout()`, the timeout may run up to one pre-emption
+/// tick long.
+///
+/// # Errors
+///
+/// * **Timeout**: The timeout expired before the thread was woken
+/// * **OutOfMemory**: The kernel has no room to record another waiter
+pub fn wait_for_address(
+    addr: &core::sync::atomic::AtomicUsize,
+    expected: usize,
+    timeout_ms: Option<usize>,
+) -> core::result::Result<(), Error> {
+    // The kernel checks this again, but there's no point in making a
+    // syscall if the value has already changed.
+    if addr.load(core::sync::atomic::Ordering::SeqCst) != expected {
+        return Ok(());
+    }
+    let address = MemoryAddress::new(addr as *const _ as usize).ok_or(Error::BadAddress)?;
+    rsyscall(SysCall::WaitForAddress(
+        address,
+        expected,
+        timeout_ms.unwrap_or(0),
+    ))
+    .and_then(|result| {
+        if let Result::Ok = result {
+            Ok(())
+        } else if let Result::Error(e) = result {
+            Err(e)
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Wake up to `count` threads that are blocked in `wait_for_address()` on
+/// `addr`. Returns the number of threads that were woken.
+pub fn wake_address(
+    addr: &core::sync::atomic::AtomicUsize,
+    count: usize,
+) -> core::result::Result<usize, Error> {
+    let address = MemoryAddress::new(addr as *const _ as usize).ok_or(Error::BadAddress)?;
+    rsyscall(SysCall::WakeAddress(address, count)).and_then(|result| {
+        if let Result::Scalar1(woken) = result {
+            Ok(woken)
+        } else if let Result::Error(e) = result {
+            Err(e)
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Return information about the first process whose PID is equal to or
+/// greater than `first_pid`.
+///
+/// # Errors
+///
+/// * **ProcessNotFound**: There are no processes at or after this PID
+pub fn process_info(first_pid: PID) -> core::result::Result<crate::ProcessInfo, Error> {
+    rsyscall(SysCall::GetProcessInfo(first_pid)).and_then(|result| {
+        if let Result::ProcessInfo(a1, a2, a3, a4, a5, a6, a7) = result {
+            crate::ProcessInfo::from_args([a1, a2, a3, a4, a5, a6, a7]).ok_or(Error::InternalError)
+        } else if let Result::Error(e) = result {
+            Err(e)
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Copy the name of the given process into `name`, returning the part of
+/// `name` that was filled in. Processes without a name return an empty slice.
+pub fn process_name(pid: PID, name: &mut [u8]) -> core::result::Result<&str, Error> {
+    let address = match MemoryAddress::new(name.as_mut_ptr() as usize) {
+        Some(address) if !name.is_empty() => address,
+        _ => return Ok(""),
+    };
+    let len = match rsyscall(SysCall::GetProcessName(pid, address, name.len()))? {
+        Result::Scalar1(len) => len.min(name.len()),
+        Result::Error(e) => return Err(e),
+        _ => return Err(Error::InternalError),
+    };
+    core::str::from_utf8(&name[..len]).or(Err(Error::InvalidString))
+}
+
+fn cpu_time_result(result: SysCallResult) -> core::result::Result<crate::CpuTime, Error> {
+    match result? {
+        Result::CpuTime(a1, a2, a3) => Ok(crate::CpuTime::from_args([a1, a2, a3])),
+        Result::Error(e) => Err(e),
+        _ => Err(Error::InternalError),
+    }
+}
+
+/// Return how much CPU time the given thread has used since it was created.
+pub fn thread_cpu_time(pid: PID, tid: TID) -> core::result::Result<crate::CpuTime, Error> {
+    cpu_time_result(rsyscall(SysCall::GetThreadCpuTime(pid, tid)))
+}
+
+/// Return how much CPU time all threads in the given process have used since
+/// the process was created.
+pub fn process_cpu_time(pid: PID) -> core::result::Result<crate::CpuTime, Error> {
+    cpu_time_result(rsyscall(SysCall::GetProcessCpuTime(pid)))
+}
+
+/// Map `range` into the server behind `connection` as well as this process,
+/// and return the range as the server sees it. See `SysCall::ShareMemory`.
+pub fn share_memory(
+    connection: CID,
+    range: MemoryRange,
+    flags: MemoryFlags,
+) -> core::result::Result<MemoryRange, Error> {
+    rsyscall(SysCall::ShareMemory(connection, range, flags)).and_then(|result| {
+        if let Result::MemoryRange(range) = result {
+            Ok(range)
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Stop sharing a range that was set up with `share_memory()`. The memory
+/// stays mapped in the process that originally owned it.
+pub fn unshare_memory(range: MemoryRange) -> core::result::Result<(), Error> {
+    rsyscall(SysCall::UnshareMemory(range)).and_then(|result| {
+        if let Result::Ok = result {
+            Ok(())
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Return the process that this process shares `range` with. A server
+/// should check this before trusting an address that a client claims to
+/// have shared with it.
+pub fn shared_memory_peer(range: MemoryRange) -> core::result::Result<PID, Error> {
+    rsyscall(SysCall::GetSharedMemoryPeer(range)).and_then(|result| {
+        if let Result::ProcessID(pid) = result {
+            Ok(pid)
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Return the message queue counters of the server behind `connection`.
+/// Only the process that owns the server, or that process' parent, may ask.
+pub fn server_stats(connection: CID) -> core::result::Result<crate::ServerStats, Error> {
+    match rsyscall(SysCall::GetServerStats(connection))? {
+        Result::ServerStats(a1, a2, a3, a4, a5, a6) => {
+            Ok(crate::ServerStats::from_args([a1, a2, a3, a4, a5, a6]))
+        }
+        Result::Error(e) => Err(e),
+        _ => Err(Error::InternalError),
+    }
+}
+
+/// Have `sid` receive a message with the given `id` whenever a process
+/// created by this one terminates. Use `ChildTerminated::from_message()`
+/// to decode it. The supervisor may then start a replacement process.
+///
+/// Only processes started with `create_process()` have a parent that can
+/// supervise them. The services the loader starts at boot are children of
+/// the kernel (PID 1), so their termination isn't reported to anyone.
+pub fn set_supervisor(sid: SID, id: MessageId) -> core::result::Result<(), Error> {
+    rsyscall(SysCall::SetSupervisor(sid, id)).and_then(|result| {
+        if let Result::Ok = result {
+            Ok(())
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
 /// Perform a raw syscall and return the result. This will transform
 /// `xous::Result::Error(e)` into an `Err(e)`.
 pub fn rsyscall(call: SysCall) -> SysCallResult {
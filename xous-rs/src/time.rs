@@ -0,0 +1,35 @@
+//! Wall-clock time for `std::time::SystemTime`.
+//!
+//! The ticktimer keeps the time of day, seeded from the RTC, but libstd can't
+//! depend on the ticktimer's client crate. This asks the ticktimer directly,
+//! the same way `std::time::Instant` gets `elapsed_ms()`.
+
+use crate::{Error, Message, ScalarMessage, SID};
+
+/// `GetUtcTimeMs` in the ticktimer's API, which takes its number from here
+pub const TICKTIMER_GET_UTC_TIME_MS: usize = 12;
+
+/// The time in UTC, in milliseconds since 1970-01-01 00:00:00.
+///
+/// # Errors
+///
+/// * **UseBeforeInit**: The ticktimer hasn't heard from the RTC yet, which
+///   happens shortly after boot and after every resume
+pub fn utc_now_ms() -> core::result::Result<u64, Error> {
+    let ticktimer = crate::connect(SID::from_bytes(b"ticktimer-server").unwrap())?;
+    let response = crate::send_message(
+        ticktimer,
+        Message::BlockingScalar(ScalarMessage {
+            id: TICKTIMER_GET_UTC_TIME_MS,
+            arg1: 0,
+            arg2: 0,
+            arg3: 0,
+            arg4: 0,
+        }),
+    )?;
+    match response {
+        crate::Result::Scalar2(lower, upper) => Ok(lower as u64 | ((upper as u64) << 32)),
+        crate::Result::Scalar1(e) => Err(Error::from_usize(e)),
+        _ => Err(Error::InternalError),
+    }
+}